@@ -0,0 +1,90 @@
+//! Measures listing, deleting and purging with the workloads of `trash::bench`.
+//!
+//! The workloads are created in the current folder, run the benchmarks from a folder on the
+//! volume that is to be measured.
+
+#[cfg(not(any(
+    target_os = "windows",
+    all(unix, not(target_os = "macos"), not(target_os = "ios"), not(target_os = "android"))
+)))]
+fn main() {
+    println!("This is currently only supported on Windows, Linux, and other Freedesktop.org compliant OSes");
+}
+
+#[cfg(any(
+    target_os = "windows",
+    all(unix, not(target_os = "macos"), not(target_os = "ios"), not(target_os = "android"))
+))]
+criterion::criterion_main!(os_limited::benches);
+
+#[cfg(any(
+    target_os = "windows",
+    all(unix, not(target_os = "macos"), not(target_os = "ios"), not(target_os = "android"))
+))]
+mod os_limited {
+    use criterion::{criterion_group, BatchSize, BenchmarkId, Criterion, Throughput};
+    use trash::{bench::Workload, os_limited, ByteSize};
+
+    criterion_group!(benches, list, delete, purge);
+
+    fn list(c: &mut Criterion) {
+        let mut group = c.benchmark_group("list");
+        for count in [100, 1000] {
+            let workload = Workload::new(".").unwrap();
+            workload.synthetic_bin(count).unwrap();
+            group.throughput(Throughput::Elements(count as u64));
+            group.bench_function(BenchmarkId::from_parameter(count), |b| b.iter(|| os_limited::list().unwrap()));
+            workload.purge().unwrap();
+        }
+        group.finish();
+    }
+
+    fn delete(c: &mut Criterion) {
+        let mut group = c.benchmark_group("delete");
+        group.sample_size(10);
+        let workload = Workload::new(".").unwrap();
+        for count in [10, 100, 1000] {
+            group.throughput(Throughput::Elements(count as u64));
+            group.bench_function(BenchmarkId::new("files", count), |b| {
+                b.iter_batched(
+                    || workload.files(count, ByteSize::kib(4)).unwrap(),
+                    |files| trash::delete_all(files).unwrap(),
+                    BatchSize::PerIteration,
+                )
+            });
+        }
+        group.bench_function("deep_tree", |b| {
+            b.iter_batched(
+                || workload.deep_tree(32, 4).unwrap(),
+                |tree| trash::delete(tree).unwrap(),
+                BatchSize::PerIteration,
+            )
+        });
+        group.bench_function("long_names", |b| {
+            b.iter_batched(
+                || workload.long_names(10, 200).unwrap(),
+                |files| trash::delete_all(files).unwrap(),
+                BatchSize::PerIteration,
+            )
+        });
+        workload.purge().unwrap();
+        group.finish();
+    }
+
+    fn purge(c: &mut Criterion) {
+        let mut group = c.benchmark_group("purge");
+        group.sample_size(10);
+        let workload = Workload::new(".").unwrap();
+        for count in [10, 100] {
+            group.throughput(Throughput::Elements(count as u64));
+            group.bench_function(BenchmarkId::from_parameter(count), |b| {
+                b.iter_batched(
+                    || workload.synthetic_bin(count).unwrap(),
+                    |items| os_limited::purge_all(items).unwrap(),
+                    BatchSize::PerIteration,
+                )
+            });
+        }
+        group.finish();
+    }
+}
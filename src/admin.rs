@@ -0,0 +1,79 @@
+//! Operations on the trash of other user accounts, for example to give back an item that a user
+//! deleted by accident.
+//!
+//! Accessing the trash of another user requires root privileges on Linux and administrator
+//! privileges on Windows. Restored items are handed over to the user they belong to: their owner
+//! and group are changed on Linux, and their owner is changed on Windows.
+//!
+//! The items returned by [`list`] must only be passed to the functions of this module. On Windows
+//! the Recycle Bin of another user can't be accessed through the shell, so its index files are read
+//! directly and the ids of the items are the paths of those files.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use trash::admin::{list, restore_all, TrashOwner};
+//!
+//! let owner = TrashOwner::from_name("alice").unwrap();
+//! let items = list(&owner).unwrap().into_iter().filter(|item| item.name == "thesis.odt");
+//! restore_all(&owner, items).unwrap();
+//! ```
+
+use crate::{ensure_no_twins, listing_order, platform, Error, TrashItem};
+
+/// A user account whose trash is accessed.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct TrashOwner(platform::UserAccount);
+
+impl TrashOwner {
+    /// Looks up the user account with the given name. On Windows the name may be qualified with a
+    /// domain, as in `DOMAIN\user`.
+    pub fn from_name(name: &str) -> Result<Self, Error> {
+        platform::UserAccount::from_name(name).map(TrashOwner)
+    }
+
+    /// Looks up the user account with the given user id.
+    #[cfg(unix)]
+    pub fn from_uid(uid: u32) -> Result<Self, Error> {
+        platform::UserAccount::from_uid(uid).map(TrashOwner)
+    }
+
+    /// The user id of the account.
+    #[cfg(unix)]
+    pub fn uid(&self) -> u32 {
+        self.0.uid
+    }
+
+    /// Creates an owner from the string form of a SID, like `S-1-5-21-...`.
+    #[cfg(windows)]
+    pub fn from_sid(sid: &str) -> Result<Self, Error> {
+        platform::UserAccount::from_sid(sid).map(TrashOwner)
+    }
+
+    /// The string form of the SID of the account.
+    #[cfg(windows)]
+    pub fn sid(&self) -> &str {
+        &self.0.sid
+    }
+}
+
+/// Returns all items in the trash of `owner`, in the same order as
+/// [`os_limited::list`](crate::os_limited::list).
+pub fn list(owner: &TrashOwner) -> Result<Vec<TrashItem>, Error> {
+    let mut items = platform::list_of_user(&owner.0)?;
+    items.sort_by(listing_order);
+    Ok(items)
+}
+
+/// Restores the given items of the trash of `owner` to their original location and makes `owner`
+/// the owner of the restored files.
+///
+/// Fails in the same way as [`os_limited::restore_all`](crate::os_limited::restore_all) when one
+/// of the original paths is taken, or when two items have the same original path.
+pub fn restore_all<I>(owner: &TrashOwner, items: I) -> Result<(), Error>
+where
+    I: IntoIterator<Item = TrashItem>,
+{
+    let items = ensure_no_twins(items.into_iter().collect())?;
+    platform::restore_all_of_user(&owner.0, items)
+}
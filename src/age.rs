@@ -0,0 +1,55 @@
+//! How long ago items were deleted.
+
+use std::time::{Duration, SystemTime};
+
+use crate::TrashItem;
+
+impl TrashItem {
+    /// How long ago the item was deleted, according to the system clock. Returns `None` if the
+    /// deletion time is unknown. Items that appear to be deleted in the future, because the clock
+    /// was changed, have an age of zero.
+    pub fn age(&self) -> Option<Duration> {
+        let deleted = self.deletion_time()?;
+        Some(SystemTime::now().duration_since(deleted).unwrap_or_default())
+    }
+
+    /// The moment the item was deleted in the local time zone. Returns `None` if the deletion
+    /// time is unknown.
+    #[cfg(feature = "chrono")]
+    pub fn deleted_at_local(&self) -> Option<chrono::DateTime<chrono::Local>> {
+        use chrono::TimeZone;
+
+        let time_deleted = self.time_deleted.filter(|&time| time >= 0)?;
+        chrono::Local.timestamp_opt(time_deleted, 0).single()
+    }
+
+    /// **Requires the `humanize` feature**
+    ///
+    /// The [`age`](TrashItem::age) of the item in English, like "3 days ago" or "just now".
+    /// Returns `None` if the deletion time is unknown.
+    #[cfg(feature = "humanize")]
+    pub fn humanized_age(&self) -> Option<String> {
+        self.age().map(humanize)
+    }
+}
+
+/// Describes an age in its largest whole unit, with months of 30 days and years of 365 days.
+#[cfg(feature = "humanize")]
+pub(crate) fn humanize(age: Duration) -> String {
+    const UNITS: [(u64, &str); 6] = [
+        (365 * 24 * 60 * 60, "year"),
+        (30 * 24 * 60 * 60, "month"),
+        (7 * 24 * 60 * 60, "week"),
+        (24 * 60 * 60, "day"),
+        (60 * 60, "hour"),
+        (60, "minute"),
+    ];
+    let seconds = age.as_secs();
+    match UNITS.iter().find(|(unit, _)| seconds >= *unit) {
+        Some((unit, name)) => match seconds / unit {
+            1 => format!("1 {name} ago"),
+            count => format!("{count} {name}s ago"),
+        },
+        None => "just now".into(),
+    }
+}
@@ -0,0 +1,173 @@
+//! Writing items of the trash as tar archives, with the `archive` feature.
+
+use std::{
+    ffi::OsStr,
+    fs::{self, File, Metadata},
+    io::{self, Read, Write},
+    path::Path,
+    time::UNIX_EPOCH,
+};
+
+use tar::{Builder, EntryType, Header};
+
+use crate::{into_unknown, io_error, platform, Error, TrashItem};
+
+/// The longest name and link target that fit into a ustar header.
+const USTAR_NAME_LEN: usize = 100;
+
+/// The sizes from this one on don't fit into the octal size field of a ustar header.
+const USTAR_SIZE_LIMIT: u64 = 8u64.pow(11);
+
+impl TrashItem {
+    /// Writes the item to `writer` as a tar archive, which holds a single entry named like the
+    /// item, along with the contents of folders. The archive is written as the files are read, so
+    /// it can be streamed to a client. The item stays in the trash.
+    ///
+    /// Symbolic links are stored as links rather than followed. Names and link targets that don't
+    /// fit into the fields of the ustar format, and files of 8 GiB or more, are described by PAX
+    /// headers, which all common tar implementations understand. On Unix, names that aren't UTF-8
+    /// are stored byte by byte.
+    ///
+    /// Requires the `archive` feature.
+    pub fn write_archive<W: Write>(&self, writer: W) -> Result<W, Error> {
+        let root = platform::file_in_trash(self)?;
+        let mut archive = Builder::new(writer);
+        let mut pending = vec![(root, self.name.clone().into_bytes())];
+        while let Some((path, name)) = pending.pop() {
+            let metadata = path.symlink_metadata().map_err(|e| io_error(&path, e))?;
+            let appended = if metadata.is_dir() {
+                let mut children = Vec::new();
+                for entry in fs::read_dir(&path).map_err(|e| io_error(&path, e))? {
+                    let entry = entry.map_err(|e| io_error(&path, e))?;
+                    let mut child_name = name.clone();
+                    child_name.push(b'/');
+                    child_name.extend(name_bytes(&entry.file_name()));
+                    children.push((entry.path(), child_name));
+                }
+                // The entries are popped from the end, so they're written in order of their names.
+                children.sort_by(|a, b| b.1.cmp(&a.1));
+                pending.extend(children);
+                let mut name = name;
+                name.push(b'/');
+                append(&mut archive, &name, &metadata, EntryType::Directory, None, 0, io::empty())
+            } else if metadata.is_symlink() {
+                let target = fs::read_link(&path).map_err(|e| io_error(&path, e))?;
+                let target = link_bytes(&target);
+                append(&mut archive, &name, &metadata, EntryType::Symlink, Some(&target), 0, io::empty())
+            } else {
+                let file = File::open(&path).map_err(|e| io_error(&path, e))?;
+                let size = metadata.len();
+                let contents = ExactReader { inner: file.take(size), remaining: size };
+                append(&mut archive, &name, &metadata, EntryType::Regular, None, size, contents)
+            };
+            appended.map_err(|e| io_error(&path, e))?;
+        }
+        archive.into_inner().map_err(into_unknown)
+    }
+}
+
+/// Appends an entry, preceded by a PAX header with the name, the link target or the size if they
+/// don't fit into the ustar header.
+fn append<W: Write>(
+    archive: &mut Builder<W>,
+    name: &[u8],
+    metadata: &Metadata,
+    kind: EntryType,
+    link: Option<&[u8]>,
+    size: u64,
+    contents: impl Read,
+) -> io::Result<()> {
+    let size_value = size.to_string();
+    let mut records: Vec<(&str, &[u8])> = Vec::new();
+    if name.len() > USTAR_NAME_LEN {
+        records.push(("path", name));
+    }
+    if let Some(link) = link.filter(|link| link.len() > USTAR_NAME_LEN) {
+        records.push(("linkpath", link));
+    }
+    if size >= USTAR_SIZE_LIMIT {
+        records.push(("size", size_value.as_bytes()));
+    }
+    if records.iter().any(|(_, value)| std::str::from_utf8(value).is_err()) {
+        records.push(("hdrcharset", b"BINARY"));
+    }
+    if !records.is_empty() {
+        archive.append_pax_extensions(records)?;
+    }
+
+    // Values that don't fit are truncated, the PAX header holds them in full.
+    let mut header = Header::new_ustar();
+    let fields = header.as_old_mut();
+    let put = |field: &mut [u8; USTAR_NAME_LEN], value: &[u8]| {
+        let len = value.len().min(USTAR_NAME_LEN);
+        field[..len].copy_from_slice(&value[..len]);
+    };
+    put(&mut fields.name, name);
+    put(&mut fields.linkname, link.unwrap_or_default());
+    header.set_entry_type(kind);
+    header.set_size(size.min(USTAR_SIZE_LIMIT - 1));
+    header.set_mode(mode(metadata, kind));
+    header.set_uid(0);
+    header.set_gid(0);
+    let mtime = metadata.modified().ok().and_then(|time| time.duration_since(UNIX_EPOCH).ok());
+    header.set_mtime(mtime.map_or(0, |mtime| mtime.as_secs()));
+    header.set_cksum();
+    archive.append(&header, contents)
+}
+
+/// Reads exactly `remaining` bytes of a file, failing if the file ends before. The size in the
+/// header was taken before, so the archive would be corrupt if the file shrank in the meantime.
+struct ExactReader<R> {
+    inner: io::Take<R>,
+    remaining: u64,
+}
+
+impl<R: Read> Read for ExactReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.inner.read(buf)?;
+        if read == 0 && self.remaining > 0 && !buf.is_empty() {
+            return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "the file shrank while it was archived"));
+        }
+        self.remaining -= read as u64;
+        Ok(read)
+    }
+}
+
+#[cfg(unix)]
+fn name_bytes(name: &OsStr) -> Vec<u8> {
+    use std::os::unix::ffi::OsStrExt;
+
+    name.as_bytes().to_vec()
+}
+
+#[cfg(not(unix))]
+fn name_bytes(name: &OsStr) -> Vec<u8> {
+    name.to_string_lossy().into_owned().into_bytes()
+}
+
+/// The target of a symbolic link, with `/` as the separator.
+#[cfg(unix)]
+fn link_bytes(target: &Path) -> Vec<u8> {
+    name_bytes(target.as_os_str())
+}
+
+#[cfg(not(unix))]
+fn link_bytes(target: &Path) -> Vec<u8> {
+    target.to_string_lossy().replace('\\', "/").into_bytes()
+}
+
+#[cfg(unix)]
+fn mode(metadata: &Metadata, _kind: EntryType) -> u32 {
+    use std::os::unix::fs::PermissionsExt;
+
+    metadata.permissions().mode() & 0o7777
+}
+
+#[cfg(not(unix))]
+fn mode(metadata: &Metadata, kind: EntryType) -> u32 {
+    match kind {
+        EntryType::Regular if metadata.permissions().readonly() => 0o444,
+        EntryType::Regular => 0o644,
+        _ => 0o755,
+    }
+}
@@ -0,0 +1,220 @@
+//! The failures of a batch operation that spans several volumes.
+
+use std::{
+    mem::{discriminant, Discriminant},
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use crate::Error;
+
+/// The failures of a batch that was split by volume and failed in more than one part, as returned
+/// in [`Error::Batch`].
+///
+/// Every failure is reported along with the volume it occurred on and the paths of the part of the
+/// batch it stopped, so that callers can tell which items to retry without matching on the nested
+/// errors themselves.
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct BatchErrors {
+    failures: Vec<BatchFailure>,
+}
+
+/// A failure of one part of a batch, see [`BatchErrors`].
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct BatchFailure {
+    /// The root of the volume the part was on, like a mount point or `C:\`, if it's known.
+    pub volume: Option<PathBuf>,
+    /// The paths of the items of the part, which are the original paths for items that were
+    /// restored. Items that were handled before the failure occurred are included too, as the
+    /// operating system doesn't report how far it got.
+    pub paths: Vec<PathBuf>,
+    pub error: Error,
+}
+
+impl BatchErrors {
+    /// Returns `None` if there are no failures, and the failure itself if there is only one.
+    #[cfg(any(
+        target_os = "windows",
+        all(unix, not(target_os = "macos"), not(target_os = "ios"), not(target_os = "android"))
+    ))]
+    pub(crate) fn combine(mut failures: Vec<BatchFailure>) -> Option<Error> {
+        match failures.len() {
+            0 => None,
+            1 => failures.pop().map(|failure| failure.error),
+            _ => Some(Error::Batch { errors: BatchErrors { failures } }),
+        }
+    }
+
+    /// The failures in the order of the parts of the batch.
+    pub fn failures(&self) -> &[BatchFailure] {
+        &self.failures
+    }
+
+    pub fn into_failures(self) -> Vec<BatchFailure> {
+        self.failures
+    }
+
+    /// The failures grouped by the volume they occurred on, in the order in which the volumes
+    /// first failed.
+    pub fn by_volume(&self) -> Vec<(Option<&Path>, Vec<&BatchFailure>)> {
+        group(&self.failures, |failure| failure.volume.as_deref())
+    }
+
+    /// The failures grouped by their cause, which is the variant of their [`Error`], in the order
+    /// in which the causes first occurred.
+    pub fn by_cause(&self) -> Vec<Vec<&BatchFailure>> {
+        group(&self.failures, |failure| discriminant(&failure.error))
+            .into_iter()
+            .map(|(_, failures): (Discriminant<Error>, _)| failures)
+            .collect()
+    }
+
+    /// The first failure that retrying won't resolve, see [`Error::is_retryable`].
+    pub fn first_fatal(&self) -> Option<&BatchFailure> {
+        self.failures.iter().find(|failure| !failure.error.is_retryable())
+    }
+
+    /// The failures that may succeed when they are retried, see [`Error::is_retryable`].
+    pub fn retryable(&self) -> impl Iterator<Item = &BatchFailure> {
+        self.failures.iter().filter(|failure| failure.error.is_retryable())
+    }
+
+    /// Every path along with the error of the part it was in. The error is shared by the paths of
+    /// a part, as it can't be cloned.
+    pub fn into_per_item(self) -> Vec<(PathBuf, Arc<Error>)> {
+        self.failures
+            .into_iter()
+            .flat_map(|failure| {
+                let error = Arc::new(failure.error);
+                failure.paths.into_iter().map(move |path| (path, Arc::clone(&error)))
+            })
+            .collect()
+    }
+}
+
+impl Error {
+    /// Whether the operation may succeed when it's tried again without changing its arguments,
+    /// as the failure is transient.
+    ///
+    /// Failures to access the trash, and failures of the operating system and the file system
+    /// because a file is in use by another process or a resource is busy, are retryable. So are
+    /// disconnections from the process that hosts the shell's COM objects on Windows. Everything
+    /// else, like invalid arguments, protected or missing items, full volumes, vetoes, collisions
+    /// and cancellations, has to be resolved first. A batch is retryable if any of its failures is.
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            Error::CouldNotAccessTrash { .. } => true,
+            Error::Os { code, .. } => is_transient_code(*code),
+            #[cfg(any(
+                target_os = "windows",
+                all(unix, not(target_os = "macos"), not(target_os = "ios"), not(target_os = "android"))
+            ))]
+            Error::FileSystem { source, .. } => source.raw_os_error().is_some_and(is_transient_code),
+            Error::Batch { errors } => errors.retryable().next().is_some(),
+            _ => false,
+        }
+    }
+}
+
+/// Whether a raw error code of the operating system stands for a condition that passes by itself,
+/// like a file that another process has open.
+fn is_transient_code(code: i32) -> bool {
+    #[cfg(windows)]
+    {
+        use windows::Win32::Foundation::{
+            ERROR_BUSY, ERROR_LOCK_VIOLATION, ERROR_SHARING_VIOLATION, RPC_E_DISCONNECTED, RPC_E_SERVER_DIED,
+            RPC_E_SERVER_DIED_DNE,
+        };
+        /// The RPC server isn't reachable, declared along with the RPC functions.
+        const RPC_S_SERVER_UNAVAILABLE: u32 = 1722;
+        // The `HRESULT`s of Win32 errors hold the error code in their lower half.
+        let win32 = match code as u32 {
+            hresult if hresult >> 16 == 0x8007 => hresult & 0xFFFF,
+            code => code,
+        };
+        [ERROR_SHARING_VIOLATION.0, ERROR_LOCK_VIOLATION.0, ERROR_BUSY.0, RPC_S_SERVER_UNAVAILABLE].contains(&win32)
+            || [RPC_E_DISCONNECTED.0, RPC_E_SERVER_DIED.0, RPC_E_SERVER_DIED_DNE.0].contains(&code)
+    }
+    #[cfg(not(windows))]
+    {
+        std::io::Error::from_raw_os_error(code).kind() == std::io::ErrorKind::ResourceBusy
+    }
+}
+
+/// Groups the failures by `key`, keeping the order in which the keys first occur.
+fn group<'a, K: PartialEq>(
+    failures: &'a [BatchFailure],
+    key: impl Fn(&'a BatchFailure) -> K,
+) -> Vec<(K, Vec<&'a BatchFailure>)> {
+    let mut groups: Vec<(K, Vec<&BatchFailure>)> = Vec::new();
+    for failure in failures {
+        let k = key(failure);
+        match groups.iter_mut().find(|(group, _)| *group == k) {
+            Some((_, group)) => group.push(failure),
+            None => groups.push((k, vec![failure])),
+        }
+    }
+    groups
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// An `EBUSY` error, or `ERROR_BUSY` as an `HRESULT` on Windows.
+    #[cfg(not(windows))]
+    const BUSY: i32 = 16;
+    #[cfg(windows)]
+    const BUSY: i32 = 0x800700AA_u32 as i32;
+    /// An `ENOSPC` error, or `ERROR_DISK_FULL` as an `HRESULT` on Windows.
+    #[cfg(not(windows))]
+    const FULL: i32 = 28;
+    #[cfg(windows)]
+    const FULL: i32 = 0x80070070_u32 as i32;
+
+    fn failure(volume: &str, paths: &[&str], error: Error) -> BatchFailure {
+        BatchFailure { volume: Some(PathBuf::from(volume)), paths: paths.iter().map(PathBuf::from).collect(), error }
+    }
+
+    #[test]
+    fn helpers() {
+        let errors = BatchErrors {
+            failures: vec![
+                failure("/a", &["/a/1", "/a/2"], Error::CouldNotAccessTrash { path: None, description: "gone".into() }),
+                failure("/b", &["/b/1"], Error::TargetedItemNotFound { path: "/b/1".into() }),
+                failure("/a", &["/a/3"], Error::Os { code: BUSY, function: None, description: "busy".into() }),
+            ],
+        };
+        assert!(!Error::Batch { errors: BatchErrors { failures: Vec::new() } }.is_retryable());
+        assert_eq!(errors.first_fatal().map(|failure| failure.paths.clone()), Some(vec![PathBuf::from("/b/1")]));
+        assert_eq!(errors.retryable().count(), 2);
+        assert!(!Error::Cancelled.is_retryable());
+        assert!(!Error::Os { code: FULL, function: None, description: "full".into() }.is_retryable());
+
+        let by_volume = errors.by_volume();
+        assert_eq!(by_volume.len(), 2);
+        assert_eq!(by_volume[0].0, Some(Path::new("/a")));
+        assert_eq!(by_volume[0].1.len(), 2);
+        assert_eq!(errors.by_cause().iter().map(Vec::len).collect::<Vec<_>>(), [1, 1, 1]);
+
+        let per_item = errors.into_per_item();
+        assert_eq!(per_item.len(), 4);
+        assert!(Arc::ptr_eq(&per_item[0].1, &per_item[1].1));
+        assert!(matches!(*per_item[2].1, Error::TargetedItemNotFound { .. }));
+    }
+
+    #[test]
+    #[cfg(any(
+        target_os = "windows",
+        all(unix, not(target_os = "macos"), not(target_os = "ios"), not(target_os = "android"))
+    ))]
+    fn single_failures_are_unwrapped() {
+        assert!(BatchErrors::combine(Vec::new()).is_none());
+        let single = BatchErrors::combine(vec![failure("/a", &["/a/1"], Error::Cancelled)]);
+        assert!(matches!(single, Some(Error::Cancelled)));
+        let both = vec![failure("/a", &["/a/1"], Error::Cancelled), failure("/b", &["/b/1"], Error::Cancelled)];
+        assert!(matches!(BatchErrors::combine(both), Some(Error::Batch { .. })));
+    }
+}
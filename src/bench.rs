@@ -0,0 +1,165 @@
+//! Workloads for measuring the performance of the trash operations, as used by the benchmarks of
+//! this crate.
+//!
+//! The benchmarks are run with `cargo bench --features bench`. The generators are public so that
+//! the same workloads can be measured from other harnesses, on the hardware and file systems an
+//! application runs on, and so that regressions can be reported with numbers that others can
+//! reproduce.
+//!
+//! Everything a [`Workload`] creates is put into a folder of its own, which is removed when it's
+//! dropped, along with the items that were moved to the trash from it, see [`Workload::purge`].
+//! On macOS, where items can't be purged through this crate, they stay in the trash.
+
+#[cfg(any(
+    target_os = "windows",
+    all(unix, not(target_os = "macos"), not(target_os = "ios"), not(target_os = "android"))
+))]
+use std::ffi::OsStr;
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use log::warn;
+
+use crate::ByteSize;
+#[cfg(any(
+    target_os = "windows",
+    all(unix, not(target_os = "macos"), not(target_os = "ios"), not(target_os = "android"))
+))]
+use crate::{Error, TrashItem, DEFAULT_TRASH_CTX};
+
+static NEXT_ID: AtomicUsize = AtomicUsize::new(0);
+
+/// A folder in which files, folders and trashed items are generated.
+///
+/// The folder should be on the volume that is to be measured, as moving items to the trash of
+/// another volume copies them.
+///
+/// # Example
+///
+/// ```
+/// use trash::{bench::Workload, ByteSize};
+///
+/// let workload = Workload::new(".").unwrap();
+/// let files = workload.files(10, ByteSize::kib(4)).unwrap();
+/// // Dropping the workload purges the files from the trash, which isn't possible on macOS.
+/// #[cfg(any(target_os = "windows", all(unix, not(target_os = "macos"))))]
+/// trash::delete_all(&files).unwrap();
+/// ```
+#[derive(Debug)]
+pub struct Workload {
+    dir: PathBuf,
+}
+
+impl Workload {
+    /// Creates a folder of its own for the workload in `parent`.
+    pub fn new<P: AsRef<Path>>(parent: P) -> io::Result<Self> {
+        let name = format!("trash-bench-{}-{}", std::process::id(), NEXT_ID.fetch_add(1, Ordering::Relaxed));
+        let dir = parent.as_ref().join(name);
+        fs::create_dir_all(&dir)?;
+        Ok(Workload { dir })
+    }
+
+    /// The folder of the workload.
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Creates `count` files of `size` bytes each and returns their paths.
+    pub fn files(&self, count: usize, size: ByteSize) -> io::Result<Vec<PathBuf>> {
+        let contents = vec![0x5a; usize::try_from(size.as_bytes()).unwrap_or(usize::MAX)];
+        (0..count).map(|_| self.create_file(&self.unique_name("file"), &contents)).collect()
+    }
+
+    /// Creates a chain of `depth` nested folders with `files_per_level` empty files in each of them
+    /// and returns the outermost folder.
+    pub fn deep_tree(&self, depth: usize, files_per_level: usize) -> io::Result<PathBuf> {
+        let root = self.dir.join(self.unique_name("tree"));
+        let mut level = root.clone();
+        for depth in 0..depth {
+            fs::create_dir_all(&level)?;
+            for file in 0..files_per_level {
+                fs::write(level.join(format!("file-{file}")), "")?;
+            }
+            level.push(format!("level-{depth}"));
+        }
+        fs::create_dir_all(&root)?;
+        Ok(root)
+    }
+
+    /// Creates `count` empty files whose names are `len` characters long and returns their paths.
+    /// Most file systems limit names to 255 bytes.
+    pub fn long_names(&self, count: usize, len: usize) -> io::Result<Vec<PathBuf>> {
+        (0..count)
+            .map(|_| {
+                let mut name = self.unique_name("long");
+                let padding = len.saturating_sub(name.len());
+                name.push_str(&"n".repeat(padding));
+                self.create_file(&name, &[])
+            })
+            .collect()
+    }
+
+    /// Moves `count` empty files to the trash and returns their items, like a Recycle Bin that
+    /// fills up over time.
+    #[cfg(any(
+        target_os = "windows",
+        all(unix, not(target_os = "macos"), not(target_os = "ios"), not(target_os = "android"))
+    ))]
+    pub fn synthetic_bin(&self, count: usize) -> Result<Vec<TrashItem>, Error> {
+        let files = self.files(count, ByteSize::ZERO).map_err(|e| crate::io_error(&self.dir, e))?;
+        DEFAULT_TRASH_CTX.delete_all(&files)?;
+        let names: Vec<_> = files.iter().filter_map(|file| file.file_name()).collect();
+        Ok(self.trashed()?.into_iter().filter(|item| names.contains(&OsStr::new(&item.name))).collect())
+    }
+
+    /// Returns the items in the trash that were deleted from the folder of the workload, but not
+    /// from folders within it.
+    #[cfg(any(
+        target_os = "windows",
+        all(unix, not(target_os = "macos"), not(target_os = "ios"), not(target_os = "android"))
+    ))]
+    pub fn trashed(&self) -> Result<Vec<TrashItem>, Error> {
+        let items = crate::os_limited::list()?;
+        // The name of the folder is unique, while its path may be spelled differently by the trash.
+        let in_dir =
+            |item: &TrashItem| item.original_parent.as_deref().and_then(Path::file_name) == self.dir.file_name();
+        Ok(items.into_iter().filter(in_dir).collect())
+    }
+
+    /// Purges the items in the trash that were deleted from the folder of the workload.
+    #[cfg(any(
+        target_os = "windows",
+        all(unix, not(target_os = "macos"), not(target_os = "ios"), not(target_os = "android"))
+    ))]
+    pub fn purge(&self) -> Result<(), Error> {
+        crate::os_limited::purge_all(self.trashed()?)
+    }
+
+    fn unique_name(&self, kind: &str) -> String {
+        format!("{kind}-{}", NEXT_ID.fetch_add(1, Ordering::Relaxed))
+    }
+
+    fn create_file(&self, name: &str, contents: &[u8]) -> io::Result<PathBuf> {
+        let path = self.dir.join(name);
+        fs::write(&path, contents)?;
+        Ok(path)
+    }
+}
+
+impl Drop for Workload {
+    fn drop(&mut self) {
+        #[cfg(any(
+            target_os = "windows",
+            all(unix, not(target_os = "macos"), not(target_os = "ios"), not(target_os = "android"))
+        ))]
+        if let Err(e) = self.purge() {
+            warn!("Failed to purge the items of the workload folder {:?}: {e:?}", self.dir);
+        }
+        if let Err(e) = fs::remove_dir_all(&self.dir) {
+            warn!("Failed to remove the workload folder {:?}: {e}", self.dir);
+        }
+    }
+}
@@ -0,0 +1,10 @@
+//! Performs trash operations on behalf of another process, see `trash::helper`.
+
+fn main() {
+    let stdin = std::io::stdin();
+    let stdout = std::io::stdout();
+    if let Err(e) = trash::helper::serve(&trash::TrashContext::default(), stdin.lock(), stdout.lock()) {
+        eprintln!("{e}");
+        std::process::exit(1);
+    }
+}
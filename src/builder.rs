@@ -0,0 +1,185 @@
+//! Building a [`TrashContext`] in a single expression.
+
+use std::path::PathBuf;
+
+use crate::{
+    CancellationToken, CaseSensitivity, DuplicatePaths, PathResolver, Preset, TrashContext, TrashHooks, TrashProgress,
+};
+#[cfg(any(
+    target_os = "windows",
+    all(unix, not(target_os = "macos"), not(target_os = "ios"), not(target_os = "android"))
+))]
+use crate::{DisplayLocale, MergePolicy, RestoreCollision, RestoreConcurrency};
+#[cfg(any(
+    target_os = "windows",
+    all(unix, not(target_os = "macos"), not(target_os = "ios"), not(target_os = "android"))
+))]
+use std::path::Path;
+
+/// Builds a [`TrashContext`], as returned by [`TrashContext::builder`].
+///
+/// Every method corresponds to a setter of `TrashContext` and starts out with the same default.
+/// Settings that don't exist on the current platform are left alone, like with [`Preset`].
+///
+/// # Example
+///
+/// ```
+/// use trash::{CaseSensitivity, TrashContext};
+///
+/// let ctx = TrashContext::builder().show_ui(true).case_sensitivity(CaseSensitivity::Insensitive).build();
+/// assert_eq!(ctx.case_sensitivity(), CaseSensitivity::Insensitive);
+/// ```
+#[derive(Clone, Debug, Default)]
+#[must_use]
+pub struct TrashContextBuilder {
+    ctx: TrashContext,
+}
+
+impl TrashContext {
+    /// Returns a builder for a context that starts out with the default settings.
+    pub fn builder() -> TrashContextBuilder {
+        TrashContextBuilder::default()
+    }
+}
+
+impl TrashContextBuilder {
+    /// Returns the context with the settings applied.
+    pub fn build(self) -> TrashContext {
+        self.ctx
+    }
+
+    /// See [`TrashContext::set_preset`]. Settings applied before are overridden by the preset.
+    pub fn preset(mut self, preset: Preset) -> Self {
+        self.ctx.set_preset(preset);
+        self
+    }
+
+    /// See [`TrashContext::set_continue_on_error`].
+    pub fn continue_on_error(mut self, continue_on_error: bool) -> Self {
+        self.ctx.set_continue_on_error(continue_on_error);
+        self
+    }
+
+    /// See [`TrashContext::set_case_sensitivity`].
+    pub fn case_sensitivity(mut self, case_sensitivity: CaseSensitivity) -> Self {
+        self.ctx.set_case_sensitivity(case_sensitivity);
+        self
+    }
+
+    /// See [`TrashContext::set_base_dir`].
+    pub fn base_dir(mut self, base_dir: impl Into<PathBuf>) -> Self {
+        self.ctx.set_base_dir(Some(base_dir.into()));
+        self
+    }
+
+    /// See [`TrashContext::set_allow_protected`].
+    pub fn allow_protected(mut self, allow_protected: bool) -> Self {
+        self.ctx.set_allow_protected(allow_protected);
+        self
+    }
+
+    /// See [`TrashContext::set_recursive`].
+    pub fn recursive(mut self, recursive: bool) -> Self {
+        self.ctx.set_recursive(recursive);
+        self
+    }
+
+    /// See [`TrashContext::set_duplicate_paths`].
+    pub fn duplicate_paths(mut self, duplicate_paths: DuplicatePaths) -> Self {
+        self.ctx.set_duplicate_paths(duplicate_paths);
+        self
+    }
+
+    /// See [`TrashContext::set_cancellation`].
+    pub fn cancellation(mut self, token: CancellationToken) -> Self {
+        self.ctx.set_cancellation(Some(token));
+        self
+    }
+
+    /// See [`TrashContext::with_hooks`].
+    pub fn hooks(mut self, hooks: impl TrashHooks + 'static) -> Self {
+        self.ctx = self.ctx.with_hooks(hooks);
+        self
+    }
+
+    /// See [`TrashContext::with_path_resolver`].
+    pub fn path_resolver(mut self, resolver: impl PathResolver + 'static) -> Self {
+        self.ctx = self.ctx.with_path_resolver(resolver);
+        self
+    }
+
+    /// See [`TrashContext::with_progress`].
+    pub fn progress(mut self, progress: impl TrashProgress + 'static) -> Self {
+        self.ctx = self.ctx.with_progress(progress);
+        self
+    }
+
+    /// See [`TrashContext::set_prescan`].
+    pub fn prescan(mut self, prescan: bool) -> Self {
+        self.ctx.set_prescan(prescan);
+        self
+    }
+
+    /// See [`TrashContext::with_shell_ui`].
+    pub fn show_ui(mut self, show_ui: bool) -> Self {
+        self.ctx = self.ctx.with_shell_ui(show_ui);
+        self
+    }
+}
+
+#[cfg(any(
+    target_os = "windows",
+    all(unix, not(target_os = "macos"), not(target_os = "ios"), not(target_os = "android"))
+))]
+impl TrashContextBuilder {
+    /// See [`TrashContext::set_purge_concurrency`].
+    pub fn purge_concurrency(mut self, threads: usize) -> Self {
+        self.ctx.set_purge_concurrency(threads);
+        self
+    }
+
+    /// See [`TrashContext::set_delete_concurrency`].
+    pub fn delete_concurrency(mut self, volumes: usize) -> Self {
+        self.ctx.set_delete_concurrency(volumes);
+        self
+    }
+
+    /// See [`TrashContext::set_restore_concurrency`].
+    pub fn restore_concurrency(mut self, concurrency: RestoreConcurrency) -> Self {
+        self.ctx.set_restore_concurrency(concurrency);
+        self
+    }
+
+    /// See [`TrashContext::set_restore_collision`].
+    pub fn restore_collision(mut self, collision: RestoreCollision) -> Self {
+        self.ctx.set_restore_collision(collision);
+        self
+    }
+
+    /// See [`TrashContext::set_directory_merge`].
+    pub fn directory_merge(mut self, policy: MergePolicy) -> Self {
+        self.ctx.set_directory_merge(Some(policy));
+        self
+    }
+
+    /// See [`TrashContext::set_display_locale`].
+    pub fn display_locale(mut self, locale: DisplayLocale) -> Self {
+        self.ctx.set_display_locale(locale);
+        self
+    }
+
+    /// See [`TrashContext::set_list_limit`].
+    pub fn list_limit(mut self, limit: usize) -> Self {
+        self.ctx.set_list_limit(Some(limit));
+        self
+    }
+
+    /// See [`TrashContext::with_restore_transform`].
+    pub fn restore_transform<F>(mut self, transform: F) -> Self
+    where
+        F: Fn(&Path) -> PathBuf + Send + Sync + 'static,
+    {
+        self.ctx = self.ctx.with_restore_transform(transform);
+        self
+    }
+}
@@ -0,0 +1,127 @@
+//! Sizes in bytes that can't be mixed up with other units or overflow when summed.
+
+use std::{fmt, iter::Sum, ops::Add};
+
+/// A number of bytes, as used for the sizes of items, the usage and capacity of trash folders and
+/// the free space of volumes.
+///
+/// Sizes are constructed from an explicit unit, so that kibibytes can't be passed where bytes are
+/// expected. Adding sizes with `+` or summing them saturates at [`ByteSize::MAX`] instead of
+/// overflowing, the `checked_*` methods report the overflow instead.
+///
+/// The size is serialized as a plain number of bytes. [`Display`](fmt::Display) formats it with
+/// the largest binary unit that keeps the value at or above 1, like `1.5 MiB`.
+///
+/// # Example
+///
+/// ```
+/// use trash::ByteSize;
+///
+/// let size = ByteSize::mib(1) + ByteSize::kib(512);
+/// assert_eq!(size.as_bytes(), 1_572_864);
+/// assert_eq!(size.to_string(), "1.5 MiB");
+/// assert_eq!(ByteSize::MAX.checked_add(ByteSize::new(1)), None);
+/// ```
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize), serde(transparent))]
+pub struct ByteSize(u64);
+
+impl ByteSize {
+    pub const ZERO: ByteSize = ByteSize(0);
+    pub const MAX: ByteSize = ByteSize(u64::MAX);
+
+    /// A size of `bytes` bytes.
+    pub const fn new(bytes: u64) -> Self {
+        ByteSize(bytes)
+    }
+
+    /// A size of `kib` kibibytes (1024 bytes), saturating at [`ByteSize::MAX`].
+    pub const fn kib(kib: u64) -> Self {
+        ByteSize(kib.saturating_mul(1 << 10))
+    }
+
+    /// A size of `mib` mebibytes (1024 kibibytes), saturating at [`ByteSize::MAX`].
+    pub const fn mib(mib: u64) -> Self {
+        ByteSize(mib.saturating_mul(1 << 20))
+    }
+
+    /// A size of `gib` gibibytes (1024 mebibytes), saturating at [`ByteSize::MAX`].
+    pub const fn gib(gib: u64) -> Self {
+        ByteSize(gib.saturating_mul(1 << 30))
+    }
+
+    /// The size in bytes.
+    pub const fn as_bytes(self) -> u64 {
+        self.0
+    }
+
+    pub const fn checked_add(self, other: ByteSize) -> Option<ByteSize> {
+        match self.0.checked_add(other.0) {
+            Some(bytes) => Some(ByteSize(bytes)),
+            None => None,
+        }
+    }
+
+    pub const fn checked_sub(self, other: ByteSize) -> Option<ByteSize> {
+        match self.0.checked_sub(other.0) {
+            Some(bytes) => Some(ByteSize(bytes)),
+            None => None,
+        }
+    }
+
+    pub const fn saturating_add(self, other: ByteSize) -> ByteSize {
+        ByteSize(self.0.saturating_add(other.0))
+    }
+
+    pub const fn saturating_sub(self, other: ByteSize) -> ByteSize {
+        ByteSize(self.0.saturating_sub(other.0))
+    }
+}
+
+impl From<ByteSize> for u64 {
+    fn from(size: ByteSize) -> u64 {
+        size.0
+    }
+}
+
+impl Add for ByteSize {
+    type Output = ByteSize;
+
+    /// Adds the sizes, saturating at [`ByteSize::MAX`].
+    fn add(self, other: ByteSize) -> ByteSize {
+        self.saturating_add(other)
+    }
+}
+
+impl Sum for ByteSize {
+    fn sum<I: Iterator<Item = ByteSize>>(iter: I) -> ByteSize {
+        iter.fold(ByteSize::ZERO, Add::add)
+    }
+}
+
+impl<'a> Sum<&'a ByteSize> for ByteSize {
+    fn sum<I: Iterator<Item = &'a ByteSize>>(iter: I) -> ByteSize {
+        iter.copied().sum()
+    }
+}
+
+impl fmt::Display for ByteSize {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        const UNITS: [&str; 6] = ["KiB", "MiB", "GiB", "TiB", "PiB", "EiB"];
+
+        if self.0 < 1024 {
+            return write!(f, "{} B", self.0);
+        }
+        let mut value = self.0 as f64;
+        let mut unit = UNITS[0];
+        for next in UNITS {
+            value /= 1024.0;
+            unit = next;
+            if value < 1024.0 {
+                break;
+            }
+        }
+        let value = format!("{value:.1}");
+        write!(f, "{} {unit}", value.strip_suffix(".0").unwrap_or(&value))
+    }
+}
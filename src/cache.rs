@@ -0,0 +1,139 @@
+//! Keeping an in-memory copy of the trash up to date.
+
+use std::{
+    collections::HashSet,
+    ffi::OsStr,
+    path::Path,
+    sync::{Arc, RwLock, RwLockReadGuard, RwLockWriteGuard},
+    time::Duration,
+};
+
+use crate::{
+    listing_order,
+    matching::{self, CaseSensitivity},
+    platform,
+    snapshot::TrashDiff,
+    watch::{self, TrashWatcher},
+    Error, TrashItem,
+};
+
+/// A copy of the trash that is kept in memory and updated in the background from the changes
+/// reported by [`watch`](crate::os_limited::watch).
+///
+/// Reading from the cache doesn't touch the file system or the shell, which makes it suitable for
+/// applications that keep showing the contents of the trash. Changes show up in the cache shortly
+/// after they happen, usually within a fraction of a second. Use [`refresh`](TrashCache::refresh)
+/// to pick up a change immediately, for example right after deleting an item.
+///
+/// # Example
+///
+/// ```no_run
+/// use trash::os_limited::TrashCache;
+///
+/// let cache = TrashCache::new().unwrap();
+/// println!("{} items in the trash", cache.stats().items);
+/// for item in cache.list() {
+///     println!("{}", item.name);
+/// }
+/// ```
+#[derive(Debug)]
+pub struct TrashCache {
+    items: Arc<RwLock<Vec<TrashItem>>>,
+    case_sensitivity: CaseSensitivity,
+    _watcher: TrashWatcher,
+}
+
+/// A summary of the contents of a [`TrashCache`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TrashStats {
+    /// The number of items in the trash.
+    pub items: usize,
+    /// The deletion time of the item that has been in the trash the longest, see
+    /// [`TrashItem::time_deleted`].
+    pub oldest: Option<i64>,
+    /// The deletion time of the item that was put into the trash last.
+    pub newest: Option<i64>,
+}
+
+impl TrashCache {
+    /// Lists the trash and starts following its changes.
+    pub fn new() -> Result<Self, Error> {
+        let items = Arc::new(RwLock::new(Vec::new()));
+        // Changes reported before the initial listing is stored have to wait for the lock.
+        let mut guard = write(&items);
+        let watched_items = items.clone();
+        let (watcher, initial) = watch::start(Duration::ZERO, move |diff, _| apply(&mut write(&watched_items), &diff))?;
+        *guard = initial;
+        guard.sort_by(listing_order);
+        drop(guard);
+        Ok(TrashCache { items, case_sensitivity: CaseSensitivity::default(), _watcher: watcher })
+    }
+
+    /// Sets how [`find`](TrashCache::find) compares paths. Defaults to [`CaseSensitivity::Auto`].
+    pub fn set_case_sensitivity(&mut self, case_sensitivity: CaseSensitivity) {
+        self.case_sensitivity = case_sensitivity;
+    }
+
+    /// See [`set_case_sensitivity`](TrashCache::set_case_sensitivity).
+    pub fn case_sensitivity(&self) -> CaseSensitivity {
+        self.case_sensitivity
+    }
+
+    /// Returns all items in the trash, in the same order as [`list`](crate::os_limited::list).
+    pub fn list(&self) -> Vec<TrashItem> {
+        read(&self.items).clone()
+    }
+
+    /// Returns the items that were originally located at `original_path`, ordered by their
+    /// deletion time.
+    ///
+    /// See: [`TrashContext::find`](crate::TrashContext::find)
+    pub fn find<T: AsRef<Path>>(&self, original_path: T) -> Result<Vec<TrashItem>, Error> {
+        matching::find_in(read(&self.items).iter(), original_path.as_ref(), self.case_sensitivity)
+    }
+
+    /// Returns the item with the given id, if it's in the trash.
+    pub fn get(&self, id: &OsStr) -> Option<TrashItem> {
+        read(&self.items).iter().find(|item| item.id == id).cloned()
+    }
+
+    /// Summarizes the contents of the trash.
+    pub fn stats(&self) -> TrashStats {
+        let items = read(&self.items);
+        TrashStats {
+            items: items.len(),
+            oldest: items.iter().filter_map(|item| item.time_deleted).min(),
+            newest: items.iter().filter_map(|item| item.time_deleted).max(),
+        }
+    }
+
+    /// Lists the trash right away instead of waiting for the background update.
+    pub fn refresh(&self) -> Result<(), Error> {
+        let mut items = platform::list()?;
+        items.sort_by(listing_order);
+        *write(&self.items) = items;
+        Ok(())
+    }
+}
+
+/// Applies the changes to `items`, which are in [`listing_order`]. Changes that are already
+/// reflected are ignored, as the cache may have been refreshed in the meantime.
+fn apply(items: &mut Vec<TrashItem>, diff: &TrashDiff) {
+    let removed: HashSet<&OsStr> = diff.removed.iter().map(|id| id.as_os_str()).collect();
+    items.retain(|item| !removed.contains(item.id.as_os_str()));
+    for item in &diff.added {
+        if let Err(index) = items.binary_search_by(|probe| listing_order(probe, item)) {
+            items.insert(index, item.clone());
+        }
+    }
+}
+
+// The lock is never held while running user code, so it can't be poisoned in a harmful way.
+fn read(items: &RwLock<Vec<TrashItem>>) -> RwLockReadGuard<'_, Vec<TrashItem>> {
+    items.read().unwrap_or_else(|e| e.into_inner())
+}
+
+fn write(items: &RwLock<Vec<TrashItem>>) -> RwLockWriteGuard<'_, Vec<TrashItem>> {
+    items.write().unwrap_or_else(|e| e.into_inner())
+}
@@ -0,0 +1,74 @@
+//! Stopping batch operations before they're done.
+
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
+
+use crate::{Error, TrashContext};
+
+/// Lets another thread, like the UI thread of an application, stop the batch operations of a
+/// [`TrashContext`] part way through.
+///
+/// Once cancelled, operations stop before the next item and fail with [`Error::Cancelled`]. The
+/// items that were handled before stay where they were moved to, items that were in progress are
+/// either finished or left untouched. The token stays cancelled, so it should be replaced with a
+/// new one for the next operation.
+///
+/// # Example
+///
+/// ```
+/// use trash::{CancellationToken, TrashContext};
+///
+/// let token = CancellationToken::new();
+/// let ctx = TrashContext::builder().cancellation(token.clone()).build();
+/// // From the thread that handles the cancel button:
+/// token.cancel();
+/// assert!(matches!(ctx.delete_all(["a", "b"]), Err(trash::Error::Cancelled)));
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct CancellationToken(Arc<AtomicBool>);
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Asks the operations that use this token to stop.
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+impl TrashContext {
+    /// Sets the token that stops the delete, restore and purge operations of this context when
+    /// it's cancelled. See [`CancellationToken`] for details.
+    ///
+    /// On Windows the shell is asked to abort the items it didn't start on yet. On other platforms
+    /// the token is checked between items. On freedesktop systems an item whose trash info file was
+    /// already written when the token was cancelled is left in place, and the info file is removed.
+    pub fn set_cancellation(&mut self, token: Option<CancellationToken>) {
+        self.cancellation = token;
+    }
+
+    /// See [`set_cancellation`](TrashContext::set_cancellation).
+    pub fn cancellation(&self) -> Option<&CancellationToken> {
+        self.cancellation.as_ref()
+    }
+
+    pub(crate) fn is_cancelled(&self) -> bool {
+        self.cancellation.as_ref().is_some_and(CancellationToken::is_cancelled)
+    }
+
+    /// Fails with [`Error::Cancelled`] if the token of the context was cancelled.
+    pub(crate) fn check_cancelled(&self) -> Result<(), Error> {
+        match self.is_cancelled() {
+            true => Err(Error::Cancelled),
+            false => Ok(()),
+        }
+    }
+}
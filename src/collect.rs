@@ -0,0 +1,76 @@
+//! Batch operations that report the outcome of every item instead of the first failure.
+
+use std::path::Path;
+
+#[cfg(any(
+    target_os = "windows",
+    all(unix, not(target_os = "macos"), not(target_os = "ios"), not(target_os = "android"))
+))]
+use crate::TrashItem;
+use crate::{Error, TrashContext};
+
+impl TrashContext {
+    /// Like [`delete_all`](TrashContext::delete_all), but moves every path to the trash on its
+    /// own and returns one result for each path, in the order of `paths`.
+    ///
+    /// A path that fails doesn't keep the others from being moved. As the paths are handled one
+    /// by one, this is slower than `delete_all` for large batches, and the [`TrashHooks`] and the
+    /// progress callback see a batch of one item for every path. A path that refers to an item
+    /// that an earlier path already moved fails, as the item is gone by then.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use std::fs::File;
+    /// use trash::delete_all_collect;
+    /// File::create("delete_collect_me").unwrap();
+    /// let results = delete_all_collect(["delete_collect_me", "delete_collect_missing"]);
+    /// assert!(results[0].is_ok());
+    /// assert!(results[1].is_err());
+    /// ```
+    ///
+    /// [`TrashHooks`]: crate::TrashHooks
+    pub fn delete_all_collect<I, T>(&self, paths: I) -> Vec<Result<(), Error>>
+    where
+        I: IntoIterator<Item = T>,
+        T: AsRef<Path>,
+    {
+        paths.into_iter().map(|path| self.delete(path)).collect()
+    }
+
+    /// Like [`purge_all`](TrashContext::purge_all), but deletes every item on its own and returns
+    /// one result for each item, in the order of `items`.
+    ///
+    /// A failing item doesn't keep the others from being deleted, see
+    /// [`delete_all_collect`](TrashContext::delete_all_collect) for the costs of that.
+    #[cfg(any(
+        target_os = "windows",
+        all(unix, not(target_os = "macos"), not(target_os = "ios"), not(target_os = "android"))
+    ))]
+    pub fn purge_all_collect<I>(&self, items: I) -> Vec<Result<(), Error>>
+    where
+        I: IntoIterator,
+        <I as IntoIterator>::Item: std::borrow::Borrow<TrashItem>,
+    {
+        items.into_iter().map(|item| self.purge_all([item])).collect()
+    }
+
+    /// Like [`restore_all`](TrashContext::restore_all), but restores every item on its own and
+    /// returns one result for each item, in the order of `items`.
+    ///
+    /// A failing item doesn't keep the others from being restored, see
+    /// [`delete_all_collect`](TrashContext::delete_all_collect) for the costs of that. Items that
+    /// would be restored to the same path aren't refused up front with
+    /// [`Error::RestoreTwins`], the first one is restored and the others fail with
+    /// [`Error::RestoreCollision`].
+    #[cfg(any(
+        target_os = "windows",
+        all(unix, not(target_os = "macos"), not(target_os = "ios"), not(target_os = "android"))
+    ))]
+    pub fn restore_all_collect<I>(&self, items: I) -> Vec<Result<(), Error>>
+    where
+        I: IntoIterator<Item = TrashItem>,
+    {
+        items.into_iter().map(|item| self.restore_all([item])).collect()
+    }
+}
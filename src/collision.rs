@@ -0,0 +1,103 @@
+//! Deciding what happens when an item is restored to a path that is taken.
+
+use std::{
+    ffi::OsString,
+    io,
+    path::{Path, PathBuf},
+};
+
+use crate::{Error, TrashContext};
+
+/// What [`restore_all`](TrashContext::restore_all) does with an item whose original path is
+/// taken by another file or folder.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum RestoreCollision {
+    /// Fail with [`Error::RestoreCollision`](crate::Error::RestoreCollision), which holds the
+    /// items that weren't restored yet.
+    #[default]
+    Fail,
+    /// Move what is at the original path to the trash and restore the item in its place.
+    Overwrite,
+    /// Restore the item next to what is at the original path, under the first free name of the
+    /// form `name (2).ext`, like the Windows shell does.
+    RenameWithSuffix,
+    /// Leave the item in the trash and carry on with the remaining items.
+    Skip,
+}
+
+impl TrashContext {
+    /// Sets what restoring does with items whose original path is taken. Defaults to
+    /// [`RestoreCollision::Fail`].
+    ///
+    /// Collisions are detected before an item is moved, an item that appears at the original
+    /// path at the same time may still be overwritten on Windows, where the shell moves the
+    /// items.
+    pub fn set_restore_collision(&mut self, collision: RestoreCollision) {
+        self.restore_collision = collision;
+    }
+
+    /// See [`set_restore_collision`](TrashContext::set_restore_collision).
+    pub fn restore_collision(&self) -> RestoreCollision {
+        self.restore_collision
+    }
+}
+
+/// The first path of the form `name (n).ext`, counting from 2, at which nothing exists.
+pub(crate) fn free_path(path: &Path) -> PathBuf {
+    let stem = path.file_stem().unwrap_or(path.as_os_str());
+    let extension = path.extension().filter(|_| path.file_stem().is_some());
+    (2..)
+        .map(|n| {
+            let mut name = OsString::from(stem);
+            name.push(format!(" ({n})"));
+            if let Some(extension) = extension {
+                name.push(".");
+                name.push(extension);
+            }
+            path.with_file_name(name)
+        })
+        .find(|candidate| candidate.symlink_metadata().is_err())
+        .unwrap_or_else(|| path.to_owned())
+}
+
+/// Moves the file, folder or symbolic link at `path` to the trash with the settings of `ctx`, so
+/// that an item can be restored in its place. Returns whether `path` is free afterwards, which it
+/// isn't when the delete left it alone, for example because a hook skipped it.
+pub(crate) fn trash_existing(ctx: &TrashContext, path: &Path) -> Result<bool, Error> {
+    if path.symlink_metadata().is_err() {
+        return Ok(true);
+    }
+    ctx.delete(path)?;
+    Ok(path.symlink_metadata().is_err())
+}
+
+/// Removes the file, folder or symbolic link at `path`, without following links. Only used for
+/// what the crate itself created or moved, items of the user are moved to the trash with
+/// [`trash_existing`].
+pub(crate) fn remove_existing(path: &Path) -> io::Result<()> {
+    match path.symlink_metadata() {
+        Ok(metadata) if metadata.is_dir() => std::fs::remove_dir_all(path),
+        Ok(_) => std::fs::remove_file(path),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(()),
+        Err(e) => Err(e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn free_paths() {
+        let dir = std::env::temp_dir().join(format!("trash-free-path-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("a.txt"), "").unwrap();
+        assert_eq!(free_path(&dir.join("a.txt")), dir.join("a (2).txt"));
+        std::fs::write(dir.join("a (2).txt"), "").unwrap();
+        assert_eq!(free_path(&dir.join("a.txt")), dir.join("a (3).txt"));
+        assert_eq!(free_path(&dir.join(".hidden")), dir.join(".hidden (2)"));
+        assert_eq!(free_path(&dir.join("folder")), dir.join("folder (2)"));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
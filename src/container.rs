@@ -0,0 +1,71 @@
+//! Detecting whether the process runs in a container.
+//!
+//! Within a container, the trash folders that the freedesktop specification selects can end up in
+//! places that the user doesn't expect. The root file system is usually the writable layer of an
+//! overlay, which is discarded with the container or baked into an image, and the home folder may
+//! be mounted from the host with files that belong to another user.
+
+use std::path::Path;
+
+use once_cell::sync::Lazy;
+
+/// The file system types that stack a writable layer onto the layers of an image.
+const LAYERED_FILE_SYSTEMS: &[&str] = &["overlay", "aufs", "fuse-overlayfs"];
+
+/// The name of the container runtime that the process runs in, or `None` if it doesn't seem to run
+/// in a container. This is detected once per process.
+pub(crate) fn runtime() -> Option<&'static str> {
+    static RUNTIME: Lazy<Option<String>> = Lazy::new(detect);
+    RUNTIME.as_deref()
+}
+
+fn detect() -> Option<String> {
+    if Path::new("/.dockerenv").exists() {
+        return Some("docker".into());
+    }
+    if Path::new("/run/.containerenv").exists() {
+        return Some("podman".into());
+    }
+    // Set by systemd-nspawn, LXC and others, following the convention of systemd.
+    if let Some(name) = std::env::var_os("container").filter(|name| !name.is_empty()) {
+        return Some(name.to_string_lossy().into_owned());
+    }
+    let cgroup = std::fs::read_to_string("/proc/1/cgroup").ok()?;
+    runtime_from_cgroup(&cgroup).map(Into::into)
+}
+
+/// Finds the runtime in the cgroup paths of the init process, which are named after the runtime
+/// by most of them.
+fn runtime_from_cgroup(cgroup: &str) -> Option<&'static str> {
+    let paths = cgroup.lines().filter_map(|line| line.splitn(3, ':').nth(2));
+    for path in paths {
+        for (marker, runtime) in
+            [("docker", "docker"), ("kubepods", "kubernetes"), ("libpod", "podman"), ("lxc", "lxc")]
+        {
+            if path.contains(marker) {
+                return Some(runtime);
+            }
+        }
+    }
+    None
+}
+
+/// Whether a file system of type `mnt_type` holds the writable layer of a container.
+pub(crate) fn is_layered(mnt_type: &str) -> bool {
+    LAYERED_FILE_SYSTEMS.contains(&mnt_type)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn runtimes_from_cgroups() {
+        assert_eq!(runtime_from_cgroup("0::/system.slice/docker-0123abcd.scope\n"), Some("docker"));
+        assert_eq!(runtime_from_cgroup("12:pids:/kubepods/besteffort/pod1/abc\n0::/\n"), Some("kubernetes"));
+        assert_eq!(runtime_from_cgroup("0::/machine.slice/libpod-0123.scope/container\n"), Some("podman"));
+        assert_eq!(runtime_from_cgroup("0::/init.scope\n"), None);
+        assert_eq!(runtime_from_cgroup("9:name=systemd:/\n0::/\n"), None);
+        assert!(is_layered("overlay") && !is_layered("ext4"));
+    }
+}
@@ -0,0 +1,37 @@
+//! Detecting the type of the content of the items in the trash.
+
+use crate::{platform, Error, TrashItem};
+
+/// The MIME type of items whose type isn't known.
+pub(crate) const UNKNOWN: &str = "application/octet-stream";
+/// The MIME type of folders.
+#[cfg(not(target_os = "macos"))]
+pub(crate) const DIRECTORY: &str = "inode/directory";
+
+impl TrashItem {
+    /// Returns the MIME type of the item, like `image/png`, or `application/octet-stream` if the
+    /// type isn't known. Folders are `inode/directory`.
+    ///
+    /// On Windows, the type is the content type the shell associates with the extension of the
+    /// item. On macOS, the type is derived from the uniform type identifier of the extension. On
+    /// freedesktop systems, the type is looked up in the shared MIME-info database, by the name of
+    /// the item first and by its first bytes if the name doesn't tell, like `xdg-mime` does.
+    ///
+    /// Requires the `mime` feature.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # #[cfg(not(target_os = "macos"))]
+    /// # {
+    /// let items = trash::os_limited::list().unwrap();
+    /// let images = items.iter().filter(|item| item.content_type().is_ok_and(|t| t.starts_with("image/")));
+    /// for image in images {
+    ///     println!("{}", image.name);
+    /// }
+    /// # }
+    /// ```
+    pub fn content_type(&self) -> Result<String, Error> {
+        platform::content_type(self)
+    }
+}
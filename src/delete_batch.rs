@@ -0,0 +1,72 @@
+//! Moving items from several volumes to the trash at the same time.
+
+use std::{collections::HashMap, path::PathBuf};
+
+use log::debug;
+
+use crate::{
+    parallel,
+    restore_batch::{volume_of, volume_root, VolumeId},
+    BatchErrors, BatchFailure, Error, TrashContext,
+};
+
+impl TrashContext {
+    /// Sets how many volumes [`delete_all`](TrashContext::delete_all) moves items from at the same
+    /// time. Defaults to 4, `1` handles one volume after the other on the calling thread.
+    ///
+    /// The paths of a batch are grouped by the volume they are on, and every group is moved to the
+    /// trash of its volume in an operation of its own. A failure on one volume, like a full or
+    /// read-only trash, then doesn't keep the items on the other volumes from being moved when
+    /// [`continue_on_error`](TrashContext::continue_on_error) is set. Otherwise no further groups
+    /// are started after the first failure. On Windows, batches are only split while the UI is
+    /// hidden, so that there's a single progress dialog.
+    pub fn set_delete_concurrency(&mut self, volumes: usize) {
+        self.delete_concurrency = volumes;
+    }
+
+    /// See [`set_delete_concurrency`](TrashContext::set_delete_concurrency).
+    pub fn delete_concurrency(&self) -> usize {
+        self.delete_concurrency
+    }
+
+    /// Moves the paths to the trash grouped by their volume, according to the
+    /// [`delete_concurrency`](TrashContext::delete_concurrency). Returns the error of the group
+    /// that failed, or [`Error::Batch`] if several did.
+    pub(crate) fn delete_by_volume(&self, full_paths: Vec<PathBuf>) -> Result<(), Error> {
+        #[cfg(target_os = "windows")]
+        if crate::windows::TrashContextExtWindows::show_ui(self) {
+            return self.delete_all_canonicalized(full_paths);
+        }
+        let mut groups = group_by_volume(full_paths);
+        if groups.len() <= 1 {
+            return self.delete_all_canonicalized(groups.pop().unwrap_or_default());
+        }
+        debug!("Moving the items of {} volumes to the trash", groups.len());
+        let errors = parallel::run(&groups, self.delete_concurrency, !self.continue_on_error, |group| {
+            self.delete_all_canonicalized(group.clone())
+        });
+        let failures = errors
+            .into_iter()
+            .map(|(index, error)| {
+                let paths = std::mem::take(&mut groups[index]);
+                let volume = paths.first().and_then(|path| path.parent()).and_then(volume_root);
+                BatchFailure { volume, paths, error }
+            })
+            .collect();
+        BatchErrors::combine(failures).map_or(Ok(()), Err)
+    }
+}
+
+/// Groups the paths by the volume they are on, keeping their order within a group.
+fn group_by_volume(full_paths: Vec<PathBuf>) -> Vec<Vec<PathBuf>> {
+    let mut groups: Vec<Vec<PathBuf>> = Vec::new();
+    let mut volumes: HashMap<Option<VolumeId>, usize> = HashMap::new();
+    for path in full_paths {
+        let index = *volumes.entry(volume_of(&path)).or_insert_with(|| {
+            groups.push(Vec::new());
+            groups.len() - 1
+        });
+        groups[index].push(path);
+    }
+    groups
+}
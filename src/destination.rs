@@ -0,0 +1,149 @@
+//! Describes where an item would end up when it's moved to the trash, without moving it.
+
+use std::path::{Path, PathBuf};
+
+use crate::{ByteSize, Error};
+
+/// How an item gets into the trash.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum TrashTransfer {
+    /// The item is renamed into the trash, which is cheap regardless of its size.
+    Rename,
+    /// The trash is on a different device than the item, so its contents need to be copied over.
+    Copy,
+    /// The item can't be put into the trash and the platform deletes it permanently instead. This
+    /// is the case for items on network drives on Windows, and for items that are larger than the
+    /// maximum size of the Recycle Bin. Such items are refused with
+    /// [`Error::CouldNotTrashWouldDelete`](crate::Error::CouldNotTrashWouldDelete) unless that's
+    /// allowed with `TrashContextExtWindows::set_allow_permanent_delete`. See
+    /// [`TrashHooks::pre_bypass_trash`](crate::TrashHooks::pre_bypass_trash) to be asked before that
+    /// happens.
+    PermanentDelete,
+}
+
+/// Where an item would be moved to by [`TrashContext::delete`](crate::TrashContext::delete).
+///
+/// Obtained from [`destination_for`](crate::destination_for).
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TrashDestination {
+    /// The trash folder the item would be moved into. It doesn't necessarily exist yet, in which
+    /// case it's created by the delete operation.
+    pub trash_root: PathBuf,
+    /// The root of the volume (mount point or drive) that contains the item.
+    pub volume: PathBuf,
+    /// Whether the item would be renamed or copied into the trash.
+    pub transfer: TrashTransfer,
+    /// The total size of the item, including the contents of directories.
+    ///
+    /// This is an estimate as entries that can't be read are skipped.
+    pub bytes: ByteSize,
+}
+
+/// What [`TrashContext::delete_all`](crate::TrashContext::delete_all) would move to the trash.
+///
+/// Obtained from [`estimate`](crate::estimate).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct OperationEstimate {
+    /// The number of paths that would be deleted, after removing the duplicates and the paths
+    /// within other paths of the batch.
+    pub paths: usize,
+    /// The number of files, folders and links, including the items themselves.
+    pub entries: u64,
+    /// The number of folders among the entries.
+    pub directories: u64,
+    /// The total size of the files.
+    pub bytes: ByteSize,
+    /// Whether the contents of any of the items would be copied into a trash on another device,
+    /// see [`TrashTransfer::Copy`].
+    pub needs_copy: bool,
+    /// Whether any of the items would be deleted permanently instead, see
+    /// [`TrashTransfer::PermanentDelete`]. On Windows, such items are refused rather than deleted
+    /// unless that's allowed with `TrashContextExtWindows::set_allow_permanent_delete`, so this is
+    /// only set when it is.
+    pub deletes_permanently: bool,
+}
+
+/// Adds the entries within the item at `path` to `estimate`, not following symbolic links.
+pub(crate) fn count_into(path: &Path, estimate: &mut OperationEstimate) -> Result<(), Error> {
+    path.symlink_metadata().map_err(|_| Error::CouldNotAccess { target: path.to_string_lossy().into_owned() })?;
+    let mut pending = vec![path.to_owned()];
+    while let Some(path) = pending.pop() {
+        let Ok(metadata) = path.symlink_metadata() else {
+            log::debug!("Skipping {:?} while counting it", path);
+            continue;
+        };
+        estimate.entries += 1;
+        if !metadata.is_dir() {
+            estimate.bytes = estimate.bytes + ByteSize::new(metadata.len());
+            continue;
+        }
+        estimate.directories += 1;
+        match std::fs::read_dir(&path) {
+            Ok(entries) => pending.extend(entries.flatten().map(|entry| entry.path())),
+            Err(e) => log::debug!("Skipping the contents of {:?} while counting them: {}", path, e),
+        }
+    }
+    Ok(())
+}
+
+/// Returns the total size of the item at `path`, not following symbolic links.
+pub(crate) fn total_size(path: &Path) -> Result<ByteSize, Error> {
+    size_up_to(path, ByteSize::MAX)
+}
+
+/// Whether the total size of the item at `path` is larger than `limit`. Only as many files are
+/// looked at as it takes to exceed the limit.
+#[cfg_attr(not(target_os = "windows"), allow(dead_code))]
+pub(crate) fn exceeds_size(path: &Path, limit: ByteSize) -> bool {
+    size_up_to(path, limit).is_ok_and(|size| size > limit)
+}
+
+/// Adds up the sizes of the files within the item at `path` until the total exceeds `limit`.
+fn size_up_to(path: &Path, limit: ByteSize) -> Result<ByteSize, Error> {
+    let metadata =
+        path.symlink_metadata().map_err(|_| Error::CouldNotAccess { target: path.to_string_lossy().into_owned() })?;
+    if !metadata.is_dir() {
+        return Ok(ByteSize::new(metadata.len()));
+    }
+    let mut total = ByteSize::ZERO;
+    let mut pending = vec![path.to_owned()];
+    while let Some(dir) = pending.pop() {
+        let entries = match std::fs::read_dir(&dir) {
+            Ok(entries) => entries,
+            Err(e) => {
+                log::debug!("Skipping {:?} while computing its size: {}", dir, e);
+                continue;
+            }
+        };
+        for entry in entries.flatten() {
+            match entry.metadata() {
+                Ok(metadata) if metadata.is_dir() => pending.push(entry.path()),
+                Ok(metadata) => total = total + ByteSize::new(metadata.len()),
+                Err(e) => log::debug!("Skipping {:?} while computing its size: {}", entry.path(), e),
+            }
+            if total > limit {
+                return Ok(total);
+            }
+        }
+    }
+    Ok(total)
+}
+
+/// Whether moving `src` to `trash_root` is a rename or a copy, based on the devices they reside on.
+///
+/// The trash root may not exist yet, in which case its closest existing ancestor is used.
+#[cfg(unix)]
+pub(crate) fn transfer_between(src: &Path, trash_root: &Path) -> TrashTransfer {
+    use std::os::unix::fs::MetadataExt;
+
+    let src_device = src.symlink_metadata().map(|m| m.dev()).ok();
+    let trash_device = trash_root.ancestors().find_map(|p| p.metadata().ok()).map(|m| m.dev());
+    if src_device.is_some() && src_device == trash_device {
+        TrashTransfer::Rename
+    } else {
+        TrashTransfer::Copy
+    }
+}
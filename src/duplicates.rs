@@ -0,0 +1,140 @@
+//! Handling paths that are passed more than once to a single delete operation.
+
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
+use log::debug;
+
+use crate::{matching, Error, TrashContext};
+
+/// What [`delete_all`](TrashContext::delete_all) and
+/// [`delete_all_permanently`](TrashContext::delete_all_permanently) do with a path that refers to
+/// the same item as an earlier path of the batch.
+///
+/// Paths are compared after they were resolved, see [`TrashContext::resolve_path`], so paths that
+/// reach the item through a symbolic link to one of its parent folders are duplicates. A symbolic
+/// link to the item itself is not, since the link is deleted rather than its target. Neither are
+/// hard links, which are separate entries that are each moved to the trash. On case-insensitive
+/// file systems paths that only differ in case are duplicates when they refer to the same file.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum DuplicatePaths {
+    /// Delete the item once, for the first path that refers to it.
+    #[default]
+    Skip,
+    /// Fail with [`Error::DuplicatePath`] before anything is deleted.
+    Fail,
+    /// Delete the item for every path, which fails for every path after the first as the item is
+    /// gone by then. This was the behaviour before duplicates were detected.
+    Keep,
+}
+
+/// Why a path was recognized as a duplicate, see [`DuplicatePath`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum DuplicateKind {
+    /// Both paths resolve to the same path.
+    Identical,
+    /// The paths only differ in case and refer to the same file, on a case-insensitive file
+    /// system.
+    CaseVariant,
+}
+
+/// A path that refers to the same item as an earlier path of the same batch.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DuplicatePath {
+    /// The resolved duplicate.
+    pub path: PathBuf,
+    /// The resolved path that referred to the item first.
+    pub duplicate_of: PathBuf,
+    pub kind: DuplicateKind,
+}
+
+/// The paths of a batch after resolving them and handling their duplicates, as returned by
+/// [`TrashContext::normalize_paths`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct NormalizedPaths {
+    /// The resolved paths that the operation deletes, in the order they were passed.
+    pub paths: Vec<PathBuf>,
+    /// The duplicates that were found, whether they were skipped or kept.
+    pub duplicates: Vec<DuplicatePath>,
+}
+
+impl TrashContext {
+    /// Sets what [`delete_all`](TrashContext::delete_all) does with paths that refer to the same
+    /// item as an earlier path of the batch. Defaults to [`DuplicatePaths::Skip`].
+    pub fn set_duplicate_paths(&mut self, duplicate_paths: DuplicatePaths) {
+        self.duplicate_paths = duplicate_paths;
+    }
+
+    /// See [`set_duplicate_paths`](TrashContext::set_duplicate_paths).
+    pub fn duplicate_paths(&self) -> DuplicatePaths {
+        self.duplicate_paths
+    }
+
+    /// Resolves the paths like [`delete_all`](TrashContext::delete_all) does and reports the
+    /// duplicates among them, handled according to the
+    /// [`duplicate_paths`](TrashContext::duplicate_paths) setting.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use std::fs::File;
+    /// use trash::TrashContext;
+    ///
+    /// File::create("normalize_me").unwrap();
+    /// let normalized = TrashContext::default().normalize_paths(["normalize_me", "./normalize_me"]).unwrap();
+    /// assert_eq!(normalized.paths.len(), 1);
+    /// assert_eq!(normalized.duplicates.len(), 1);
+    /// # std::fs::remove_file("normalize_me").unwrap();
+    /// ```
+    pub fn normalize_paths<I, T>(&self, paths: I) -> Result<NormalizedPaths, Error>
+    where
+        I: IntoIterator<Item = T>,
+        T: AsRef<Path>,
+    {
+        let full_paths = self.resolve_paths(paths)?;
+        let normalized = normalize(full_paths, self.duplicate_paths)?;
+        for duplicate in &normalized.duplicates {
+            debug!("{:?} is a duplicate of {:?} ({:?})", duplicate.path, duplicate.duplicate_of, duplicate.kind);
+        }
+        Ok(normalized)
+    }
+}
+
+fn normalize(full_paths: Vec<PathBuf>, duplicate_paths: DuplicatePaths) -> Result<NormalizedPaths, Error> {
+    let mut normalized = NormalizedPaths { paths: Vec::with_capacity(full_paths.len()), duplicates: Vec::new() };
+    // The indices of the earlier paths, by a key that is the same for all case variants.
+    let mut earlier: HashMap<PathBuf, Vec<usize>> = HashMap::new();
+    for path in full_paths {
+        let candidates = earlier.entry(matching::comparison_key(&path, false)).or_default();
+        let duplicate = candidates.iter().map(|&index| &normalized.paths[index]).find_map(|first| {
+            if *first == path {
+                Some((first, DuplicateKind::Identical))
+            } else if matching::same_file(first, &path) {
+                Some((first, DuplicateKind::CaseVariant))
+            } else {
+                None
+            }
+        });
+        let Some((first, kind)) = duplicate else {
+            candidates.push(normalized.paths.len());
+            normalized.paths.push(path);
+            continue;
+        };
+        let duplicate = DuplicatePath { path, duplicate_of: first.clone(), kind };
+        match duplicate_paths {
+            DuplicatePaths::Skip => {}
+            DuplicatePaths::Fail => {
+                return Err(Error::DuplicatePath { path: duplicate.path, duplicate_of: duplicate.duplicate_of })
+            }
+            DuplicatePaths::Keep => normalized.paths.push(duplicate.path.clone()),
+        }
+        normalized.duplicates.push(duplicate);
+    }
+    Ok(normalized)
+}
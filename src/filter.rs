@@ -0,0 +1,133 @@
+//! Listing the items in the trash that match some criteria, like for a search field.
+
+use std::path::{Path, PathBuf};
+
+use crate::{absolute_path, glob, limit, matching, ByteSize, Error, TrashContext, TrashItem};
+
+/// The criteria of [`list_filtered`](TrashContext::list_filtered). An item is listed if it meets
+/// all criteria that are set, the default lists every item.
+///
+/// # Example
+///
+/// ```
+/// use trash::{os_limited::TrashFilter, ByteSize};
+///
+/// let week_ago = std::time::SystemTime::now()
+///     .duration_since(std::time::UNIX_EPOCH)
+///     .unwrap()
+///     .as_secs() as i64
+///     - 7 * 24 * 60 * 60;
+/// let filter = TrashFilter {
+///     name_glob: Some("*.log".into()),
+///     deleted_after: Some(week_ago),
+///     min_size: Some(ByteSize::mib(1)),
+///     ..TrashFilter::default()
+/// };
+/// let large_logs = trash::os_limited::list_filtered(&filter).unwrap();
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TrashFilter {
+    /// Lists the items that were located at this path or within it. Relative paths are resolved
+    /// against the [`base_dir`](TrashContext::base_dir). Items whose original location is unknown
+    /// aren't listed.
+    pub original_path_prefix: Option<PathBuf>,
+    /// Lists the items that were deleted at this Unix timestamp or later. Items whose deletion time
+    /// is unknown aren't listed if either bound is set.
+    pub deleted_after: Option<i64>,
+    /// Lists the items that were deleted before this Unix timestamp.
+    pub deleted_before: Option<i64>,
+    /// Lists the items whose [`name`](TrashItem::name) matches this pattern, which supports the
+    /// `*`, `?` and `[...]` wildcards.
+    pub name_glob: Option<String>,
+    /// Lists the items of at least this size. Items whose size is unknown aren't listed.
+    pub min_size: Option<ByteSize>,
+}
+
+impl TrashContext {
+    /// Returns the items in the trash that match the `filter`, ordered like
+    /// [`os_limited::list`](crate::os_limited::list).
+    ///
+    /// The items are checked as the trash is read, see
+    /// [`os_limited::list_iter`](crate::os_limited::list_iter), so only the matching items are
+    /// kept in memory. The trash is read according to the settings of the context, like with
+    /// [`list`](TrashContext::list), and the [`list_limit`](TrashContext::list_limit) applies to
+    /// the matching items.
+    /// The original path and the name are compared according to the
+    /// [`case_sensitivity`](TrashContext::case_sensitivity), which is detected from the
+    /// `original_path_prefix` if there is one.
+    pub fn list_filtered(&self, filter: &TrashFilter) -> Result<Vec<TrashItem>, Error> {
+        let prefix = match &filter.original_path_prefix {
+            Some(prefix) => Some(absolute_path(prefix, self.base_dir())?),
+            None => None,
+        };
+        let case_sensitive = match &prefix {
+            Some(prefix) => self.case_sensitivity.is_sensitive_for(prefix),
+            None => self.case_sensitivity.is_sensitive_for(Path::new("")),
+        };
+        let criteria = Criteria {
+            prefix: prefix.map(|prefix| matching::comparison_key(&prefix, case_sensitive)),
+            name_glob: filter.name_glob.as_ref().map(|glob| fold_case(glob, case_sensitive)),
+            filter,
+            case_sensitive,
+        };
+
+        let mut items = Vec::new();
+        for item in self.list_iter() {
+            let item = item?;
+            if criteria.matches(&item) {
+                limit::push(&mut items, item, self.list_limit)?;
+            }
+        }
+        items.sort_by(crate::listing_order);
+        Ok(items)
+    }
+}
+
+/// A [`TrashFilter`] with the path and the pattern prepared for comparisons.
+struct Criteria<'a> {
+    filter: &'a TrashFilter,
+    prefix: Option<PathBuf>,
+    name_glob: Option<String>,
+    case_sensitive: bool,
+}
+
+impl Criteria<'_> {
+    fn matches(&self, item: &TrashItem) -> bool {
+        if let Some(prefix) = &self.prefix {
+            let Some(original_path) = item.original_path() else {
+                return false;
+            };
+            if !matching::comparison_key(&original_path, self.case_sensitive).starts_with(prefix) {
+                return false;
+            }
+        }
+        if self.filter.deleted_after.is_some() || self.filter.deleted_before.is_some() {
+            let Some(time_deleted) = item.time_deleted else {
+                return false;
+            };
+            if self.filter.deleted_after.is_some_and(|after| time_deleted < after)
+                || self.filter.deleted_before.is_some_and(|before| time_deleted >= before)
+            {
+                return false;
+            }
+        }
+        if let Some(glob) = &self.name_glob {
+            if !glob::matches(glob, &fold_case(&item.name, self.case_sensitive)) {
+                return false;
+            }
+        }
+        match self.filter.min_size {
+            Some(min) => item.size.is_some_and(|size| size >= min),
+            None => true,
+        }
+    }
+}
+
+fn fold_case(s: &str, case_sensitive: bool) -> String {
+    if case_sensitive {
+        s.to_owned()
+    } else {
+        s.to_lowercase()
+    }
+}
@@ -0,0 +1,122 @@
+//! Moving the contents of a folder to the trash, except for the entries matching some patterns.
+
+use std::{
+    ffi::OsString,
+    path::{Path, PathBuf},
+};
+
+use log::{debug, warn};
+
+use crate::{
+    glob, platform,
+    tracked::{self, Trashed},
+    Error, TrashContext,
+};
+
+impl TrashContext {
+    /// Moves the contents of the folder `dir` to the trash, except for the entries that match one
+    /// of the `exclude_globs`, and returns the paths of the entries that were moved. The folder
+    /// itself is kept.
+    ///
+    /// Patterns support the `*`, `?` and `[...]` wildcards. A pattern without a `/`, like `.git` or
+    /// `*.keep`, is matched against the names of the entries at any depth. Other patterns are
+    /// matched against the paths of the entries relative to `dir` with `/` as the separator, like
+    /// `assets/*.png`, where `*` also matches `/`. Folders that contain excluded entries are kept
+    /// and their remaining entries moved one by one, other folders are moved as a whole.
+    ///
+    /// All entries are moved in a single batch through [`delete_all`](TrashContext::delete_all). If
+    /// that fails, the items the batch put into the trash are restored before the error is
+    /// returned, which leaves alone older items that were deleted from the same paths. A failed
+    /// restore is logged, as the error of the delete is the one that's returned.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use std::fs;
+    ///
+    /// fs::create_dir_all("workspace/.git").unwrap();
+    /// fs::write("workspace/build.log", "").unwrap();
+    /// let trashed = trash::TrashContext::default().delete_dir_filtered("workspace", [".git"]).unwrap();
+    /// assert_eq!(trashed.len(), 1);
+    /// assert!(fs::metadata("workspace/.git").is_ok());
+    /// # fs::remove_dir_all("workspace").unwrap();
+    /// # trash::os_limited::purge_all(trash::os_limited::find(&trashed[0]).unwrap()).unwrap();
+    /// ```
+    pub fn delete_dir_filtered<T, I, P>(&self, dir: T, exclude_globs: I) -> Result<Vec<PathBuf>, Error>
+    where
+        T: AsRef<Path>,
+        I: IntoIterator<Item = P>,
+        P: AsRef<str>,
+    {
+        let dir = self.resolve_path(dir)?;
+        let patterns: Vec<String> = exclude_globs.into_iter().map(|p| p.as_ref().to_owned()).collect();
+        let mut planned = Vec::new();
+        plan(&dir, "", &patterns, &mut planned)?;
+        planned.sort();
+        debug!("Moving {} entries of {:?} to the trash", planned.len(), dir);
+        if planned.is_empty() {
+            return Ok(planned);
+        }
+        let Trashed { paths, ids, result } = tracked::delete_all(self, &planned)?;
+        if let Err(e) = result {
+            self.roll_back(&paths, &ids);
+            return Err(e);
+        }
+        Ok(planned)
+    }
+
+    /// Restores the items with the given ids, which are the ones the failed batch moved to the
+    /// trash from `paths`.
+    fn roll_back(&self, paths: &[PathBuf], ids: &[OsString]) {
+        if ids.is_empty() {
+            return;
+        }
+        let ids: Vec<_> = ids.iter().map(OsString::as_os_str).collect();
+        let mut restore = Vec::with_capacity(ids.len());
+        for (path, item) in paths.iter().zip(platform::items_by_ids(&ids)) {
+            match item {
+                Ok(item) => restore.push(item),
+                Err(e) => warn!("Failed to find {:?} in the trash to restore it: {:?}", path, e),
+            }
+        }
+        debug!("Restoring {} entries after the batch failed", restore.len());
+        if let Err(e) = self.restore_all(restore) {
+            warn!("Failed to restore the entries that were moved to the trash: {:?}", e);
+        }
+    }
+}
+
+/// Adds the entries of `dir` to `planned`, where `relative` is the path of `dir` relative to the
+/// folder that's cleaned. Returns whether any entry within `dir` is excluded.
+fn plan(dir: &Path, relative: &str, patterns: &[String], planned: &mut Vec<PathBuf>) -> Result<bool, Error> {
+    let entries = std::fs::read_dir(dir).map_err(|_| Error::CouldNotAccess { target: dir.to_string_lossy().into() })?;
+    let mut excludes_any = false;
+    for entry in entries {
+        let entry = entry.map_err(|_| Error::CouldNotAccess { target: dir.to_string_lossy().into() })?;
+        let name = entry.file_name().to_string_lossy().into_owned();
+        let relative = if relative.is_empty() { name.clone() } else { format!("{relative}/{name}") };
+        if is_excluded(patterns, &name, &relative) {
+            excludes_any = true;
+            continue;
+        }
+        let path = entry.path();
+        // Links to folders are moved themselves, the folders they point to are left alone.
+        if entry.file_type().is_ok_and(|t| t.is_dir()) {
+            let mut nested = Vec::new();
+            if plan(&path, &relative, patterns, &mut nested)? {
+                excludes_any = true;
+                planned.extend(nested);
+                continue;
+            }
+        }
+        planned.push(path);
+    }
+    Ok(excludes_any)
+}
+
+fn is_excluded(patterns: &[String], name: &str, relative: &str) -> bool {
+    patterns.iter().any(|pattern| match pattern.contains('/') {
+        true => glob::matches(pattern, relative),
+        false => glob::matches(pattern, name),
+    })
+}
@@ -8,100 +8,540 @@
 
 use std::{
     borrow::Borrow,
-    collections::HashSet,
+    collections::{HashMap, HashSet},
+    ffi::{OsStr, OsString},
     fs::{self, File, OpenOptions},
     io::{BufRead, BufReader, Write},
-    os::unix::{ffi::OsStrExt, fs::PermissionsExt},
+    os::unix::{
+        ffi::{OsStrExt, OsStringExt},
+        fs::{MetadataExt, PermissionsExt},
+    },
     path::{Path, PathBuf},
+    time::{Duration, Instant},
 };
 
 use log::{debug, warn};
 
-use crate::{Error, TrashContext, TrashItem, TrashItemMetadata, TrashItemSize};
+use crate::{
+    collision, destination, limit, matching, merge, metrics::TrashMetrics, parallel, trash_info, ByteSize,
+    CancellationToken, Error, ForeignFormat, Preset, RestoreCollision, TrashContext, TrashItem, TrashItemKind,
+    TrashItemMetadata, TrashItemSize, TrashTransfer, UnknownReason,
+};
 
 type FsError = (PathBuf, std::io::Error);
 
 #[derive(Clone, Default, Debug)]
-pub struct PlatformTrashContext;
+pub struct PlatformTrashContext {
+    #[cfg(feature = "polkit")]
+    polkit_elevation: bool,
+    home_trash: Option<PathBuf>,
+    #[cfg(target_os = "linux")]
+    wsl_recycle_bin: bool,
+    #[cfg(target_os = "linux")]
+    container_checks: bool,
+    /// The user that items are moved to the trash for, when that's not the current user.
+    owner: Option<UserAccount>,
+}
 impl PlatformTrashContext {
     pub const fn new() -> Self {
-        PlatformTrashContext
+        PlatformTrashContext {
+            #[cfg(feature = "polkit")]
+            polkit_elevation: false,
+            home_trash: None,
+            #[cfg(target_os = "linux")]
+            wsl_recycle_bin: false,
+            #[cfg(target_os = "linux")]
+            container_checks: false,
+            owner: None,
+        }
+    }
+
+    pub(crate) fn apply_preset(&mut self, _preset: Preset) {}
+
+    /// The home trash set through [`TrashContext::set_home_trash`], or the detected one.
+    pub(crate) fn home_trash(&self) -> Result<PathBuf, Error> {
+        match (&self.home_trash, &self.owner) {
+            (Some(home_trash), _) => Ok(home_trash.clone()),
+            (None, Some(owner)) => Ok(owner.home.join(".local/share/Trash")),
+            (None, None) => home_trash(),
+        }
+    }
+
+    /// The user whose trash folders are used on mount points.
+    fn uid(&self) -> libc::uid_t {
+        self.owner.as_ref().map_or_else(|| unsafe { libc::getuid() }, |owner| owner.uid)
+    }
+
+    /// Whether items the current user can't move are moved through polkit.
+    fn elevates(&self) -> bool {
+        #[cfg(feature = "polkit")]
+        return self.polkit_elevation;
+        #[cfg(not(feature = "polkit"))]
+        false
+    }
+}
+impl TrashContext {
+    /// **freedesktop only**, requires the `polkit` feature
+    ///
+    /// Enables moving items that the current user isn't allowed to move, like files in
+    /// `/usr/local`, to and from the trash with root privileges. Before each such item is moved,
+    /// polkit asks the user to authenticate as an administrator through `pkexec`. The item is
+    /// then moved by a small shell script running as root. Items the current user can move are not
+    /// affected. Disabled by default.
+    ///
+    /// Fails with a [`std::io::ErrorKind::PermissionDenied`] error if the authorization is denied
+    /// or dismissed, or if no polkit agent is running.
+    #[cfg(feature = "polkit")]
+    pub fn set_polkit_elevation(&mut self, enabled: bool) {
+        self.platform_specific.polkit_elevation = enabled;
+    }
+
+    /// See [`set_polkit_elevation`](TrashContext::set_polkit_elevation).
+    #[cfg(feature = "polkit")]
+    pub fn polkit_elevation(&self) -> bool {
+        self.platform_specific.polkit_elevation
+    }
+
+    /// **freedesktop only**
+    ///
+    /// Uses `home_trash` as the home trash instead of the detected one, for environments that keep
+    /// the trash in an unusual place. Items are moved there when they're on the same file system
+    /// as it, and [`list`](TrashContext::list) lists it along with the trash folders of the mount
+    /// points.
+    ///
+    /// By default the home trash is `$XDG_DATA_HOME/Trash`, or `~/.local/share/Trash` if
+    /// `XDG_DATA_HOME` isn't set. Within Flatpak and Snap sandboxes, which point `XDG_DATA_HOME`
+    /// to a folder of the application, the trash of the host is used instead if it's accessible.
+    pub fn set_home_trash(&mut self, home_trash: Option<PathBuf>) {
+        self.platform_specific.home_trash = home_trash;
+    }
+
+    /// See [`set_home_trash`](TrashContext::set_home_trash).
+    pub fn home_trash(&self) -> Option<&Path> {
+        self.platform_specific.home_trash.as_deref()
+    }
+
+    /// **Linux only**
+    ///
+    /// When running within the Windows Subsystem for Linux, moves items on the Windows drives
+    /// mounted at `/mnt/<letter>` to the Windows Recycle Bin instead of a trash folder on the drive,
+    /// so that they can be restored from Windows. The mount folder follows the `automount.root`
+    /// setting of `/etc/wsl.conf`. The items are moved by `powershell.exe`, which WSL runs on the
+    /// Windows side, without showing any dialogs. Has no effect outside of WSL. Disabled by
+    /// default.
+    #[cfg(target_os = "linux")]
+    pub fn set_wsl_recycle_bin(&mut self, enabled: bool) {
+        self.platform_specific.wsl_recycle_bin = enabled;
+    }
+
+    /// See [`set_wsl_recycle_bin`](TrashContext::set_wsl_recycle_bin).
+    #[cfg(target_os = "linux")]
+    pub fn wsl_recycle_bin(&self) -> bool {
+        self.platform_specific.wsl_recycle_bin
+    }
+
+    /// **Linux only**
+    ///
+    /// When running within a container, refuses to move items to a trash folder that the user
+    /// wouldn't find them in, with [`Error::TrashUnavailable`]. That's a trash folder in the
+    /// writable layer of the container, which is discarded with the container or kept in the image
+    /// it's committed to, and a home trash that belongs to another user, which is the case when the
+    /// home folder of the host is mounted into the container. Items on volumes that are mounted
+    /// into the container are moved to the trash folder of the volume as usual.
+    ///
+    /// A home trash set with [`set_home_trash`](TrashContext::set_home_trash) is always used. Has
+    /// no effect outside of containers. Disabled by default, as containers used for development
+    /// and CI are often meant to be thrown away along with their trash.
+    #[cfg(target_os = "linux")]
+    pub fn set_container_checks(&mut self, enabled: bool) {
+        self.platform_specific.container_checks = enabled;
+    }
+
+    /// See [`set_container_checks`](TrashContext::set_container_checks).
+    #[cfg(target_os = "linux")]
+    pub fn container_checks(&self) -> bool {
+        self.platform_specific.container_checks
+    }
+
+    /// Moves items to the trash folders of `owner` instead of those of the current user, and hands
+    /// the trash folders and info files it creates over to `owner`. Used by a helper that runs as
+    /// root on behalf of another user.
+    #[cfg(feature = "helper")]
+    pub(crate) fn set_owner(&mut self, owner: UserAccount) {
+        self.platform_specific.owner = Some(owner);
     }
 }
 impl TrashContext {
     pub(crate) fn delete_all_canonicalized(&self, full_paths: Vec<PathBuf>) -> Result<(), Error> {
-        let home_trash = home_trash()?;
+        let home_trash = self.platform_specific.home_trash()?;
         let sorted_mount_points = get_sorted_mount_points()?;
-        let home_topdir = home_topdir(&sorted_mount_points)?;
+        let home_topdir = home_topdir(&home_trash, &sorted_mount_points);
         debug!("The home topdir is {:?}", home_topdir);
-        let uid = unsafe { libc::getuid() };
+        let uid = self.platform_specific.uid();
+        let owner = self.platform_specific.owner.as_ref();
+        let mut first_error = None;
+        #[cfg(target_os = "linux")]
+        let full_paths = self.recycle_on_windows(full_paths, &mut first_error)?;
+        let cancellation = self.cancellation();
         for path in full_paths {
+            self.check_cancelled()?;
             debug!("Deleting {:?}", path);
             let topdir = get_first_topdir_containing_path(&path, &sorted_mount_points);
             debug!("The topdir of this file is {:?}", topdir);
-            if topdir == home_topdir {
+            #[cfg(target_os = "linux")]
+            if let Err(error) =
+                self.check_container_trash(&home_trash, topdir == home_topdir, topdir, &sorted_mount_points)
+            {
+                self.handle_item_error(error, &mut first_error)?;
+                continue;
+            }
+            let elevate = self.platform_specific.elevates() && !can_remove(&path);
+            let result = if topdir == home_topdir {
                 debug!("The topdir was identical to the home topdir, so moving to the home trash.");
                 // Note that the following function creates the trash folder
                 // and its required subfolders in case they don't exist.
-                move_to_trash(path, &home_trash, topdir).map_err(|(p, e)| fs_error(p, e))?;
+                move_to_trash(&path, &home_trash, topdir, elevate, owner, cancellation)
             } else {
                 execute_on_mounted_trash_folders(uid, topdir, true, true, |trash_path| {
-                    move_to_trash(&path, trash_path, topdir)
+                    move_to_trash(&path, trash_path, topdir, elevate, owner, cancellation)
                 })
-                .map_err(|(p, e)| fs_error(p, e))?;
+            };
+            if let Err((p, e)) = result {
+                self.check_cancelled()?;
+                let error = match e.kind() {
+                    std::io::ErrorKind::NotFound if p == path => Error::TargetedItemNotFound { path: p },
+                    _ => fs_error(p, e),
+                };
+                self.handle_item_error(error, &mut first_error)?;
+            }
+        }
+        first_error.map_or(Ok(()), Err)
+    }
+
+    pub(crate) fn destination_canonicalized(
+        &self,
+        full_path: &Path,
+    ) -> Result<(PathBuf, PathBuf, TrashTransfer), Error> {
+        let home_trash = self.platform_specific.home_trash()?;
+        let sorted_mount_points = get_sorted_mount_points()?;
+        let home_topdir = home_topdir(&home_trash, &sorted_mount_points);
+        let topdir = get_first_topdir_containing_path(full_path, &sorted_mount_points);
+        #[cfg(target_os = "linux")]
+        self.check_container_trash(&home_trash, topdir == home_topdir, topdir, &sorted_mount_points)?;
+        let trash_root = if topdir == home_topdir {
+            home_trash
+        } else {
+            let uid = self.platform_specific.uid();
+            let mut existing = None;
+            execute_on_mounted_trash_folders(uid, topdir, true, false, |trash_path| {
+                existing = Some(trash_path);
+                Ok(())
+            })
+            .map_err(|(p, e)| fs_error(p, e))?;
+            // When there's no trash folder yet, `delete` creates this one.
+            existing.unwrap_or_else(|| topdir.join(format!(".Trash-{uid}")))
+        };
+        let transfer = destination::transfer_between(full_path, &trash_root);
+        Ok((trash_root, topdir.to_owned(), transfer))
+    }
+
+    pub(crate) fn delete_permanently_canonicalized(&self, full_paths: &[PathBuf]) -> Result<(), Error> {
+        let mut first_error = None;
+        for path in full_paths {
+            debug!("Deleting {:?} permanently", path);
+            if let Err(e) = crate::permanent::remove(path, self.path_progress()) {
+                self.handle_item_error(fs_error(path, e), &mut first_error)?;
             }
         }
+        first_error.map_or(Ok(()), Err)
+    }
+
+    /// Moves the items on Windows drives to the Recycle Bin if that was enabled through
+    /// [`set_wsl_recycle_bin`](TrashContext::set_wsl_recycle_bin), and returns the other paths.
+    #[cfg(target_os = "linux")]
+    fn recycle_on_windows(
+        &self,
+        full_paths: Vec<PathBuf>,
+        first_error: &mut Option<Error>,
+    ) -> Result<Vec<PathBuf>, Error> {
+        if !self.platform_specific.wsl_recycle_bin || !crate::wsl::is_wsl() {
+            return Ok(full_paths);
+        }
+        let root = crate::wsl::automount_root();
+        let mut windows_paths = Vec::new();
+        let mut remaining = Vec::with_capacity(full_paths.len());
+        for path in full_paths {
+            match crate::wsl::windows_path(&root, &path) {
+                Some(windows_path) => windows_paths.push(windows_path),
+                None => remaining.push(path),
+            }
+        }
+        if !windows_paths.is_empty() {
+            debug!("Moving {:?} to the Windows Recycle Bin", windows_paths);
+            if let Err(e) = crate::wsl::recycle(&windows_paths) {
+                self.handle_item_error(e, first_error)?;
+            }
+        }
+        Ok(remaining)
+    }
+
+    /// Fails with [`Error::TrashUnavailable`] if an item on `topdir` shouldn't be moved to the
+    /// trash, see [`set_container_checks`](TrashContext::set_container_checks). `to_home` is
+    /// whether the item goes to the home trash rather than a trash folder on `topdir`.
+    #[cfg(target_os = "linux")]
+    fn check_container_trash(
+        &self,
+        home_trash: &Path,
+        to_home: bool,
+        topdir: &Path,
+        mount_points: &[MountPoint],
+    ) -> Result<(), Error> {
+        if !self.platform_specific.container_checks {
+            return Ok(());
+        }
+        let Some(runtime) = crate::container::runtime() else {
+            return Ok(());
+        };
+        if to_home && self.platform_specific.home_trash.is_some() {
+            return Ok(());
+        }
+        let uid = unsafe { libc::getuid() };
+        check_trash_in_container(runtime, uid, to_home.then_some(home_trash), topdir, mount_points)
+    }
+
+    /// Either fails right away with `error`, or remembers it if it's the first error and the
+    /// context is configured to continue with the remaining items.
+    fn handle_item_error(&self, error: Error, first_error: &mut Option<Error>) -> Result<(), Error> {
+        if !self.continue_on_error || matches!(error, Error::Cancelled) {
+            return Err(error);
+        }
+        warn!("Continuing with the remaining items after an error: {:?}", error);
+        first_error.get_or_insert(error);
         Ok(())
     }
 }
 
 pub fn list() -> Result<Vec<TrashItem>, Error> {
-    let mut trash_folders = HashSet::new();
-    // Get home trash folder and add it to the set of trash folders.
-    // It may not exist and that's completely fine as long as there are other trash folders.
-    let home_error;
-    match home_trash() {
-        Ok(home_trash) => {
-            if !home_trash.is_dir() {
-                home_error = Some(Error::Unknown {
-                    description:
-                        "The 'home trash' either does not exist or is not a directory (or a link pointing to a dir)"
-                            .into(),
-                });
-            } else {
-                trash_folders.insert(home_trash);
-                home_error = None;
+    list_trash_of(unsafe { libc::getuid() }, home_trash(), None)
+}
+
+impl TrashContext {
+    /// Lists the items according to the settings of the context. The names are the file names the
+    /// items were deleted with, so there's nothing to localize.
+    pub(crate) fn list_items(&self) -> Result<Vec<TrashItem>, Error> {
+        list_trash_of(unsafe { libc::getuid() }, self.platform_specific.home_trash(), self.list_limit)
+    }
+
+    /// Lists the items one by one according to the settings of the context, see [`ListIter`].
+    pub(crate) fn list_iter(&self) -> ListIter {
+        ListIter::new(unsafe { libc::getuid() }, self.platform_specific.home_trash())
+    }
+
+    /// Lists the items in the trash folder at `trash_root` only, failing once there are more items
+    /// than the list limit of the context.
+    pub(crate) fn list_items_in(&self, trash_root: &Path) -> Result<Vec<TrashItem>, Error> {
+        let mount_points = get_sorted_mount_points()?;
+        let mut result = Vec::new();
+        if let Some(mut entries) = TrashFolderEntries::open(trash_root.to_owned(), &mount_points) {
+            while let Some(item) = entries.next_item() {
+                limit::push(&mut result, item, self.list_limit)?;
             }
         }
-        Err(e) => {
-            home_error = Some(e);
+        Ok(result)
+    }
+
+    /// Counts the info files of the trash folders and adds up the sizes of their items, without
+    /// reading the info files themselves.
+    pub(crate) fn trash_metrics(&self) -> Result<TrashMetrics, Error> {
+        let mount_points = get_sorted_mount_points()?;
+        let folders = trash_folders_of(unsafe { libc::getuid() }, self.platform_specific.home_trash(), &mount_points)?;
+        let mut metrics = TrashMetrics { complete: true, ..TrashMetrics::default() };
+        for folder in folders {
+            let info_folder = folder.join("info");
+            let read_dir = match fs::read_dir(&info_folder) {
+                Ok(read_dir) => read_dir,
+                Err(e) => {
+                    warn!("The trash info folder {:?} could not be read. Error was {:?}", info_folder, e);
+                    continue;
+                }
+            };
+            let directory_sizes = read_directory_sizes(&folder);
+            for info_entry in read_dir.flatten() {
+                if !info_entry.file_type().is_ok_and(|file_type| file_type.is_file()) {
+                    continue;
+                }
+                let info_path = info_entry.path();
+                let in_trash_path = info_path.file_stem().map(|name| folder.join("files").join(name));
+                let size = in_trash_path
+                    .and_then(|path| path.symlink_metadata().ok())
+                    .and_then(|metadata| item_size(&metadata, &info_path, &directory_sizes));
+                metrics.item_count += 1;
+                match size {
+                    Some(size) => metrics.total_bytes = metrics.total_bytes.saturating_add(size),
+                    None => metrics.complete = false,
+                }
+            }
         }
+        Ok(metrics)
     }
 
-    // Get all mount-points and attempt to find a trash folder in each adding them to the SET of
-    // trash folders when found one.
-    let uid = unsafe { libc::getuid() };
-    let sorted_mount_points = get_sorted_mount_points()?;
-    for mount in &sorted_mount_points {
-        execute_on_mounted_trash_folders(uid, &mount.mnt_dir, false, false, |trash_path| {
-            trash_folders.insert(trash_path);
+    /// The items in the home trash and the trash folder on the mount point of `original_path` that
+    /// were located there. Only the info files named after the item are read, which are
+    /// `<name>.trashinfo` and `<name>.<number>.trashinfo`.
+    pub(crate) fn find_original(&self, original_path: &Path, case_sensitive: bool) -> Result<Vec<TrashItem>, Error> {
+        let Some(file_name) = original_path.file_name() else {
+            return Ok(Vec::new());
+        };
+        let mut trash_folders = Vec::new();
+        match self.platform_specific.home_trash() {
+            Ok(home_trash) => trash_folders.push(home_trash),
+            Err(e) => debug!("Not looking into the home trash as it can't be determined: {:?}", e),
+        }
+        let sorted_mount_points = get_sorted_mount_points()?;
+        let topdir = get_first_topdir_containing_path(original_path, &sorted_mount_points);
+        execute_on_mounted_trash_folders(unsafe { libc::getuid() }, topdir, false, false, |trash_path| {
+            trash_folders.push(trash_path);
             Ok(())
         })
         .map_err(|(p, e)| fs_error(p, e))?;
+
+        let mut items = Vec::new();
+        for folder in &trash_folders {
+            let info_folder = folder.join("info");
+            let read_dir = match std::fs::read_dir(&info_folder) {
+                Ok(read_dir) => read_dir,
+                Err(e) => {
+                    debug!("Skipping the trash info folder {:?}: {}", info_folder, e);
+                    continue;
+                }
+            };
+            let top_dir = get_first_topdir_containing_path(folder, &sorted_mount_points);
+            for entry in read_dir.flatten() {
+                if !is_info_file_of(&entry.file_name(), file_name, case_sensitive) {
+                    continue;
+                }
+                let Some(item) = parse_trash_info(&entry.path(), top_dir) else {
+                    continue;
+                };
+                if item.original_path().is_some_and(|path| matching::paths_match(&path, original_path, case_sensitive))
+                {
+                    items.push(item);
+                }
+            }
+        }
+        Ok(items)
     }
-    if trash_folders.is_empty() {
-        warn!("No trash folder was found. The error when looking for the 'home trash' was: {:?}", home_error);
-        return Ok(vec![]);
+}
+
+/// Whether `info_name` is the name of an info file for an item named `file_name`, which is either
+/// `<file_name>.trashinfo` or `<file_name>.<number>.trashinfo`.
+fn is_info_file_of(info_name: &OsStr, file_name: &OsStr, case_sensitive: bool) -> bool {
+    let (info_name, file_name) = match case_sensitive {
+        true => (info_name.as_bytes().to_owned(), file_name.as_bytes().to_owned()),
+        false => (
+            info_name.to_string_lossy().to_lowercase().into_bytes(),
+            file_name.to_string_lossy().to_lowercase().into_bytes(),
+        ),
+    };
+    let Some(stem) = info_name.strip_suffix(b".trashinfo") else {
+        return false;
+    };
+    match stem.strip_prefix(file_name.as_slice()) {
+        Some([]) => true,
+        Some([b'.', number @ ..]) => !number.is_empty() && number.iter().all(u8::is_ascii_digit),
+        _ => false,
+    }
+}
+
+/// The ids of the items are the paths of their info files, within the `info` folder of the trash.
+pub(crate) fn trash_root_of(id: &Path) -> Option<PathBuf> {
+    let info_folder = id.parent()?;
+    if info_folder.file_name()? != "info" {
+        return None;
     }
-    // List all items from the set of trash folders
+    info_folder.parent().map(Path::to_owned)
+}
+
+/// Lists the items in the home trash and in the trash folders of all mount points that belong to
+/// the user with the given `uid`, failing once there are more than `limit` items.
+fn list_trash_of(
+    uid: libc::uid_t,
+    home_trash: Result<PathBuf, Error>,
+    limit: Option<usize>,
+) -> Result<Vec<TrashItem>, Error> {
     let mut result = Vec::new();
-    for folder in &trash_folders {
+    for item in ListIter::new(uid, home_trash) {
+        limit::push(&mut result, item?, limit)?;
+    }
+    Ok(result)
+}
+
+pub(crate) fn list_iter() -> ListIter {
+    ListIter::new(unsafe { libc::getuid() }, home_trash())
+}
+
+/// Lists the items of the trash folders of a user one by one, reading the info files of a folder
+/// as the items are asked for. A failure to find the trash folders is the only item.
+pub(crate) struct ListIter {
+    error: Option<Error>,
+    mount_points: Vec<MountPoint>,
+    folders: std::collections::hash_set::IntoIter<PathBuf>,
+    entries: Option<TrashFolderEntries>,
+}
+
+impl ListIter {
+    fn new(uid: libc::uid_t, home_trash: Result<PathBuf, Error>) -> Self {
+        let folders = get_sorted_mount_points()
+            .and_then(|mount_points| Ok((trash_folders_of(uid, home_trash, &mount_points)?, mount_points)));
+        match folders {
+            Ok((folders, mount_points)) => {
+                ListIter { error: None, mount_points, folders: folders.into_iter(), entries: None }
+            }
+            Err(e) => ListIter {
+                error: Some(e),
+                mount_points: Vec::new(),
+                folders: HashSet::new().into_iter(),
+                entries: None,
+            },
+        }
+    }
+}
+
+impl Iterator for ListIter {
+    type Item = Result<TrashItem, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(e) = self.error.take() {
+            return Some(Err(e));
+        }
+        loop {
+            if let Some(item) = self.entries.as_mut().and_then(TrashFolderEntries::next_item) {
+                return Some(Ok(item));
+            }
+            let folder = self.folders.next()?;
+            self.entries = TrashFolderEntries::open(folder, &self.mount_points);
+        }
+    }
+}
+
+/// The info files of a trash folder that weren't read yet.
+struct TrashFolderEntries {
+    folder: PathBuf,
+    top_dir: PathBuf,
+    directory_sizes: HashMap<OsString, (u64, i64)>,
+    read_dir: fs::ReadDir,
+}
+
+impl TrashFolderEntries {
+    /// Returns `None` for trash folders that can't be listed, which are skipped.
+    fn open(folder: PathBuf, sorted_mount_points: &[MountPoint]) -> Option<Self> {
         // Read the info files for every file
-        let top_dir = get_first_topdir_containing_path(folder, &sorted_mount_points);
+        let top_dir = get_first_topdir_containing_path(&folder, sorted_mount_points).to_owned();
+        let directory_sizes = read_directory_sizes(&folder);
         let info_folder = folder.join("info");
         if !info_folder.is_dir() {
             warn!("The path {:?} did not point to a directory, skipping this trash folder.", info_folder);
-            continue;
+            return None;
         }
         let read_dir = match std::fs::read_dir(&info_folder) {
             Ok(d) => d,
@@ -110,11 +550,14 @@ pub fn list() -> Result<Vec<TrashItem>, Error> {
                 // because another process may have deleted it or modified its access rights in the meantime.
                 // So let's just pring a warning and continue to the rest of the folders
                 warn!("The trash info folder {:?} could not be read. Error was {:?}", info_folder, e);
-                continue;
+                return None;
             }
         };
-        #[cfg_attr(not(feature = "chrono"), allow(unused_labels))]
-        'trash_item: for entry in read_dir {
+        Some(TrashFolderEntries { folder, top_dir, directory_sizes, read_dir })
+    }
+
+    fn next_item(&mut self) -> Option<TrashItem> {
+        for entry in &mut self.read_dir {
             let info_entry = match entry {
                 Ok(entry) => entry,
                 Err(e) => {
@@ -137,199 +580,1023 @@ pub fn list() -> Result<Vec<TrashItem>, Error> {
                 warn!("Found an item that's not a file, among the trash info files. This is unexpected. The path to the item is: '{:?}'", info_path);
                 continue;
             }
-            let info_file = match File::open(&info_path) {
-                Ok(file) => file,
-                Err(e) => {
-                    // Another thread or process may have removed that entry by now
-                    debug!("Tried opening the trash info '{:?}' but failed with: {}", info_path, e);
-                    continue;
+            if let Some(mut item) = parse_trash_info(&info_path, &self.top_dir) {
+                let in_trash_path = info_path.file_stem().map(|name| self.folder.join("files").join(name));
+                if let Some(metadata) = in_trash_path.and_then(|path| path.symlink_metadata().ok()) {
+                    item.kind = Some(TrashItemKind::of(metadata.file_type()));
+                    item.size = item_size(&metadata, &info_path, &self.directory_sizes);
                 }
+                return Some(item);
+            }
+        }
+        None
+    }
+}
+
+/// The home trash, if it exists, and the trash folders of the user on all mount points.
+fn trash_folders_of(
+    uid: libc::uid_t,
+    home_trash: Result<PathBuf, Error>,
+    sorted_mount_points: &[MountPoint],
+) -> Result<HashSet<PathBuf>, Error> {
+    let mut trash_folders = HashSet::new();
+    // Get home trash folder and add it to the set of trash folders.
+    // It may not exist and that's completely fine as long as there are other trash folders.
+    let home_error;
+    match home_trash {
+        Ok(home_trash) => {
+            if !home_trash.is_dir() {
+                home_error = Some(Error::CouldNotAccessTrash {
+                    path: Some(home_trash.clone()),
+                    description:
+                        "The 'home trash' either does not exist or is not a directory (or a link pointing to a dir)"
+                            .into(),
+                });
+            } else {
+                trash_folders.insert(home_trash);
+                home_error = None;
+            }
+        }
+        Err(e) => {
+            home_error = Some(e);
+        }
+    }
+
+    // Get all mount-points and attempt to find a trash folder in each adding them to the SET of
+    // trash folders when found one.
+    for mount in sorted_mount_points {
+        execute_on_mounted_trash_folders(uid, &mount.mnt_dir, false, false, |trash_path| {
+            trash_folders.insert(trash_path);
+            Ok(())
+        })
+        .map_err(|(p, e)| fs_error(p, e))?;
+    }
+    if trash_folders.is_empty() {
+        warn!("No trash folder was found. The error when looking for the 'home trash' was: {:?}", home_error);
+    }
+    Ok(trash_folders)
+}
+
+/// The size of the item with the `metadata` and the info file at `info_path`. Folders are looked
+/// up in the `directory_sizes` of the trash, whose entries are only valid as long as the info file
+/// wasn't modified.
+fn item_size(
+    metadata: &fs::Metadata,
+    info_path: &Path,
+    directory_sizes: &HashMap<OsString, (u64, i64)>,
+) -> Option<ByteSize> {
+    if !metadata.is_dir() {
+        return Some(ByteSize::new(metadata.len()));
+    }
+    let in_trash_name = info_path.file_stem()?;
+    let &(size, mtime) = directory_sizes.get(in_trash_name)?;
+    let info_mtime = info_path.metadata().ok()?.mtime();
+    (mtime == info_mtime).then_some(ByteSize::new(size))
+}
+
+/// Reads the `directorysizes` file of `trash_folder`, which caches the sizes of the folders in the
+/// trash along with the modification time of their info files, keyed by the name of the folders.
+/// Lines that can't be read are skipped.
+fn read_directory_sizes(trash_folder: &Path) -> HashMap<OsString, (u64, i64)> {
+    let Ok(contents) = std::fs::read(trash_folder.join("directorysizes")) else {
+        return HashMap::new();
+    };
+    let mut sizes = HashMap::new();
+    for line in contents.split(|&b| b == b'\n') {
+        let mut fields = line.splitn(3, |&b| b == b' ');
+        let (Some(size), Some(mtime), Some(name)) = (fields.next(), fields.next(), fields.next()) else {
+            continue;
+        };
+        let size = std::str::from_utf8(size).ok().and_then(|size| size.parse().ok());
+        let mtime = std::str::from_utf8(mtime).ok().and_then(|mtime| mtime.parse().ok());
+        if let (Some(size), Some(mtime)) = (size, mtime) {
+            sizes.insert(decode_uri_path(name).into_os_string(), (size, mtime));
+        }
+    }
+    sizes
+}
+
+/// Reads the trash info file at `info_path`, that belongs to the trash folder on the mount point
+/// `top_dir`. Problems with the file are logged and result in `None`.
+fn parse_trash_info(info_path: &Path, top_dir: &Path) -> Option<TrashItem> {
+    read_trash_info(info_path, top_dir).map(|(item, _)| item)
+}
+
+/// Like [`parse_trash_info`], and also reports how the file deviates from the specification.
+/// Deviations are tolerated as long as the original path can be determined.
+fn read_trash_info(info_path: &Path, top_dir: &Path) -> Option<(TrashItem, ForeignFormat)> {
+    let info_file = match File::open(info_path) {
+        Ok(file) => file,
+        Err(e) => {
+            // Another thread or process may have removed that entry by now
+            debug!("Tried opening the trash info '{:?}' but failed with: {}", info_path, e);
+            return None;
+        }
+    };
+    let id = info_path.as_os_str().to_owned();
+    let mut name = None;
+    let mut original_parent: Option<PathBuf> = None;
+    #[cfg_attr(not(feature = "chrono"), allow(unused_mut))]
+    let mut time_deleted = None;
+    let mut pinned = false;
+    let mut format = ForeignFormat::default();
+
+    let info_reader = BufReader::new(info_file);
+    'info_lines: for (index, line_result) in info_reader.split(b'\n').enumerate() {
+        // Another thread or process may have removed the infofile by now
+        let line = if let Ok(line) = line_result {
+            line
+        } else {
+            break 'info_lines;
+        };
+        // The first line must be "[Trash Info]", but some tools leave it out.
+        if index == 0 {
+            if line.trim_ascii() == b"[Trash Info]" {
+                continue 'info_lines;
+            }
+            format.missing_header = true;
+        }
+        // Lines without a key, like comments or empty lines, are skipped.
+        let Some(separator) = line.iter().position(|&b| b == b'=') else {
+            continue 'info_lines;
+        };
+        // The path is decoded from the raw bytes, as some tools don't escape bytes that aren't
+        // valid UTF-8.
+        let raw_value = line[separator + 1..].trim_ascii();
+        let key = String::from_utf8_lossy(&line[..separator]);
+        let value = String::from_utf8_lossy(raw_value);
+        let (key, value) = (key.trim(), value.trim());
+
+        if key == "Path" {
+            format.file_uri = raw_value.starts_with(b"file://");
+            let path_bytes = raw_value.strip_prefix(b"file://").unwrap_or(raw_value);
+            format.unescaped_path = !path_bytes.iter().all(|&b| b == b'%' || trash_info::is_unescaped_path_byte(b));
+            let mut full_path = decode_uri_path(raw_value);
+            if full_path.is_relative() {
+                full_path = top_dir.join(full_path);
+            }
+            let (Some(file_name), Some(parent)) = (full_path.file_name(), full_path.parent()) else {
+                warn!("The original path {:?} in the trash info file {:?} has no file name", value, info_path);
+                return None;
             };
-            let id = info_path.clone().into();
-            let mut name = None;
-            let mut original_parent: Option<PathBuf> = None;
-            #[cfg_attr(not(feature = "chrono"), allow(unused_mut))]
-            let mut time_deleted = None;
-
-            let info_reader = BufReader::new(info_file);
-            // Skip 1 because the first line must be "[Trash Info]"
-            'info_lines: for line_result in info_reader.lines().skip(1) {
-                // Another thread or process may have removed the infofile by now
-                let line = if let Ok(line) = line_result {
-                    line
-                } else {
-                    break 'info_lines;
-                };
-                let mut split = line.split('=');
+            name = Some(file_name.to_string_lossy().into_owned());
+            original_parent = Some(parent.into());
+        } else if key == "DeletionDate" {
+            #[cfg(feature = "chrono")]
+            match parse_deletion_date(value) {
+                Some(time) => time_deleted = Some(time),
+                None => warn!("Failed to parse the deletion date '{}' of the trash info file {:?}", value, info_path),
+            }
+        } else if key == PINNED_KEY {
+            pinned = value == "true";
+        }
+    }
+    let (Some(name), Some(original_parent)) = (name, original_parent) else {
+        warn!("Could not determine the original path of the trash item. (The `Path` field is probably missing from the info file.) The info file path is: '{:?}'", info_path);
+        return None;
+    };
+    // Without chrono, the deletion date isn't read at all, which says nothing about the file.
+    if time_deleted.is_none() && cfg!(feature = "chrono") {
+        warn!("Could not determine the deletion time of the trash item. (The `DeletionDate` field is probably missing or invalid.) The info file path is: '{:?}'", info_path);
+        format.invalid_deletion_date = true;
+    }
+    let unknown_reason = match time_deleted {
+        Some(_) => None,
+        None if format.invalid_deletion_date => Some(UnknownReason::Invalid),
+        None => Some(UnknownReason::Unsupported),
+    };
+    let item = TrashItem {
+        id,
+        name,
+        original_parent: Some(original_parent),
+        time_deleted,
+        size: None,
+        kind: None,
+        unknown_reason,
+        pinned,
+    };
+    Some((item, format))
+}
+
+/// Parses the `DeletionDate` of a trash info file into seconds since the UNIX epoch.
+///
+/// The specification asks for the local time without an offset, which is what this crate writes.
+/// Some tools write UTC with a `Z` suffix or add an offset instead, which are honoured so that
+/// their items aren't shifted by the offset of the local time zone.
+#[cfg(feature = "chrono")]
+fn parse_deletion_date(value: &str) -> Option<i64> {
+    use chrono::{DateTime, Local, NaiveDateTime, TimeZone};
+
+    if let Ok(time) = DateTime::parse_from_rfc3339(value) {
+        return Some(time.timestamp());
+    }
+    if let Ok(time) = DateTime::parse_from_str(value, "%Y-%m-%dT%H:%M:%S%.f%z") {
+        return Some(time.timestamp());
+    }
+    let naive_local = NaiveDateTime::parse_from_str(value, "%Y-%m-%dT%H:%M:%S%.f").ok()?;
+    // Of the times that are repeated when the clocks go back, the earlier one is taken. Times that
+    // are skipped when the clocks go forward are read with the offset in effect around them.
+    match Local.from_local_datetime(&naive_local).earliest() {
+        Some(time) => Some(time.timestamp()),
+        None => {
+            let offset = Local.offset_from_utc_datetime(&naive_local);
+            offset.from_local_datetime(&naive_local).single().map(|time| time.timestamp())
+        }
+    }
+}
 
-                // Just unwraping here because the system is assumed to follow the specification.
-                let key = split.next().unwrap().trim();
-                let value = split.next().unwrap().trim();
+/// Reads the item with the given id without listing the trash. The id is the path of the item's
+/// trash info file.
+pub(crate) fn item_by_id(id: &OsStr) -> Result<TrashItem, Error> {
+    items_by_ids(&[id]).remove(0)
+}
 
-                if key == "Path" {
-                    let mut value_path = Path::new(value).to_owned();
-                    if value_path.is_relative() {
-                        value_path = top_dir.join(value_path);
-                    }
-                    let full_path_utf8 = PathBuf::from(parse_uri_path(&value_path));
-                    name = Some(full_path_utf8.file_name().unwrap().to_str().unwrap().to_owned());
-                    let parent = full_path_utf8.parent().unwrap();
-                    original_parent = Some(parent.into());
-                } else if key == "DeletionDate" {
-                    #[cfg(feature = "chrono")]
-                    {
-                        use chrono::{NaiveDateTime, TimeZone};
-                        let parsed_time = NaiveDateTime::parse_from_str(value, "%Y-%m-%dT%H:%M:%S");
-                        let naive_local = match parsed_time {
-                            Ok(t) => t,
-                            Err(e) => {
-                                log::error!("Failed to parse the deletion date of the trash item {:?}. The deletion date was '{}'. Parse error was: {:?}", name, value, e);
-                                continue 'trash_item;
-                            }
-                        };
-                        let time = chrono::Local.from_local_datetime(&naive_local).earliest();
-                        match time {
-                            Some(time) => time_deleted = Some(time.timestamp()),
-                            None => {
-                                log::error!(
-                                    "Failed to convert the local time to a UTC time. Local time was {:?}",
-                                    naive_local
-                                );
-                                continue 'trash_item;
-                            }
-                        }
+/// Resolves many ids at once, looking up the mount points only once.
+pub(crate) fn items_by_ids(ids: &[&OsStr]) -> Vec<Result<TrashItem, Error>> {
+    let sorted_mount_points = get_sorted_mount_points();
+    ids.iter()
+        .map(|&id| {
+            let info_path = Path::new(id);
+            let in_info_folder = info_path.parent().and_then(Path::file_name) == Some(OsStr::new("info"));
+            if !in_info_folder || info_path.extension() != Some(OsStr::new("trashinfo")) {
+                return Err(Error::InvalidItemId { id: id.to_owned() });
+            }
+            if !info_path.is_file() {
+                return Err(Error::TargetedItemNotFound { path: info_path.to_owned() });
+            }
+            let sorted_mount_points = sorted_mount_points.as_ref().map_err(|e| Error::CouldNotAccessTrash {
+                path: None,
+                description: format!("Failed to determine the mount point of {info_path:?}: {e}"),
+            })?;
+            let top_dir = get_first_topdir_containing_path(info_path, sorted_mount_points);
+            info_path.symlink_metadata().map_err(|e| fs_error(info_path, e))?;
+            parse_trash_info(info_path, top_dir).ok_or_else(|| {
+                fs_error(info_path, std::io::Error::new(std::io::ErrorKind::InvalidData, "invalid trash info file"))
+            })
+        })
+        .collect()
+}
+
+pub fn metadata(item: &TrashItem) -> Result<TrashItemMetadata, Error> {
+    // When purging an item the "in-trash" filename must be parsed from the trashinfo filename
+    // which is the filename in the `id` field.
+    let info_file = &item.id;
+
+    let file = restorable_file_in_trash_from_info_file(info_file)?;
+    ensure_virtually_exists(&file)?;
+    let metadata = fs::symlink_metadata(&file).map_err(|e| fs_error(&file, e))?;
+    let is_dir = metadata.is_dir();
+    let size = if is_dir {
+        // Counting saturates rather than overflowing `usize` on 32-bit targets.
+        let entries = fs::read_dir(&file).map_err(|e| fs_error(&file, e))?;
+        TrashItemSize::Entries(entries.fold(0usize, |count, _| count.saturating_add(1)))
+    } else {
+        TrashItemSize::Bytes(metadata.len())
+    };
+    // Deviations don't depend on the mount point, so any will do for reading them.
+    let foreign_format = read_trash_info(Path::new(info_file), Path::new("/"))
+        .map(|(_, format)| format)
+        .filter(|format| *format != ForeignFormat::default());
+    Ok(TrashItemMetadata { size, foreign_format })
+}
+
+#[cfg(feature = "mime")]
+pub(crate) fn content_type(item: &TrashItem) -> Result<String, Error> {
+    use crate::{content_type, xdg_mime};
+    use std::io::Read;
+
+    let file = restorable_file_in_trash_from_info_file(&item.id)?;
+    let metadata = fs::symlink_metadata(&file).map_err(|e| fs_error(&file, e))?;
+    if metadata.is_dir() {
+        return Ok(content_type::DIRECTORY.into());
+    } else if metadata.is_symlink() {
+        return Ok("inode/symlink".into());
+    } else if let Some(mime_type) = xdg_mime::from_name(&item.name) {
+        return Ok(mime_type.into());
+    } else if metadata.len() == 0 {
+        return Ok("application/x-zerosize".into());
+    }
+    let mut data = Vec::new();
+    File::open(&file)
+        .and_then(|f| f.take(xdg_mime::magic_len().max(512) as u64).read_to_end(&mut data))
+        .map_err(|e| fs_error(&file, e))?;
+    let mime_type = match xdg_mime::from_data(&data) {
+        Some(mime_type) => mime_type,
+        // Files that look like text are text, as far as the specification is concerned.
+        // The data may end in the middle of a character.
+        None if !data.contains(&0) && std::str::from_utf8(&data).map_or_else(|e| e.error_len().is_none(), |_| true) => {
+            "text/plain"
+        }
+        None => content_type::UNKNOWN,
+    };
+    Ok(mime_type.into())
+}
+
+#[cfg(feature = "thumbnails")]
+pub(crate) fn icon(item: &TrashItem, size: u32) -> Result<crate::Icon, Error> {
+    use crate::{icon_theme, xdg_mime};
+
+    let file = restorable_file_in_trash_from_info_file(&item.id)?;
+    let metadata = fs::symlink_metadata(&file).map_err(|e| fs_error(&file, e))?;
+    let names = if metadata.is_dir() {
+        vec!["inode-directory".to_owned(), "folder".to_owned()]
+    } else if metadata.is_symlink() {
+        vec!["inode-symlink".to_owned(), "application-x-generic".to_owned()]
+    } else {
+        xdg_mime::icon_names(xdg_mime::from_name(&item.name).unwrap_or("application/octet-stream"))
+    };
+    let path = icon_theme::find_icon(&names, size).ok_or_else(|| {
+        let not_found = std::io::Error::new(std::io::ErrorKind::NotFound, "no icon in the icon themes");
+        fs_error(Path::new(&names[0]), not_found)
+    })?;
+    icon_from_png(&path, size)
+}
+
+/// Decodes the PNG file at `path` and fits it into a square of `size` pixels.
+#[cfg(feature = "thumbnails")]
+fn icon_from_png(path: &Path, size: u32) -> Result<crate::Icon, Error> {
+    let data = fs::read(path).map_err(|e| fs_error(path, e))?;
+    let image = crate::png::decode(&data).ok_or_else(|| {
+        fs_error(path, std::io::Error::new(std::io::ErrorKind::InvalidData, "the icon isn't a valid PNG image"))
+    })?;
+    Ok(crate::icon::fit(crate::Icon { width: image.width, height: image.height, rgba: image.rgba }, size))
+}
+
+/// Kernel interfaces that are mounted like file systems but never hold user files.
+const PSEUDO_FILE_SYSTEMS: &[&str] = &[
+    "autofs",
+    "binfmt_misc",
+    "bpf",
+    "cgroup",
+    "cgroup2",
+    "configfs",
+    "debugfs",
+    "devpts",
+    "devtmpfs",
+    "fusectl",
+    "hugetlbfs",
+    "mqueue",
+    "proc",
+    "pstore",
+    "securityfs",
+    "sysfs",
+    "tracefs",
+];
+
+/// Returns the mount points of all file systems that may hold user files.
+pub(crate) fn volumes() -> Result<Vec<PathBuf>, Error> {
+    let mut volumes: Vec<_> = get_mount_points()?
+        .into_iter()
+        .filter(|mp| !PSEUDO_FILE_SYSTEMS.contains(&mp.mnt_type.as_str()))
+        .filter(|mp| !["/proc", "/sys", "/dev"].iter().any(|pseudo| mp.mnt_dir.starts_with(pseudo)))
+        .map(|mp| mp.mnt_dir)
+        .collect();
+    volumes.sort_unstable();
+    volumes.dedup();
+    Ok(volumes)
+}
+
+/// Returns `true` if the canonicalized `path` is a trash folder, is inside of one, or contains the
+/// home trash.
+pub(crate) fn is_trash_folder(path: &Path) -> bool {
+    if let Ok(home_trash) = home_trash() {
+        let home_trash = home_trash.canonicalize().unwrap_or(home_trash);
+        if path.starts_with(&home_trash) || home_trash.starts_with(path) {
+            return true;
+        }
+    }
+    let Ok(mount_points) = get_mount_points() else {
+        return false;
+    };
+    let uid_trash = format!(".Trash-{}", unsafe { libc::getuid() });
+    path.ancestors().any(|ancestor| {
+        let (Some(name), Some(parent)) = (ancestor.file_name(), ancestor.parent()) else {
+            return false;
+        };
+        (name == ".Trash" || name == uid_trash.as_str()) && mount_points.iter().any(|mp| mp.mnt_dir == parent)
+    })
+}
+
+/// The path points to:
+/// - existing file | directory | symlink => Ok(true)
+/// - broken symlink => Ok(true)
+/// - nothing => Ok(false)
+/// - I/O Error => Err(Io)
+#[inline]
+fn virtually_exists(path: &Path) -> std::io::Result<bool> {
+    Ok(path.try_exists()? || path.is_symlink())
+}
+
+impl TrashContext {
+    pub(crate) fn purge_items<I>(&self, items: I) -> Result<(), Error>
+    where
+        I: IntoIterator,
+        <I as IntoIterator>::Item: Borrow<TrashItem>,
+    {
+        let items: Vec<_> = items.into_iter().collect();
+        let items: Vec<&TrashItem> = items.iter().map(Borrow::borrow).collect();
+        let errors = parallel::run(&items, self.purge_concurrency, !self.continue_on_error, |item| {
+            self.check_cancelled()?;
+            purge(item)
+        });
+        let mut first_error = None;
+        for (_, error) in errors {
+            self.handle_item_error(error, &mut first_error)?;
+        }
+        first_error.map_or(Ok(()), Err)
+    }
+
+    /// Restores the items and returns the paths they were restored to.
+    pub(crate) fn restore_items(&self, items: Vec<TrashItem>) -> Result<Vec<PathBuf>, Error> {
+        restore_all(self, items, None)
+    }
+
+    /// Empties the home trash and the trash folders of all mount points, without parsing the
+    /// trash info files.
+    pub(crate) fn empty_trash(&self) -> Result<(), Error> {
+        let uid = unsafe { libc::getuid() };
+        let sorted_mount_points = get_sorted_mount_points()?;
+        let trash_folders = trash_folders_of(uid, self.platform_specific.home_trash(), &sorted_mount_points)?;
+        let mut first_error = None;
+        for folder in &trash_folders {
+            self.empty_trash_folder(folder, &mut first_error)?;
+        }
+        first_error.map_or(Ok(()), Err)
+    }
+
+    /// Removes the items of the trash folder, followed by the files that have no trash info file
+    /// and the cached sizes of the directories.
+    fn empty_trash_folder(&self, trash_folder: &Path, first_error: &mut Option<Error>) -> Result<(), Error> {
+        let info_folder = trash_folder.join("info");
+        let info_files: Vec<_> = match std::fs::read_dir(&info_folder) {
+            Ok(entries) => entries
+                .flatten()
+                .map(|entry| entry.path())
+                .filter(|path| path.extension() == Some(OsStr::new("trashinfo")))
+                .collect(),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Vec::new(),
+            Err(e) => return self.handle_item_error(fs_error(info_folder, e), first_error),
+        };
+        let errors = parallel::run(&info_files, self.purge_concurrency, !self.continue_on_error, |info_file| {
+            self.check_cancelled()?;
+            remove_entry(info_file)
+        });
+        for (_, error) in errors {
+            self.handle_item_error(error, first_error)?;
+        }
+        if let Err(e) = empty_orphans(trash_folder) {
+            self.handle_item_error(e, first_error)?;
+        }
+        Ok(())
+    }
+}
+
+/// Removes the cached directory sizes of a trash folder and the files that are left in it.
+fn empty_orphans(trash_folder: &Path) -> Result<(), Error> {
+    let sizes = trash_folder.join("directorysizes");
+    match std::fs::remove_file(&sizes) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(fs_error(&sizes, e)),
+        _ => {}
+    }
+    let files = trash_folder.join("files");
+    let entries = match std::fs::read_dir(&files) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+        Err(e) => return Err(fs_error(&files, e)),
+    };
+    for entry in entries {
+        let path = entry.map_err(|e| fs_error(&files, e))?.path();
+        remove_file_in_trash(&path)?;
+    }
+    Ok(())
+}
+
+/// Removes the file of a trash info file, if there is one, and then the info file.
+fn remove_entry(info_file: &Path) -> Result<(), Error> {
+    remove_file_in_trash(&restorable_file_in_trash_from_info_file(info_file)?)?;
+    std::fs::remove_file(info_file).map_err(|e| fs_error(info_file, e))
+}
+
+/// Removes a file or folder within the `files` folder of a trash, without following links.
+fn remove_file_in_trash(path: &Path) -> Result<(), Error> {
+    let result = match path.symlink_metadata() {
+        Ok(metadata) if metadata.is_dir() => std::fs::remove_dir_all(path),
+        Ok(_) => std::fs::remove_file(path),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+        Err(e) => Err(e),
+    };
+    result.map_err(|e| fs_error(path, e))
+}
+
+fn purge(item: &TrashItem) -> Result<(), Error> {
+    // When purging an item the "in-trash" filename must be parsed from the trashinfo filename
+    // which is the filename in the `id` field.
+    let info_file = &item.id;
+
+    // A bunch of unwraps here. This is fine because if any of these fail that means
+    // that either there's a bug in this code or the target system didn't follow
+    // the specification.
+    let file = restorable_file_in_trash_from_info_file(info_file)?;
+    if file.is_dir() {
+        std::fs::remove_dir_all(&file).map_err(|e| fs_error(&file, e))?;
+    // TODO Update directory size cache if there's one.
+    } else {
+        std::fs::remove_file(&file).map_err(|e| fs_error(&file, e))?;
+    }
+    std::fs::remove_file(info_file).map_err(|e| fs_error(info_file, e))?;
+    Ok(())
+}
+
+/// The key of the trash info file that marks an item as pinned. The specification asks
+/// implementations to ignore the keys they don't know, so other file managers keep working.
+const PINNED_KEY: &str = "X-Pinned";
+
+/// Adds the [`PINNED_KEY`] to the trash info file of the item, or removes it.
+///
+/// The info file is replaced by a copy, so that listing never sees it half written, and the copy
+/// keeps the modification time, which some tools take as the deletion time.
+pub(crate) fn set_pinned(item: &TrashItem, pinned: bool) -> Result<(), Error> {
+    let info_file = Path::new(&item.id);
+    let (Some(info_folder), Some(info_name)) = (info_file.parent(), info_file.file_name()) else {
+        return Err(Error::InvalidItemId { id: item.id.clone() });
+    };
+    let metadata = info_file.metadata().map_err(|e| fs_error(info_file, e))?;
+    let contents = std::fs::read(info_file).map_err(|e| fs_error(info_file, e))?;
+    let pinned_line = format!("{PINNED_KEY}=true");
+    let mut lines: Vec<&[u8]> = contents
+        .split(|&b| b == b'\n')
+        .filter(|line| !line.is_empty() && !line.starts_with(format!("{PINNED_KEY}=").as_bytes()))
+        .collect();
+    if pinned {
+        lines.push(pinned_line.as_bytes());
+    }
+    let mut contents = lines.join(&b'\n');
+    contents.push(b'\n');
+
+    // The name doesn't end with `.trashinfo`, so the copy isn't listed if it's left behind.
+    let mut temp_name = OsString::from(".");
+    temp_name.push(info_name);
+    temp_name.push(format!(".{}.tmp", std::process::id()));
+    let temp_file = info_folder.join(temp_name);
+    let written = File::create(&temp_file).and_then(|mut file| {
+        file.write_all(&contents)?;
+        file.set_permissions(metadata.permissions())?;
+        file.set_times(fs::FileTimes::new().set_accessed(metadata.accessed()?).set_modified(metadata.modified()?))
+    });
+    if let Err(e) = written.and_then(|()| std::fs::rename(&temp_file, info_file)) {
+        let _ = std::fs::remove_file(&temp_file);
+        return Err(fs_error(&temp_file, e));
+    }
+    Ok(())
+}
+
+/// The file or folder of the item within the `files` folder of its trash.
+pub(crate) fn file_in_trash(item: &TrashItem) -> Result<PathBuf, Error> {
+    restorable_file_in_trash_from_info_file(&item.id)
+}
+
+/// Returns the `file://` URIs of the files in the trash as `text/uri-list`.
+pub(crate) fn uri_list(items: &[TrashItem]) -> String {
+    items
+        .iter()
+        .filter_map(|item| url::Url::from_file_path(restorable_file_in_trash_from_info_file(&item.id).ok()?).ok())
+        .map(|url| format!("{url}\r\n"))
+        .collect()
+}
+
+fn restorable_file_in_trash_from_info_file(info_file: impl AsRef<std::ffi::OsStr>) -> Result<PathBuf, Error> {
+    let info_file = Path::new(info_file.as_ref());
+    match (info_file.parent().and_then(Path::parent), info_file.file_stem()) {
+        (Some(trash_folder), Some(name_in_trash)) => Ok(trash_folder.join("files").join(name_in_trash)),
+        _ => Err(Error::InvalidItemId { id: info_file.as_os_str().to_owned() }),
+    }
+}
+
+/// Fails with a [`std::io::ErrorKind::NotFound`] error if nothing exists at `path`, not even a
+/// broken symlink.
+fn ensure_virtually_exists(path: &Path) -> Result<(), Error> {
+    match virtually_exists(path) {
+        Ok(true) => Ok(()),
+        Ok(false) => Err(fs_error(path, std::io::ErrorKind::NotFound.into())),
+        Err(e) => Err(fs_error(path, e)),
+    }
+}
+
+/// Restores the items, handing the ownership of the restored files over to `owner` if it's
+/// specified. Items that can't be restored by the current user are moved through polkit if `ctx`
+/// elevates. Items whose original path is taken are handled according to the
+/// [`restore_collision`](TrashContext::restore_collision) of `ctx`. Returns the paths the items
+/// were restored to.
+fn restore_all<I>(ctx: &TrashContext, items: I, owner: Option<&UserAccount>) -> Result<Vec<PathBuf>, Error>
+where
+    I: IntoIterator<Item = TrashItem>,
+{
+    let elevate = ctx.platform_specific.elevates();
+    let collision = ctx.restore_collision();
+    // Simply read the items' original location from the infofile and attemp to move the items there
+    // and delete the infofile if the move operation was sucessful.
+
+    let mut iter = items.into_iter();
+    let mut targets = Vec::new();
+    'items: while let Some(item) = iter.next() {
+        ctx.check_cancelled()?;
+        // The "in-trash" filename must be parsed from the trashinfo filename
+        // which is the filename in the `id` field.
+        let info_file = &item.id;
+
+        let file = restorable_file_in_trash_from_info_file(info_file)?;
+        ensure_virtually_exists(&file)?;
+        let original_path = item.known_original_path()?;
+        let original_parent = item.original_parent.clone().unwrap_or_default();
+        let mut target = original_path.clone();
+        if elevate && !can_create_in(&original_parent) {
+            if target.symlink_metadata().is_ok() {
+                match collision {
+                    RestoreCollision::Skip => continue,
+                    RestoreCollision::RenameWithSuffix => target = collision::free_path(&original_path),
+                    // Removing the existing item would need another authorization.
+                    RestoreCollision::Fail | RestoreCollision::Overwrite => {
+                        let remaining: Vec<_> = std::iter::once(item).chain(iter).collect();
+                        return Err(Error::RestoreCollision { path: original_path, remaining_items: remaining });
                     }
                 }
             }
-            if let Some(name) = name {
-                if let Some(original_parent) = original_parent {
-                    if time_deleted.is_none() {
-                        warn!("Could not determine the deletion time of the trash item. (The `DeletionDate` field is probably missing from the info file.) The info file path is: '{:?}'", info_path);
-                    }
-                    result.push(TrashItem { id, name, original_parent, time_deleted: time_deleted.unwrap_or(-1) });
-                } else {
-                    warn!("Could not determine the original parent folder of the trash item. (The `Path` field is probably missing from the info file.) The info file path is: '{:?}'", info_path);
+            move_with_polkit(&file, &target).map_err(|(p, e)| fs_error(p, e))?;
+            std::fs::remove_file(info_file).map_err(|e| fs_error(info_file, e))?;
+            targets.push(target);
+            continue;
+        }
+        // Make sure the parent exists so that `create_dir` doesn't faile due to that.
+        std::fs::create_dir_all(&original_parent).map_err(|e| fs_error(&original_parent, e))?;
+        // The target is reserved with an empty file or folder, which the rename then replaces, so
+        // that an item that appears at the target in the meantime isn't overwritten.
+        while !reserve(&target, file.is_dir()).map_err(|e| fs_error(&target, e))? {
+            match collision {
+                RestoreCollision::Fail => {
+                    let remaining: Vec<_> = std::iter::once(item).chain(iter).collect();
+                    return Err(Error::RestoreCollision { path: original_path, remaining_items: remaining });
                 }
-            } else {
-                warn!("Could not determine the name of the trash item. (The `Path` field is probably missing from the info file.) The info file path is: '{:?}'", info_path);
+                RestoreCollision::Overwrite if collision::trash_existing(ctx, &target)? => {}
+                RestoreCollision::Overwrite => {
+                    let remaining: Vec<_> = std::iter::once(item).chain(iter).collect();
+                    return Err(Error::RestoreCollision { path: original_path, remaining_items: remaining });
+                }
+                RestoreCollision::RenameWithSuffix => target = collision::free_path(&original_path),
+                RestoreCollision::Skip => continue 'items,
             }
         }
+        move_onto_reserved(&file, &target).map_err(|e| fs_error(&file, e))?;
+        std::fs::remove_file(info_file).map_err(|e| fs_error(info_file, e))?;
+        if let Some(owner) = owner {
+            chown_recursive(&target, owner.uid, owner.gid).map_err(|(p, e)| fs_error(p, e))?;
+        }
+        targets.push(target);
+    }
+    Ok(targets)
+}
+
+/// Moves the item from the trash onto the empty file or folder that [`reserve`] created at
+/// `target`. If they're on different devices, the item is copied into the placeholder and removed
+/// from the trash once the copy is complete. The placeholder, along with a partial copy, is removed
+/// if the item can't be moved.
+fn move_onto_reserved(file: &Path, target: &Path) -> std::io::Result<()> {
+    match std::fs::rename(file, target) {
+        Err(e) if e.kind() == std::io::ErrorKind::CrossesDevices => {}
+        Err(e) => {
+            let _ = collision::remove_existing(target);
+            return Err(e);
+        }
+        Ok(()) => return Ok(()),
+    }
+    let copied = match file.symlink_metadata() {
+        // The placeholder of a symbolic link is replaced by the link itself.
+        Ok(metadata) if metadata.is_symlink() => {
+            collision::remove_existing(target).and_then(|()| merge::copy_entry(file, target))
+        }
+        Ok(metadata) if metadata.is_dir() => std::fs::read_dir(file)
+            .and_then(|entries| {
+                entries.into_iter().try_for_each(|entry| {
+                    let entry = entry?;
+                    merge::copy_entry(&entry.path(), &target.join(entry.file_name()))
+                })
+            })
+            .and_then(|()| std::fs::set_permissions(target, metadata.permissions())),
+        Ok(_) => std::fs::copy(file, target).map(drop),
+        Err(e) => Err(e),
+    };
+    if let Err(e) = copied {
+        let _ = collision::remove_existing(target);
+        return Err(e);
+    }
+    collision::remove_existing(file)
+}
+
+/// Creates an empty folder or file at `path`, or returns `false` if something exists there.
+fn reserve(path: &Path, is_dir: bool) -> std::io::Result<bool> {
+    // NOTE create_dir_all succeeds when the path already exist but create_dir
+    // fails with `std::io::ErrorKind::AlreadyExists`.
+    let result = if is_dir {
+        std::fs::create_dir(path)
+    } else {
+        // File or symlink
+        OpenOptions::new().create_new(true).write(true).open(path).map(drop)
+    };
+    match result {
+        Ok(()) => Ok(true),
+        Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => Ok(false),
+        Err(e) => Err(e),
     }
-    Ok(result)
 }
 
-pub fn metadata(item: &TrashItem) -> Result<TrashItemMetadata, Error> {
-    // When purging an item the "in-trash" filename must be parsed from the trashinfo filename
-    // which is the filename in the `id` field.
-    let info_file = &item.id;
+/// Whether the current user may remove `path` from its folder. Removing from a sticky folder, like
+/// `/tmp`, additionally requires owning either the folder or the item.
+fn can_remove(path: &Path) -> bool {
+    use std::os::unix::fs::MetadataExt;
 
-    let file = restorable_file_in_trash_from_info_file(info_file);
-    assert!(virtually_exists(&file).map_err(|e| fs_error(&file, e))?);
-    let metadata = fs::symlink_metadata(&file).map_err(|e| fs_error(&file, e))?;
-    let is_dir = metadata.is_dir();
-    let size = if is_dir {
-        TrashItemSize::Entries(fs::read_dir(&file).map_err(|e| fs_error(&file, e))?.count())
+    let Some(folder) = path.parent() else {
+        return false;
+    };
+    if !is_writable(folder) {
+        return false;
+    }
+    let uid = unsafe { libc::getuid() };
+    match (folder.metadata(), path.symlink_metadata()) {
+        (Ok(folder), Ok(item)) => folder.mode() & libc::S_ISVTX == 0 || uid == folder.uid() || uid == item.uid(),
+        _ => true,
+    }
+}
+
+/// Whether `user` may remove `path` from its folder by themselves, judged by the permission bits of
+/// the folder. Membership in supplementary groups isn't considered.
+#[cfg(feature = "helper")]
+pub(crate) fn user_can_remove(path: &Path, user: &UserAccount) -> bool {
+    use std::os::unix::fs::MetadataExt;
+
+    let (Some(folder), Ok(item)) = (path.parent(), path.symlink_metadata()) else {
+        return false;
+    };
+    let Ok(folder) = folder.metadata() else {
+        return false;
+    };
+    if user.uid == 0 {
+        return true;
+    }
+    let mode = folder.mode();
+    let permissions = if folder.uid() == user.uid {
+        mode >> 6
+    } else if folder.gid() == user.gid {
+        mode >> 3
     } else {
-        TrashItemSize::Bytes(metadata.len())
+        mode
     };
-    Ok(TrashItemMetadata { size })
+    // Removing needs write and search permission on the folder.
+    permissions & 0o3 == 0o3 && (mode & libc::S_ISVTX == 0 || user.uid == folder.uid() || user.uid == item.uid())
 }
 
-/// The path points to:
-/// - existing file | directory | symlink => Ok(true)
-/// - broken symlink => Ok(true)
-/// - nothing => Ok(false)
-/// - I/O Error => Err(Io)
-#[inline]
-fn virtually_exists(path: &Path) -> std::io::Result<bool> {
-    Ok(path.try_exists()? || path.is_symlink())
+/// Whether the current user may create `folder` if it's missing and create items within it.
+fn can_create_in(folder: &Path) -> bool {
+    folder.ancestors().find(|p| p.symlink_metadata().is_ok()).is_some_and(is_writable)
 }
 
-pub fn purge_all<I>(items: I) -> Result<(), Error>
-where
-    I: IntoIterator,
-    <I as IntoIterator>::Item: Borrow<TrashItem>,
-{
-    for item in items.into_iter() {
-        // When purging an item the "in-trash" filename must be parsed from the trashinfo filename
-        // which is the filename in the `id` field.
-        let info_file = &item.borrow().id;
-
-        // A bunch of unwraps here. This is fine because if any of these fail that means
-        // that either there's a bug in this code or the target system didn't follow
-        // the specification.
-        let file = restorable_file_in_trash_from_info_file(info_file);
-        if file.is_dir() {
-            std::fs::remove_dir_all(&file).map_err(|e| fs_error(&file, e))?;
-        // TODO Update directory size cache if there's one.
-        } else {
-            std::fs::remove_file(&file).map_err(|e| fs_error(&file, e))?;
+/// Whether the current user may create items in `folder`.
+pub(crate) fn is_writable(folder: &Path) -> bool {
+    let Ok(folder) = std::ffi::CString::new(folder.as_os_str().as_bytes()) else {
+        return false;
+    };
+    unsafe { libc::access(folder.as_ptr(), libc::W_OK | libc::X_OK) == 0 }
+}
+
+/// Moves `src` to `dst` as root, creating the missing parent folders of `dst`. Authorization is
+/// requested through polkit every time.
+fn move_with_polkit(src: &Path, dst: &Path) -> Result<(), FsError> {
+    #[cfg(feature = "polkit")]
+    {
+        use std::io::{Error as IoError, ErrorKind};
+
+        const SCRIPT: &str = r#"mkdir -p -- "$(dirname -- "$2")" && mv -T -- "$1" "$2""#;
+        debug!("Moving {:?} to {:?} through polkit", src, dst);
+        let output = std::process::Command::new("pkexec")
+            .args(["/bin/sh", "-c", SCRIPT, "trash-rs"])
+            .arg(src)
+            .arg(dst)
+            .output()
+            .map_err(|e| (src.to_owned(), e))?;
+        match output.status.code() {
+            Some(0) => Ok(()),
+            // `pkexec` exits with these codes when the authorization is dismissed or denied.
+            Some(126 | 127) => Err((
+                src.to_owned(),
+                IoError::new(ErrorKind::PermissionDenied, "the authorization through polkit was not granted"),
+            )),
+            _ => Err((
+                src.to_owned(),
+                IoError::other(format!(
+                    "the move through polkit failed: {}",
+                    String::from_utf8_lossy(&output.stderr).trim()
+                )),
+            )),
         }
-        std::fs::remove_file(info_file).map_err(|e| fs_error(info_file, e))?;
     }
+    #[cfg(not(feature = "polkit"))]
+    Err((src.to_owned(), std::io::Error::new(std::io::ErrorKind::Unsupported, format!("can't move to {dst:?}"))))
+}
 
+/// Changes the owner of `path` and everything within it, without following symbolic links.
+fn chown_recursive(path: &Path, uid: libc::uid_t, gid: libc::gid_t) -> Result<(), FsError> {
+    let mut pending = vec![path.to_owned()];
+    while let Some(path) = pending.pop() {
+        std::os::unix::fs::lchown(&path, Some(uid), Some(gid)).map_err(|e| (path.clone(), e))?;
+        if path.symlink_metadata().map_err(|e| (path.clone(), e))?.is_dir() {
+            for entry in fs::read_dir(&path).map_err(|e| (path.clone(), e))? {
+                pending.push(entry.map_err(|e| (path.clone(), e))?.path());
+            }
+        }
+    }
     Ok(())
 }
 
-fn restorable_file_in_trash_from_info_file(info_file: impl AsRef<std::ffi::OsStr>) -> PathBuf {
-    let info_file = info_file.as_ref();
-    let trash_folder = Path::new(info_file).parent().unwrap().parent().unwrap();
-    let name_in_trash = Path::new(info_file).file_stem().unwrap();
-    trash_folder.join("files").join(name_in_trash)
+/// A user account, as found in the user database.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub(crate) struct UserAccount {
+    pub(crate) uid: libc::uid_t,
+    pub(crate) gid: libc::gid_t,
+    pub(crate) home: PathBuf,
 }
 
-pub fn restore_all<I>(items: I) -> Result<(), Error>
-where
-    I: IntoIterator<Item = TrashItem>,
-{
-    // Simply read the items' original location from the infofile and attemp to move the items there
-    // and delete the infofile if the move operation was sucessful.
+impl UserAccount {
+    pub(crate) fn from_name(name: &str) -> Result<Self, Error> {
+        let c_name = std::ffi::CString::new(name).map_err(|_| Error::Os {
+            code: libc::EINVAL,
+            function: Some("getpwnam_r".into()),
+            description: format!("The user name {name:?} contains a NUL character"),
+        })?;
+        Self::lookup(name, "getpwnam_r", |passwd, buffer, len, result| unsafe {
+            libc::getpwnam_r(c_name.as_ptr(), passwd, buffer, len, result)
+        })
+    }
 
-    let mut iter = items.into_iter();
-    while let Some(item) = iter.next() {
-        // The "in-trash" filename must be parsed from the trashinfo filename
-        // which is the filename in the `id` field.
-        let info_file = &item.id;
+    pub(crate) fn from_uid(uid: libc::uid_t) -> Result<Self, Error> {
+        Self::lookup(&uid.to_string(), "getpwuid_r", |passwd, buffer, len, result| unsafe {
+            libc::getpwuid_r(uid, passwd, buffer, len, result)
+        })
+    }
 
-        // A bunch of unwraps here. This is fine because if any of these fail that means
-        // that either there's a bug in this code or the target system didn't follow
-        // the specification.
-        let file = restorable_file_in_trash_from_info_file(info_file);
-        assert!(virtually_exists(&file).map_err(|e| fs_error(&file, e))?);
-        // TODO add option to forcefully replace any target at the restore location
-        // if it already exists.
-        let original_path = item.original_path();
-        // Make sure the parent exists so that `create_dir` doesn't faile due to that.
-        std::fs::create_dir_all(&item.original_parent).map_err(|e| fs_error(&item.original_parent, e))?;
-        let mut collision = false;
-        if file.is_dir() {
-            // NOTE create_dir_all succeeds when the path already exist but create_dir
-            // fails with `std::io::ErrorKind::AlreadyExists`.
-            if let Err(e) = std::fs::create_dir(&original_path) {
-                if e.kind() == std::io::ErrorKind::AlreadyExists {
-                    collision = true;
-                } else {
-                    return Err(fs_error(&original_path, e));
-                }
+    /// Calls one of the `getpw*_r` functions with a growing buffer until it fits the entry, or the
+    /// buffer reaches a size no sane entry needs.
+    fn lookup(
+        user: &str,
+        function: &str,
+        getpw: impl Fn(*mut libc::passwd, *mut libc::c_char, libc::size_t, *mut *mut libc::passwd) -> libc::c_int,
+    ) -> Result<Self, Error> {
+        const MAX_BUFFER_LEN: usize = 1 << 20;
+
+        let mut buffer: Vec<libc::c_char> = vec![0; 1024];
+        loop {
+            let mut passwd: libc::passwd = unsafe { std::mem::zeroed() };
+            let mut result = std::ptr::null_mut();
+            let code = getpw(&mut passwd, buffer.as_mut_ptr(), buffer.len(), &mut result);
+            if code == libc::ERANGE && buffer.len() < MAX_BUFFER_LEN {
+                let len = buffer.len() * 2;
+                buffer.resize(len, 0);
+                continue;
             }
-        } else {
-            // File or symlink
-            if let Err(e) = OpenOptions::new().create_new(true).write(true).open(&original_path) {
-                if e.kind() == std::io::ErrorKind::AlreadyExists {
-                    collision = true;
-                } else {
-                    return Err(fs_error(&original_path, e));
+            if code != 0 {
+                return Err(Error::Os {
+                    code,
+                    function: Some(function.into()),
+                    description: format!(
+                        "Failed to look up the user {user:?}: {}",
+                        std::io::Error::from_raw_os_error(code)
+                    ),
+                });
+            }
+            if result.is_null() {
+                return Err(Error::Os {
+                    code: libc::ENOENT,
+                    function: Some(function.into()),
+                    description: format!("There is no user {user:?}"),
+                });
+            }
+            let home = unsafe { std::ffi::CStr::from_ptr(passwd.pw_dir) };
+            let home = PathBuf::from(std::ffi::OsStr::from_bytes(home.to_bytes()));
+            return Ok(UserAccount { uid: passwd.pw_uid, gid: passwd.pw_gid, home });
+        }
+    }
+}
+
+pub(crate) fn list_of_user(user: &UserAccount) -> Result<Vec<TrashItem>, Error> {
+    // The environment of the other user isn't available, so `XDG_DATA_HOME` is assumed to be unset.
+    list_trash_of(user.uid, Ok(user.home.join(".local/share/Trash")), None)
+}
+
+pub(crate) fn restore_all_of_user(user: &UserAccount, items: Vec<TrashItem>) -> Result<(), Error> {
+    restore_all(&TrashContext::new(), items, Some(user)).map(drop)
+}
+
+/// Notices changes to the trash folders of the current user.
+///
+/// Uses inotify on Linux. On other systems, or when inotify isn't available, a change is reported
+/// every few seconds, so that the trash is polled instead.
+pub(crate) struct ChangeMonitor {
+    #[cfg(target_os = "linux")]
+    inotify: Option<std::os::fd::OwnedFd>,
+    last_poll: Instant,
+}
+
+impl ChangeMonitor {
+    const POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+    pub(crate) fn new() -> Self {
+        #[cfg(target_os = "linux")]
+        let inotify = {
+            use std::os::fd::FromRawFd;
+
+            let fd = unsafe { libc::inotify_init1(libc::IN_NONBLOCK | libc::IN_CLOEXEC) };
+            if fd < 0 {
+                warn!("Failed to initialize inotify, polling the trash instead: {}", std::io::Error::last_os_error());
+                None
+            } else {
+                Some(unsafe { std::os::fd::OwnedFd::from_raw_fd(fd) })
+            }
+        };
+        let mut monitor = ChangeMonitor {
+            #[cfg(target_os = "linux")]
+            inotify,
+            last_poll: Instant::now(),
+        };
+        monitor.refresh();
+        monitor
+    }
+
+    /// Starts watching the trash folders that were created since the last call.
+    pub(crate) fn refresh(&mut self) {
+        #[cfg(target_os = "linux")]
+        if let Some(inotify) = &self.inotify {
+            use std::os::fd::AsRawFd;
+
+            let mask = libc::IN_CREATE
+                | libc::IN_DELETE
+                | libc::IN_MOVED_FROM
+                | libc::IN_MOVED_TO
+                | libc::IN_CLOSE_WRITE
+                | libc::IN_ONLYDIR;
+            for folder in watched_folders() {
+                let Ok(path) = std::ffi::CString::new(folder.as_os_str().as_bytes()) else {
+                    continue;
+                };
+                // Adding a watch for a folder that is already watched only updates its mask.
+                if unsafe { libc::inotify_add_watch(inotify.as_raw_fd(), path.as_ptr(), mask) } < 0 {
+                    debug!("Failed to watch {:?}: {}", folder, std::io::Error::last_os_error());
                 }
             }
         }
-        if collision {
-            let remaining: Vec<_> = std::iter::once(item).chain(iter).collect();
-            return Err(Error::RestoreCollision { path: original_path, remaining_items: remaining });
+    }
+
+    /// Waits at most `timeout` for a change. Returns `true` if the trash may have changed.
+    pub(crate) fn wait(&mut self, timeout: Duration) -> bool {
+        #[cfg(target_os = "linux")]
+        if let Some(inotify) = &self.inotify {
+            use std::os::fd::AsRawFd;
+
+            let mut poll_fd = libc::pollfd { fd: inotify.as_raw_fd(), events: libc::POLLIN, revents: 0 };
+            let timeout = timeout.as_millis().min(libc::c_int::MAX as u128) as libc::c_int;
+            if unsafe { libc::poll(&mut poll_fd, 1, timeout) } <= 0 {
+                return false;
+            }
+            // The trash is listed again anyway, so the events themselves are discarded.
+            let mut buffer = [0u8; 4096];
+            while unsafe { libc::read(inotify.as_raw_fd(), buffer.as_mut_ptr().cast(), buffer.len()) } > 0 {}
+            return true;
         }
-        std::fs::rename(&file, &original_path).map_err(|e| fs_error(&file, e))?;
-        std::fs::remove_file(info_file).map_err(|e| fs_error(info_file, e))?;
+        std::thread::sleep(timeout);
+        if self.last_poll.elapsed() < Self::POLL_INTERVAL {
+            return false;
+        }
+        self.last_poll = Instant::now();
+        true
     }
-    Ok(())
+}
+
+/// The folders whose changes affect the listing of the trash. When the home trash doesn't exist
+/// yet, its closest existing ancestor is watched to notice when it's created.
+#[cfg(target_os = "linux")]
+fn watched_folders() -> Vec<PathBuf> {
+    let mut folders = Vec::new();
+    if let Ok(home_trash) = home_trash() {
+        if home_trash.is_dir() {
+            folders.push(home_trash.join("info"));
+        } else {
+            folders.extend(home_trash.ancestors().skip(1).find(|p| p.is_dir()).map(Path::to_owned));
+        }
+    }
+    let uid = unsafe { libc::getuid() };
+    for mount in get_sorted_mount_points().unwrap_or_default() {
+        let _ = execute_on_mounted_trash_folders(uid, &mount.mnt_dir, false, false, |trash_path| {
+            folders.push(trash_path.join("info"));
+            Ok(())
+        });
+    }
+    folders
 }
 
 /// According to the specification (see at the top of the file) there are two kinds of
@@ -387,6 +1654,9 @@ fn move_to_trash(
     src: impl AsRef<Path>,
     trash_folder: impl AsRef<Path>,
     _topdir: impl AsRef<Path>,
+    elevate: bool,
+    owner: Option<&UserAccount>,
+    cancellation: Option<&CancellationToken>,
 ) -> Result<(), FsError> {
     let src = src.as_ref();
     let trash_folder = trash_folder.as_ref();
@@ -396,6 +1666,15 @@ fn move_to_trash(
     // Ensure the `files` and `info` folders exist
     std::fs::create_dir_all(&files_folder).map_err(|e| (files_folder.to_owned(), e))?;
     std::fs::create_dir_all(&info_folder).map_err(|e| (info_folder.to_owned(), e))?;
+    let hand_over = |path: &Path| match owner {
+        Some(owner) => {
+            std::os::unix::fs::lchown(path, Some(owner.uid), Some(owner.gid)).map_err(|e| (path.to_owned(), e))
+        }
+        None => Ok(()),
+    };
+    for folder in [trash_folder, &files_folder, &info_folder] {
+        hand_over(folder)?;
+    }
 
     // This kind of validity must only apply ot administrator style trash folders
     // See Trash directories, (1) at https://specifications.freedesktop.org/trash-spec/trashspec-1.0.html
@@ -411,50 +1690,43 @@ fn move_to_trash(
     // try creating a placeholder file in the trash but don't do so if one with an identical name
     // already exist. This newly created empty file can then be safely overwritten by the src file
     // using the `rename` function.
-    let filename = src.file_name().unwrap();
+    let filename = src.file_name().ok_or_else(|| {
+        (src.to_owned(), std::io::Error::new(std::io::ErrorKind::InvalidInput, "the path has no file name"))
+    })?;
+    let absolute_uri = encode_uri_path(src).map_err(|e| (src.to_owned(), e))?;
+    #[cfg(feature = "chrono")]
+    let deletion_date = Some(chrono::Local::now().format("%Y-%m-%dT%H:%M:%S").to_string());
+    #[cfg(not(feature = "chrono"))]
+    let deletion_date: Option<String> = None;
     let mut appendage = 0;
     loop {
         appendage += 1;
-        let in_trash_name = if appendage > 1 {
-            format!("{}.{}", filename.to_str().unwrap(), appendage)
-        } else {
-            filename.to_str().unwrap().into()
-        };
-        let info_name = format!("{in_trash_name}.trashinfo");
+        let (in_trash_name, info_name) = trash_info::names(filename, appendage);
         let info_file_path = info_folder.join(&info_name);
-        let info_result = OpenOptions::new().create_new(true).write(true).open(&info_file_path);
-        match info_result {
+        // Write the info file before actually moving anything
+        match trash_info::create(&info_file_path, &absolute_uri, deletion_date.as_deref()) {
+            Err(error) if error.kind() == std::io::ErrorKind::AlreadyExists => continue,
             Err(error) => {
-                if error.kind() == std::io::ErrorKind::AlreadyExists {
-                    continue;
-                } else {
-                    debug!("Failed to create the new file {:?}", info_file_path);
-                    return Err((info_file_path, error));
-                }
+                debug!("Failed to create the new file {:?}", info_file_path);
+                return Err((info_file_path, error));
             }
-            Ok(mut file) => {
+            Ok(()) => {
                 debug!("Successfully created {:?}", info_file_path);
-                // Write the info file before actually moving anything
-                writeln!(file, "[Trash Info]")
-                    .and_then(|_| {
-                        let absolute_uri = encode_uri_path(src);
-                        writeln!(file, "Path={absolute_uri}").and_then(|_| {
-                            #[cfg(feature = "chrono")]
-                            {
-                                let now = chrono::Local::now();
-                                writeln!(file, "DeletionDate={}", now.format("%Y-%m-%dT%H:%M:%S"))
-                            }
-                            #[cfg(not(feature = "chrono"))]
-                            {
-                                Ok(())
-                            }
-                        })
-                    })
-                    .map_err(|e| (info_file_path.to_owned(), e))?;
+                hand_over(&info_file_path)?;
             }
         }
+        if cancellation.is_some_and(CancellationToken::is_cancelled) {
+            debug!("Cancelled before moving {:?}, removing {:?}", src, info_file_path);
+            let cancelled = std::io::Error::new(std::io::ErrorKind::Interrupted, "the operation was cancelled");
+            return Err(match std::fs::remove_file(&info_file_path) {
+                Ok(()) => (src.to_owned(), cancelled),
+                Err(e) => (info_file_path, e),
+            });
+        }
         let path = files_folder.join(&in_trash_name);
-        match move_items_no_replace(src, &path) {
+        // The info file reserves the name, so the elevated move doesn't need placeholders.
+        let moved = if elevate { move_with_polkit(src, &path) } else { move_items_no_replace(src, &path) };
+        match moved {
             Err((path, error)) => {
                 debug!("Failed moving item to the trash (this is usually OK). {:?}", error);
                 // Try to delete the info file
@@ -547,15 +1819,37 @@ fn try_creating_placeholders(src: impl AsRef<Path>, dst: impl AsRef<Path>) -> Re
     Ok(())
 }
 
-fn parse_uri_path(absolute_file_path: impl AsRef<Path>) -> String {
-    let file_path_chars = absolute_file_path.as_ref().to_str().unwrap().chars();
-    let url: String = "file://".chars().chain(file_path_chars).collect();
-    return url::Url::parse(&url).unwrap().to_file_path().unwrap().to_str().unwrap().into();
+/// Decodes the percent-encoded path of a trash info file byte by byte, so that paths that aren't
+/// UTF-8 come back unchanged. Invalid escapes, like a `%` that isn't followed by two hex digits,
+/// are kept as they are, and a `file://` prefix that some tools write is removed.
+fn decode_uri_path(value: &[u8]) -> PathBuf {
+    let value = value.strip_prefix(b"file://").unwrap_or(value);
+    let mut bytes = Vec::with_capacity(value.len());
+    let mut i = 0;
+    while i < value.len() {
+        let hex = value.get(i + 1..i + 3).and_then(|hex| std::str::from_utf8(hex).ok());
+        match hex.and_then(|hex| u8::from_str_radix(hex, 16).ok()) {
+            Some(byte) if value[i] == b'%' => {
+                bytes.push(byte);
+                i += 3;
+            }
+            _ => {
+                bytes.push(value[i]);
+                i += 1;
+            }
+        }
+    }
+    PathBuf::from(OsString::from_vec(bytes))
 }
 
-fn encode_uri_path(absolute_file_path: impl AsRef<Path>) -> String {
-    let url = url::Url::from_file_path(absolute_file_path.as_ref()).unwrap();
-    url.path().to_owned()
+/// Percent-encodes an absolute path for the `Path` of a trash info file, byte by byte, so that
+/// paths that aren't valid UTF-8 survive as well.
+fn encode_uri_path(absolute_file_path: impl AsRef<Path>) -> std::io::Result<String> {
+    let path = absolute_file_path.as_ref();
+    if !path.is_absolute() {
+        return Err(std::io::Error::new(std::io::ErrorKind::InvalidInput, "the path isn't absolute"));
+    }
+    Ok(trash_info::encode_path(path.as_os_str().as_bytes()))
 }
 
 #[derive(Eq, PartialEq, Debug)]
@@ -586,35 +1880,82 @@ fn folder_validity(path: impl AsRef<Path>) -> Result<TrashValidity, FsError> {
 /// Corresponds to the definition of "home_trash" from
 /// https://specifications.freedesktop.org/trash-spec/trashspec-1.0.html
 fn home_trash() -> Result<PathBuf, Error> {
-    if let Some(data_home) = std::env::var_os("XDG_DATA_HOME") {
-        if data_home.len() > 0 {
-            let data_home_path = AsRef::<Path>::as_ref(data_home.as_os_str());
-            return Ok(data_home_path.join("Trash"));
-        }
+    home_trash_in(|name| std::env::var_os(name).filter(|value| !value.is_empty()).map(PathBuf::from))
+}
+
+/// Finds the home trash with the environment variables returned by `var`.
+fn home_trash_in(var: impl Fn(&str) -> Option<PathBuf>) -> Result<PathBuf, Error> {
+    // Flatpak and Snap point `XDG_DATA_HOME` to a folder of the application, while file managers
+    // use the trash of the host. It's only accessible if the sandbox grants access to it.
+    let host_trash = if var("FLATPAK_ID").is_some() {
+        var("HOST_XDG_DATA_HOME").map(|data_home| data_home.join("Trash")).or_else(|| {
+            let home = var("HOME")?;
+            Some(home.join(".local/share/Trash"))
+        })
+    } else {
+        var("SNAP_REAL_HOME").map(|home| home.join(".local/share/Trash"))
+    };
+    if let Some(host_trash) = host_trash.filter(|trash| trash.is_dir()) {
+        return Ok(host_trash);
     }
-    if let Some(home) = std::env::var_os("HOME") {
-        if home.len() > 0 {
-            let home_path = AsRef::<Path>::as_ref(home.as_os_str());
-            return Ok(home_path.join(".local/share/Trash"));
-        }
+    if let Some(data_home) = var("XDG_DATA_HOME") {
+        return Ok(data_home.join("Trash"));
+    }
+    if let Some(home) = var("HOME") {
+        return Ok(home.join(".local/share/Trash"));
     }
-    Err(Error::Unknown { description: "Neither the XDG_DATA_HOME nor the HOME environment variable was found".into() })
+    Err(Error::CouldNotAccessTrash {
+        path: None,
+        description: "Neither the XDG_DATA_HOME nor the HOME environment variable was found".into(),
+    })
 }
 
-fn home_topdir(mnt_points: &[MountPoint]) -> Result<PathBuf, Error> {
-    if let Some(data_home) = std::env::var_os("XDG_DATA_HOME") {
-        if data_home.len() > 0 {
-            let data_home_path = AsRef::<Path>::as_ref(data_home.as_os_str());
-            return Ok(get_first_topdir_containing_path(data_home_path, mnt_points).to_owned());
-        }
+/// Fails with [`Error::TrashUnavailable`] if the process runs in a container of `runtime` and a
+/// trash folder on `topdir` would be in the writable layer of the container, or if `home_trash`,
+/// when the item goes there, belongs to another user than `uid`.
+#[cfg(target_os = "linux")]
+fn check_trash_in_container(
+    runtime: &str,
+    uid: u32,
+    home_trash: Option<&Path>,
+    topdir: &Path,
+    mount_points: &[MountPoint],
+) -> Result<(), Error> {
+    let trash_folder = match home_trash {
+        Some(home_trash) => home_trash.to_owned(),
+        None => topdir.join(format!(".Trash-{uid}")),
+    };
+    // The root can be listed more than once, like as `rootfs` below the overlay.
+    if mount_points.iter().any(|mp| mp.mnt_dir == topdir && crate::container::is_layered(&mp.mnt_type)) {
+        return Err(Error::TrashUnavailable {
+            path: trash_folder,
+            reason: format!(
+                "The process runs in a {runtime} container and the trash folder is in its writable layer, \
+                 which is discarded with the container or kept in its image. Mount a volume for the trash \
+                 and set it with `TrashContext::set_home_trash`, or delete the item permanently."
+            ),
+        });
     }
-    if let Some(home) = std::env::var_os("HOME") {
-        if home.len() > 0 {
-            let home_path = AsRef::<Path>::as_ref(home.as_os_str());
-            return Ok(get_first_topdir_containing_path(home_path, mnt_points).to_owned());
-        }
+    let owner = home_trash.and_then(|home_trash| std::fs::metadata(home_trash).ok()).map(|metadata| metadata.uid());
+    match owner {
+        Some(owner) if owner != uid => Err(Error::TrashUnavailable {
+            path: trash_folder,
+            reason: format!(
+                "The process runs in a {runtime} container as user {uid}, but the home trash belongs to \
+                 user {owner}, which happens when the home folder of the host is mounted into the \
+                 container. Run the container as the owner of the home folder, or set a trash of its own \
+                 with `TrashContext::set_home_trash`."
+            ),
+        }),
+        _ => Ok(()),
     }
-    Err(Error::Unknown { description: "Neither the XDG_DATA_HOME nor the HOME environment variable was found".into() })
+}
+
+/// The mount point that holds the home trash. Symbolic links are resolved first, as some systems
+/// like Fedora Silverblue link `/home` to `/var/home`.
+fn home_topdir(home_trash: &Path, mnt_points: &[MountPoint]) -> PathBuf {
+    let existing = home_trash.ancestors().find_map(|p| p.canonicalize().ok()).unwrap_or_else(|| home_trash.into());
+    get_first_topdir_containing_path(&existing, mnt_points).to_owned()
 }
 
 fn get_first_topdir_containing_path<'a>(path: &Path, mnt_points: &'a [MountPoint]) -> &'a Path {
@@ -624,7 +1965,7 @@ fn get_first_topdir_containing_path<'a>(path: &Path, mnt_points: &'a [MountPoint
 
 struct MountPoint {
     mnt_dir: PathBuf,
-    _mnt_type: String,
+    mnt_type: String,
     _mnt_fsname: String,
 }
 
@@ -643,8 +1984,8 @@ fn get_sorted_mount_points() -> Result<Vec<MountPoint>, Error> {
 fn get_mount_points() -> Result<Vec<MountPoint>, Error> {
     use once_cell::sync::Lazy;
     use scopeguard::defer;
-    use std::ffi::{CStr, CString};
-    use std::sync::Mutex;
+    use std::ffi::CStr;
+    use std::sync::{Mutex, PoisonError};
 
     // The getmntinfo() function writes the array of structures to an internal
     // static object and returns a pointer to that object.  Subsequent calls to
@@ -654,18 +1995,17 @@ fn get_mount_points() -> Result<Vec<MountPoint>, Error> {
     // We understand that threads can still call `libc::getmntent(…)` directly
     // to bypass the lock and trigger UB.
     static LOCK: Lazy<Mutex<()>> = Lazy::new(|| Mutex::new(()));
-    let _lock = LOCK.lock().unwrap();
+    let _lock = LOCK.lock().unwrap_or_else(PoisonError::into_inner);
 
-    //let file;
-    let read_arg = CString::new("r").unwrap();
-    let mounts_path = CString::new("/proc/mounts").unwrap();
-    let mut file = unsafe { libc::fopen(mounts_path.as_c_str().as_ptr(), read_arg.as_c_str().as_ptr()) };
+    let mut file = unsafe { libc::fopen(c"/proc/mounts".as_ptr(), c"r".as_ptr()) };
     if file.is_null() {
-        let mtab_path = CString::new("/etc/mtab").unwrap();
-        file = unsafe { libc::fopen(mtab_path.as_c_str().as_ptr(), read_arg.as_c_str().as_ptr()) };
+        file = unsafe { libc::fopen(c"/etc/mtab".as_ptr(), c"r".as_ptr()) };
     }
     if file.is_null() {
-        return Err(Error::Unknown { description: "Neither '/proc/mounts' nor '/etc/mtab' could be opened.".into() });
+        return Err(Error::CouldNotAccessTrash {
+            path: None,
+            description: "Neither '/proc/mounts' nor '/etc/mtab' could be opened.".into(),
+        });
     }
     defer! { unsafe { libc::fclose(file); } }
     let mut result = Vec::new();
@@ -674,21 +2014,22 @@ fn get_mount_points() -> Result<Vec<MountPoint>, Error> {
         if mntent.is_null() {
             break;
         }
-        let dir = unsafe { CStr::from_ptr((*mntent).mnt_dir).to_str().unwrap() };
-        if dir.bytes().len() == 0 {
+        let dir = unsafe { CStr::from_ptr((*mntent).mnt_dir).to_bytes() };
+        if dir.is_empty() {
             continue;
         }
         let mount_point = unsafe {
             MountPoint {
-                mnt_dir: dir.into(),
-                _mnt_fsname: CStr::from_ptr((*mntent).mnt_fsname).to_str().unwrap().into(),
-                _mnt_type: CStr::from_ptr((*mntent).mnt_type).to_str().unwrap().into(),
+                mnt_dir: OsStr::from_bytes(dir).into(),
+                _mnt_fsname: CStr::from_ptr((*mntent).mnt_fsname).to_string_lossy().into_owned(),
+                mnt_type: CStr::from_ptr((*mntent).mnt_type).to_string_lossy().into_owned(),
             }
         };
         result.push(mount_point);
     }
     if result.is_empty() {
-        return Err(Error::Unknown {
+        return Err(Error::CouldNotAccessTrash {
+            path: None,
             description: "A mount points file could be opened, but the call to `getmntent` returned NULL.".into(),
         });
     }
@@ -698,7 +2039,7 @@ fn get_mount_points() -> Result<Vec<MountPoint>, Error> {
 #[cfg(any(target_os = "dragonfly", target_os = "freebsd", target_os = "openbsd"))]
 fn get_mount_points() -> Result<Vec<MountPoint>, Error> {
     use once_cell::sync::Lazy;
-    use std::sync::Mutex;
+    use std::sync::{Mutex, PoisonError};
 
     // The getmntinfo() function writes the array of structures to an internal
     // static object and returns a pointer to that object.  Subsequent calls to
@@ -708,7 +2049,7 @@ fn get_mount_points() -> Result<Vec<MountPoint>, Error> {
     // We understand that threads can still call `libc::getmntinfo(…)` directly
     // to bypass the lock and trigger UB.
     static LOCK: Lazy<Mutex<()>> = Lazy::new(|| Mutex::new(()));
-    let _lock = LOCK.lock().unwrap();
+    let _lock = LOCK.lock().unwrap_or_else(PoisonError::into_inner);
 
     fn c_buf_to_str(buf: &[libc::c_char]) -> Option<&str> {
         let buf: &[u8] = unsafe { std::slice::from_raw_parts(buf.as_ptr() as _, buf.len()) };
@@ -743,7 +2084,7 @@ fn get_mount_points() -> Result<Vec<MountPoint>, Error> {
         let mount_from = c_buf_to_str(&fs_info.f_mntfromname).unwrap_or_default();
 
         let mount_point =
-            MountPoint { mnt_dir: mount_to.into(), _mnt_fsname: mount_from.into(), _mnt_type: fs_type.into() };
+            MountPoint { mnt_dir: mount_to.into(), _mnt_fsname: mount_from.into(), mnt_type: fs_type.into() };
         result.push(mount_point);
     }
     Ok(result)
@@ -752,7 +2093,7 @@ fn get_mount_points() -> Result<Vec<MountPoint>, Error> {
 #[cfg(target_os = "netbsd")]
 fn get_mount_points() -> Result<Vec<MountPoint>, Error> {
     use once_cell::sync::Lazy;
-    use std::sync::Mutex;
+    use std::sync::{Mutex, PoisonError};
 
     // The getmntinfo() function writes the array of structures to an internal
     // static object and returns a pointer to that object.  Subsequent calls to
@@ -763,7 +2104,7 @@ fn get_mount_points() -> Result<Vec<MountPoint>, Error> {
     // to bypass the lock and trigger UB.
     // NetBSD does not support statfs since 2005, so we need to use statvfs instead.
     static LOCK: Lazy<Mutex<()>> = Lazy::new(|| Mutex::new(()));
-    let _lock = LOCK.lock().unwrap();
+    let _lock = LOCK.lock().unwrap_or_else(PoisonError::into_inner);
 
     fn c_buf_to_str(buf: &[libc::c_char]) -> Option<&str> {
         let buf: &[u8] = unsafe { std::slice::from_raw_parts(buf.as_ptr() as _, buf.len()) };
@@ -798,7 +2139,7 @@ fn get_mount_points() -> Result<Vec<MountPoint>, Error> {
         let mount_from = c_buf_to_str(&fs_info.f_mntfromname).unwrap_or_default();
 
         let mount_point =
-            MountPoint { mnt_dir: mount_to.into(), _mnt_fsname: mount_from.into(), _mnt_type: fs_type.into() };
+            MountPoint { mnt_dir: mount_to.into(), _mnt_fsname: mount_from.into(), mnt_type: fs_type.into() };
         result.push(mount_point);
     }
     Ok(result)
@@ -813,7 +2154,14 @@ fn get_mount_points() -> Result<Vec<MountPoint>, Error> {
 )))]
 fn get_mount_points() -> Result<Vec<MountPoint>, Error> {
     // On platforms that don't have support yet, return an error
-    Err(Error::Unknown { description: "Mount points cannot be determined on this operating system".into() })
+    Err(Error::CouldNotAccessTrash {
+        path: None,
+        description: "Mount points cannot be determined on this operating system".into(),
+    })
+}
+
+fn fs_error(path: impl Into<PathBuf>, source: std::io::Error) -> Error {
+    Error::FileSystem { path: path.into(), source }
 }
 
 #[cfg(test)]
@@ -833,10 +2181,10 @@ mod tests {
     use log::warn;
 
     use crate::{
-        canonicalize_paths, delete, delete_all,
+        canonicalize_path, delete, delete_all,
         os_limited::{list, purge_all, restore_all},
         tests::get_unique_name,
-        Error,
+        ByteSize, Error, TrashItemKind, TrashItemSize, UnknownReason,
     };
 
     #[test]
@@ -887,6 +2235,80 @@ mod tests {
         let _ = purge_all(items.into_values().flatten());
     }
 
+    #[test]
+    #[serial]
+    fn restore_to_other_device() {
+        use std::os::unix::fs::MetadataExt;
+
+        crate::tests::init_logging();
+        let shm = Path::new("/dev/shm");
+        let cwd = env::current_dir().unwrap();
+        if shm.metadata().map_or(true, |shm| shm.dev() == cwd.metadata().unwrap().dev()) {
+            warn!("Skipping the test as /dev/shm isn't on another device than {:?}", cwd);
+            return;
+        }
+        let name = get_unique_name();
+        let folder = PathBuf::from(format!("{name}-folder"));
+        std::fs::create_dir(&folder).unwrap();
+        std::fs::write(folder.join("a.txt"), "alpha").unwrap();
+        std::fs::write(&name, "file").unwrap();
+        delete_all([&name, folder.to_str().unwrap()]).unwrap();
+
+        let items: Vec<_> = list().unwrap().into_iter().filter(|item| item.name.starts_with(&name)).collect();
+        assert_eq!(items.len(), 2);
+        let target = shm.join(&name);
+        crate::os_limited::restore_all_to(items, &target).unwrap();
+        assert_eq!(std::fs::read_to_string(target.join(&name)).unwrap(), "file");
+        assert_eq!(std::fs::read_to_string(target.join(&folder).join("a.txt")).unwrap(), "alpha");
+        assert!(list().unwrap().iter().all(|item| !item.name.starts_with(&name)));
+        std::fs::remove_dir_all(&target).unwrap();
+    }
+
+    #[test]
+    #[serial]
+    fn test_metadata_of_huge_file() {
+        crate::tests::init_logging();
+
+        // A sparse file doesn't take up any space, but reports a size beyond 32 bits.
+        const SIZE: u64 = 5 << 40;
+        let name = get_unique_name();
+        let file = File::create(&name).unwrap();
+        if let Err(e) = file.set_len(SIZE) {
+            warn!("Skipping the test as the file system doesn't support a file of {} bytes: {}", SIZE, e);
+            std::fs::remove_file(&name).unwrap();
+            return;
+        }
+        drop(file);
+        delete(&name).unwrap();
+        let item = list().unwrap().into_iter().find(|item| item.name == name).unwrap();
+        assert_eq!(crate::os_limited::metadata(&item).unwrap().size, TrashItemSize::Bytes(SIZE));
+        purge_all([item]).unwrap();
+    }
+
+    #[test]
+    #[serial]
+    fn test_malformed_trash_info() {
+        crate::tests::init_logging();
+
+        let name = get_unique_name();
+        File::create(&name).unwrap();
+        delete(&name).unwrap();
+        let item = list().unwrap().into_iter().find(|item| item.name == name).unwrap();
+        let info_file = PathBuf::from(&item.id);
+        let mut contents = std::fs::read_to_string(&info_file).unwrap();
+        contents.push_str("a line without a key\n");
+        std::fs::write(&info_file, contents).unwrap();
+        let broken_info_file = info_file.with_file_name(format!("{name}-broken.trashinfo"));
+        std::fs::write(&broken_info_file, "[Trash Info]\nPath=/\nDeletionDate=garbage\n").unwrap();
+
+        let items = list().unwrap();
+        assert!(items.contains(&item));
+        assert!(!items.iter().any(|item| item.id == broken_info_file.as_os_str()));
+        assert!(crate::os_limited::purge_by_id("/").is_err());
+        std::fs::remove_file(&broken_info_file).unwrap();
+        purge_all([item]).unwrap();
+    }
+
     #[test]
     #[serial]
     fn test_broken_symlinks() {
@@ -924,6 +2346,379 @@ mod tests {
         }
     }
 
+    #[test]
+    #[serial]
+    #[cfg(feature = "thumbnails")]
+    fn test_icon() {
+        crate::tests::init_logging();
+
+        let name = format!("{}.txt", get_unique_name());
+        File::create(&name).unwrap();
+        delete(&name).unwrap();
+        let item = list().unwrap().into_iter().find(|item| item.name == name).unwrap();
+        let icon = item.icon(24);
+        purge_all([item]).unwrap();
+        // Minimal systems may not have any icon theme installed.
+        match icon {
+            Ok(icon) => {
+                assert_eq!((icon.width, icon.height), (24, 24));
+                assert_eq!(icon.rgba.len(), 24 * 24 * 4);
+                assert!(icon.rgba.chunks_exact(4).any(|pixel| pixel[3] > 0), "the icon is fully transparent");
+            }
+            Err(e) => warn!("No icon was found: {e}"),
+        }
+
+        // An opaque red square with a transparent right half, which is scaled down by half.
+        let png = format!("{}.png", get_unique_name());
+        let mut pixels = Vec::new();
+        for x in (0..48 * 48).map(|i| i % 48) {
+            pixels.extend_from_slice(if x < 24 { &[255, 0, 0, 255] } else { &[0, 0, 0, 0] });
+        }
+        let mut encoder = ::png::Encoder::new(File::create(&png).unwrap(), 48, 48);
+        encoder.set_color(::png::ColorType::Rgba);
+        encoder.write_header().unwrap().write_image_data(&pixels).unwrap();
+        let icon = super::icon_from_png(Path::new(&png), 24).unwrap();
+        std::fs::remove_file(&png).unwrap();
+        assert_eq!((icon.width, icon.height), (24, 24));
+        for (i, pixel) in icon.rgba.chunks_exact(4).enumerate() {
+            let expected: &[u8] = if i % 24 < 12 { &[255, 0, 0, 255] } else { &[0, 0, 0, 0] };
+            assert_eq!(pixel, expected, "pixel {i}");
+        }
+    }
+
+    #[test]
+    #[serial]
+    #[cfg(feature = "mime")]
+    fn test_content_type() {
+        crate::tests::init_logging();
+
+        let name = get_unique_name();
+        let files = [(format!("{name}.txt"), &b"text"[..]), (format!("{name}-png"), b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR")];
+        for (file, contents) in &files {
+            std::fs::write(file, contents).unwrap();
+        }
+        let folder = format!("{name}-folder");
+        std::fs::create_dir(&folder).unwrap();
+        delete_all(files.iter().map(|(file, _)| file).chain([&folder])).unwrap();
+        let items: Vec<_> = list().unwrap().into_iter().filter(|item| item.name.starts_with(&name)).collect();
+        let content_type = |name: &str| items.iter().find(|item| item.name == name).unwrap().content_type().unwrap();
+        let types = [content_type(&files[0].0), content_type(&files[1].0), content_type(&folder)];
+        purge_all(&items).unwrap();
+
+        assert_eq!(types[2], "inode/directory");
+        // Minimal systems may not have the shared MIME-info database installed.
+        if std::path::Path::new("/usr/share/mime/magic").exists() {
+            assert_eq!(types[0], "text/plain");
+            assert_eq!(types[1], "image/png");
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "chrono")]
+    fn test_deletion_date_time_zones() {
+        use chrono::{Local, NaiveDate, TimeZone};
+
+        let utc = 1704164645;
+        assert_eq!(super::parse_deletion_date("2024-01-02T03:04:05Z"), Some(utc));
+        assert_eq!(super::parse_deletion_date("2024-01-02T05:04:05+02:00"), Some(utc));
+        assert_eq!(super::parse_deletion_date("2024-01-02T05:04:05+0200"), Some(utc));
+        assert_eq!(super::parse_deletion_date("2024-01-02T03:04:05.250Z"), Some(utc));
+
+        let naive = NaiveDate::from_ymd_opt(2024, 1, 2).unwrap().and_hms_opt(3, 4, 5).unwrap();
+        let local = Local.from_local_datetime(&naive).earliest().unwrap().timestamp();
+        assert_eq!(super::parse_deletion_date("2024-01-02T03:04:05"), Some(local));
+        assert_eq!(super::parse_deletion_date("garbage"), None);
+    }
+
+    #[test]
+    fn test_uri_path_round_trip() {
+        use std::os::unix::ffi::OsStringExt;
+
+        // A xorshift generator, so that failures can be reproduced.
+        let mut state = 0x2545_f491_4f6c_dd1d_u64;
+        let mut next = || {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state
+        };
+        for _ in 0..1000 {
+            let len = next() % 32;
+            let mut bytes = vec![b'/'];
+            bytes.extend((0..len).map(|_| (next() % 255 + 1) as u8));
+            let path = PathBuf::from(OsString::from_vec(bytes));
+            let encoded = super::encode_uri_path(&path).unwrap();
+            assert!(encoded.bytes().all(|b| crate::trash_info::is_unescaped_path_byte(b) || b == b'%'), "{encoded}");
+            assert_eq!(super::decode_uri_path(encoded.as_bytes()), path);
+        }
+
+        assert_eq!(super::encode_uri_path("/a b/100%/\u{e9}").unwrap(), "/a%20b/100%25/%C3%A9");
+        assert_eq!(super::decode_uri_path(b"/100%/%zz/%4"), Path::new("/100%/%zz/%4"));
+        assert_eq!(super::decode_uri_path(b"file:///a%20b"), Path::new("/a b"));
+        assert_eq!(super::decode_uri_path(b"/raw \xff"), PathBuf::from(OsString::from_vec(b"/raw \xff".to_vec())));
+    }
+
+    #[test]
+    #[serial]
+    fn test_non_utf8_name() {
+        use std::os::unix::ffi::OsStringExt;
+
+        crate::tests::init_logging();
+        let mut name = get_unique_name().into_bytes();
+        name.extend_from_slice(b" 100% \xff\xfe");
+        let name = PathBuf::from(OsString::from_vec(name));
+        File::create(&name).unwrap();
+        delete(&name).unwrap();
+        assert!(!name.exists());
+        let cwd = env::current_dir().unwrap().canonicalize().unwrap();
+        let lossy_name = name.to_string_lossy();
+        let items: Vec<_> = list()
+            .unwrap()
+            .into_iter()
+            .filter(|item| item.original_parent.as_ref() == Some(&cwd) && item.name == lossy_name)
+            .collect();
+        assert_eq!(items.len(), 1);
+        let info = std::fs::read(&items[0].id).unwrap();
+        let line = info.split(|&b| b == b'\n').find_map(|line| line.strip_prefix(b"Path=")).unwrap();
+        assert!(line.is_ascii());
+        assert_eq!(super::decode_uri_path(line), cwd.join(&name));
+        purge_all(items).unwrap();
+    }
+
+    #[test]
+    fn test_trash_is_protected() {
+        let home_trash = super::home_trash().unwrap();
+        std::fs::create_dir_all(home_trash.join("files")).unwrap();
+        for path in [home_trash.clone(), home_trash.join("files"), home_trash.parent().unwrap().to_owned()] {
+            match delete(&path) {
+                Err(Error::Protected { kind: crate::ProtectedKind::Trash, .. }) => {}
+                other => panic!("{path:?} wasn't protected: {other:?}"),
+            }
+        }
+    }
+
+    #[test]
+    fn test_home_trash_in_sandboxes() {
+        let host = tempfile::tempdir().unwrap();
+        let host_trash = host.path().join(".local/share/Trash");
+        let vars = |vars: &'static [(&'static str, &'static str)]| {
+            let host = host.path().to_owned();
+            move |name: &str| {
+                let value = vars.iter().find(|(var, _)| *var == name)?.1;
+                Some(if value == "<host>" { host.clone() } else { PathBuf::from(value) })
+            }
+        };
+        let flatpak = vars(&[("FLATPAK_ID", "org.example.App"), ("HOME", "<host>"), ("XDG_DATA_HOME", "/app/data")]);
+        let snap = vars(&[("SNAP_REAL_HOME", "<host>"), ("XDG_DATA_HOME", "/snap/data")]);
+
+        // The trash of the host is only used if the sandbox can access it.
+        assert_eq!(super::home_trash_in(&flatpak).unwrap(), Path::new("/app/data/Trash"));
+        assert_eq!(super::home_trash_in(&snap).unwrap(), Path::new("/snap/data/Trash"));
+        std::fs::create_dir_all(&host_trash).unwrap();
+        assert_eq!(super::home_trash_in(&flatpak).unwrap(), host_trash);
+        assert_eq!(super::home_trash_in(&snap).unwrap(), host_trash);
+        assert_eq!(
+            super::home_trash_in(vars(&[("HOME", "/home/user")])).unwrap(),
+            Path::new("/home/user/.local/share/Trash")
+        );
+        assert!(super::home_trash_in(vars(&[])).is_err());
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_trash_in_containers() {
+        use super::{check_trash_in_container, MountPoint};
+        use std::os::unix::fs::MetadataExt;
+
+        let mount = |dir: &str, fs_type: &str| MountPoint {
+            mnt_dir: dir.into(),
+            mnt_type: fs_type.into(),
+            _mnt_fsname: fs_type.into(),
+        };
+        let mount_points = [mount("/data", "ext4"), mount("/", "rootfs"), mount("/", "overlay")];
+        let home = tempfile::tempdir().unwrap();
+        let owner = home.path().metadata().unwrap().uid();
+
+        // Trash folders in the writable layer are refused, those on volumes are used.
+        match check_trash_in_container("docker", owner, None, Path::new("/"), &mount_points) {
+            Err(Error::TrashUnavailable { path, .. }) => assert_eq!(path, Path::new(&format!("/.Trash-{owner}"))),
+            other => panic!("expected the trash to be unavailable, got {other:?}"),
+        }
+        assert!(check_trash_in_container("docker", owner, None, Path::new("/data"), &mount_points).is_ok());
+        assert!(check_trash_in_container("docker", owner, Some(home.path()), Path::new("/data"), &mount_points).is_ok());
+
+        // A home trash of another user is refused.
+        match check_trash_in_container("podman", owner + 1, Some(home.path()), Path::new("/data"), &mount_points) {
+            Err(Error::TrashUnavailable { path, reason }) => {
+                assert_eq!(path, home.path());
+                assert!(reason.contains("podman"));
+            }
+            other => panic!("expected the trash to be unavailable, got {other:?}"),
+        }
+    }
+
+    #[test]
+    #[serial]
+    fn test_home_trash_override() {
+        crate::tests::init_logging();
+        let trash = env::current_dir().unwrap().join(format!("{}-trash", get_unique_name()));
+        let mut ctx = crate::TrashContext::default();
+        ctx.set_home_trash(Some(trash.clone()));
+        let file_name = get_unique_name();
+        File::create_new(&file_name).unwrap();
+        ctx.delete(&file_name).unwrap();
+
+        let items: Vec<_> = ctx.list().unwrap().into_iter().filter(|item| item.name == file_name).collect();
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0].trash_root(), Some(trash.clone()));
+        assert!(trash.join("files").join(&file_name).exists());
+        let filter = crate::os_limited::TrashFilter { name_glob: Some(file_name.clone()), ..Default::default() };
+        assert_eq!(ctx.list_filtered(&filter).unwrap(), items);
+        ctx.purge_all(items).unwrap();
+        std::fs::remove_dir_all(&trash).unwrap();
+    }
+
+    #[test]
+    #[serial]
+    fn test_foreign_trash_formats() {
+        crate::tests::init_logging();
+        let trash = env::current_dir().unwrap().join(format!("{}-trash", get_unique_name()));
+        let mut ctx = crate::TrashContext::default();
+        ctx.set_home_trash(Some(trash.clone()));
+        std::fs::create_dir_all(trash.join("files")).unwrap();
+        std::fs::create_dir_all(trash.join("info")).unwrap();
+        let cwd = env::current_dir().unwrap().canonicalize().unwrap();
+        let date = "DeletionDate=2024-01-02T03:04:05";
+        let cases = [
+            ("headerless", format!("Path={}\n{date}\n", cwd.join("headerless").display())),
+            ("uri", format!("[Trash Info]\nPath=file://{}\n{date}\n", cwd.join("uri").display())),
+            ("with space", format!("[Trash Info]\nPath={}\n{date}\n", cwd.join("with space").display())),
+            ("undated", format!("[Trash Info]\nPath={}\nDeletionDate=yesterday\n", cwd.join("undated").display())),
+        ];
+        for (suffix, info) in &cases {
+            let name = format!("{}-{suffix}", get_unique_name());
+            let info = info.replace(suffix, &name);
+            File::create_new(trash.join("files").join(&name)).unwrap();
+            std::fs::write(trash.join("info").join(format!("{name}.trashinfo")), info).unwrap();
+        }
+
+        let items = ctx.list().unwrap();
+        assert_eq!(items.len(), cases.len());
+        let format_of = |suffix: &str| {
+            let item = items.iter().find(|item| item.name.ends_with(suffix)).unwrap();
+            assert_eq!(item.original_parent.as_ref(), Some(&cwd));
+            crate::os_limited::metadata(item).unwrap().foreign_format.unwrap()
+        };
+        assert!(format_of("headerless").missing_header);
+        assert!(format_of("uri").file_uri);
+        assert!(format_of("with space").unescaped_path);
+        let undated = items.iter().find(|item| item.name.ends_with("undated")).unwrap();
+        if cfg!(feature = "chrono") {
+            assert!(format_of("undated").invalid_deletion_date);
+            assert_eq!(undated.unknown_reason, Some(UnknownReason::Invalid));
+        } else {
+            assert_eq!(undated.unknown_reason, Some(UnknownReason::Unsupported));
+        }
+
+        let restored: Vec<_> = items.iter().map(|item| item.original_path().unwrap()).collect();
+        ctx.restore_all(items).unwrap();
+        for path in restored {
+            assert!(path.exists());
+            std::fs::remove_file(path).unwrap();
+        }
+        std::fs::remove_dir_all(&trash).unwrap();
+    }
+
+    #[test]
+    #[serial]
+    fn test_item_sizes_and_kinds() {
+        use std::os::unix::fs::MetadataExt;
+
+        crate::tests::init_logging();
+        let trash = env::current_dir().unwrap().join(format!("{}-trash", get_unique_name()));
+        let mut ctx = crate::TrashContext::default();
+        ctx.set_home_trash(Some(trash.clone()));
+        let (file, dir, stale_dir) = (get_unique_name(), get_unique_name(), get_unique_name());
+        std::fs::write(&file, "12345").unwrap();
+        std::fs::create_dir(&dir).unwrap();
+        std::fs::create_dir(&stale_dir).unwrap();
+        let metrics_before = ctx.metrics().unwrap();
+        ctx.delete_all([&file, &dir, &stale_dir]).unwrap();
+
+        let mtime_of = |name: &str| trash.join("info").join(format!("{name}.trashinfo")).metadata().unwrap().mtime();
+        let directory_sizes =
+            format!("4096 {} {dir}\n4096 {} {stale_dir}\nbroken line\n", mtime_of(&dir), mtime_of(&stale_dir) - 1);
+        std::fs::write(trash.join("directorysizes"), directory_sizes).unwrap();
+        let items = ctx.list().unwrap();
+        let size_of = |name: &str| items.iter().find(|item| item.name == name).unwrap().size;
+        assert_eq!(size_of(&file), Some(ByteSize::new(5)));
+        assert_eq!(size_of(&dir), Some(ByteSize::new(4096)));
+        assert_eq!(size_of(&stale_dir), None);
+        let kind_of = |name: &str| items.iter().find(|item| item.name == name).unwrap().kind;
+        assert_eq!(kind_of(&file), Some(TrashItemKind::File));
+        assert_eq!(kind_of(&dir), Some(TrashItemKind::Directory));
+
+        // The stale folder is counted, but its size is unknown.
+        let metrics = ctx.metrics().unwrap();
+        assert_eq!(metrics.item_count, metrics_before.item_count + 3);
+        assert_eq!(metrics.total_bytes, metrics_before.total_bytes + ByteSize::new(5 + 4096));
+        assert!(!metrics.complete);
+        std::fs::remove_dir_all(&trash).unwrap();
+    }
+
+    #[test]
+    fn test_cancelled_move_removes_info_file() {
+        let trash = env::current_dir().unwrap().join(format!("{}-trash", get_unique_name()));
+        let name = env::current_dir().unwrap().join(get_unique_name());
+        File::create(&name).unwrap();
+        let token = crate::CancellationToken::new();
+        token.cancel();
+        let (_, error) = super::move_to_trash(&name, &trash, "/", false, None, Some(&token)).unwrap_err();
+        assert_eq!(error.kind(), std::io::ErrorKind::Interrupted);
+        assert!(name.exists());
+        assert_eq!(std::fs::read_dir(trash.join("info")).unwrap().count(), 0);
+        std::fs::remove_file(&name).unwrap();
+        std::fs::remove_dir_all(&trash).unwrap();
+    }
+
+    #[test]
+    fn test_empty_trash_folder() {
+        crate::tests::init_logging();
+        let trash = env::current_dir().unwrap().join(format!("{}-trash", get_unique_name()));
+        let mut ctx = crate::TrashContext::default();
+        ctx.set_home_trash(Some(trash.clone()));
+        let (file, dir) = (get_unique_name(), get_unique_name());
+        std::fs::write(&file, "12345").unwrap();
+        std::fs::create_dir(&dir).unwrap();
+        std::fs::write(Path::new(&dir).join("inner"), "").unwrap();
+        ctx.delete_all([&file, &dir]).unwrap();
+        std::fs::write(trash.join("files").join("orphan"), "").unwrap();
+        std::fs::write(trash.join("info").join("broken.trashinfo"), "[Trash Info]\n").unwrap();
+        std::fs::write(trash.join("directorysizes"), "").unwrap();
+
+        let mut first_error = None;
+        ctx.empty_trash_folder(&trash, &mut first_error).unwrap();
+        assert!(first_error.is_none());
+        assert_eq!(std::fs::read_dir(trash.join("files")).unwrap().count(), 0);
+        assert_eq!(std::fs::read_dir(trash.join("info")).unwrap().count(), 0);
+        assert!(!trash.join("directorysizes").exists());
+        std::fs::remove_dir_all(&trash).unwrap();
+    }
+
+    #[test]
+    fn test_elevation_check() {
+        let name = get_unique_name();
+        File::create(&name).unwrap();
+        assert!(super::can_remove(&env::current_dir().unwrap().join(&name)));
+        assert!(super::can_create_in(&env::current_dir().unwrap().join(format!("{name}-missing")).join("missing")));
+        std::fs::remove_file(&name).unwrap();
+        // Root may remove anything, so there's nothing to check for it.
+        if unsafe { libc::getuid() } != 0 {
+            assert!(!super::can_remove(Path::new("/usr/bin/env")));
+            assert!(!super::can_create_in(Path::new("/usr/local/trash-rs/missing")));
+        }
+    }
+
     //////////////////////////////////////////////////////////////////////////////////////
     /// System
     //////////////////////////////////////////////////////////////////////////////////////
@@ -931,6 +2726,7 @@ mod tests {
     #[derive(Debug)]
     pub enum SystemTrashError {
         NoTrashProgram,
+        #[allow(dead_code)]
         Other(Error),
     }
     impl fmt::Display for SystemTrashError {
@@ -991,13 +2787,17 @@ mod tests {
         let mut command = Command::new(trash);
         command.args(argv);
         let result = command.output().map_err(|e| {
-            SystemTrashError::Other(Error::Unknown {
+            SystemTrashError::Other(Error::Os {
+                code: e.raw_os_error().unwrap_or_default(),
+                function: Some(trash.into()),
                 description: format!("Tried executing: {:?} - Error was: {}", command, e),
             })
         })?;
         if !result.status.success() {
             let stderr = String::from_utf8_lossy(&result.stderr);
-            return Err(SystemTrashError::Other(Error::Unknown {
+            return Err(SystemTrashError::Other(Error::Os {
+                code: result.status.code().unwrap_or(-1),
+                function: Some(trash.into()),
                 description: format!("Used '{}', stderr: {}", trash, stderr),
             }));
         }
@@ -1009,7 +2809,11 @@ mod tests {
         I: IntoIterator<Item = T>,
         T: AsRef<Path>,
     {
-        let full_paths = canonicalize_paths(paths).map_err(SystemTrashError::Other)?;
+        let full_paths = paths
+            .into_iter()
+            .map(|path| canonicalize_path(path.as_ref(), None))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(SystemTrashError::Other)?;
         delete_all_canonicalized_using_system_program(full_paths)
     }
 
@@ -1119,7 +2923,3 @@ mod tests {
         DesktopEnvironment::Other
     }
 }
-
-fn fs_error(path: impl Into<PathBuf>, source: std::io::Error) -> Error {
-    Error::FileSystem { path: path.into(), source }
-}
@@ -0,0 +1,89 @@
+//! Async versions of the trash operations, for applications that run on tokio.
+//!
+//! Moving items to the trash and listing it block on the file system, and on Windows on the
+//! shell, which can take seconds for a large Recycle Bin. The functions of this module run the
+//! operations on the blocking thread pool of the current tokio runtime with
+//! [`spawn_blocking`](tokio::task::spawn_blocking), so the executor isn't stalled while they run.
+//! They must be called from within a tokio runtime.
+//!
+//! The functions use the [`DEFAULT_TRASH_CTX`](crate::DEFAULT_TRASH_CTX). To use a configured
+//! [`TrashContext`](crate::TrashContext), clone it into a closure that is passed to
+//! `spawn_blocking`.
+//!
+//! Dropping the returned future doesn't stop the operation, it runs to completion on the thread
+//! pool. Use a [`CancellationToken`](crate::CancellationToken) to stop it.
+
+use std::path::{Path, PathBuf};
+
+use tokio::task::{self, JoinError};
+
+#[cfg(any(
+    target_os = "windows",
+    all(unix, not(target_os = "macos"), not(target_os = "ios"), not(target_os = "android"))
+))]
+use crate::TrashItem;
+use crate::{absolute_path, Error, DEFAULT_TRASH_CTX};
+
+/// Async version of [`delete_all`](crate::delete_all).
+///
+/// Relative paths are resolved against the current working directory when this function is
+/// called, not when the operation runs.
+///
+/// # Example
+///
+/// ```
+/// # tokio::runtime::Builder::new_current_thread().build().unwrap().block_on(async {
+/// use std::fs::File;
+/// File::create("async_delete_me").unwrap();
+/// trash::futures::delete_all(["async_delete_me"]).await.unwrap();
+/// assert!(File::open("async_delete_me").is_err());
+/// # });
+/// ```
+pub async fn delete_all<I, T>(paths: I) -> Result<(), Error>
+where
+    I: IntoIterator<Item = T>,
+    T: AsRef<Path>,
+{
+    let paths =
+        paths.into_iter().map(|path| absolute_path(path.as_ref(), None)).collect::<Result<Vec<PathBuf>, Error>>()?;
+    spawn(move || DEFAULT_TRASH_CTX.delete_all(paths)).await
+}
+
+/// Async version of [`os_limited::list`](crate::os_limited::list).
+#[cfg(any(
+    target_os = "windows",
+    all(unix, not(target_os = "macos"), not(target_os = "ios"), not(target_os = "android"))
+))]
+pub async fn list() -> Result<Vec<TrashItem>, Error> {
+    spawn(crate::os_limited::list).await
+}
+
+/// Async version of [`os_limited::restore_all`](crate::os_limited::restore_all).
+#[cfg(any(
+    target_os = "windows",
+    all(unix, not(target_os = "macos"), not(target_os = "ios"), not(target_os = "android"))
+))]
+pub async fn restore_all<I>(items: I) -> Result<(), Error>
+where
+    I: IntoIterator<Item = TrashItem>,
+{
+    let items: Vec<TrashItem> = items.into_iter().collect();
+    spawn(move || DEFAULT_TRASH_CTX.restore_all(items)).await
+}
+
+/// Runs `f` on the blocking thread pool. Panics of `f`, which can only come from hooks and
+/// callbacks, are resumed on the awaiting task.
+async fn spawn<F, R>(f: F) -> Result<R, Error>
+where
+    F: FnOnce() -> Result<R, Error> + Send + 'static,
+    R: Send + 'static,
+{
+    task::spawn_blocking(f).await.unwrap_or_else(|e| Err(join_error(e)))
+}
+
+fn join_error(e: JoinError) -> Error {
+    match e.try_into_panic() {
+        Ok(payload) => std::panic::resume_unwind(payload),
+        Err(e) => Error::Unknown { description: format!("the blocking task didn't finish: {e}") },
+    }
+}
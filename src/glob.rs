@@ -0,0 +1,87 @@
+//! Matching names against shell-style glob patterns.
+
+/// Matches `name` against the `*`, `?` and `[...]` wildcards of `pattern`. A `*` matches any
+/// characters, including `/`.
+pub(crate) fn matches(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+    matches_from(&pattern, &name)
+}
+
+/// Matches with a single backtrack point, the position after the last `*` and the part of `name`
+/// it matched so far, which keeps the time linear in the length of `name` for every `pattern`.
+fn matches_from(pattern: &[char], name: &[char]) -> bool {
+    let (mut p, mut n) = (0, 0);
+    let mut backtrack = None;
+    while n < name.len() {
+        let matched = match pattern.get(p) {
+            Some('*') => {
+                backtrack = Some((p + 1, n));
+                p += 1;
+                continue;
+            }
+            Some(_) => matches_one(&pattern[p..], name[n]),
+            None => None,
+        };
+        if let Some(len) = matched {
+            p += len;
+            n += 1;
+            continue;
+        }
+        // Let the last `*` match one more character and try again from there.
+        let Some((star_p, star_n)) = backtrack else {
+            return false;
+        };
+        backtrack = Some((star_p, star_n + 1));
+        p = star_p;
+        n = star_n + 1;
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+/// Matches `c` against the wildcard or character at the start of `pattern`, which isn't a `*`, and
+/// returns how many characters of the pattern it took up.
+fn matches_one(pattern: &[char], c: char) -> Option<usize> {
+    match pattern.split_first()? {
+        ('?', _) => Some(1),
+        ('[', rest) => {
+            let end = rest.iter().position(|&c| c == ']')?;
+            let set = &rest[..end];
+            let (negated, set) = match set.split_first() {
+                Some(('!', set)) => (true, set),
+                _ => (false, set),
+            };
+            let mut found = false;
+            let mut i = 0;
+            while i < set.len() {
+                if i + 2 < set.len() && set[i + 1] == '-' {
+                    found |= (set[i]..=set[i + 2]).contains(&c);
+                    i += 3;
+                } else {
+                    found |= set[i] == c;
+                    i += 1;
+                }
+            }
+            (found != negated).then_some(end + 2)
+        }
+        (&literal, _) => (literal == c).then_some(1),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::matches;
+
+    #[test]
+    fn wildcards() {
+        assert!(matches("*.log", "logs/a.log"));
+        assert!(matches("a?c", "abc"));
+        assert!(!matches("a?c", "ac"));
+        assert!(matches("[!x]b[a-c]*", "abc-d"));
+        assert!(!matches("[a-c", "a"));
+        assert!(matches("*a*b**", "xxaxxbxx"));
+        assert!(!matches("*a*b", "xxaxxbxxa"));
+        // Many stars against a long name that doesn't match don't take exponential time.
+        assert!(!matches("*a*a*a*a*a*a*a*a*a*a*b", &"a".repeat(200)));
+    }
+}
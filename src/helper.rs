@@ -0,0 +1,397 @@
+//! Performing trash operations in a separate helper process, for example one with elevated rights.
+//!
+//! The `trash-helper` binary that is built with the `helper` feature reads requests from its
+//! standard input and writes the results to its standard output, one JSON message per line. A
+//! [`HelperClient`] starts the helper, checks that it speaks the same protocol version, and shuts
+//! it down when it's dropped.
+//!
+//! The helper only moves items to the trash. In the handshake, the client sends the settings of
+//! its [`TrashContext`] that can be sent, along with its user id on Unix. A helper that runs as
+//! another user, like root, moves the items to the trash folders of the client's user and refuses
+//! items that user could move by themselves. Paths are resolved against the working directory of
+//! the client before they are sent, as elevation tools like `pkexec` start the helper in another
+//! folder. Paths that aren't valid UTF-8 can't be sent to the helper.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use std::process::Command;
+//! use trash::helper::{delete_all_or_elevate, HelperClient};
+//!
+//! let ctx = trash::TrashContext::default();
+//! delete_all_or_elevate(&ctx, ["/var/lib/app/cache"], || {
+//!     // On freedesktop systems `HelperClient::launch_with_pkexec` does the same.
+//!     let mut command = Command::new("pkexec");
+//!     command.arg("/usr/libexec/trash-helper");
+//!     HelperClient::spawn(&ctx, command)
+//! })
+//! .unwrap();
+//! ```
+
+use std::{
+    io::{BufRead, BufReader, Read, Write},
+    path::{Path, PathBuf},
+    process::{Child, Command, Stdio},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{absolute_path, into_unknown, CaseSensitivity, DuplicatePaths, Error, TrashContext};
+
+/// Changes whenever the messages change in a way that older helpers or clients don't understand.
+pub const PROTOCOL_VERSION: u32 = 2;
+
+/// The settings of the client's context that the helper applies to its own.
+#[derive(Debug, Serialize, Deserialize)]
+struct Settings {
+    continue_on_error: bool,
+    case_sensitivity: CaseSensitivity,
+    allow_protected: bool,
+    recursive: bool,
+    duplicate_paths: DuplicatePaths,
+    /// The home trash of the client, which the helper can't find if it runs as another user.
+    #[cfg(all(unix, not(target_os = "macos"), not(target_os = "ios"), not(target_os = "android")))]
+    home_trash: Option<PathBuf>,
+}
+
+impl Settings {
+    fn of(ctx: &TrashContext) -> Self {
+        Settings {
+            continue_on_error: ctx.continue_on_error(),
+            case_sensitivity: ctx.case_sensitivity(),
+            allow_protected: ctx.allow_protected(),
+            recursive: ctx.recursive(),
+            duplicate_paths: ctx.duplicate_paths(),
+            #[cfg(all(unix, not(target_os = "macos"), not(target_os = "ios"), not(target_os = "android")))]
+            home_trash: ctx.platform_specific.home_trash().ok(),
+        }
+    }
+
+    fn apply(self, ctx: &mut TrashContext) {
+        ctx.set_continue_on_error(self.continue_on_error);
+        ctx.set_case_sensitivity(self.case_sensitivity);
+        ctx.set_allow_protected(self.allow_protected);
+        ctx.set_recursive(self.recursive);
+        ctx.set_duplicate_paths(self.duplicate_paths);
+        #[cfg(all(unix, not(target_os = "macos"), not(target_os = "ios"), not(target_os = "android")))]
+        ctx.set_home_trash(self.home_trash);
+    }
+}
+
+/// Sent from the client to the helper.
+#[derive(Debug, Serialize, Deserialize)]
+enum Request {
+    /// The first message of the client, answered with [`Reply::Done`].
+    Hello {
+        version: u32,
+        settings: Settings,
+        /// The user id of the client on Unix.
+        uid: Option<u32>,
+    },
+    Delete {
+        paths: Vec<PathBuf>,
+    },
+    Shutdown,
+}
+
+/// Sent from the helper to the client.
+#[derive(Debug, Serialize, Deserialize)]
+enum Reply {
+    /// The first message of the helper.
+    Hello { version: u32 },
+    /// The result of a request.
+    Done(Result<(), Error>),
+}
+
+/// Serves the requests of a client until it shuts the helper down or closes `input`.
+///
+/// The settings the client sends in its handshake are applied to a copy of `ctx`. This is the
+/// main loop of the `trash-helper` binary, which serves its standard input and output. It's public
+/// so that applications can embed the helper in a binary of their own.
+pub fn serve(ctx: &TrashContext, input: impl BufRead, mut output: impl Write) -> Result<(), Error> {
+    send(&mut output, &Reply::Hello { version: PROTOCOL_VERSION })?;
+    let mut client = None;
+    for line in input.lines() {
+        let line = line.map_err(into_unknown)?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let request: Request = serde_json::from_str(&line).map_err(into_unknown)?;
+        log::debug!("The helper received {request:?}");
+        let result = match (request, &client) {
+            (Request::Hello { version, settings, uid }, None) => {
+                Client::accept(ctx, version, settings, uid).map(|accepted| client = Some(accepted))
+            }
+            (Request::Hello { .. }, Some(_)) => {
+                Err(Error::Unknown { description: "The client greeted the helper twice".into() })
+            }
+            (Request::Delete { .. }, None) => {
+                Err(Error::Unknown { description: "The client didn't greet the helper".into() })
+            }
+            (Request::Delete { paths }, Some(client)) => client.delete_all(paths),
+            (Request::Shutdown, _) => return Ok(()),
+        };
+        send(&mut output, &Reply::Done(result))?;
+    }
+    Ok(())
+}
+
+/// A client that greeted the helper.
+struct Client {
+    ctx: TrashContext,
+    /// The account of the client, if the helper runs as another user.
+    #[cfg(all(unix, not(target_os = "macos"), not(target_os = "ios"), not(target_os = "android")))]
+    other_user: Option<crate::platform::UserAccount>,
+}
+
+impl Client {
+    fn accept(base: &TrashContext, version: u32, settings: Settings, uid: Option<u32>) -> Result<Self, Error> {
+        if version != PROTOCOL_VERSION {
+            return Err(Error::Unknown {
+                description: format!(
+                    "The client speaks version {version} of the protocol instead of {PROTOCOL_VERSION}"
+                ),
+            });
+        }
+        // Elevation tools record who started them, so the client can't claim to be another user.
+        let started_by = ["PKEXEC_UID", "SUDO_UID"].iter().find_map(|name| std::env::var(name).ok()?.parse().ok());
+        if started_by.is_some_and(|started_by| uid != Some(started_by)) {
+            return Err(Error::Unknown {
+                description: format!("The client claims the user id {uid:?}, but started the helper as {started_by:?}"),
+            });
+        }
+        let mut ctx = base.clone();
+        settings.apply(&mut ctx);
+        #[cfg(all(unix, not(target_os = "macos"), not(target_os = "ios"), not(target_os = "android")))]
+        let other_user = match uid.filter(|&uid| uid != unsafe { libc::getuid() }) {
+            Some(uid) => {
+                let user = crate::platform::UserAccount::from_uid(uid)?;
+                ctx.set_owner(user.clone());
+                Some(user)
+            }
+            None => None,
+        };
+        Ok(Client {
+            ctx,
+            #[cfg(all(unix, not(target_os = "macos"), not(target_os = "ios"), not(target_os = "android")))]
+            other_user,
+        })
+    }
+
+    /// Moves the items to the trash, refusing those the client could move by themselves.
+    fn delete_all(&self, paths: Vec<PathBuf>) -> Result<(), Error> {
+        #[cfg(all(unix, not(target_os = "macos"), not(target_os = "ios"), not(target_os = "android")))]
+        if let Some(user) = &self.other_user {
+            if let Some(path) = paths.iter().find(|path| crate::platform::user_can_remove(path, user)) {
+                return Err(Error::Unknown {
+                    description: format!("The helper refuses {path:?}, which the client can move to the trash itself"),
+                });
+            }
+        }
+        self.ctx.delete_all(paths)
+    }
+}
+
+fn send(output: &mut impl Write, message: &impl Serialize) -> Result<(), Error> {
+    let mut line = serde_json::to_vec(message).map_err(into_unknown)?;
+    line.push(b'\n');
+    output.write_all(&line).and_then(|()| output.flush()).map_err(into_unknown)
+}
+
+/// A connection to a helper process.
+///
+/// Dropping the client shuts the helper down and waits for it to exit.
+pub struct HelperClient {
+    writer: Box<dyn Write + Send>,
+    reader: Box<dyn BufRead + Send>,
+    child: Option<Child>,
+    closed: bool,
+}
+
+impl HelperClient {
+    /// Starts the helper with `command` and talks to it over its standard input and output, which
+    /// are replaced by pipes. The helper applies the settings of `ctx` that can be sent.
+    pub fn spawn(ctx: &TrashContext, mut command: Command) -> Result<Self, Error> {
+        let mut child = command.stdin(Stdio::piped()).stdout(Stdio::piped()).spawn().map_err(into_unknown)?;
+        let (Some(stdin), Some(stdout)) = (child.stdin.take(), child.stdout.take()) else {
+            return Err(Error::Unknown { description: "The helper was started without pipes".into() });
+        };
+        let mut client = Self::from_streams(stdout, stdin);
+        client.child = Some(child);
+        client.handshake(ctx)?;
+        Ok(client)
+    }
+
+    /// **freedesktop only**
+    ///
+    /// Starts the helper binary at `program` as root through `pkexec`, which asks the user to
+    /// authenticate. Fails with [`Error::NeedsElevation`] if the user doesn't.
+    #[cfg(all(unix, not(target_os = "macos"), not(target_os = "ios"), not(target_os = "android")))]
+    pub fn launch_with_pkexec(ctx: &TrashContext, program: &Path) -> Result<Self, Error> {
+        let mut command = Command::new("pkexec");
+        command.arg(program);
+        Self::spawn(ctx, command).map_err(|e| match e {
+            // `pkexec` exits with these codes when the authentication is dismissed or fails.
+            Error::Os { code: code @ (126 | 127), description, .. } => Error::NeedsElevation { code, description },
+            e => e,
+        })
+    }
+
+    /// Talks to a helper that was started in another way, like a service started on demand, over
+    /// the given streams. The helper applies the settings of `ctx` that can be sent.
+    pub fn connect(
+        ctx: &TrashContext,
+        reader: impl Read + Send + 'static,
+        writer: impl Write + Send + 'static,
+    ) -> Result<Self, Error> {
+        let mut client = Self::from_streams(reader, writer);
+        client.handshake(ctx)?;
+        Ok(client)
+    }
+
+    fn from_streams(reader: impl Read + Send + 'static, writer: impl Write + Send + 'static) -> Self {
+        HelperClient { writer: Box::new(writer), reader: Box::new(BufReader::new(reader)), child: None, closed: false }
+    }
+
+    fn handshake(&mut self, ctx: &TrashContext) -> Result<(), Error> {
+        match self.receive()? {
+            Reply::Hello { version } if version == PROTOCOL_VERSION => {
+                #[cfg(unix)]
+                let uid = Some(unsafe { libc::getuid() });
+                #[cfg(not(unix))]
+                let uid = None;
+                self.request(&Request::Hello { version, settings: Settings::of(ctx), uid })
+            }
+            Reply::Hello { version } => Err(Error::Unknown {
+                description: format!(
+                    "The helper speaks version {version} of the protocol instead of {PROTOCOL_VERSION}"
+                ),
+            }),
+            reply => Err(Error::Unknown { description: format!("The helper didn't greet, but sent {reply:?}") }),
+        }
+    }
+
+    /// Moves the items to the trash in the helper process.
+    ///
+    /// See: [`TrashContext::delete_all`]
+    pub fn delete_all<I, T>(&mut self, paths: I) -> Result<(), Error>
+    where
+        I: IntoIterator<Item = T>,
+        T: AsRef<Path>,
+    {
+        let paths = resolve(paths)?;
+        self.request(&Request::Delete { paths })
+    }
+
+    /// Shuts the helper down and waits for it to exit, reporting failures that dropping the client
+    /// ignores.
+    pub fn shutdown(mut self) -> Result<(), Error> {
+        self.close()
+    }
+
+    fn request(&mut self, request: &Request) -> Result<(), Error> {
+        send(&mut self.writer, request)?;
+        match self.receive()? {
+            Reply::Done(result) => result,
+            reply => Err(Error::Unknown { description: format!("The helper sent {reply:?} instead of a result") }),
+        }
+    }
+
+    fn receive(&mut self) -> Result<Reply, Error> {
+        let mut line = String::new();
+        if self.reader.read_line(&mut line).map_err(into_unknown)? == 0 {
+            let status = self.child.as_mut().and_then(|child| child.wait().ok());
+            let description = match status {
+                Some(status) => format!("The helper exited unexpectedly with {status}"),
+                None => "The helper closed the connection unexpectedly".into(),
+            };
+            return Err(match status.and_then(|status| status.code()) {
+                Some(code) => Error::Os { code, function: None, description },
+                None => Error::Unknown { description },
+            });
+        }
+        serde_json::from_str(&line).map_err(into_unknown)
+    }
+
+    fn close(&mut self) -> Result<(), Error> {
+        if std::mem::replace(&mut self.closed, true) {
+            return Ok(());
+        }
+        let sent = send(&mut self.writer, &Request::Shutdown);
+        match self.child.take() {
+            Some(mut child) => {
+                let status = child.wait().map_err(into_unknown)?;
+                sent?;
+                if status.success() {
+                    Ok(())
+                } else {
+                    Err(Error::Unknown { description: format!("The helper exited with {status}") })
+                }
+            }
+            None => sent,
+        }
+    }
+}
+
+impl Drop for HelperClient {
+    fn drop(&mut self) {
+        if let Err(e) = self.close() {
+            log::warn!("Failed to shut the helper down: {e:?}");
+        }
+    }
+}
+
+fn resolve<I, T>(paths: I) -> Result<Vec<PathBuf>, Error>
+where
+    I: IntoIterator<Item = T>,
+    T: AsRef<Path>,
+{
+    paths.into_iter().map(|path| absolute_path(path.as_ref(), None)).collect()
+}
+
+/// Moves the items to the trash with `ctx`, and hands those that the current process isn't allowed
+/// to move over to a helper started by `launch`. The helper is only started if it's needed.
+///
+/// Items fail for lack of permissions with [`Error::NeedsElevation`], or on freedesktop systems
+/// with an [`Error::FileSystem`] of the kind `PermissionDenied`. Other failures are returned right
+/// away.
+pub fn delete_all_or_elevate<I, T>(
+    ctx: &TrashContext,
+    paths: I,
+    launch: impl FnOnce() -> Result<HelperClient, Error>,
+) -> Result<(), Error>
+where
+    I: IntoIterator<Item = T>,
+    T: AsRef<Path>,
+{
+    let mut denied = Vec::new();
+    for path in paths {
+        let path = absolute_path(path.as_ref(), ctx.base_dir())?;
+        match ctx.delete(&path) {
+            Ok(()) => {}
+            Err(e) if is_denied(&e) => {
+                log::debug!("Handing {path:?} over to the helper after {e:?}");
+                denied.push(path);
+            }
+            Err(e) => return Err(e),
+        }
+    }
+    if denied.is_empty() {
+        return Ok(());
+    }
+    let mut client = launch()?;
+    client.delete_all(&denied)?;
+    client.shutdown()
+}
+
+fn is_denied(error: &Error) -> bool {
+    match error {
+        Error::NeedsElevation { .. } => true,
+        #[cfg(any(
+            target_os = "windows",
+            all(unix, not(target_os = "macos"), not(target_os = "ios"), not(target_os = "android"))
+        ))]
+        Error::FileSystem { source, .. } => source.kind() == std::io::ErrorKind::PermissionDenied,
+        _ => false,
+    }
+}
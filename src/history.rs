@@ -0,0 +1,229 @@
+//! A history of delete and restore operations that can be undone and redone, kept in a file.
+//!
+//! [`TrashHistory`] performs the operations through a [`TrashContext`] and records the items they
+//! moved. Undoing a delete restores its items from the trash, and undoing a restore moves them
+//! back into the trash, so the trash itself holds the files of every step. The history is saved
+//! after every change, which lets applications offer undo across restarts.
+//!
+//! # Example
+//!
+//! ```
+//! use std::fs::File;
+//! use trash::{history::TrashHistory, TrashContext};
+//!
+//! let ctx = TrashContext::default();
+//! let file = std::env::temp_dir().join("trash-history-example.json");
+//! let mut history = TrashHistory::open(&file).unwrap();
+//!
+//! File::create("trash-history-undo-me").unwrap();
+//! history.delete_all(&ctx, ["trash-history-undo-me"]).unwrap();
+//! history.undo_last(&ctx).unwrap();
+//! assert!(std::path::Path::new("trash-history-undo-me").exists());
+//! # std::fs::remove_file("trash-history-undo-me").unwrap();
+//! # std::fs::remove_file(&file).unwrap();
+//! ```
+
+use std::{
+    ffi::{OsStr, OsString},
+    fs,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    into_unknown, io_error, platform,
+    tracked::{self, Trashed},
+    Error, TrashContext, TrashItem,
+};
+
+/// The number of operations that are kept by default, see [`TrashHistory::set_capacity`].
+pub const DEFAULT_CAPACITY: usize = 100;
+
+/// The kind of operation that a [`HistoryEntry`] records.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum HistoryOperation {
+    /// Items were moved to the trash.
+    Delete,
+    /// Items were restored from the trash.
+    Restore,
+}
+
+/// An operation in a [`TrashHistory`].
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct HistoryEntry {
+    pub operation: HistoryOperation,
+    /// The paths of the items outside of the trash, which are those they were deleted from or
+    /// restored to.
+    pub paths: Vec<PathBuf>,
+    /// The [`id`](TrashItem::id)s of the items while they're in the trash, which is after a
+    /// delete and after undoing a restore. Otherwise this is empty.
+    pub ids: Vec<OsString>,
+    /// The time of the operation in seconds since the Unix epoch.
+    pub time: i64,
+}
+
+impl HistoryEntry {
+    /// Whether the items are in the trash, given whether the entry is on the undo stack.
+    fn in_trash(&self, undoable: bool) -> bool {
+        (self.operation == HistoryOperation::Delete) == undoable
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Stacks {
+    undo: Vec<HistoryEntry>,
+    redo: Vec<HistoryEntry>,
+}
+
+/// Operations that can be undone and redone, saved as JSON in a file.
+///
+/// Only the operations that succeeded are recorded, and recording an operation clears the
+/// operations that could be redone. An operation fails to be undone or redone if its items were
+/// changed in the meantime, like an item that was purged from the trash, in which case it stays
+/// where it was in the history.
+///
+/// The items a delete moved into the trash are found by their original paths right after it,
+/// like with [`find`](TrashContext::find). Items that are deleted from the same paths at the same
+/// time by another process may be mistaken for them.
+#[derive(Debug)]
+pub struct TrashHistory {
+    path: PathBuf,
+    capacity: usize,
+    stacks: Stacks,
+}
+
+impl TrashHistory {
+    /// Loads the history saved in the file at `path`, or starts an empty one if the file doesn't
+    /// exist. The file is written with the first recorded operation.
+    pub fn open<P: Into<PathBuf>>(path: P) -> Result<Self, Error> {
+        let path = path.into();
+        let stacks = match fs::read(&path) {
+            Ok(contents) => serde_json::from_slice(&contents).map_err(into_unknown)?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Stacks::default(),
+            Err(e) => return Err(io_error(&path, e)),
+        };
+        Ok(TrashHistory { path, capacity: DEFAULT_CAPACITY, stacks })
+    }
+
+    /// The maximum number of operations that can be undone. The oldest operations are forgotten
+    /// once there are more. Defaults to [`DEFAULT_CAPACITY`].
+    pub fn set_capacity(&mut self, capacity: usize) {
+        self.capacity = capacity;
+    }
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// The operations that can be undone, the most recent one last.
+    pub fn undo_entries(&self) -> &[HistoryEntry] {
+        &self.stacks.undo
+    }
+
+    /// The operations that can be redone, the one that was undone most recently last.
+    pub fn redo_entries(&self) -> &[HistoryEntry] {
+        &self.stacks.redo
+    }
+
+    /// Deletes the items at `paths` with [`TrashContext::delete_all`] and records the operation.
+    ///
+    /// Only the items that ended up in the trash are recorded, leaving out those that a hook
+    /// skipped. If the delete fails for some of the items, those that were moved are recorded
+    /// before the error is returned.
+    pub fn delete_all<I, T>(&mut self, ctx: &TrashContext, paths: I) -> Result<(), Error>
+    where
+        I: IntoIterator<Item = T>,
+        T: AsRef<Path>,
+    {
+        let paths = ctx.normalize_paths(paths)?.paths;
+        let Trashed { paths, ids, result } = tracked::delete_all(ctx, &paths)?;
+        if !ids.is_empty() {
+            self.record(HistoryEntry { operation: HistoryOperation::Delete, paths, ids, time: now() })?;
+        }
+        result
+    }
+
+    /// Restores the items with [`TrashContext::restore_all`] and records the operation, with the
+    /// paths the items were restored to.
+    pub fn restore_all<I>(&mut self, ctx: &TrashContext, items: I) -> Result<(), Error>
+    where
+        I: IntoIterator<Item = TrashItem>,
+    {
+        let paths = ctx.restore_all_to_targets(items)?;
+        self.record(HistoryEntry { operation: HistoryOperation::Restore, paths, ids: Vec::new(), time: now() })
+    }
+
+    /// Undoes the most recent operation that wasn't undone yet, and returns it. Returns `None` if
+    /// there's nothing to undo.
+    pub fn undo_last(&mut self, ctx: &TrashContext) -> Result<Option<HistoryEntry>, Error> {
+        self.step(ctx, true)
+    }
+
+    /// Performs the operation that was undone most recently again, and returns it. Returns `None`
+    /// if there's nothing to redo.
+    pub fn redo(&mut self, ctx: &TrashContext) -> Result<Option<HistoryEntry>, Error> {
+        self.step(ctx, false)
+    }
+
+    /// Forgets all operations.
+    pub fn clear(&mut self) -> Result<(), Error> {
+        self.stacks = Stacks::default();
+        self.save()
+    }
+
+    fn record(&mut self, entry: HistoryEntry) -> Result<(), Error> {
+        self.stacks.redo.clear();
+        self.stacks.undo.push(entry);
+        let excess = self.stacks.undo.len().saturating_sub(self.capacity);
+        self.stacks.undo.drain(..excess);
+        self.save()
+    }
+
+    /// Inverts the last entry of the undo or the redo stack and moves it onto the other one.
+    fn step(&mut self, ctx: &TrashContext, undo: bool) -> Result<Option<HistoryEntry>, Error> {
+        let (from, to) = match undo {
+            true => (&mut self.stacks.undo, &mut self.stacks.redo),
+            false => (&mut self.stacks.redo, &mut self.stacks.undo),
+        };
+        let Some(entry) = from.last_mut() else {
+            return Ok(None);
+        };
+        match entry.in_trash(undo) {
+            true => {
+                entry.paths = untrash(ctx, &entry.ids)?;
+                entry.ids.clear();
+            }
+            false => {
+                let Trashed { paths, ids, result } = tracked::delete_all(ctx, &entry.paths)?;
+                result?;
+                (entry.paths, entry.ids) = (paths, ids);
+            }
+        }
+        let entry = from.pop();
+        to.extend(entry.clone());
+        self.save()?;
+        Ok(entry)
+    }
+
+    /// Replaces the file through a temporary file, so that it's never left half written.
+    fn save(&self) -> Result<(), Error> {
+        let contents = serde_json::to_vec(&self.stacks).map_err(into_unknown)?;
+        let mut temp_name = self.path.file_name().unwrap_or(OsStr::new("history")).to_owned();
+        temp_name.push(".tmp");
+        let temp_path = self.path.with_file_name(temp_name);
+        fs::write(&temp_path, contents).map_err(|e| io_error(&temp_path, e))?;
+        fs::rename(&temp_path, &self.path).map_err(|e| io_error(&self.path, e))
+    }
+}
+
+/// Restores the items with the given ids and returns the paths they were restored to.
+fn untrash(ctx: &TrashContext, ids: &[OsString]) -> Result<Vec<PathBuf>, Error> {
+    let ids: Vec<_> = ids.iter().map(OsString::as_os_str).collect();
+    let items = platform::items_by_ids(&ids).into_iter().collect::<Result<Vec<_>, _>>()?;
+    ctx.restore_all_to_targets(items)
+}
+
+fn now() -> i64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |elapsed| elapsed.as_secs() as i64)
+}
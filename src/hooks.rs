@@ -0,0 +1,117 @@
+//! Lifecycle callbacks that are invoked by a [`TrashContext`](crate::TrashContext) around every
+//! item of an operation.
+
+use std::{fmt, path::Path, sync::Arc};
+
+use crate::TrashItem;
+
+/// What should happen with an item, as decided by one of the `pre_*` callbacks of [`TrashHooks`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum HookDecision {
+    /// Go ahead with the operation for this item.
+    #[default]
+    Proceed,
+    /// Leave this item alone but carry on with the rest of the batch.
+    Skip,
+    /// Abort the whole operation before anything is done. The operation returns
+    /// [`Error::Vetoed`](crate::Error::Vetoed).
+    Veto,
+}
+
+/// Callbacks that are invoked before and after each item of an operation is processed.
+///
+/// All `pre_*` callbacks are invoked for every item of a batch before the batch is handed over to
+/// the platform, so a veto guarantees that nothing was changed. The `post_*` callbacks are only
+/// invoked when the whole batch succeeded.
+///
+/// Every method has a default implementation that does nothing, so implementors only need to
+/// override the callbacks they are interested in.
+///
+/// # Example
+///
+/// ```
+/// use std::path::Path;
+/// use trash::{HookDecision, TrashContext, TrashHooks};
+///
+/// struct KeepGitFolders;
+/// impl TrashHooks for KeepGitFolders {
+///     fn pre_delete(&self, path: &Path) -> HookDecision {
+///         if path.ends_with(".git") {
+///             HookDecision::Veto
+///         } else {
+///             HookDecision::Proceed
+///         }
+///     }
+/// }
+///
+/// let ctx = TrashContext::new().with_hooks(KeepGitFolders);
+/// ```
+pub trait TrashHooks: Send + Sync {
+    /// Invoked with the canonicalized path of each item that is about to be moved to the trash.
+    fn pre_delete(&self, _path: &Path) -> HookDecision {
+        HookDecision::Proceed
+    }
+
+    /// Invoked with the canonicalized path of each item that was moved to the trash.
+    fn post_delete(&self, _path: &Path) {}
+
+    /// Invoked with the canonicalized path of each item that is about to be deleted without moving
+    /// it to the trash. Defaults to [`pre_delete`](TrashHooks::pre_delete), so that rules about
+    /// what may be deleted apply to both.
+    fn pre_delete_permanently(&self, path: &Path) -> HookDecision {
+        self.pre_delete(path)
+    }
+
+    /// Invoked with the canonicalized path of each item that is about to be moved to the trash, but
+    /// that the platform would delete permanently instead, like an item on a network drive or one
+    /// that is larger than the Recycle Bin on Windows. It's invoked instead of
+    /// [`pre_delete`](TrashHooks::pre_delete), and the item is reported to
+    /// [`post_delete_permanently`](TrashHooks::post_delete_permanently) once it's gone.
+    ///
+    /// Defaults to [`pre_delete_permanently`](TrashHooks::pre_delete_permanently), as that's what
+    /// happens to the item, which in turn defaults to `pre_delete`. Return [`HookDecision::Skip`]
+    /// to keep the item, or [`HookDecision::Veto`] to abort the whole operation. Determining
+    /// whether an item bypasses the trash is only done when hooks are registered. On Windows, it
+    /// involves adding up the sizes of the files within the item, up to the maximum size of the
+    /// Recycle Bin.
+    ///
+    /// On Windows, items that are allowed here are still refused with
+    /// [`Error::CouldNotTrashWouldDelete`](crate::Error::CouldNotTrashWouldDelete) unless
+    /// permanent deletes are allowed with `TrashContextExtWindows::set_allow_permanent_delete`.
+    fn pre_bypass_trash(&self, path: &Path) -> HookDecision {
+        self.pre_delete_permanently(path)
+    }
+
+    /// Invoked with the canonicalized path of each item that was deleted without moving it to the
+    /// trash, including the items that the platform deleted instead of trashing them, see
+    /// [`pre_bypass_trash`](TrashHooks::pre_bypass_trash).
+    fn post_delete_permanently(&self, _path: &Path) {}
+
+    /// Invoked for each item that is about to be restored.
+    fn pre_restore(&self, _item: &TrashItem) -> HookDecision {
+        HookDecision::Proceed
+    }
+
+    /// Invoked for each item that was restored.
+    fn post_restore(&self, _item: &TrashItem) {}
+
+    /// Invoked for each item that is about to be removed from the trash permanently.
+    fn pre_purge(&self, _item: &TrashItem) -> HookDecision {
+        HookDecision::Proceed
+    }
+
+    /// Invoked for each item that was removed from the trash permanently.
+    fn post_purge(&self, _item: &TrashItem) {}
+}
+
+/// The hooks stored in a `TrashContext`.
+///
+/// This only exists so that `TrashContext` can keep deriving `Debug`.
+#[derive(Clone)]
+pub(crate) struct Hooks(pub(crate) Arc<dyn TrashHooks>);
+impl fmt::Debug for Hooks {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Hooks")
+    }
+}
@@ -0,0 +1,75 @@
+//! Icons and thumbnails of the items in the trash.
+
+use crate::{platform, Error, TrashItem};
+
+/// An image with 8 bit RGBA pixels, row by row starting at the top, which are not premultiplied by
+/// their alpha.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Icon {
+    /// The width of the image in pixels.
+    pub width: u32,
+    /// The height of the image in pixels.
+    pub height: u32,
+    /// The pixels, four bytes each.
+    pub rgba: Vec<u8>,
+}
+
+impl TrashItem {
+    /// Returns the image the system shows for the item, fitted into a square of `size` pixels.
+    ///
+    /// On Windows, this is the thumbnail of the item as shown by Explorer, or the icon of its type if
+    /// there's no thumbnail. On freedesktop systems, this is the icon of its MIME type, which is
+    /// guessed from the name of the item, from the current icon theme. Only icon themes that
+    /// contain PNG files are supported. On macOS, this is the Quick Look thumbnail of the file at
+    /// the item's [`id`](TrashItem::id) if that's a path, as shown by Finder, or else the icon of
+    /// the item's type, which is guessed from its name.
+    ///
+    /// Requires the `thumbnails` feature.
+    pub fn icon(&self, size: u32) -> Result<Icon, Error> {
+        platform::icon(self, size)
+    }
+}
+
+/// Scales the icon to fit into a square of `size` pixels, averaging the pixels when scaling down.
+#[cfg(all(unix, not(target_os = "macos"), not(target_os = "ios"), not(target_os = "android")))]
+pub(crate) fn fit(icon: Icon, size: u32) -> Icon {
+    let (width, height) = (icon.width as usize, icon.height as usize);
+    let longest = width.max(height).max(1);
+    let size = size.max(1) as usize;
+    let target_width = (width * size / longest).max(1);
+    let target_height = (height * size / longest).max(1);
+    if (target_width, target_height) == (width, height) {
+        return icon;
+    }
+    let mut rgba = Vec::with_capacity(target_width * target_height * 4);
+    for y in 0..target_height {
+        let (y0, y1) = span(y, height, target_height);
+        for x in 0..target_width {
+            let (x0, x1) = span(x, width, target_width);
+            // The colors are weighted by their alpha so that transparent pixels don't darken the edges.
+            let mut sums = [0u64; 4];
+            for row in y0..y1 {
+                for pixel in icon.rgba[(row * width + x0) * 4..(row * width + x1) * 4].chunks_exact(4) {
+                    let alpha = u64::from(pixel[3]);
+                    for (sum, &channel) in sums.iter_mut().zip(&pixel[..3]) {
+                        *sum += u64::from(channel) * alpha;
+                    }
+                    sums[3] += alpha;
+                }
+            }
+            let count = ((y1 - y0) * (x1 - x0)) as u64;
+            let alpha = sums[3];
+            let color = |sum: u64| sum.checked_div(alpha).unwrap_or(0) as u8;
+            rgba.extend_from_slice(&[color(sums[0]), color(sums[1]), color(sums[2]), (alpha / count) as u8]);
+        }
+    }
+    Icon { width: target_width as u32, height: target_height as u32, rgba }
+}
+
+/// The range of source pixels that make up the `index`th of `target` pixels.
+#[cfg(all(unix, not(target_os = "macos"), not(target_os = "ios"), not(target_os = "android")))]
+fn span(index: usize, source: usize, target: usize) -> (usize, usize) {
+    let start = index * source / target;
+    let end = ((index + 1) * source / target).max(start + 1).min(source);
+    (start, end)
+}
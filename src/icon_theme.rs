@@ -0,0 +1,126 @@
+//! Finding icons in the icon themes.
+//!
+//! See: <https://specifications.freedesktop.org/icon-theme-spec/latest/>
+
+use std::path::{Path, PathBuf};
+
+use once_cell::sync::Lazy;
+
+use crate::xdg_mime::data_dirs;
+
+/// A folder of an icon theme that holds icons of a fixed size.
+struct IconDir {
+    path: PathBuf,
+    size: u32,
+}
+
+/// The folders of the current theme and the themes it inherits from, ordered by their precedence.
+static ICON_DIRS: Lazy<Vec<Vec<IconDir>>> = Lazy::new(|| {
+    let base_dirs = base_dirs();
+    let mut themes: Vec<String> = Vec::new();
+    let mut pending: Vec<String> = current_theme().into_iter().collect();
+    // Most themes end up at `hicolor`, but that rarely has icons for MIME types, so the most
+    // common theme comes before it.
+    pending.extend(["Adwaita".to_owned(), "hicolor".to_owned()]);
+    pending.reverse();
+    let mut dirs = Vec::new();
+    while let Some(theme) = pending.pop() {
+        if themes.contains(&theme) {
+            continue;
+        }
+        let (theme_dirs, inherits) = read_theme(&base_dirs, &theme);
+        themes.push(theme);
+        dirs.push(theme_dirs);
+        pending.extend(inherits.into_iter().rev());
+    }
+    dirs
+});
+
+/// The folders that may contain icon themes, ordered by their precedence.
+fn base_dirs() -> Vec<PathBuf> {
+    let home_icons = std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".icons"));
+    home_icons.into_iter().chain(data_dirs().into_iter().map(|dir| dir.join("icons"))).collect()
+}
+
+/// Reads the icon theme configured for GTK or KDE applications.
+fn current_theme() -> Option<String> {
+    let config_home = match std::env::var_os("XDG_CONFIG_HOME") {
+        Some(config_home) if !config_home.is_empty() => PathBuf::from(config_home),
+        _ => PathBuf::from(std::env::var_os("HOME")?).join(".config"),
+    };
+    for (file, key) in [
+        ("gtk-4.0/settings.ini", "gtk-icon-theme-name"),
+        ("gtk-3.0/settings.ini", "gtk-icon-theme-name"),
+        ("kdeglobals", "Theme"),
+    ] {
+        let Ok(contents) = std::fs::read_to_string(config_home.join(file)) else {
+            continue;
+        };
+        let theme = contents
+            .lines()
+            .filter_map(|line| line.split_once('='))
+            .find(|(k, _)| k.trim() == key)
+            .map(|(_, theme)| theme.trim().trim_matches('"').to_owned());
+        if theme.as_ref().is_some_and(|theme| !theme.is_empty()) {
+            return theme;
+        }
+    }
+    None
+}
+
+/// Reads the fixed size folders of a theme from its `index.theme` files, along with the themes it
+/// inherits from.
+fn read_theme(base_dirs: &[PathBuf], theme: &str) -> (Vec<IconDir>, Vec<String>) {
+    let mut dirs = Vec::new();
+    let mut inherits = Vec::new();
+    for theme_dir in base_dirs.iter().map(|base| base.join(theme)) {
+        let Ok(index) = std::fs::read_to_string(theme_dir.join("index.theme")) else {
+            continue;
+        };
+        let mut section = String::new();
+        let mut size = None;
+        let mut scale = 1;
+        let mut push = |section: &str, size: Option<u32>, scale: u32| {
+            if let (Some(size), 1, false) = (size, scale, section.is_empty() || section == "Icon Theme") {
+                dirs.push(IconDir { path: theme_dir.join(section), size });
+            }
+        };
+        for line in index.lines().map(str::trim) {
+            if let Some(name) = line.strip_prefix('[').and_then(|line| line.strip_suffix(']')) {
+                push(&section, size.take(), scale);
+                section = name.to_owned();
+                scale = 1;
+            } else if let Some((key, value)) = line.split_once('=') {
+                match (section.as_str(), key.trim()) {
+                    ("Icon Theme", "Inherits") if inherits.is_empty() => {
+                        inherits = value.split(',').map(|theme| theme.trim().to_owned()).collect();
+                    }
+                    (_, "Size") => size = value.trim().parse().ok(),
+                    (_, "Scale") => scale = value.trim().parse().unwrap_or(1),
+                    _ => {}
+                }
+            }
+        }
+        push(&section, size, scale);
+    }
+    (dirs, inherits)
+}
+
+/// Finds the PNG file of the first of `names` that exists, in the size closest to `size` but not
+/// smaller if possible.
+pub(crate) fn find_icon(names: &[String], size: u32) -> Option<PathBuf> {
+    for theme in ICON_DIRS.iter() {
+        for name in names {
+            let file_name = format!("{name}.png");
+            let best = theme
+                .iter()
+                .filter(|dir| dir.path.join(&file_name).is_file())
+                // Smaller icons would have to be scaled up, which looks worse than scaling down.
+                .min_by_key(|dir| (dir.size < size, dir.size.abs_diff(size)));
+            if let Some(dir) = best {
+                return Some(dir.path.join(file_name));
+            }
+        }
+    }
+    names.iter().map(|name| Path::new("/usr/share/pixmaps").join(format!("{name}.png"))).find(|path| path.is_file())
+}
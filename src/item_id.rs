@@ -0,0 +1,234 @@
+//! The format of the ids of items in the trash, for tools that store ids and check them later on.
+
+use std::{
+    ffi::{OsStr, OsString},
+    path::{Path, PathBuf},
+};
+
+use crate::{Error, TrashContext};
+
+/// The parts of the [`id`](crate::TrashItem::id) of an item in the trash, as returned by
+/// [`parse_item_id`](TrashContext::parse_item_id).
+///
+/// Ids are the paths of files within the trash, and their format is kept stable between versions
+/// of this crate:
+///
+/// - On freedesktop systems, an id is the trash info file of the item,
+///   `<trash_root>/info/<key>.trashinfo`, and the item itself is `<trash_root>/files/<key>`.
+/// - On Windows, an id is the `$R` file of the item, `<trash_root>\$R<key>`, and its original path
+///   and deletion time are held by `<trash_root>\$I<key>`. The items listed by
+///   [`admin::list`](crate::admin::list) have the `$I` file as their id instead.
+///
+/// Should the format change in a later version, that version's
+/// [`migrate_id`](TrashContext::migrate_id) converts the ids of this version to the new format,
+/// as long as the items are still in the trash.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ItemId {
+    /// The trash folder that holds the item, like
+    /// [`TrashItem::trash_root`](crate::TrashItem::trash_root).
+    pub trash_root: PathBuf,
+    /// The name that the item is stored under in the trash folder, without the `.trashinfo`
+    /// extension or the `$R` prefix.
+    pub key: OsString,
+}
+
+impl ItemId {
+    /// Formats the id, which is the one of listed items, as described for [`ItemId`].
+    pub fn to_id(&self) -> OsString {
+        #[cfg(windows)]
+        return self.file_in_trash().into_os_string();
+        #[cfg(not(windows))]
+        self.info_file().into_os_string()
+    }
+
+    /// The file or folder of the item within the trash.
+    pub fn file_in_trash(&self) -> PathBuf {
+        #[cfg(windows)]
+        return self.trash_root.join(prefixed("$R", &self.key));
+        #[cfg(not(windows))]
+        self.trash_root.join("files").join(&self.key)
+    }
+
+    /// The file that holds the original path and the deletion time of the item, which is its
+    /// trash info file on freedesktop systems and its `$I` file on Windows.
+    pub fn info_file(&self) -> PathBuf {
+        #[cfg(windows)]
+        return self.trash_root.join(prefixed("$I", &self.key));
+        #[cfg(not(windows))]
+        {
+            let mut name = self.key.clone();
+            name.push(".trashinfo");
+            self.trash_root.join("info").join(name)
+        }
+    }
+}
+
+impl TrashContext {
+    /// Splits the id of an item into its parts, failing with [`Error::InvalidItemId`] if it doesn't
+    /// have the form of an id on this platform. Only the form is checked, not whether the item is
+    /// still in the trash.
+    ///
+    /// This lets tools that keep ids in a database check them before using them, and rebuild
+    /// them with [`ItemId::to_id`], for example after the trash was moved along with the home
+    /// folder.
+    ///
+    /// See: [`os_limited::parse_item_id`](crate::os_limited::parse_item_id)
+    pub fn parse_item_id<T: AsRef<OsStr>>(&self, id: T) -> Result<ItemId, Error> {
+        parse(Path::new(id.as_ref())).ok_or_else(|| Error::InvalidItemId { id: id.as_ref().to_owned() })
+    }
+
+    /// Converts an id that was persisted by an earlier version of this crate, or that refers to an
+    /// item in another way, to the id that the item is listed with by this version. Ids that are
+    /// current already are returned as they are. Fails with [`Error::InvalidItemId`] if the id
+    /// can't be converted. Like [`parse_item_id`](TrashContext::parse_item_id), this doesn't check
+    /// whether the item is still in the trash.
+    ///
+    /// Besides the current ids, this accepts:
+    /// - On freedesktop systems, `file://` URIs of trash info files, and the paths and `file://`
+    ///   URIs of the items in the `files` folder of the trash, as used by file managers.
+    /// - On Windows, ids with the `\\?\` prefix, and the `$I` files of items, which are the ids
+    ///   of the items listed by [`admin::list`](crate::admin::list).
+    ///
+    /// See: [`os_limited::migrate_id`](crate::os_limited::migrate_id)
+    pub fn migrate_id<T: AsRef<OsStr>>(&self, id: T) -> Result<OsString, Error> {
+        let id = id.as_ref();
+        migrate(id).ok_or_else(|| Error::InvalidItemId { id: id.to_owned() })
+    }
+}
+
+#[cfg(not(windows))]
+fn migrate(id: &OsStr) -> Option<OsString> {
+    let path = match id.to_str().filter(|id| id.starts_with("file://")) {
+        Some(uri) => url::Url::parse(uri).ok()?.to_file_path().ok()?,
+        None => PathBuf::from(id),
+    };
+    let item_id = parse(&path).or_else(|| {
+        // The item itself, `<trash_root>/files/<key>`.
+        let files_folder = path.parent()?;
+        if !path.is_absolute() || files_folder.file_name()? != "files" {
+            return None;
+        }
+        Some(ItemId { trash_root: files_folder.parent()?.to_owned(), key: path.file_name()?.to_owned() })
+    })?;
+    Some(item_id.to_id())
+}
+
+#[cfg(windows)]
+fn migrate(id: &OsStr) -> Option<OsString> {
+    use std::os::windows::ffi::{OsStrExt, OsStringExt};
+
+    let wide: Vec<u16> = id.encode_wide().collect();
+    let prefix: Vec<u16> = r"\\?\".encode_utf16().collect();
+    let path = PathBuf::from(OsString::from_wide(wide.strip_prefix(prefix.as_slice()).unwrap_or(&wide)));
+    parse(&path).map(|item_id| item_id.to_id())
+}
+
+/// `<trash_root>/info/<key>.trashinfo`
+#[cfg(not(windows))]
+fn parse(id: &Path) -> Option<ItemId> {
+    use std::os::unix::ffi::OsStrExt;
+
+    if !id.is_absolute() {
+        return None;
+    }
+    let info_folder = id.parent()?;
+    if info_folder.file_name()? != "info" {
+        return None;
+    }
+    let key = id.file_name()?.as_bytes().strip_suffix(b".trashinfo")?;
+    if key.is_empty() {
+        return None;
+    }
+    Some(ItemId { trash_root: info_folder.parent()?.to_owned(), key: OsStr::from_bytes(key).to_owned() })
+}
+
+/// `<drive>\$Recycle.Bin\<SID>\$R<key>` or `$I<key>`
+#[cfg(windows)]
+fn parse(id: &Path) -> Option<ItemId> {
+    if !id.is_absolute() {
+        return None;
+    }
+    let user_folder = id.parent()?;
+    let recycle_bin = user_folder.parent()?.file_name()?;
+    let sid = user_folder.file_name()?.to_str()?;
+    if !recycle_bin.eq_ignore_ascii_case("$Recycle.Bin") || !sid.starts_with("S-") {
+        return None;
+    }
+    let name = id.file_name()?.to_str()?;
+    let key = name.strip_prefix("$R").or_else(|| name.strip_prefix("$I")).filter(|key| !key.is_empty())?;
+    Some(ItemId { trash_root: user_folder.to_owned(), key: key.into() })
+}
+
+#[cfg(windows)]
+fn prefixed(prefix: &str, key: &OsStr) -> OsString {
+    let mut name = OsString::from(prefix);
+    name.push(key);
+    name
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    #[cfg(not(windows))]
+    fn trash_info_ids() {
+        let ctx = TrashContext::default();
+        let id = ctx.parse_item_id("/home/user/.local/share/Trash/info/notes.txt.2.trashinfo").unwrap();
+        assert_eq!(id.trash_root, Path::new("/home/user/.local/share/Trash"));
+        assert_eq!(id.key, "notes.txt.2");
+        assert_eq!(id.file_in_trash(), Path::new("/home/user/.local/share/Trash/files/notes.txt.2"));
+        assert_eq!(id.to_id(), "/home/user/.local/share/Trash/info/notes.txt.2.trashinfo");
+        for invalid in ["info/a.trashinfo", "/Trash/files/a.trashinfo", "/Trash/info/a", "/Trash/info/.trashinfo"] {
+            assert!(matches!(ctx.parse_item_id(invalid), Err(Error::InvalidItemId { .. })), "{invalid}");
+        }
+    }
+
+    #[test]
+    #[cfg(not(windows))]
+    fn migrate_trash_info_ids() {
+        let ctx = TrashContext::default();
+        let current = "/home/user/.local/share/Trash/info/my notes.txt.trashinfo";
+        for old in [
+            current,
+            "file:///home/user/.local/share/Trash/info/my%20notes.txt.trashinfo",
+            "/home/user/.local/share/Trash/files/my notes.txt",
+            "file:///home/user/.local/share/Trash/files/my%20notes.txt",
+        ] {
+            assert_eq!(ctx.migrate_id(old).unwrap(), current, "{old}");
+        }
+        for invalid in ["files/a", "file:///Trash/a", "https://example.com/Trash/info/a.trashinfo"] {
+            assert!(matches!(ctx.migrate_id(invalid), Err(Error::InvalidItemId { .. })), "{invalid}");
+        }
+    }
+
+    #[test]
+    #[cfg(windows)]
+    fn recycle_bin_ids() {
+        let ctx = TrashContext::default();
+        let id = ctx.parse_item_id(r"C:\$Recycle.Bin\S-1-5-21-1\$RABC123.txt").unwrap();
+        assert_eq!(id.trash_root, Path::new(r"C:\$Recycle.Bin\S-1-5-21-1"));
+        assert_eq!(id.key, "ABC123.txt");
+        assert_eq!(id.info_file(), Path::new(r"C:\$Recycle.Bin\S-1-5-21-1\$IABC123.txt"));
+        assert_eq!(ctx.parse_item_id(id.info_file()).unwrap(), id);
+        assert_eq!(id.to_id(), r"C:\$Recycle.Bin\S-1-5-21-1\$RABC123.txt");
+        for invalid in
+            [r"$Recycle.Bin\S-1-5\$RA", r"C:\Temp\S-1-5\$RA", r"C:\$Recycle.Bin\S-1-5\A", r"C:\$Recycle.Bin\S-1\$R"]
+        {
+            assert!(matches!(ctx.parse_item_id(invalid), Err(Error::InvalidItemId { .. })), "{invalid}");
+        }
+    }
+
+    #[test]
+    #[cfg(windows)]
+    fn migrate_recycle_bin_ids() {
+        let ctx = TrashContext::default();
+        let current = r"C:\$Recycle.Bin\S-1-5-21-1\$RABC123.txt";
+        for old in [current, r"\\?\C:\$Recycle.Bin\S-1-5-21-1\$RABC123.txt", r"C:\$Recycle.Bin\S-1-5-21-1\$IABC123.txt"]
+        {
+            assert_eq!(ctx.migrate_id(old).unwrap(), current, "{old}");
+        }
+        assert!(matches!(ctx.migrate_id(r"\\?\C:\Temp\$RABC123.txt"), Err(Error::InvalidItemId { .. })));
+    }
+}
@@ -0,0 +1,45 @@
+//! Bounding the number of items that listing the trash keeps in memory.
+
+use crate::{Error, TrashContext, TrashItem};
+
+impl TrashContext {
+    /// Sets the maximum number of items that [`list`](TrashContext::list) returns. If the trash
+    /// holds more items, listing stops as soon as the limit is exceeded and fails with
+    /// [`Error::TooManyItems`], instead of loading all of them into memory. Defaults to `None`,
+    /// which doesn't limit the listing.
+    ///
+    /// Every item takes up a few hundred bytes, mostly for its id and original path, so a trash
+    /// with hundreds of thousands of items needs hundreds of megabytes to be listed at once.
+    pub fn set_list_limit(&mut self, limit: Option<usize>) {
+        self.list_limit = limit;
+    }
+
+    /// See [`set_list_limit`](TrashContext::set_list_limit).
+    pub fn list_limit(&self) -> Option<usize> {
+        self.list_limit
+    }
+}
+
+/// Adds `item` to `items`, unless that would make them exceed the `limit`.
+pub(crate) fn push(items: &mut Vec<TrashItem>, item: TrashItem, limit: Option<usize>) -> Result<(), Error> {
+    match limit {
+        Some(limit) if items.len() >= limit => Err(Error::TooManyItems { limit }),
+        _ => {
+            items.push(item);
+            Ok(())
+        }
+    }
+}
+
+/// Collects the `items`, failing as soon as there are more than `limit` of them.
+#[cfg(target_os = "windows")]
+pub(crate) fn collect(
+    items: impl IntoIterator<Item = TrashItem>,
+    limit: Option<usize>,
+) -> Result<Vec<TrashItem>, Error> {
+    let mut collected = Vec::new();
+    for item in items {
+        push(&mut collected, item, limit)?;
+    }
+    Ok(collected)
+}
@@ -0,0 +1,48 @@
+//! Choosing the language that items in the trash are displayed in.
+
+use crate::{Error, TrashContext, TrashItem};
+
+/// The language of the [`name`](TrashItem::name)s that [`TrashContext::list`] returns.
+///
+/// Windows lists items with the names the shell displays, which are localized for some folders,
+/// like the known folders that were moved to the Recycle Bin. On freedesktop systems the names are
+/// the file names the items were deleted with, so all variants behave the same.
+///
+/// The deletion times are always Unix timestamps, which aren't affected by the locale.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum DisplayLocale {
+    /// The user interface language of the current thread, which is the language of the user
+    /// running the process unless the thread changed it.
+    #[default]
+    System,
+    /// The language with the given BCP 47 tag, like `de-DE`. Windows falls back to the language of
+    /// the user for strings that aren't available in that language.
+    Language(String),
+    /// The names that the items had before they were deleted, without any localization.
+    Raw,
+}
+
+impl TrashContext {
+    /// Sets the language of the names that [`list`](TrashContext::list) returns, so that a server
+    /// can list items for users in another locale. Defaults to [`DisplayLocale::System`].
+    pub fn set_display_locale(&mut self, locale: DisplayLocale) {
+        self.display_locale = locale;
+    }
+
+    /// See [`set_display_locale`](TrashContext::set_display_locale).
+    pub fn display_locale(&self) -> &DisplayLocale {
+        &self.display_locale
+    }
+
+    /// Returns all items in the trash with names in the
+    /// [`display_locale`](TrashContext::display_locale), ordered like
+    /// [`os_limited::list`](crate::os_limited::list).
+    ///
+    /// See: [`os_limited::list`](crate::os_limited::list)
+    pub fn list(&self) -> Result<Vec<TrashItem>, Error> {
+        let mut items = self.list_items()?;
+        items.sort_by(crate::listing_order);
+        Ok(items)
+    }
+}
@@ -1,4 +1,9 @@
-use std::{ffi::OsString, path::PathBuf, process::Command};
+use std::{
+    ffi::OsString,
+    os::unix::fs::MetadataExt,
+    path::{Path, PathBuf},
+    process::Command,
+};
 
 use log::{trace, warn};
 use objc::{
@@ -7,7 +12,7 @@ use objc::{
     sel, sel_impl,
 };
 
-use crate::{into_unknown, Error, TrashContext};
+use crate::{destination, into_unknown, CancellationToken, Error, Preset, TrashContext, TrashTransfer};
 
 #[link(name = "Foundation", kind = "framework")]
 extern "C" {
@@ -64,6 +69,13 @@ impl PlatformTrashContext {
     pub const fn new() -> Self {
         Self { delete_method: DeleteMethod::new() }
     }
+
+    pub(crate) fn apply_preset(&mut self, preset: Preset) {
+        self.delete_method = match preset {
+            Preset::Silent => DeleteMethod::NsFileManager,
+            Preset::Interactive | Preset::Paranoid | Preset::BestEffort => DeleteMethod::Finder,
+        };
+    }
 }
 pub trait TrashContextExtMacos {
     fn set_delete_method(&mut self, method: DeleteMethod);
@@ -82,58 +94,127 @@ impl TrashContext {
         let full_paths = full_paths.into_iter().map(to_string).collect::<Result<Vec<_>, _>>()?;
         match self.platform_specific.delete_method {
             DeleteMethod::Finder => delete_using_finder(full_paths),
-            DeleteMethod::NsFileManager => delete_using_file_mgr(full_paths),
+            DeleteMethod::NsFileManager => {
+                delete_using_file_mgr(full_paths, self.continue_on_error, self.cancellation())
+            }
+        }
+    }
+
+    /// Finder moves all items of a batch in a single operation, regardless of their volume.
+    pub(crate) fn delete_by_volume(&self, full_paths: Vec<PathBuf>) -> Result<(), Error> {
+        self.delete_all_canonicalized(full_paths)
+    }
+
+    pub(crate) fn destination_canonicalized(
+        &self,
+        full_path: &Path,
+    ) -> Result<(PathBuf, PathBuf, TrashTransfer), Error> {
+        let device = |path: &Path| path.symlink_metadata().map(|m| m.dev()).ok();
+        let item_device = device(full_path);
+        // The volume root is the topmost ancestor that's still on the same device as the item.
+        let volume = full_path.ancestors().take_while(|p| device(p) == item_device).last().unwrap_or(full_path);
+        let home = std::env::var_os("HOME").map(PathBuf::from);
+        let trash_root = match home {
+            Some(home) if device(&home) == item_device => home.join(".Trash"),
+            _ => volume.join(".Trashes").join(unsafe { libc::getuid() }.to_string()),
+        };
+        let transfer = destination::transfer_between(full_path, &trash_root);
+        Ok((trash_root, volume.to_owned(), transfer))
+    }
+}
+
+/// Returns `true` if the canonicalized `path` is the trash of the home folder or of a volume, or
+/// is inside of one.
+pub(crate) fn is_trash_folder(path: &Path) -> bool {
+    if let Some(home) = std::env::var_os("HOME").map(PathBuf::from) {
+        let home_trash = home.join(".Trash");
+        let home_trash = home_trash.canonicalize().unwrap_or(home_trash);
+        if path.starts_with(home_trash) {
+            return true;
+        }
+    }
+    path.ancestors().any(|ancestor| ancestor.file_name().is_some_and(|name| name == ".Trashes"))
+}
+
+impl TrashContext {
+    pub(crate) fn delete_permanently_canonicalized(&self, full_paths: &[PathBuf]) -> Result<(), Error> {
+        let mut first_error = None;
+        for path in full_paths {
+            if let Err(e) = crate::permanent::remove(path, self.path_progress()) {
+                let error = into_unknown(format!("Failed to delete {path:?} permanently: {e}"));
+                if !self.continue_on_error {
+                    return Err(error);
+                }
+                warn!("Continuing with the remaining items after an error: {:?}", error);
+                first_error.get_or_insert(error);
+            }
         }
+        first_error.map_or(Ok(()), Err)
     }
 }
 
-fn delete_using_file_mgr(full_paths: Vec<String>) -> Result<(), Error> {
+fn delete_using_file_mgr(
+    full_paths: Vec<String>,
+    continue_on_error: bool,
+    cancellation: Option<&CancellationToken>,
+) -> Result<(), Error> {
     trace!("Starting delete_using_file_mgr");
-    let url_cls = class!(NSURL);
-    let file_mgr_cls = class!(NSFileManager);
-    let file_mgr: id = unsafe { msg_send![file_mgr_cls, defaultManager] };
+    let mut first_error = None;
     for path in full_paths {
-        let string = to_ns_string(&path);
-        trace!("Starting fileURLWithPath");
-        let url: id = unsafe { msg_send![url_cls, fileURLWithPath:string.ptr] };
-        if url == nil {
-            return Err(Error::Unknown {
-                description: format!("Failed to convert a path to an NSURL. Path: '{path}'"),
-            });
+        if cancellation.is_some_and(CancellationToken::is_cancelled) {
+            return Err(Error::Cancelled);
         }
-        trace!("Finished fileURLWithPath");
-        // WARNING: I don't know why but if we try to call release on the url, it sometimes
-        // crashes with SIGSEGV, so we instead don't try to release the url
-        // let url = OwnedObject { ptr: url };
-        let mut error: id = nil;
-        trace!("Calling trashItemAtURL");
-        let success: BOOL = unsafe {
-            msg_send![
-                file_mgr,
-                trashItemAtURL:url
-                resultingItemURL:nil
-                error:(&mut error as *mut id)
-            ]
-        };
-        trace!("Finished trashItemAtURL");
-        if success == NO {
-            trace!("success was NO");
-            if error == nil {
-                return Err(Error::Unknown {
-                    description: format!(
-                        "While deleting '{path}', `trashItemAtURL` returned with failure but no error was specified.",
-                    ),
-                });
+        if let Err(error) = trash_using_file_mgr(&path) {
+            if !continue_on_error {
+                return Err(error);
             }
-            let code: isize = unsafe { msg_send![error, code] };
-            let domain: id = unsafe { msg_send![error, domain] };
-            let domain = unsafe { ns_string_to_rust(domain)? };
+            warn!("Continuing with the remaining items after an error: {:?}", error);
+            first_error.get_or_insert(error);
+        }
+    }
+    first_error.map_or(Ok(()), Err)
+}
+
+fn trash_using_file_mgr(path: &str) -> Result<(), Error> {
+    let url_cls = class!(NSURL);
+    let file_mgr_cls = class!(NSFileManager);
+    let file_mgr: id = unsafe { msg_send![file_mgr_cls, defaultManager] };
+    let string = to_ns_string(path);
+    trace!("Starting fileURLWithPath");
+    let url: id = unsafe { msg_send![url_cls, fileURLWithPath:string.ptr] };
+    if url == nil {
+        return Err(Error::Unknown { description: format!("Failed to convert a path to an NSURL. Path: '{path}'") });
+    }
+    trace!("Finished fileURLWithPath");
+    // WARNING: I don't know why but if we try to call release on the url, it sometimes
+    // crashes with SIGSEGV, so we instead don't try to release the url
+    // let url = OwnedObject { ptr: url };
+    let mut error: id = nil;
+    trace!("Calling trashItemAtURL");
+    let success: BOOL = unsafe {
+        msg_send![
+            file_mgr,
+            trashItemAtURL:url
+            resultingItemURL:nil
+            error:(&mut error as *mut id)
+        ]
+    };
+    trace!("Finished trashItemAtURL");
+    if success == NO {
+        trace!("success was NO");
+        if error == nil {
             return Err(Error::Unknown {
                 description: format!(
-                    "While deleting '{path}', `trashItemAtURL` failed, code: {code}, domain: {domain}",
+                    "While deleting '{path}', `trashItemAtURL` returned with failure but no error was specified.",
                 ),
             });
         }
+        let code: isize = unsafe { msg_send![error, code] };
+        let domain: id = unsafe { msg_send![error, domain] };
+        let domain = unsafe { ns_string_to_rust(domain)? };
+        return Err(Error::Unknown {
+            description: format!("While deleting '{path}', `trashItemAtURL` failed, code: {code}, domain: {domain}",),
+        });
     }
     Ok(())
 }
@@ -163,6 +244,7 @@ fn delete_using_finder(full_paths: Vec<String>) -> Result<(), Error> {
             Some(code) => {
                 return Err(Error::Os {
                     code,
+                    function: Some("osascript".into()),
                     description: format!("The AppleScript exited with error. stderr: {}", stderr),
                 })
             }
@@ -171,6 +253,192 @@ fn delete_using_finder(full_paths: Vec<String>) -> Result<(), Error> {
     Ok(())
 }
 
+#[cfg(feature = "mime")]
+#[link(name = "CoreServices", kind = "framework")]
+extern "C" {
+    static kUTTagClassFilenameExtension: id;
+    static kUTTagClassMIMEType: id;
+    fn UTTypeCreatePreferredIdentifierForTag(tag_class: id, tag: id, conforming_to: id) -> id;
+    fn UTTypeCopyPreferredTagWithClass(uti: id, tag_class: id) -> id;
+}
+
+#[cfg(feature = "mime")]
+pub(crate) fn content_type(item: &crate::TrashItem) -> Result<String, Error> {
+    use crate::content_type::UNKNOWN;
+
+    let Some(extension) = Path::new(&item.name).extension().and_then(|extension| extension.to_str()) else {
+        return Ok(UNKNOWN.into());
+    };
+    let extension = to_ns_string(extension);
+    // The `CFString`s are interchangeable with `NSString`s.
+    unsafe {
+        let uti = UTTypeCreatePreferredIdentifierForTag(kUTTagClassFilenameExtension, extension.ptr, nil);
+        if uti == nil {
+            return Ok(UNKNOWN.into());
+        }
+        let uti = OwnedObject { ptr: uti };
+        let mime_type = UTTypeCopyPreferredTagWithClass(uti.ptr, kUTTagClassMIMEType);
+        if mime_type == nil {
+            return Ok(UNKNOWN.into());
+        }
+        let mime_type = OwnedObject { ptr: mime_type };
+        ns_string_to_rust(mime_type.ptr)
+    }
+}
+
+#[cfg(feature = "thumbnails")]
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct CGPoint {
+    x: f64,
+    y: f64,
+}
+
+#[cfg(feature = "thumbnails")]
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct CGSize {
+    width: f64,
+    height: f64,
+}
+
+#[cfg(feature = "thumbnails")]
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct CGRect {
+    origin: CGPoint,
+    size: CGSize,
+}
+
+#[cfg(feature = "thumbnails")]
+type CFTypeRef = *mut std::ffi::c_void;
+
+/// The colors of a bitmap context are premultiplied by their alpha, which follows them.
+#[cfg(feature = "thumbnails")]
+const K_CG_IMAGE_ALPHA_PREMULTIPLIED_LAST: u32 = 1;
+
+#[cfg(feature = "thumbnails")]
+#[link(name = "AppKit", kind = "framework")]
+extern "C" {
+    // Links AppKit for `NSWorkspace`.
+}
+
+#[cfg(feature = "thumbnails")]
+#[link(name = "QuickLook", kind = "framework")]
+extern "C" {
+    fn QLThumbnailImageCreate(allocator: CFTypeRef, url: id, max_size: CGSize, options: id) -> CFTypeRef;
+}
+
+#[cfg(feature = "thumbnails")]
+#[link(name = "CoreGraphics", kind = "framework")]
+extern "C" {
+    fn CGImageGetWidth(image: CFTypeRef) -> usize;
+    fn CGImageGetHeight(image: CFTypeRef) -> usize;
+    fn CGImageRelease(image: CFTypeRef);
+    fn CGColorSpaceCreateDeviceRGB() -> CFTypeRef;
+    fn CGColorSpaceRelease(space: CFTypeRef);
+    fn CGBitmapContextCreate(
+        data: *mut std::ffi::c_void,
+        width: usize,
+        height: usize,
+        bits_per_component: usize,
+        bytes_per_row: usize,
+        space: CFTypeRef,
+        bitmap_info: u32,
+    ) -> CFTypeRef;
+    fn CGContextDrawImage(context: CFTypeRef, rect: CGRect, image: CFTypeRef);
+    fn CGContextRelease(context: CFTypeRef);
+}
+
+/// The Quick Look thumbnail of the file at the item's id if that's a path, or the icon Finder
+/// shows for the type of the item, which is guessed from its name.
+#[cfg(feature = "thumbnails")]
+pub(crate) fn icon(item: &crate::TrashItem, size: u32) -> Result<crate::Icon, Error> {
+    let size = size.max(1);
+    let path = Path::new(&item.id);
+    let exists = path.is_absolute() && path.symlink_metadata().is_ok();
+    unsafe {
+        let workspace: id = msg_send![class!(NSWorkspace), sharedWorkspace];
+        let image: id = if exists {
+            let path = to_ns_string(&to_string(path.as_os_str())?);
+            let url: id = msg_send![class!(NSURL), fileURLWithPath:path.ptr];
+            let side = f64::from(size);
+            let thumbnail =
+                QLThumbnailImageCreate(std::ptr::null_mut(), url, CGSize { width: side, height: side }, nil);
+            if !thumbnail.is_null() {
+                let icon = render(thumbnail, size);
+                CGImageRelease(thumbnail);
+                return icon;
+            }
+            msg_send![workspace, iconForFile:path.ptr]
+        } else {
+            let file_type = match (item.kind, Path::new(&item.name).extension().and_then(|ext| ext.to_str())) {
+                (Some(crate::TrashItemKind::Directory), _) => "public.folder",
+                (_, Some(extension)) => extension,
+                (_, None) => "public.data",
+            };
+            let file_type = to_ns_string(file_type);
+            msg_send![workspace, iconForFileType:file_type.ptr]
+        };
+        let cg_image: CFTypeRef = if image == nil {
+            std::ptr::null_mut()
+        } else {
+            msg_send![image, CGImageForProposedRect:std::ptr::null_mut::<CGRect>() context:nil hints:nil]
+        };
+        if cg_image.is_null() {
+            return Err(Error::Os {
+                code: 0,
+                function: Some("NSWorkspace::iconForFile".into()),
+                description: format!("No icon was found for {:?}", item.name),
+            });
+        }
+        // The image belongs to the `NSImage`, it isn't released here.
+        render(cg_image, size)
+    }
+}
+
+/// Draws the `image` into a square of `size` pixels, keeping its aspect ratio.
+#[cfg(feature = "thumbnails")]
+unsafe fn render(image: CFTypeRef, size: u32) -> Result<crate::Icon, Error> {
+    let (width, height) = (CGImageGetWidth(image), CGImageGetHeight(image));
+    let longest = width.max(height).max(1);
+    let size = size as usize;
+    let (width, height) = ((width * size / longest).max(1), (height * size / longest).max(1));
+    let mut rgba = vec![0u8; width * height * 4];
+    let space = CGColorSpaceCreateDeviceRGB();
+    let context = CGBitmapContextCreate(
+        rgba.as_mut_ptr().cast(),
+        width,
+        height,
+        8,
+        width * 4,
+        space,
+        K_CG_IMAGE_ALPHA_PREMULTIPLIED_LAST,
+    );
+    CGColorSpaceRelease(space);
+    if context.is_null() {
+        return Err(Error::Os {
+            code: 0,
+            function: Some("CGBitmapContextCreate".into()),
+            description: format!("Failed to create a bitmap of {width} by {height} pixels"),
+        });
+    }
+    let bounds =
+        CGRect { origin: CGPoint { x: 0.0, y: 0.0 }, size: CGSize { width: width as f64, height: height as f64 } };
+    CGContextDrawImage(context, bounds, image);
+    CGContextRelease(context);
+    // Icons hold colors that aren't premultiplied.
+    for pixel in rgba.chunks_exact_mut(4) {
+        let alpha = u32::from(pixel[3]);
+        if alpha > 0 && alpha < 255 {
+            for channel in &mut pixel[..3] {
+                *channel = ((u32::from(*channel) * 255 + alpha / 2) / alpha).min(255) as u8;
+            }
+        }
+    }
+    Ok(crate::Icon { width: width as u32, height: height as u32, rgba })
+}
+
 fn to_string<T: Into<OsString>>(str_in: T) -> Result<String, Error> {
     let os_string = str_in.into();
     let s = os_string.to_str();
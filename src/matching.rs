@@ -0,0 +1,158 @@
+//! Comparing user supplied paths against the original paths of trashed items.
+
+use std::{
+    borrow::Cow,
+    path::{Path, PathBuf},
+};
+
+/// Whether paths are compared case-sensitively when looking up items by their original path.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum CaseSensitivity {
+    /// Detect the case sensitivity of the file system that holds the looked up path. This is done
+    /// by checking whether a differently cased variant of one of its existing ancestors refers to
+    /// the same file. When the path doesn't have such an ancestor, the platform's usual behaviour
+    /// is assumed: case-insensitive on Windows and case-sensitive elsewhere.
+    #[default]
+    Auto,
+    /// Paths only match when their case is identical.
+    Sensitive,
+    /// Paths match regardless of their case.
+    Insensitive,
+}
+
+impl CaseSensitivity {
+    /// Returns `true` if paths on the file system holding `path` should be compared
+    /// case-sensitively.
+    ///
+    /// For [`CaseSensitivity::Auto`] this touches the file system, the other variants are returned
+    /// as is.
+    pub fn is_sensitive_for(self, path: &Path) -> bool {
+        match self {
+            CaseSensitivity::Sensitive => true,
+            CaseSensitivity::Insensitive => false,
+            CaseSensitivity::Auto => detect(path).unwrap_or(cfg!(not(target_os = "windows"))),
+        }
+    }
+}
+
+/// Probes the closest existing ancestor of `path` that has a name with cased letters. Returns
+/// `None` if there's no such ancestor.
+fn detect(path: &Path) -> Option<bool> {
+    for ancestor in path.ancestors().filter(|p| p.symlink_metadata().is_ok()) {
+        let (Some(parent), Some(name)) = (ancestor.parent(), ancestor.file_name().and_then(|n| n.to_str())) else {
+            continue;
+        };
+        let swapped: String = name
+            .chars()
+            .map(|c| if c.is_ascii_lowercase() { c.to_ascii_uppercase() } else { c.to_ascii_lowercase() })
+            .collect();
+        if swapped == name {
+            continue;
+        }
+        return Some(!same_file(ancestor, &parent.join(swapped)));
+    }
+    None
+}
+
+/// Whether both paths refer to the same file, without following symbolic links on Unix.
+#[cfg(unix)]
+pub(crate) fn same_file(a: &Path, b: &Path) -> bool {
+    use std::os::unix::fs::MetadataExt;
+
+    match (a.symlink_metadata(), b.symlink_metadata()) {
+        (Ok(a), Ok(b)) => a.dev() == b.dev() && a.ino() == b.ino(),
+        _ => false,
+    }
+}
+
+#[cfg(not(unix))]
+pub(crate) fn same_file(a: &Path, b: &Path) -> bool {
+    // Canonicalizing yields the name as it's stored on disk, so a differently cased variant only
+    // canonicalizes to the same path if it refers to the same file.
+    match (a.canonicalize(), b.canonicalize()) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => false,
+    }
+}
+
+/// Compares two paths, ignoring the case of their letters if `case_sensitive` is false.
+#[cfg(any(
+    target_os = "windows",
+    all(unix, not(target_os = "macos"), not(target_os = "ios"), not(target_os = "android"))
+))]
+pub(crate) fn paths_match(a: &Path, b: &Path, case_sensitive: bool) -> bool {
+    comparison_key(a, case_sensitive) == comparison_key(b, case_sensitive)
+}
+
+/// Returns a form of `path` that is the same for all paths that refer to the same file, ignoring
+/// the case of letters if `case_sensitive` is false.
+///
+/// On macOS, names are also brought into the decomposed Unicode form (NFD), as APFS and HFS+ treat
+/// names that only differ in their normalization as the same.
+pub(crate) fn comparison_key(path: &Path, case_sensitive: bool) -> PathBuf {
+    let path = strip_verbatim(path);
+    let Some(text) = path.to_str() else {
+        return path.into_owned();
+    };
+    #[cfg(target_os = "macos")]
+    let text = &unicode_normalization::UnicodeNormalization::nfd(text).collect::<String>();
+    match case_sensitive {
+        true => PathBuf::from(text),
+        false => PathBuf::from(text.to_lowercase()),
+    }
+}
+
+/// Removes the `\\?\` prefix of extended-length paths on Windows, which refer to the same file as
+/// the path without the prefix. Other paths are returned as is.
+pub(crate) fn strip_verbatim(path: &Path) -> Cow<'_, Path> {
+    #[cfg(windows)]
+    if let Some(path) = path.to_str() {
+        if let Some(share) = path.strip_prefix(r"\\?\UNC\") {
+            return Cow::Owned(PathBuf::from(format!(r"\\{share}")));
+        }
+        if let Some(local) = path.strip_prefix(r"\\?\") {
+            return Cow::Borrowed(Path::new(local));
+        }
+    }
+    Cow::Borrowed(path)
+}
+
+/// Returns the items that were originally located at `original_path`, ordered by their deletion
+/// time. Relative paths are resolved against the current working directory.
+#[cfg(any(
+    target_os = "windows",
+    all(unix, not(target_os = "macos"), not(target_os = "ios"), not(target_os = "android"))
+))]
+pub(crate) fn find_in<'a>(
+    items: impl IntoIterator<Item = &'a crate::TrashItem>,
+    original_path: &Path,
+    case_sensitivity: CaseSensitivity,
+) -> Result<Vec<crate::TrashItem>, crate::Error> {
+    let original_path = crate::absolute_path(original_path, None)?;
+    let case_sensitive = case_sensitivity.is_sensitive_for(&original_path);
+    let mut found: Vec<_> = items
+        .into_iter()
+        .filter(|item| item.original_path().is_some_and(|path| paths_match(&path, &original_path, case_sensitive)))
+        .cloned()
+        .collect();
+    found.sort_by(crate::TrashItem::by_deletion);
+    Ok(found)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn comparison_keys() {
+        assert_eq!(comparison_key(Path::new("/Tmp/A.txt"), false), comparison_key(Path::new("/tmp/a.TXT"), false));
+        assert_ne!(comparison_key(Path::new("/Tmp/A.txt"), true), comparison_key(Path::new("/tmp/a.TXT"), true));
+
+        // The precomposed and the decomposed form of "é" only name the same file on macOS.
+        let composed = comparison_key(Path::new("/tmp/caf\u{e9}"), true);
+        let decomposed = comparison_key(Path::new("/tmp/cafe\u{301}"), true);
+        assert_eq!(composed == decomposed, cfg!(target_os = "macos"));
+        assert_eq!(composed, comparison_key(&composed, true));
+    }
+}
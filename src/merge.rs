@@ -0,0 +1,239 @@
+//! Merging a folder from the trash into a folder that took its place in the meantime.
+
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use crate::{collision, io_error, platform, Error, TrashContext, TrashItem};
+
+/// How a folder from the trash is merged into a folder that exists at its original path, as set
+/// with [`set_directory_merge`](TrashContext::set_directory_merge).
+///
+/// The trees are merged recursively: files and folders that only exist in the trash are moved
+/// over, and folders that exist on both sides are merged in turn. The policy decides about the
+/// remaining conflicts, where a file of the trash meets an existing file, or a file meets a
+/// folder.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum MergePolicy {
+    /// Replace the existing items with those of the trash, moving the existing items to the trash.
+    Overwrite,
+    /// Replace the existing items that were modified before the items of the trash, and keep the
+    /// others. Items whose modification time can't be read are kept.
+    OverwriteOlder,
+    /// Keep the existing items.
+    SkipExisting,
+}
+
+/// What merging does with an item of a folder in the trash.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum MergeAction {
+    /// Nothing exists at the target, so the item is moved there.
+    Move,
+    /// The item replaces the one at the target, which is moved to the trash.
+    Replace,
+    /// The item at the target is kept, and the item stays in the trash.
+    Keep,
+}
+
+/// An item of a folder in the trash and what merging does with it.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MergeEntry {
+    /// The path of the item relative to the folder, which is empty for the folder itself.
+    pub path: PathBuf,
+    pub action: MergeAction,
+}
+
+/// The outcome of merging a folder from the trash into the folder at its original path, as
+/// returned by [`plan_merge`](TrashContext::plan_merge).
+///
+/// Folders that exist on both sides aren't listed, only what's within them. The plan reflects
+/// the state of the file system at the time it was made.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MergePlan {
+    /// The original path of the folder, which the entries are merged into.
+    pub target: PathBuf,
+    /// The items of the folder in the trash, in the order they're merged.
+    pub entries: Vec<MergeEntry>,
+}
+
+impl MergePlan {
+    /// Whether all the items are taken out of the trash, in which case the folder is removed
+    /// from the trash once they're merged. Otherwise it stays in the trash with the kept items.
+    pub fn is_complete(&self) -> bool {
+        self.entries.iter().all(|entry| entry.action != MergeAction::Keep)
+    }
+}
+
+impl TrashContext {
+    /// Merges folders from the trash into the folders that exist at their original paths when
+    /// they're restored with [`restore_all`](TrashContext::restore_all), instead of handling
+    /// them as a collision according to [`restore_collision`](TrashContext::restore_collision).
+    /// Defaults to `None`, which merges nothing.
+    ///
+    /// A folder whose items are all merged is removed from the trash. If some of its items are
+    /// kept by the policy, it stays in the trash with those items. Use
+    /// [`plan_merge`](TrashContext::plan_merge) to see what a merge does beforehand.
+    pub fn set_directory_merge(&mut self, policy: Option<MergePolicy>) {
+        self.directory_merge = policy;
+    }
+
+    /// See [`set_directory_merge`](TrashContext::set_directory_merge).
+    pub fn directory_merge(&self) -> Option<MergePolicy> {
+        self.directory_merge
+    }
+
+    /// Works out what merging the item into its original path according to `policy` does,
+    /// without changing anything. If nothing is at the original path, the plan moves the whole
+    /// item, and if either side isn't a folder, the item as a whole is a conflict.
+    ///
+    /// See: [`os_limited::plan_merge`](crate::os_limited::plan_merge)
+    pub fn plan_merge(&self, item: &TrashItem, policy: MergePolicy) -> Result<MergePlan, Error> {
+        let target = item.known_original_path()?;
+        let source = platform::file_in_trash(item)?;
+        let mut entries = Vec::new();
+        plan_entries(&source, &target, PathBuf::new(), policy, &mut entries)?;
+        Ok(MergePlan { target, entries })
+    }
+
+    /// Merges the folders of the trash whose original path is taken by a folder, according to the
+    /// [`directory_merge`](TrashContext::directory_merge) policy, and restores the other items
+    /// by volume. Returns the paths that were restored, which are those of the merged entries for
+    /// merged folders.
+    pub(crate) fn restore_merging(&self, items: Vec<TrashItem>) -> Result<Vec<PathBuf>, Error> {
+        let Some(policy) = self.directory_merge else {
+            return self.restore_by_volume(items);
+        };
+        let (merged, remaining): (Vec<_>, Vec<_>) = items.into_iter().partition(is_folder_onto_folder);
+        let mut targets = Vec::new();
+        for item in merged {
+            self.check_cancelled()?;
+            let plan = self.plan_merge(&item, policy)?;
+            self.apply_merge(&item, &plan)?;
+            targets.extend(
+                plan.entries
+                    .iter()
+                    .filter(|entry| entry.action != MergeAction::Keep)
+                    .map(|entry| plan.target.join(&entry.path)),
+            );
+        }
+        targets.extend(self.restore_by_volume(remaining)?);
+        Ok(targets)
+    }
+
+    fn apply_merge(&self, item: &TrashItem, plan: &MergePlan) -> Result<(), Error> {
+        let source = platform::file_in_trash(item)?;
+        for entry in &plan.entries {
+            self.check_cancelled()?;
+            let (from, to) = (source.join(&entry.path), plan.target.join(&entry.path));
+            match entry.action {
+                MergeAction::Keep => continue,
+                // The replaced item is kept in the trash.
+                MergeAction::Replace if collision::trash_existing(self, &to)? => {}
+                MergeAction::Replace => {
+                    return Err(Error::RestoreCollision { path: to, remaining_items: vec![item.clone()] });
+                }
+                MergeAction::Move => {}
+            }
+            move_entry(&from, &to).map_err(|e| io_error(&to, e))?;
+        }
+        if plan.is_complete() {
+            // Only the emptied folders are left in the trash.
+            self.purge_all([item])?;
+        }
+        Ok(())
+    }
+}
+
+/// Moves the file, folder or symbolic link at `from` to `to`. If they're on different devices, the
+/// item is copied over and removed from `from` once the copy is complete, and a partial copy is
+/// removed again.
+fn move_entry(from: &Path, to: &Path) -> io::Result<()> {
+    match fs::rename(from, to) {
+        Err(e) if e.kind() == io::ErrorKind::CrossesDevices => {}
+        result => return result,
+    }
+    if let Err(e) = copy_entry(from, to) {
+        let _ = collision::remove_existing(to);
+        return Err(e);
+    }
+    collision::remove_existing(from)
+}
+
+/// Copies the file, folder or symbolic link at `from` to `to`, without following links.
+pub(crate) fn copy_entry(from: &Path, to: &Path) -> io::Result<()> {
+    let metadata = from.symlink_metadata()?;
+    if metadata.is_symlink() {
+        let link = fs::read_link(from)?;
+        #[cfg(unix)]
+        return std::os::unix::fs::symlink(link, to);
+        #[cfg(windows)]
+        return match from.metadata() {
+            Ok(target) if target.is_dir() => std::os::windows::fs::symlink_dir(link, to),
+            _ => std::os::windows::fs::symlink_file(link, to),
+        };
+    }
+    if !metadata.is_dir() {
+        return fs::copy(from, to).map(drop);
+    }
+    fs::create_dir(to)?;
+    for entry in fs::read_dir(from)? {
+        let entry = entry?;
+        copy_entry(&entry.path(), &to.join(entry.file_name()))?;
+    }
+    fs::set_permissions(to, metadata.permissions())
+}
+
+/// Whether the item is a folder in the trash whose original path is taken by a folder.
+fn is_folder_onto_folder(item: &TrashItem) -> bool {
+    let is_dir = |path: &Path| path.symlink_metadata().is_ok_and(|metadata| metadata.is_dir());
+    let source = platform::file_in_trash(item).ok();
+    source.is_some_and(|source| is_dir(&source)) && item.original_path().is_some_and(|target| is_dir(&target))
+}
+
+/// Adds the entries for merging `source` into `target`, which are at `relative` within the
+/// merged folders.
+fn plan_entries(
+    source: &Path,
+    target: &Path,
+    relative: PathBuf,
+    policy: MergePolicy,
+    entries: &mut Vec<MergeEntry>,
+) -> Result<(), Error> {
+    let source_metadata = source.symlink_metadata().map_err(|e| io_error(source, e))?;
+    let target_metadata = match target.symlink_metadata() {
+        Ok(metadata) => metadata,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            entries.push(MergeEntry { path: relative, action: MergeAction::Move });
+            return Ok(());
+        }
+        Err(e) => return Err(io_error(target, e)),
+    };
+    if source_metadata.is_dir() && target_metadata.is_dir() {
+        let mut names: Vec<_> = fs::read_dir(source)
+            .map_err(|e| io_error(source, e))?
+            .map(|entry| entry.map(|entry| entry.file_name()))
+            .collect::<Result<_, _>>()
+            .map_err(|e| io_error(source, e))?;
+        names.sort();
+        for name in names {
+            plan_entries(&source.join(&name), &target.join(&name), relative.join(&name), policy, entries)?;
+        }
+        return Ok(());
+    }
+    let replace = match policy {
+        MergePolicy::Overwrite => true,
+        MergePolicy::SkipExisting => false,
+        MergePolicy::OverwriteOlder => match (source_metadata.modified(), target_metadata.modified()) {
+            (Ok(source_time), Ok(target_time)) => target_time < source_time,
+            _ => false,
+        },
+    };
+    let action = if replace { MergeAction::Replace } else { MergeAction::Keep };
+    entries.push(MergeEntry { path: relative, action });
+    Ok(())
+}
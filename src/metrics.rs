@@ -0,0 +1,33 @@
+//! The total size of the trash, for showing it without listing every item.
+
+use crate::{ByteSize, Error, TrashContext};
+
+/// The number of items in the trash and their total size, as returned by
+/// [`metrics`](TrashContext::metrics).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TrashMetrics {
+    /// The number of items in the trash, counting folders as one item.
+    pub item_count: u64,
+    /// The total size of the items in the trash, including the contents of folders.
+    pub total_bytes: ByteSize,
+    /// Whether the size of every item is included in `total_bytes`. This is `false` on freedesktop
+    /// systems if the size of a folder in the trash isn't recorded in the `directorysizes` file,
+    /// which makes `total_bytes` a lower bound.
+    pub complete: bool,
+}
+
+impl TrashContext {
+    /// Returns the number of items in the trash and their total size, without reading the
+    /// metadata of every item the way [`list`](crate::os_limited::list) does.
+    ///
+    /// On Windows the totals are queried from the shell with `SHQueryRecycleBin`. On freedesktop
+    /// systems the items are counted in the trash folders of the current user, and the sizes of
+    /// folders are taken from the `directorysizes` file that file managers maintain instead of
+    /// adding up their contents, see [`TrashMetrics::complete`].
+    ///
+    /// See: [`os_limited::metrics`](crate::os_limited::metrics)
+    pub fn metrics(&self) -> Result<TrashMetrics, Error> {
+        self.trash_metrics()
+    }
+}
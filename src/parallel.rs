@@ -0,0 +1,48 @@
+//! A bounded pool of worker threads for batch operations.
+
+use std::sync::{
+    atomic::{AtomicBool, AtomicUsize, Ordering},
+    Mutex,
+};
+
+use crate::Error;
+
+/// Runs `task` on every item, on up to `workers` threads at the same time, and returns the errors
+/// along with the index of the item they occurred on, ordered by that index.
+///
+/// If `stop_on_error` is set, no further items are started after the first error. Items are run on
+/// the calling thread if only one worker is needed.
+pub(crate) fn run<T, F>(items: &[T], workers: usize, stop_on_error: bool, task: F) -> Vec<(usize, Error)>
+where
+    T: Sync,
+    F: Fn(&T) -> Result<(), Error> + Sync,
+{
+    let workers = workers.clamp(1, items.len().max(1));
+    let next = AtomicUsize::new(0);
+    let stop = AtomicBool::new(false);
+    let errors = Mutex::new(Vec::new());
+    let work = || {
+        while !stop.load(Ordering::Relaxed) {
+            let index = next.fetch_add(1, Ordering::Relaxed);
+            let Some(item) = items.get(index) else {
+                break;
+            };
+            if let Err(e) = task(item) {
+                stop.store(stop_on_error, Ordering::Relaxed);
+                errors.lock().unwrap_or_else(|e| e.into_inner()).push((index, e));
+            }
+        }
+    };
+    if workers == 1 {
+        work();
+    } else {
+        std::thread::scope(|scope| {
+            for _ in 0..workers {
+                scope.spawn(work);
+            }
+        });
+    }
+    let mut errors = errors.into_inner().unwrap_or_else(|e| e.into_inner());
+    errors.sort_by_key(|(index, _)| *index);
+    errors
+}
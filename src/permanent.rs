@@ -0,0 +1,77 @@
+//! Deleting items right away, without moving them to the trash.
+
+use std::path::Path;
+
+#[cfg(not(target_os = "windows"))]
+use crate::progress::PathProgress;
+use crate::{Error, TrashContext};
+
+impl TrashContext {
+    /// Removes a single file or directory permanently, without moving it to the trash.
+    ///
+    /// See [`delete_all_permanently`](TrashContext::delete_all_permanently).
+    pub fn delete_permanently<T: AsRef<Path>>(&self, path: T) -> Result<(), Error> {
+        self.delete_all_permanently(&[path])
+    }
+
+    /// Removes all the files and directories permanently, without moving them to the trash.
+    ///
+    /// The paths go through the same checks as with [`delete_all`](TrashContext::delete_all):
+    /// relative paths are resolved against the [`base_dir`](TrashContext::base_dir), roots,
+    /// protected folders and, unless the context is [`recursive`](TrashContext::recursive),
+    /// folders that aren't empty are refused, and the
+    /// [`pre_delete_permanently`](crate::TrashHooks::pre_delete_permanently) and
+    /// [`post_delete_permanently`](crate::TrashHooks::post_delete_permanently) hooks are invoked.
+    /// Failures are handled according to
+    /// [`continue_on_error`](TrashContext::continue_on_error).
+    ///
+    /// Directories are removed along with everything in them. Symbolic links are removed, while
+    /// their targets are kept. On Windows the items are deleted by the shell, just like with
+    /// `delete_all` except that they can't be undone, so the UI settings of the context apply.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use std::fs::File;
+    /// File::create("delete_me_for_good").unwrap();
+    /// trash::delete_permanently("delete_me_for_good").unwrap();
+    /// assert!(File::open("delete_me_for_good").is_err());
+    /// ```
+    pub fn delete_all_permanently<I, T>(&self, paths: I) -> Result<(), Error>
+    where
+        I: IntoIterator<Item = T>,
+        T: AsRef<Path>,
+    {
+        let full_paths = self.normalize_paths(paths)?.paths;
+        self.check_protected(&full_paths)?;
+        self.check_recursive(&full_paths)?;
+        let full_paths =
+            self.run_pre_hooks(full_paths, |hooks, path| hooks.pre_delete_permanently(path), |path| path.clone())?;
+        self.delete_with_progress(&full_paths, |ctx, paths| ctx.delete_permanently_canonicalized(paths))?;
+        self.run_post_hooks(&full_paths, |hooks, path| hooks.post_delete_permanently(path));
+        Ok(())
+    }
+}
+
+/// Removes a file, a symbolic link, or a directory with everything in it. With `progress`, the
+/// contents of directories are removed one by one, and every removed entry is reported.
+#[cfg(not(target_os = "windows"))]
+pub(crate) fn remove(path: &Path, progress: Option<&PathProgress>) -> std::io::Result<()> {
+    let metadata = path.symlink_metadata()?;
+    match progress {
+        None if metadata.is_dir() => std::fs::remove_dir_all(path)?,
+        None => std::fs::remove_file(path)?,
+        Some(progress) if metadata.is_dir() => {
+            for entry in std::fs::read_dir(path)? {
+                remove(&entry?.path(), Some(progress))?;
+            }
+            std::fs::remove_dir(path)?;
+            progress.entry_done(0);
+        }
+        Some(progress) => {
+            std::fs::remove_file(path)?;
+            progress.entry_done(metadata.len());
+        }
+    }
+    Ok(())
+}
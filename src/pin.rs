@@ -0,0 +1,49 @@
+//! Pinning items in the trash, so that they aren't purged to make room.
+
+use std::borrow::Borrow;
+
+use crate::{platform, Error, TrashContext, TrashItem};
+
+impl TrashContext {
+    /// Pins the items, which exempts them from being purged to make room in the trash, like by
+    /// `QuotaPolicy::PurgeOldest` on Windows. Pinned items can still be purged explicitly, and
+    /// are listed with [`pinned`](TrashItem::pinned) set, so that cleanup tools can honour them
+    /// as well.
+    ///
+    /// The pin is stored along with the item in the trash, and is gone once the item is restored
+    /// or purged. On freedesktop systems it's an `X-Pinned` key in the trash info file, which
+    /// other file managers ignore. On Windows it's an alternate data stream of the `$I` file of
+    /// the item, so pinning fails on volumes that don't support them, like FAT drives.
+    ///
+    /// See: [`os_limited::pin`](crate::os_limited::pin)
+    pub fn pin<I>(&self, items: I) -> Result<(), Error>
+    where
+        I: IntoIterator,
+        <I as IntoIterator>::Item: Borrow<TrashItem>,
+    {
+        self.set_pinned(items, true)
+    }
+
+    /// Removes the pins of the items, see [`pin`](TrashContext::pin).
+    ///
+    /// See: [`os_limited::unpin`](crate::os_limited::unpin)
+    pub fn unpin<I>(&self, items: I) -> Result<(), Error>
+    where
+        I: IntoIterator,
+        <I as IntoIterator>::Item: Borrow<TrashItem>,
+    {
+        self.set_pinned(items, false)
+    }
+
+    fn set_pinned<I>(&self, items: I, pinned: bool) -> Result<(), Error>
+    where
+        I: IntoIterator,
+        <I as IntoIterator>::Item: Borrow<TrashItem>,
+    {
+        for item in items {
+            self.check_cancelled()?;
+            platform::set_pinned(item.borrow(), pinned)?;
+        }
+        Ok(())
+    }
+}
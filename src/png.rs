@@ -0,0 +1,81 @@
+//! Decoding the icons of icon themes, which are almost always PNG files.
+
+/// Images with more pixels than this are rejected, the largest icons are 512 by 512 pixels.
+const MAX_PIXELS: u64 = 4096 * 4096;
+
+/// A decoded image with 8 bit RGBA pixels, row by row.
+pub(crate) struct Image {
+    pub width: u32,
+    pub height: u32,
+    pub rgba: Vec<u8>,
+}
+
+/// Decodes a PNG file, returning `None` if it's malformed. Images of every color type and bit
+/// depth are converted to 8 bit RGBA.
+pub(crate) fn decode(data: &[u8]) -> Option<Image> {
+    let limits = ::png::Limits { bytes: (MAX_PIXELS * 4) as usize };
+    let mut decoder = ::png::Decoder::new_with_limits(data, limits);
+    decoder.set_transformations(::png::Transformations::normalize_to_color8());
+    let mut reader = decoder.read_info().ok()?;
+    let mut buffer = vec![0; reader.output_buffer_size()];
+    let frame = reader.next_frame(&mut buffer).ok()?;
+    buffer.truncate(frame.buffer_size());
+    let rgba = match frame.color_type {
+        ::png::ColorType::Rgba => buffer,
+        ::png::ColorType::Rgb => buffer.chunks_exact(3).flat_map(|rgb| [rgb[0], rgb[1], rgb[2], 255]).collect(),
+        ::png::ColorType::GrayscaleAlpha => {
+            buffer.chunks_exact(2).flat_map(|gray| [gray[0], gray[0], gray[0], gray[1]]).collect()
+        }
+        ::png::ColorType::Grayscale => buffer.iter().flat_map(|&gray| [gray, gray, gray, 255]).collect(),
+        // Palettes are expanded to RGB or RGBA by the transformations.
+        ::png::ColorType::Indexed => return None,
+    };
+    Some(Image { width: frame.width, height: frame.height, rgba })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::decode;
+
+    #[test]
+    fn decode_rgba_and_palette() {
+        // 2x2 RGBA, compressed with fixed codes, the second row is filtered with "Up".
+        let rgba: &[u8] = &[
+            137, 80, 78, 71, 13, 10, 26, 10, 0, 0, 0, 13, 73, 72, 68, 82, 0, 0, 0, 2, 0, 0, 0, 2, 8, 6, 0, 0, 0, 114,
+            182, 13, 36, 0, 0, 0, 20, 73, 68, 65, 84, 120, 218, 99, 248, 207, 192, 240, 31, 8, 27, 152, 64, 52, 8, 0,
+            0, 53, 243, 4, 127, 158, 137, 82, 55, 0, 0, 0, 0, 73, 69, 78, 68, 174, 66, 96, 130,
+        ];
+        let image = decode(rgba).unwrap();
+        assert_eq!((image.width, image.height), (2, 2));
+        assert_eq!(image.rgba, [255, 0, 0, 255, 0, 255, 0, 128, 255, 0, 255, 255, 0, 255, 0, 128]);
+
+        // 3x1 with a 1 bit palette whose first color is transparent, in a stored block.
+        let palette: &[u8] = &[
+            137, 80, 78, 71, 13, 10, 26, 10, 0, 0, 0, 13, 73, 72, 68, 82, 0, 0, 0, 3, 0, 0, 0, 1, 1, 3, 0, 0, 0, 33,
+            46, 134, 247, 0, 0, 0, 6, 80, 76, 84, 69, 10, 20, 30, 40, 50, 60, 213, 27, 180, 233, 0, 0, 0, 1, 116, 82,
+            78, 83, 0, 64, 230, 216, 102, 0, 0, 0, 13, 73, 68, 65, 84, 120, 1, 1, 2, 0, 253, 255, 0, 160, 0, 162, 0,
+            161, 1, 140, 108, 204, 0, 0, 0, 0, 73, 69, 78, 68, 174, 66, 96, 130,
+        ];
+        let image = decode(palette).unwrap();
+        assert_eq!((image.width, image.height), (3, 1));
+        assert_eq!(image.rgba, [40, 50, 60, 255, 10, 20, 30, 0, 40, 50, 60, 255]);
+
+        assert!(decode(&rgba[..rgba.len() - 40]).is_none());
+    }
+
+    #[test]
+    fn decode_gray_and_16_bit() {
+        let encode = |color, depth, pixels: &[u8]| {
+            let mut data = Vec::new();
+            let mut encoder = ::png::Encoder::new(&mut data, 2, 1);
+            encoder.set_color(color);
+            encoder.set_depth(depth);
+            encoder.write_header().unwrap().write_image_data(pixels).unwrap();
+            data
+        };
+        let gray = decode(&encode(::png::ColorType::Grayscale, ::png::BitDepth::Sixteen, &[0x80, 0, 0xFF, 0xFF]));
+        assert_eq!(gray.unwrap().rgba, [0x80, 0x80, 0x80, 255, 255, 255, 255, 255]);
+        let rgb = decode(&encode(::png::ColorType::Rgb, ::png::BitDepth::Eight, &[1, 2, 3, 4, 5, 6]));
+        assert_eq!(rgb.unwrap().rgba, [1, 2, 3, 255, 4, 5, 6, 255]);
+    }
+}
@@ -0,0 +1,279 @@
+//! Reporting the progress of delete operations, optionally with the total amount of work counted
+//! up front.
+
+use std::{
+    fmt,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+
+use log::{debug, warn};
+
+use crate::{ByteSize, Error, TrashContext};
+
+/// The entries and bytes within some of the items of an operation.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ProgressCount {
+    /// The number of files, folders and links, including the items themselves.
+    pub entries: u64,
+    /// The total size of the files.
+    pub bytes: ByteSize,
+}
+
+/// The state of a delete operation, as reported to [`TrashProgress::progress`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DeleteProgress {
+    /// How many of the paths passed to the operation were processed.
+    pub paths_done: usize,
+    /// How many paths were passed to the operation, after the hooks skipped some of them.
+    pub paths_total: usize,
+    /// The contents of the processed paths, or `None` if the pre-scan was skipped.
+    pub done: Option<ProgressCount>,
+    /// The contents of all paths, or `None` if the pre-scan was skipped.
+    pub total: Option<ProgressCount>,
+}
+
+/// What the pre-scan should do next, as decided by [`TrashProgress::scanning`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ScanDecision {
+    /// Keep counting.
+    #[default]
+    Continue,
+    /// Stop counting and go on with the operation, reporting its progress without totals.
+    Skip,
+}
+
+/// Callbacks that report the progress of [`delete_all`](TrashContext::delete_all) and
+/// [`delete_all_permanently`](TrashContext::delete_all_permanently).
+///
+/// Before anything is deleted, the contents of the paths are counted, so that the progress can be
+/// shown as a percentage. Counting large folders takes a while, which is why it can be turned off
+/// with [`set_prescan`](TrashContext::set_prescan) and stopped through
+/// [`scanning`](TrashProgress::scanning).
+///
+/// # Example
+///
+/// ```
+/// use trash::{DeleteProgress, TrashContext, TrashProgress};
+///
+/// struct PrintPercentage;
+/// impl TrashProgress for PrintPercentage {
+///     fn progress(&self, progress: &DeleteProgress) {
+///         if let (Some(done), Some(total)) = (progress.done, progress.total) {
+///             println!("{}%", done.entries * 100 / total.entries.max(1));
+///         }
+///     }
+/// }
+///
+/// let ctx = TrashContext::new().with_progress(PrintPercentage);
+/// ```
+pub trait TrashProgress: Send + Sync {
+    /// Invoked regularly during the pre-scan with what was counted so far.
+    fn scanning(&self, _counted: &ProgressCount) -> ScanDecision {
+        ScanDecision::Continue
+    }
+
+    /// Invoked once before the first path is processed and after each path. When the totals are
+    /// known, it's also invoked while a path is being processed: after each entry that's deleted
+    /// permanently, and, on Windows, whenever the shell reports how far it got.
+    fn progress(&self, progress: &DeleteProgress);
+}
+
+/// The progress callbacks stored in a `TrashContext`.
+///
+/// This only exists so that `TrashContext` can keep deriving `Debug`.
+#[derive(Clone)]
+pub(crate) struct Progress(pub(crate) Arc<dyn TrashProgress>);
+impl fmt::Debug for Progress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Progress")
+    }
+}
+
+/// Reports how far the processing of a single path got, for the platforms that process it in
+/// steps. The path's share of the totals is known from the pre-scan, and the reports stay within
+/// it, so they never get ahead of the report that follows the path.
+#[derive(Clone)]
+pub(crate) struct PathProgress(Arc<PathProgressInner>);
+
+struct PathProgressInner {
+    progress: Arc<dyn TrashProgress>,
+    /// The counts of the path, from the pre-scan.
+    count: ProgressCount,
+    /// The report before the path was started, and what was processed of the path since.
+    state: Mutex<(DeleteProgress, ProgressCount)>,
+}
+
+impl fmt::Debug for PathProgress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("PathProgress")
+    }
+}
+
+impl PathProgress {
+    /// Reports that an entry with `bytes` bytes of the path was processed.
+    #[cfg_attr(target_os = "windows", allow(dead_code))]
+    pub(crate) fn entry_done(&self, bytes: u64) {
+        self.update(|done| ProgressCount { entries: done.entries + 1, bytes: done.bytes + ByteSize::new(bytes) });
+    }
+
+    /// Reports that `done` out of `total` units of work of the path were processed, for the
+    /// operations that only tell how far they got.
+    #[cfg_attr(not(target_os = "windows"), allow(dead_code))]
+    pub(crate) fn fraction_done(&self, done: u64, total: u64) {
+        let count = self.0.count;
+        let share = |value: u64| (u128::from(value) * u128::from(done) / u128::from(total.max(1))) as u64;
+        self.update(|_| ProgressCount {
+            entries: share(count.entries),
+            bytes: ByteSize::new(share(count.bytes.as_bytes())),
+        });
+    }
+
+    fn update(&self, next: impl FnOnce(ProgressCount) -> ProgressCount) {
+        let inner = &self.0;
+        let Ok(mut state) = inner.state.lock() else {
+            return;
+        };
+        let (report, done) = &mut *state;
+        let next = next(*done);
+        *done =
+            ProgressCount { entries: next.entries.min(inner.count.entries), bytes: next.bytes.min(inner.count.bytes) };
+        let mut report = *report;
+        if let Some(report_done) = &mut report.done {
+            *report_done =
+                ProgressCount { entries: report_done.entries + done.entries, bytes: report_done.bytes + done.bytes };
+        }
+        inner.progress.progress(&report);
+    }
+}
+
+/// The pre-scan asks whether to continue after this many entries.
+const SCAN_INTERVAL: u64 = 256;
+
+impl TrashContext {
+    /// Registers callbacks that report the progress of the delete operations performed through
+    /// this context. See [`TrashProgress`] for details.
+    ///
+    /// Paths are handed to the platform one at a time when progress is reported, which makes
+    /// deleting many small items slower.
+    pub fn with_progress(mut self, progress: impl TrashProgress + 'static) -> Self {
+        self.progress = Some(Progress(Arc::new(progress)));
+        self
+    }
+
+    /// Sets whether the contents of the paths are counted before they are deleted, so that the
+    /// [`DeleteProgress`] includes totals. Defaults to `true`, and has no effect unless callbacks
+    /// were registered with [`with_progress`](TrashContext::with_progress).
+    pub fn set_prescan(&mut self, prescan: bool) {
+        self.prescan = prescan;
+    }
+
+    /// See [`set_prescan`](TrashContext::set_prescan).
+    pub fn prescan(&self) -> bool {
+        self.prescan
+    }
+
+    /// Deletes the paths with `delete`, one at a time if progress is reported. Failures are
+    /// handled according to the [`continue_on_error`](TrashContext::continue_on_error) setting.
+    ///
+    /// When the totals are known, `delete` gets a context with a [`PathProgress`] for each path,
+    /// see [`path_progress`](TrashContext::path_progress).
+    pub(crate) fn delete_with_progress(
+        &self,
+        full_paths: &[PathBuf],
+        delete: impl Fn(&TrashContext, &[PathBuf]) -> Result<(), Error>,
+    ) -> Result<(), Error> {
+        self.check_cancelled()?;
+        let Some(Progress(progress)) = &self.progress else {
+            return delete(self, full_paths);
+        };
+        let counts = if self.prescan { prescan(full_paths, progress.as_ref()) } else { None };
+        let total = counts.as_ref().map(|counts| sum(counts));
+        let mut report = DeleteProgress {
+            paths_done: 0,
+            paths_total: full_paths.len(),
+            done: total.map(|_| ProgressCount::default()),
+            total,
+        };
+        progress.progress(&report);
+        let mut first_error = None;
+        for (index, path) in full_paths.iter().enumerate() {
+            self.check_cancelled()?;
+            let mut ctx = self.clone();
+            if let Some(counts) = &counts {
+                let state = Mutex::new((report, ProgressCount::default()));
+                let inner = PathProgressInner { progress: progress.clone(), count: counts[index], state };
+                ctx.path_progress = Some(PathProgress(Arc::new(inner)));
+            }
+            if let Err(e) = delete(&ctx, std::slice::from_ref(path)) {
+                if !self.continue_on_error || matches!(e, Error::Cancelled) {
+                    return Err(e);
+                }
+                warn!("Continuing with the remaining items after an error: {:?}", e);
+                first_error.get_or_insert(e);
+            }
+            report.paths_done = index + 1;
+            if let (Some(done), Some(counts)) = (&mut report.done, &counts) {
+                done.entries += counts[index].entries;
+                done.bytes = done.bytes + counts[index].bytes;
+            }
+            progress.progress(&report);
+        }
+        first_error.map_or(Ok(()), Err)
+    }
+
+    /// The progress of the path that's being deleted, if it's reported.
+    pub(crate) fn path_progress(&self) -> Option<&PathProgress> {
+        self.path_progress.as_ref()
+    }
+}
+
+/// Counts the contents of every path, or returns `None` if the callbacks skipped the scan.
+fn prescan(full_paths: &[PathBuf], progress: &dyn TrashProgress) -> Option<Vec<ProgressCount>> {
+    let mut counted = ProgressCount::default();
+    let mut counts = Vec::with_capacity(full_paths.len());
+    for path in full_paths {
+        let before = counted;
+        count(path, &mut counted, progress)?;
+        counts.push(ProgressCount {
+            entries: counted.entries - before.entries,
+            bytes: counted.bytes.saturating_sub(before.bytes),
+        });
+    }
+    Some(counts)
+}
+
+/// Adds the entries within `path` to `counted`, not following symbolic links.
+fn count(path: &Path, counted: &mut ProgressCount, progress: &dyn TrashProgress) -> Option<()> {
+    let mut pending = vec![path.to_owned()];
+    while let Some(path) = pending.pop() {
+        let Ok(metadata) = path.symlink_metadata() else {
+            debug!("Skipping {:?} while counting it", path);
+            continue;
+        };
+        counted.entries += 1;
+        if counted.entries.is_multiple_of(SCAN_INTERVAL) && progress.scanning(counted) == ScanDecision::Skip {
+            debug!("The pre-scan was skipped after {} entries", counted.entries);
+            return None;
+        }
+        if !metadata.is_dir() {
+            counted.bytes = counted.bytes + ByteSize::new(metadata.len());
+            continue;
+        }
+        match std::fs::read_dir(&path) {
+            Ok(entries) => pending.extend(entries.flatten().map(|entry| entry.path())),
+            Err(e) => debug!("Skipping the contents of {:?} while counting them: {}", path, e),
+        }
+    }
+    Some(())
+}
+
+fn sum(counts: &[ProgressCount]) -> ProgressCount {
+    counts.iter().fold(ProgressCount::default(), |total, count| ProgressCount {
+        entries: total.entries + count.entries,
+        bytes: total.bytes + count.bytes,
+    })
+}
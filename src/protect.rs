@@ -0,0 +1,119 @@
+//! Refusing to trash folders whose removal is almost certainly a mistake.
+
+use std::path::{Path, PathBuf};
+
+use crate::{platform, Error, TrashContext};
+
+/// Why a path is protected from being moved to the trash, see [`Error::Protected`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ProtectedKind {
+    /// The path is the home folder of the user, or one of the folders that contain it.
+    Home,
+    /// The path is a trash folder, a folder inside of one, or a folder that contains the home
+    /// trash. The trash can't be moved into itself.
+    Trash,
+    /// The path is the root of a mounted file system. This is only detected on Unix.
+    MountPoint,
+}
+
+impl TrashContext {
+    /// Allows [`delete`](TrashContext::delete) to move protected folders to the trash, which it
+    /// refuses with [`Error::Protected`] otherwise. See [`ProtectedKind`] for what's protected.
+    ///
+    /// The root of the file system and drive roots are never moved to the trash, and fail with
+    /// [`Error::TargetedRoot`] regardless. Defaults to `false`.
+    pub fn set_allow_protected(&mut self, allow_protected: bool) {
+        self.allow_protected = allow_protected;
+    }
+    pub fn allow_protected(&self) -> bool {
+        self.allow_protected
+    }
+
+    /// Whether [`delete`](TrashContext::delete) and
+    /// [`delete_permanently`](TrashContext::delete_permanently) remove folders along with their
+    /// contents, like `rm -r`. When disabled, folders that aren't empty are refused with
+    /// [`Error::DirectoryNotEmpty`] before anything is removed, while files, symbolic links and
+    /// empty folders are removed as usual. Folders whose entries can't be read are treated as not
+    /// empty.
+    ///
+    /// Cautious tools can disable this and only enable it when the user asked for a recursive
+    /// delete. Defaults to `true`.
+    pub fn set_recursive(&mut self, recursive: bool) {
+        self.keep_non_empty_folders = !recursive;
+    }
+    pub fn recursive(&self) -> bool {
+        !self.keep_non_empty_folders
+    }
+
+    /// Fails if any of the canonicalized paths is a folder that isn't empty while the context
+    /// isn't [`recursive`](TrashContext::recursive).
+    pub(crate) fn check_recursive(&self, full_paths: &[PathBuf]) -> Result<(), Error> {
+        if !self.keep_non_empty_folders {
+            return Ok(());
+        }
+        let is_filled_dir = |path: &Path| {
+            path.symlink_metadata().is_ok_and(|metadata| metadata.is_dir())
+                && std::fs::read_dir(path).map_or(true, |mut entries| entries.next().is_some())
+        };
+        match full_paths.iter().find(|path| is_filled_dir(path)) {
+            Some(path) => Err(Error::DirectoryNotEmpty { path: path.clone() }),
+            None => Ok(()),
+        }
+    }
+
+    /// Fails if any of the canonicalized paths is a root or protected, so that either all or none
+    /// of them are moved to the trash.
+    pub(crate) fn check_protected(&self, full_paths: &[PathBuf]) -> Result<(), Error> {
+        if let Some(root) = full_paths.iter().find(|path| path.parent().is_none()) {
+            log::warn!("Refusing to trash the root {root:?}");
+            return Err(Error::TargetedRoot);
+        }
+        if self.allow_protected {
+            return Ok(());
+        }
+        let home = home_dir();
+        for path in full_paths {
+            if let Some(kind) = protected_kind(path, home.as_deref()) {
+                return Err(Error::Protected { path: path.clone(), kind });
+            }
+        }
+        Ok(())
+    }
+}
+
+fn protected_kind(path: &Path, home: Option<&Path>) -> Option<ProtectedKind> {
+    if home.is_some_and(|home| home.starts_with(path)) {
+        return Some(ProtectedKind::Home);
+    }
+    if platform::is_trash_folder(path) {
+        return Some(ProtectedKind::Trash);
+    }
+    if is_mount_point(path) {
+        return Some(ProtectedKind::MountPoint);
+    }
+    None
+}
+
+/// The canonicalized home folder of the current user.
+fn home_dir() -> Option<PathBuf> {
+    let var = if cfg!(windows) { "USERPROFILE" } else { "HOME" };
+    let home = PathBuf::from(std::env::var_os(var).filter(|home| !home.is_empty())?);
+    Some(home.canonicalize().unwrap_or(home))
+}
+
+/// A folder is the root of a mounted file system if it's on another device than its parent.
+#[cfg(unix)]
+fn is_mount_point(path: &Path) -> bool {
+    use std::os::unix::fs::MetadataExt;
+
+    let (Ok(metadata), Some(parent)) = (path.symlink_metadata(), path.parent()) else {
+        return false;
+    };
+    metadata.is_dir() && parent.metadata().is_ok_and(|parent| parent.dev() != metadata.dev())
+}
+
+#[cfg(not(unix))]
+fn is_mount_point(_path: &Path) -> bool {
+    false
+}
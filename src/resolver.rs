@@ -0,0 +1,99 @@
+//! Resolving the paths that are passed to a [`TrashContext`](crate::TrashContext) to the paths the
+//! platform operates on.
+
+use std::{
+    fmt,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use crate::{canonicalize_path, Error, TrashContext};
+
+/// Maps the paths that are passed to a [`TrashContext`] to the absolute paths of the items that
+/// are deleted, as set with [`TrashContext::with_path_resolver`].
+///
+/// This is for embedders whose paths aren't those of the operating system, like the paths of a
+/// virtual file system, a chroot or an overlay view. The resolver replaces the canonicalization of
+/// the context, so it has to return the path that the platform should operate on. Resolvers that
+/// only translate their paths can hand the translated path to [`PlatformResolver`] to have it
+/// canonicalized like by default.
+///
+/// The resolver is used for the paths of items that are deleted, including
+/// [`resolve_path`](TrashContext::resolve_path), [`normalize_paths`](TrashContext::normalize_paths)
+/// and [`destination_for`](TrashContext::destination_for). Original paths of items in the trash,
+/// like the one passed to `find`, are compared as they are, as those items don't exist anymore.
+///
+/// # Example
+///
+/// ```
+/// use std::path::{Path, PathBuf};
+/// use trash::{Error, PathResolver, PlatformResolver, TrashContext};
+///
+/// /// Maps the paths of a virtual file system that's stored in a folder.
+/// struct Chroot(PathBuf);
+/// impl PathResolver for Chroot {
+///     fn resolve(&self, path: &Path, base_dir: Option<&Path>) -> Result<PathBuf, Error> {
+///         let relative = path.strip_prefix("/").unwrap_or(path);
+///         PlatformResolver.resolve(&self.0.join(relative), base_dir)
+///     }
+/// }
+///
+/// let root = std::env::temp_dir().canonicalize().unwrap();
+/// let ctx = TrashContext::new().with_path_resolver(Chroot(root.clone()));
+/// assert_eq!(ctx.resolve_path("/file.txt").unwrap(), root.join("file.txt"));
+/// ```
+pub trait PathResolver: Send + Sync {
+    /// Returns the absolute path of the item that `path` refers to, where `base_dir` is the
+    /// [`base_dir`](TrashContext::base_dir) of the context. Relative results are refused with
+    /// [`Error::CanonicalizePath`].
+    fn resolve(&self, path: &Path, base_dir: Option<&Path>) -> Result<PathBuf, Error>;
+}
+
+/// The resolution that a [`TrashContext`] uses by default. Relative paths are resolved against the
+/// `base_dir`, or the current working directory, and the symbolic links of the parent folders are
+/// resolved. The last component is kept, so that symbolic links are deleted rather than their
+/// targets.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct PlatformResolver;
+
+impl PathResolver for PlatformResolver {
+    fn resolve(&self, path: &Path, base_dir: Option<&Path>) -> Result<PathBuf, Error> {
+        canonicalize_path(path, base_dir)
+    }
+}
+
+/// The resolver stored in a `TrashContext`.
+///
+/// This only exists so that `TrashContext` can keep deriving `Debug`.
+#[derive(Clone)]
+pub(crate) struct Resolver(pub(crate) Arc<dyn PathResolver>);
+impl fmt::Debug for Resolver {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Resolver")
+    }
+}
+
+impl TrashContext {
+    /// Resolves the paths of items that are about to be deleted with the [`PathResolver`] of the
+    /// context, or the [`PlatformResolver`] if there's none.
+    pub(crate) fn resolve_paths<I, T>(&self, paths: I) -> Result<Vec<PathBuf>, Error>
+    where
+        I: IntoIterator<Item = T>,
+        T: AsRef<Path>,
+    {
+        let resolver = match &self.path_resolver {
+            Some(resolver) => resolver.0.as_ref(),
+            None => &PlatformResolver,
+        };
+        paths
+            .into_iter()
+            .map(|path| {
+                let resolved = resolver.resolve(path.as_ref(), self.base_dir())?;
+                match resolved.is_absolute() {
+                    true => Ok(resolved),
+                    false => Err(Error::CanonicalizePath { original: path.as_ref().to_owned() }),
+                }
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,192 @@
+//! Restoring items to several volumes at the same time.
+
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+use crate::{parallel, BatchErrors, BatchFailure, Error, TrashContext, TrashItem};
+
+/// Limits how many items are restored at the same time.
+///
+/// Items are grouped by the volume they are restored to. Restoring to different volumes in
+/// parallel speeds up large restores, while restoring to the same volume in parallel mostly makes
+/// a spinning disk seek back and forth.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RestoreConcurrency {
+    /// How many volumes items are restored to at the same time. Defaults to 4.
+    pub volumes: usize,
+    /// How many items are restored to the same volume at the same time. Defaults to 1.
+    pub per_volume: usize,
+}
+
+impl RestoreConcurrency {
+    /// Restores one item at a time.
+    pub const SERIAL: RestoreConcurrency = RestoreConcurrency { volumes: 1, per_volume: 1 };
+
+    pub const fn new() -> Self {
+        RestoreConcurrency { volumes: 4, per_volume: 1 }
+    }
+}
+
+impl Default for RestoreConcurrency {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TrashContext {
+    /// Sets how many items [`restore_all`](TrashContext::restore_all) restores at the same time.
+    ///
+    /// Defaults to [`RestoreConcurrency::new`]. Zero limits are treated as one. On Windows, the
+    /// items are restored with a single shell operation instead, unless that's turned off with
+    /// `TrashContextExtWindows::set_single_restore_operation`.
+    pub fn set_restore_concurrency(&mut self, concurrency: RestoreConcurrency) {
+        self.restore_concurrency = concurrency;
+    }
+
+    /// See [`set_restore_concurrency`](TrashContext::set_restore_concurrency).
+    pub fn restore_concurrency(&self) -> RestoreConcurrency {
+        self.restore_concurrency
+    }
+
+    /// Restores the items grouped by their destination volume, according to the
+    /// [`restore_concurrency`](TrashContext::restore_concurrency), and returns the paths they
+    /// were restored to.
+    pub(crate) fn restore_by_volume(&self, items: Vec<TrashItem>) -> Result<Vec<PathBuf>, Error> {
+        #[cfg(target_os = "windows")]
+        if crate::windows::TrashContextExtWindows::single_restore_operation(self) {
+            return self.restore_items(items);
+        }
+        let RestoreConcurrency { volumes, per_volume } = self.restore_concurrency;
+        let (volumes, per_volume) = (volumes.max(1), per_volume.max(1));
+        let mut batches: Vec<Vec<TrashItem>> = Vec::new();
+        for group in group_by_volume(items) {
+            // Every worker of a volume takes a share of the items of that volume.
+            let workers = per_volume.min(group.len());
+            let mut shares = vec![Vec::new(); workers];
+            for (i, item) in group.into_iter().enumerate() {
+                shares[i % workers].push(item);
+            }
+            batches.extend(shares);
+        }
+        if batches.len() <= 1 {
+            return self.restore_items(batches.pop().unwrap_or_default());
+        }
+
+        let mut paths: Vec<Vec<PathBuf>> =
+            batches.iter().map(|batch| batch.iter().filter_map(TrashItem::original_path).collect()).collect();
+        // The pool hands out shared references, so every batch is taken out of its own lock.
+        let batches: Vec<Mutex<Vec<TrashItem>>> = batches.into_iter().map(Mutex::new).collect();
+        let targets = Mutex::new(Vec::new());
+        let errors = parallel::run(&batches, volumes * per_volume, false, |batch| {
+            let restored = self.restore_items(std::mem::take(&mut *batch.lock().unwrap_or_else(|e| e.into_inner())))?;
+            targets.lock().unwrap_or_else(|e| e.into_inner()).extend(restored);
+            Ok(())
+        });
+        let targets = targets.into_inner().unwrap_or_else(|e| e.into_inner());
+        if errors.is_empty() {
+            return Ok(targets);
+        }
+        if errors.iter().all(|(_, e)| matches!(e, Error::RestoreCollision { .. })) {
+            return combine_collisions(errors.into_iter().map(|(_, e)| e).collect()).map(|()| targets);
+        }
+        let failures = errors
+            .into_iter()
+            .map(|(index, error)| {
+                let paths = std::mem::take(&mut paths[index]);
+                let volume = paths.first().and_then(|path| path.parent()).and_then(volume_root);
+                BatchFailure { volume, paths, error }
+            })
+            .collect();
+        BatchErrors::combine(failures).map_or(Ok(targets), Err)
+    }
+}
+
+/// Groups the items by the volume of their original folder, keeping their order within a group.
+/// Items whose original folder is unknown are grouped together.
+fn group_by_volume(items: Vec<TrashItem>) -> Vec<Vec<TrashItem>> {
+    let mut groups: Vec<Vec<TrashItem>> = Vec::new();
+    let mut volumes: HashMap<Option<VolumeId>, usize> = HashMap::new();
+    let mut folders: HashMap<Option<PathBuf>, usize> = HashMap::new();
+    for item in items {
+        let index = *folders.entry(item.original_parent.clone()).or_insert_with(|| {
+            *volumes.entry(item.original_parent.as_deref().and_then(volume_of)).or_insert_with(|| {
+                groups.push(Vec::new());
+                groups.len() - 1
+            })
+        });
+        groups[index].push(item);
+    }
+    groups
+}
+
+/// Reports the remaining items of all the collisions together, with the path of the first one.
+fn combine_collisions(errors: Vec<Error>) -> Result<(), Error> {
+    let mut combined: Option<Error> = None;
+    for error in errors {
+        combined = Some(match (combined, error) {
+            (
+                Some(Error::RestoreCollision { path, mut remaining_items }),
+                Error::RestoreCollision { remaining_items: more, .. },
+            ) => {
+                remaining_items.extend(more);
+                Error::RestoreCollision { path, remaining_items }
+            }
+            (Some(first), _) => first,
+            (None, error) => error,
+        });
+    }
+    combined.map_or(Ok(()), Err)
+}
+
+#[cfg(unix)]
+pub(crate) type VolumeId = u64;
+
+/// The device of the closest ancestor that exists, as missing folders are created on that one.
+#[cfg(unix)]
+pub(crate) fn volume_of(folder: &Path) -> Option<VolumeId> {
+    use std::os::unix::fs::MetadataExt;
+
+    folder.ancestors().find_map(|ancestor| ancestor.metadata().ok()).map(|metadata| metadata.dev())
+}
+
+/// The topmost ancestor of the folder that is on the same device, which is where the volume is
+/// mounted.
+#[cfg(unix)]
+pub(crate) fn volume_root(folder: &Path) -> Option<PathBuf> {
+    let volume = volume_of(folder)?;
+    folder
+        .ancestors()
+        .filter(|ancestor| ancestor.exists())
+        .take_while(|ancestor| volume_of(ancestor) == Some(volume))
+        .last()
+        .map(Path::to_owned)
+}
+
+#[cfg(windows)]
+pub(crate) type VolumeId = std::ffi::OsString;
+
+/// The drive or share of the folder, compared case-insensitively.
+#[cfg(windows)]
+pub(crate) fn volume_of(folder: &Path) -> Option<VolumeId> {
+    match crate::matching::strip_verbatim(folder).components().next()? {
+        std::path::Component::Prefix(prefix) => Some(prefix.as_os_str().to_ascii_uppercase()),
+        _ => None,
+    }
+}
+
+/// The root of the drive or share of the folder, like `C:\`.
+#[cfg(windows)]
+pub(crate) fn volume_root(folder: &Path) -> Option<PathBuf> {
+    match crate::matching::strip_verbatim(folder).components().next()? {
+        std::path::Component::Prefix(prefix) => {
+            let mut root = prefix.as_os_str().to_owned();
+            root.push("\\");
+            Some(root.into())
+        }
+        _ => None,
+    }
+}
@@ -0,0 +1,326 @@
+//! Checking ahead of a restore which items can be put back to their original location.
+
+use std::{
+    collections::{HashMap, HashSet},
+    fmt, io,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use crate::{
+    io_error,
+    matching::{self, CaseSensitivity},
+    platform, Error, TrashContext, TrashItem,
+};
+
+/// The reason why an item can't be restored to its original location.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum UnrestorableReason {
+    /// The volume the item was deleted from isn't available, for example because the drive or
+    /// removable medium isn't connected.
+    OriginalVolumeMissing,
+    /// Something already exists at the original path of the item.
+    TargetExists,
+    /// The current process isn't allowed to create the item in its original folder.
+    PermissionDenied,
+    /// One of the ancestors of the original path is a file rather than a folder.
+    ParentIsFile,
+    /// The original path is longer than the platform supports.
+    PathTooLong,
+    /// Another of the planned items has the same original path. Only one of them can be restored.
+    DuplicateOriginalPath,
+    /// The original location of the item couldn't be read when the trash was listed.
+    OriginalLocationUnknown,
+    /// The [restore transform](TrashContext::with_restore_transform) rewrote the original path to
+    /// one without a parent folder, or with a name that isn't valid UTF-8.
+    InvalidTarget,
+}
+
+impl fmt::Display for UnrestorableReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            UnrestorableReason::OriginalVolumeMissing => "the volume the item was deleted from isn't available",
+            UnrestorableReason::TargetExists => "an item with the same name exists in the original folder",
+            UnrestorableReason::PermissionDenied => "the original folder can't be written to",
+            UnrestorableReason::ParentIsFile => "a file is in the way of the original folder",
+            UnrestorableReason::PathTooLong => "the original path is too long",
+            UnrestorableReason::DuplicateOriginalPath => "another item has the same original path",
+            UnrestorableReason::OriginalLocationUnknown => "the original location of the item is unknown",
+            UnrestorableReason::InvalidTarget => "the rewritten original path has no parent folder or a non-UTF-8 name",
+        })
+    }
+}
+
+/// A single item of a [`RestorePlan`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PlannedRestore {
+    /// The item in the trash.
+    pub item: TrashItem,
+    /// Why the item can't be restored, or `None` if it can.
+    pub blocked_by: Option<UnrestorableReason>,
+}
+
+/// The outcome of checking which items can be restored, in the order the items were passed to
+/// [`plan_restore`](crate::os_limited::plan_restore).
+///
+/// The plan reflects the state of the file system at the time it was made. Restoring may still
+/// fail if the file system changes in the meantime.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RestorePlan {
+    /// The planned items.
+    pub items: Vec<PlannedRestore>,
+}
+
+impl RestorePlan {
+    /// Returns `true` if all items can be restored.
+    pub fn is_restorable(&self) -> bool {
+        self.items.iter().all(|planned| planned.blocked_by.is_none())
+    }
+
+    /// The items that can be restored.
+    pub fn restorable(&self) -> impl Iterator<Item = &TrashItem> {
+        self.items.iter().filter(|planned| planned.blocked_by.is_none()).map(|planned| &planned.item)
+    }
+
+    /// The items that can't be restored, along with the reason.
+    pub fn blocked(&self) -> impl Iterator<Item = (&TrashItem, UnrestorableReason)> {
+        self.items.iter().filter_map(|planned| Some((&planned.item, planned.blocked_by?)))
+    }
+
+    /// Consumes the plan and returns the items that can be restored, to pass them on to
+    /// [`restore_all`](crate::os_limited::restore_all).
+    pub fn into_restorable(self) -> Vec<TrashItem> {
+        self.items.into_iter().filter(|planned| planned.blocked_by.is_none()).map(|planned| planned.item).collect()
+    }
+}
+
+impl TrashContext {
+    /// Checks which of the items can be restored to their original location.
+    ///
+    /// Existing items and items with the same original path are detected according to
+    /// [`case_sensitivity`](TrashContext::case_sensitivity), so that on case-insensitive file
+    /// systems `Report.txt` is found to collide with `report.txt`.
+    ///
+    /// See: [`os_limited::plan_restore`](crate::os_limited::plan_restore)
+    pub fn plan_restore<I>(&self, items: I) -> RestorePlan
+    where
+        I: IntoIterator<Item = TrashItem>,
+    {
+        let mut planner = Planner { case_sensitivity: self.case_sensitivity, ..Default::default() };
+        let items = items
+            .into_iter()
+            .map(|item| {
+                let blocked_by = match self.restore_target(&item) {
+                    Some(original_path)
+                        if self.restore_transform.is_some() && split_target(&original_path).is_err() =>
+                    {
+                        Some(UnrestorableReason::InvalidTarget)
+                    }
+                    Some(original_path) => planner.check(&original_path),
+                    None => Some(UnrestorableReason::OriginalLocationUnknown),
+                };
+                PlannedRestore { item, blocked_by }
+            })
+            .collect();
+        RestorePlan { items }
+    }
+
+    /// Rewrites the original path of every item that's restored through this context with
+    /// `transform` before it's restored, like to move the items of a migrated disk to its new
+    /// drive letter. The transform is also applied by [`plan_restore`](TrashContext::plan_restore),
+    /// so that collisions are checked against the rewritten paths.
+    ///
+    /// The [`TrashHooks`](crate::TrashHooks), and the items of the errors of a restore, see the
+    /// rewritten items. The transform isn't applied by
+    /// [`restore_all_to`](TrashContext::restore_all_to), which chooses the target folder itself.
+    /// A restore fails without restoring any item if a rewritten path has no parent folder, or a
+    /// name that isn't valid UTF-8, and [`plan_restore`](TrashContext::plan_restore) reports such
+    /// items as [`InvalidTarget`](UnrestorableReason::InvalidTarget).
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use std::path::{Path, PathBuf};
+    /// use trash::TrashContext;
+    ///
+    /// let ctx = TrashContext::new().with_restore_transform(|path: &Path| match path.strip_prefix("/old-home") {
+    ///     Ok(relative) => Path::new("/home").join(relative),
+    ///     Err(_) => path.to_owned(),
+    /// });
+    /// ```
+    pub fn with_restore_transform<F>(mut self, transform: F) -> Self
+    where
+        F: Fn(&Path) -> PathBuf + Send + Sync + 'static,
+    {
+        self.restore_transform = Some(RestoreTransform(Arc::new(transform)));
+        self
+    }
+
+    /// The path that `item` is restored to, which is its original path after the
+    /// [restore transform](TrashContext::with_restore_transform).
+    fn restore_target(&self, item: &TrashItem) -> Option<PathBuf> {
+        let original_path = item.original_path()?;
+        match &self.restore_transform {
+            Some(transform) => Some((transform.0)(&original_path)),
+            None => Some(original_path),
+        }
+    }
+
+    /// Applies the [restore transform](TrashContext::with_restore_transform) to the original
+    /// paths of the items. Fails if any of the rewritten paths can't be split into an original
+    /// parent and a name.
+    pub(crate) fn transform_restore_targets(&self, items: Vec<TrashItem>) -> Result<Vec<TrashItem>, Error> {
+        if self.restore_transform.is_none() {
+            return Ok(items);
+        }
+        items
+            .into_iter()
+            .map(|item| {
+                let Some(target) = self.restore_target(&item) else {
+                    return Ok(item);
+                };
+                let (parent, name) = split_target(&target)?;
+                Ok(TrashItem { original_parent: Some(parent.to_owned()), name, ..item })
+            })
+            .collect()
+    }
+}
+
+/// Splits a rewritten original path into the `original_parent` and `name` of a [`TrashItem`].
+fn split_target(target: &Path) -> Result<(&Path, String), Error> {
+    let (Some(parent), Some(name)) = (target.parent(), target.file_name()) else {
+        return Err(io_error(
+            target,
+            io::Error::new(io::ErrorKind::InvalidInput, "the rewritten path has no parent folder or name"),
+        ));
+    };
+    let name = name.to_owned().into_string().map_err(|original| Error::ConvertOsString { original })?;
+    Ok((parent, name))
+}
+
+/// The restore transform stored in a `TrashContext`.
+///
+/// This only exists so that `TrashContext` can keep deriving `Debug`.
+#[derive(Clone)]
+pub(crate) struct RestoreTransform(pub(crate) Arc<dyn Fn(&Path) -> PathBuf + Send + Sync>);
+impl fmt::Debug for RestoreTransform {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("RestoreTransform")
+    }
+}
+
+#[derive(Default)]
+struct Planner {
+    case_sensitivity: CaseSensitivity,
+    /// The comparison keys of the names within the folders that were searched so far.
+    folders: HashMap<PathBuf, HashSet<PathBuf>>,
+    /// The comparison keys of the original paths of the items planned to be restored.
+    planned: HashSet<PathBuf>,
+}
+
+impl Planner {
+    /// Finds the first reason why the item can't be restored to `original_path`.
+    fn check(&mut self, original_path: &Path) -> Option<UnrestorableReason> {
+        if is_too_long(&matching::strip_verbatim(original_path)) {
+            return Some(UnrestorableReason::PathTooLong);
+        }
+        if is_volume_missing(original_path) {
+            return Some(UnrestorableReason::OriginalVolumeMissing);
+        }
+        let case_sensitive = self.case_sensitivity.is_sensitive_for(original_path);
+        if self.target_exists(original_path, case_sensitive) {
+            return Some(UnrestorableReason::TargetExists);
+        }
+        // Missing folders are created by the restore, in the closest ancestor that exists.
+        let Some(existing) = original_path.ancestors().skip(1).find(|p| p.symlink_metadata().is_ok()) else {
+            return Some(UnrestorableReason::OriginalVolumeMissing);
+        };
+        if !existing.is_dir() {
+            return Some(UnrestorableReason::ParentIsFile);
+        }
+        if !platform::is_writable(existing) {
+            return Some(UnrestorableReason::PermissionDenied);
+        }
+        if !self.planned.insert(matching::comparison_key(original_path, case_sensitive)) {
+            return Some(UnrestorableReason::DuplicateOriginalPath);
+        }
+        None
+    }
+
+    /// Looks up `path` by its exact name first, which the file system resolves according to its
+    /// own rules. When names are compared case-insensitively, the folder is searched for a name
+    /// that only differs in case as well, as some folders are case-sensitive even on file systems
+    /// that usually aren't, like folders shared with WSL on Windows.
+    fn target_exists(&mut self, path: &Path, case_sensitive: bool) -> bool {
+        if path.symlink_metadata().is_ok() {
+            return true;
+        }
+        let (false, Some(folder), Some(name)) = (case_sensitive, path.parent(), path.file_name()) else {
+            return false;
+        };
+        let names = self.folders.entry(folder.to_owned()).or_insert_with(|| match std::fs::read_dir(folder) {
+            Ok(entries) => {
+                entries.flatten().map(|entry| matching::comparison_key(Path::new(&entry.file_name()), false)).collect()
+            }
+            Err(_) => HashSet::new(),
+        });
+        names.contains(&matching::comparison_key(Path::new(name), false))
+    }
+}
+
+#[cfg(unix)]
+fn is_too_long(path: &Path) -> bool {
+    path.as_os_str().len() >= libc::PATH_MAX as usize || path.iter().any(|component| component.len() > 255)
+}
+
+#[cfg(windows)]
+fn is_too_long(path: &Path) -> bool {
+    use std::os::windows::ffi::OsStrExt;
+
+    // The shell doesn't support paths beyond `MAX_PATH`, including the terminating NUL.
+    const MAX_PATH: usize = 260;
+    path.as_os_str().encode_wide().count() >= MAX_PATH
+}
+
+/// Removable media are mounted in a folder of their own within these folders, which stays behind
+/// or is removed when the medium is unmounted.
+#[cfg(unix)]
+fn is_volume_missing(path: &Path) -> bool {
+    use std::os::unix::fs::MetadataExt;
+
+    const MEDIA_FOLDERS: &[(&str, usize)] = &[("/media", 2), ("/run/media", 2), ("/mnt", 1)];
+    let media = MEDIA_FOLDERS
+        .iter()
+        .find_map(|(folder, depth)| path.strip_prefix(folder).ok().map(|rest| (Path::new(folder), rest, *depth)));
+    let Some((folder, rest, depth)) = media else {
+        return false;
+    };
+    // Media are mounted at `/media/<user>/<label>` or `/mnt/<label>`, folders at a shallower depth
+    // belong to the root file system. `/media/<label>` is used by some distributions as well.
+    let mut mount_point = folder.to_owned();
+    for component in rest.iter().take(depth) {
+        mount_point.push(component);
+        let (Ok(metadata), Some(parent)) = (mount_point.metadata(), mount_point.parent()) else {
+            return mount_point != path;
+        };
+        let Ok(parent_metadata) = parent.metadata() else {
+            return false;
+        };
+        if metadata.dev() != parent_metadata.dev() {
+            return false;
+        }
+    }
+    // Nothing is mounted on the way down. An empty folder is most likely a mount point without its
+    // medium, unless the item was deleted from that folder itself.
+    rest.iter().count() > depth && mount_point.read_dir().is_ok_and(|mut entries| entries.next().is_none())
+}
+
+#[cfg(windows)]
+fn is_volume_missing(path: &Path) -> bool {
+    let root: std::path::PathBuf =
+        path.components().take_while(|c| !matches!(c, std::path::Component::Normal(_))).collect();
+    !root.as_os_str().is_empty() && root.metadata().is_err()
+}
@@ -0,0 +1,110 @@
+//! Checking end to end whether items can be moved to the trash and back.
+
+use std::{
+    fs::OpenOptions,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use log::{debug, warn};
+
+use crate::{platform, Error, TrashTransfer, DEFAULT_TRASH_CTX};
+
+/// The outcome of [`self_test`](crate::os_limited::self_test) for a single volume.
+#[derive(Debug)]
+pub struct VolumeSelfTest {
+    /// The root of the volume (mount point or drive).
+    pub volume: PathBuf,
+    /// The folder the test file was created in.
+    pub directory: PathBuf,
+    /// Whether the test file could be moved to the trash, found in it and restored.
+    pub result: Result<(), Error>,
+}
+
+pub(crate) fn self_test() -> Result<Vec<VolumeSelfTest>, Error> {
+    // Users usually can't write to the root of the volumes holding their home folder or the temp
+    // folder, so those folders are used for the test instead.
+    let home = std::env::var_os(if cfg!(windows) { "USERPROFILE" } else { "HOME" }).map(PathBuf::from);
+    let user_dirs: Vec<_> = home
+        .into_iter()
+        .chain(Some(std::env::temp_dir()))
+        .filter_map(|dir| Some((DEFAULT_TRASH_CTX.destination_for(&dir).ok()?.volume, dir)))
+        .collect();
+    let mut results = Vec::new();
+    for volume in platform::volumes()? {
+        let directory = match user_dirs.iter().find(|(v, _)| *v == volume) {
+            Some((_, dir)) => dir.clone(),
+            None => volume.clone(),
+        };
+        match DEFAULT_TRASH_CTX.destination_for(&directory) {
+            Ok(destination) if destination.transfer == TrashTransfer::PermanentDelete => {
+                debug!("Skipping {:?} as it doesn't have a trash", volume);
+                continue;
+            }
+            Err(e) => {
+                debug!("Skipping {:?} as it can't be accessed: {:?}", volume, e);
+                continue;
+            }
+            Ok(_) => {}
+        }
+        let Some(test_file) = create_test_file(&directory) else {
+            debug!("Skipping {:?} as {:?} isn't writable", volume, directory);
+            continue;
+        };
+        let result = round_trip(&test_file);
+        results.push(VolumeSelfTest { volume, directory, result });
+    }
+    Ok(results)
+}
+
+pub(crate) fn self_test_in(directory: &Path) -> Result<(), Error> {
+    let test_file = create_test_file(directory)
+        .ok_or_else(|| Error::CouldNotAccess { target: directory.to_string_lossy().into_owned() })?;
+    round_trip(&test_file)
+}
+
+fn create_test_file(directory: &Path) -> Option<PathBuf> {
+    let nanos = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_nanos()).unwrap_or_default();
+    // No extension, so that the shell doesn't hide a part of the name on Windows.
+    let path = directory.join(format!(".trash-rs-self-test-{}-{}", std::process::id(), nanos));
+    OpenOptions::new().write(true).create_new(true).open(&path).ok()?;
+    Some(path)
+}
+
+/// Moves `test_file` to the trash, restores it and removes it. The file is removed even if the
+/// test fails.
+fn round_trip(test_file: &Path) -> Result<(), Error> {
+    let result = try_round_trip(test_file);
+    if test_file.exists() {
+        if let Err(e) = std::fs::remove_file(test_file) {
+            warn!("Failed to remove the self test file {:?}: {}", test_file, e);
+        }
+    }
+    result
+}
+
+fn try_round_trip(test_file: &Path) -> Result<(), Error> {
+    let name = test_file.file_name().unwrap_or_default();
+    DEFAULT_TRASH_CTX.delete(test_file)?;
+    if test_file.exists() {
+        return Err(Error::Unknown { description: format!("{test_file:?} still exists after moving it to the trash") });
+    }
+    let items: Vec<_> = platform::list()?.into_iter().filter(|item| name == item.name.as_str()).collect();
+    if items.is_empty() {
+        return Err(Error::Unknown {
+            description: format!(
+                "{test_file:?} was deleted but can't be found in the trash, so it was most likely deleted permanently"
+            ),
+        });
+    }
+    if let Err(e) = DEFAULT_TRASH_CTX.restore_all(items.clone()) {
+        if let Err(purge_error) = DEFAULT_TRASH_CTX.purge_all(&items) {
+            warn!("Failed to purge the self test file {:?} from the trash: {:?}", test_file, purge_error);
+        }
+        return Err(e);
+    }
+    if !test_file.exists() {
+        return Err(Error::Unknown { description: format!("{test_file:?} doesn't exist after restoring it") });
+    }
+    Ok(())
+}
@@ -0,0 +1,25 @@
+//! Serde support for the fields that don't implement it themselves.
+
+/// Serializes an [`std::io::Error`] as its raw OS error code, if it has one, and its message.
+/// Errors with a code are deserialized from the code alone, others get the kind `Other`.
+pub(crate) mod io_error {
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    #[derive(Serialize, Deserialize)]
+    struct IoError {
+        code: Option<i32>,
+        message: String,
+    }
+
+    pub(crate) fn serialize<S: Serializer>(error: &std::io::Error, serializer: S) -> Result<S::Ok, S::Error> {
+        IoError { code: error.raw_os_error(), message: error.to_string() }.serialize(serializer)
+    }
+
+    pub(crate) fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<std::io::Error, D::Error> {
+        let IoError { code, message } = IoError::deserialize(deserializer)?;
+        Ok(match code {
+            Some(code) => std::io::Error::from_raw_os_error(code),
+            None => std::io::Error::other(message),
+        })
+    }
+}
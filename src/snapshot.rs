@@ -0,0 +1,102 @@
+//! Capturing the state of the trash to find out later what changed since.
+
+use std::{
+    collections::HashSet,
+    ffi::{OsStr, OsString},
+};
+
+use crate::{platform, Error, TrashItem};
+
+/// The ids of the items that were in the trash at a given moment.
+///
+/// A snapshot only holds ids, so it's cheap to keep around. To find out what changed while the
+/// application wasn't running, persist the [`ids`](TrashSnapshot::ids) on exit and recreate the
+/// snapshot with [`from_ids`](TrashSnapshot::from_ids) on the next start.
+///
+/// Obtained from [`snapshot`](crate::os_limited::snapshot).
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TrashSnapshot {
+    ids: HashSet<OsString>,
+}
+
+impl TrashSnapshot {
+    /// Creates a snapshot from previously captured item ids.
+    pub fn from_ids<I, T>(ids: I) -> Self
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<OsString>,
+    {
+        Self { ids: ids.into_iter().map(Into::into).collect() }
+    }
+
+    /// Creates a snapshot of the given items.
+    pub fn from_items<'a>(items: impl IntoIterator<Item = &'a TrashItem>) -> Self {
+        Self::from_ids(items.into_iter().map(|item| item.id.clone()))
+    }
+
+    /// The ids of the items that were in the trash when this snapshot was taken, in no particular
+    /// order.
+    pub fn ids(&self) -> impl Iterator<Item = &OsStr> {
+        self.ids.iter().map(OsString::as_os_str)
+    }
+
+    /// Returns `true` if an item with the given id was in the trash when this snapshot was taken.
+    pub fn contains(&self, id: &OsStr) -> bool {
+        self.ids.contains(id)
+    }
+
+    /// Returns the number of items in the snapshot.
+    pub fn len(&self) -> usize {
+        self.ids.len()
+    }
+
+    /// Returns `true` if the trash was empty when this snapshot was taken.
+    pub fn is_empty(&self) -> bool {
+        self.ids.is_empty()
+    }
+
+    /// Compares the snapshot against the given listing of the trash.
+    pub fn diff_with(&self, items: Vec<TrashItem>) -> TrashDiff {
+        let current: HashSet<&OsStr> = items.iter().map(|item| item.id.as_os_str()).collect();
+        let mut removed: Vec<OsString> =
+            self.ids.iter().filter(|id| !current.contains(id.as_os_str())).cloned().collect();
+        removed.sort();
+        let mut added: Vec<TrashItem> = items.into_iter().filter(|item| !self.ids.contains(&item.id)).collect();
+        added.sort_by(TrashItem::by_deletion);
+        TrashDiff { added, removed }
+    }
+
+    /// Updates the snapshot with the given changes.
+    pub(crate) fn apply(&mut self, diff: &TrashDiff) {
+        for id in &diff.removed {
+            self.ids.remove(id);
+        }
+        self.ids.extend(diff.added.iter().map(|item| item.id.clone()));
+    }
+}
+
+/// The changes to the trash since a [`TrashSnapshot`] was taken.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TrashDiff {
+    /// Items that were put into the trash, ordered by their deletion time.
+    pub added: Vec<TrashItem>,
+    /// The ids of the items that were restored or purged.
+    pub removed: Vec<OsString>,
+}
+
+impl TrashDiff {
+    /// Returns `true` if nothing changed.
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty()
+    }
+}
+
+pub(crate) fn snapshot() -> Result<TrashSnapshot, Error> {
+    Ok(TrashSnapshot::from_items(&platform::list()?))
+}
+
+pub(crate) fn diff(snapshot: &TrashSnapshot) -> Result<TrashDiff, Error> {
+    Ok(snapshot.diff_with(platform::list()?))
+}
@@ -0,0 +1,41 @@
+//! Reading items out of the trash without restoring them, like for a download.
+
+use std::{
+    fs::File,
+    io::{self, Write},
+};
+
+use crate::{io_error, platform, Error, TrashItem};
+
+impl TrashItem {
+    /// Copies the contents of the file in the trash to `writer` and returns the number of bytes
+    /// copied. The item stays in the trash.
+    ///
+    /// Fails for folders and symbolic links, use `write_archive` with the `archive` feature for
+    /// those.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use std::fs::File;
+    /// use trash::{delete, os_limited::{find, purge_all}};
+    ///
+    /// std::fs::write("read_into_example", "contents").unwrap();
+    /// delete("read_into_example").unwrap();
+    /// let items = find("read_into_example").unwrap();
+    /// let mut contents = Vec::new();
+    /// items[0].read_into(&mut contents).unwrap();
+    /// assert_eq!(contents, b"contents");
+    /// purge_all(&items).unwrap();
+    /// ```
+    pub fn read_into<W: Write + ?Sized>(&self, writer: &mut W) -> Result<u64, Error> {
+        let path = platform::file_in_trash(self)?;
+        let metadata = path.symlink_metadata().map_err(|e| io_error(&path, e))?;
+        if !metadata.is_file() {
+            let not_a_file = io::Error::new(io::ErrorKind::InvalidInput, "the item isn't a file, it can't be read");
+            return Err(io_error(&path, not_a_file));
+        }
+        let mut file = File::open(&path).map_err(|e| io_error(&path, e))?;
+        io::copy(&mut file, writer).map_err(|e| io_error(&path, e))
+    }
+}
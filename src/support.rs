@@ -0,0 +1,114 @@
+//! Which parts of the API the current platform implements.
+
+/// How a part of the API is supported on the current platform.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Support {
+    /// The functions are available and do what they're documented to do.
+    Implemented,
+    /// The functions are available but don't have any effect, like settings that only exist on
+    /// another platform.
+    NoOp,
+    /// The functions aren't available, either because they aren't compiled for the platform, or
+    /// because the process isn't allowed to use them, see [`PlatformSupport::current`].
+    Unsupported,
+}
+
+impl Support {
+    /// Returns `true` if the functions do what they're documented to do.
+    pub fn is_implemented(self) -> bool {
+        self == Support::Implemented
+    }
+}
+
+/// Which parts of the API are supported on the current platform, so that applications can hide
+/// features instead of finding out through failing calls.
+///
+/// [`SUPPORT`] describes what the platform implements, which is known at compile time.
+/// [`PlatformSupport::current`] additionally takes the environment of the process into account.
+///
+/// # Example
+///
+/// ```
+/// use trash::SUPPORT;
+///
+/// if SUPPORT.restore.is_implemented() {
+///     println!("Items can be restored");
+/// }
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PlatformSupport {
+    /// Moving items to the trash, like [`delete`](crate::delete) and
+    /// [`delete_all`](crate::delete_all).
+    pub delete: Support,
+    /// Deleting items without moving them to the trash, like
+    /// [`TrashContext::delete_permanently`](crate::TrashContext::delete_permanently).
+    pub delete_permanently: Support,
+    /// Listing the trash and reading the metadata of its items, like `os_limited::list` and
+    /// `os_limited::metadata`.
+    pub list: Support,
+    /// Restoring items from the trash, like `os_limited::restore_all`.
+    pub restore: Support,
+    /// Removing items from the trash permanently, like `os_limited::purge_all`.
+    pub purge: Support,
+    /// Following the changes to the trash, like `os_limited::watch`.
+    pub watch: Support,
+    /// Showing the UI of the platform during operations, like
+    /// [`TrashContext::with_shell_ui`](crate::TrashContext::with_shell_ui).
+    pub shell_ui: Support,
+}
+
+/// What the current platform implements, see [`PlatformSupport`].
+#[cfg(any(
+    target_os = "windows",
+    all(unix, not(target_os = "macos"), not(target_os = "ios"), not(target_os = "android"))
+))]
+pub const SUPPORT: PlatformSupport = PlatformSupport {
+    delete: Support::Implemented,
+    delete_permanently: Support::Implemented,
+    list: Support::Implemented,
+    restore: Support::Implemented,
+    purge: Support::Implemented,
+    watch: Support::Implemented,
+    shell_ui: if cfg!(target_os = "windows") { Support::Implemented } else { Support::NoOp },
+};
+
+/// What the current platform implements, see [`PlatformSupport`].
+#[cfg(target_os = "macos")]
+pub const SUPPORT: PlatformSupport = PlatformSupport {
+    delete: Support::Implemented,
+    delete_permanently: Support::Implemented,
+    list: Support::Unsupported,
+    restore: Support::Unsupported,
+    purge: Support::Unsupported,
+    watch: Support::Unsupported,
+    shell_ui: Support::NoOp,
+};
+
+impl PlatformSupport {
+    /// What's supported in the environment of the current process, which refines [`SUPPORT`].
+    ///
+    /// On Windows, processes inside an AppContainer can only move items to the Recycle Bin, see
+    /// `windows::sandbox_capabilities`. Elsewhere this is the same as [`SUPPORT`].
+    pub fn current() -> PlatformSupport {
+        #[cfg(target_os = "windows")]
+        {
+            let sandbox = crate::windows::sandbox_capabilities();
+            let refine = |support: Support, available: bool| match available {
+                true => support,
+                false => Support::Unsupported,
+            };
+            PlatformSupport {
+                delete: refine(SUPPORT.delete, sandbox.delete),
+                list: refine(SUPPORT.list, sandbox.list),
+                restore: refine(SUPPORT.restore, sandbox.restore),
+                purge: refine(SUPPORT.purge, sandbox.purge),
+                watch: refine(SUPPORT.watch, sandbox.list),
+                ..SUPPORT
+            }
+        }
+        #[cfg(not(target_os = "windows"))]
+        SUPPORT
+    }
+}
@@ -0,0 +1,123 @@
+//! Mirroring the changes to the trash into an external store, like a database or a queue.
+
+use crate::{
+    platform,
+    snapshot::{TrashDiff, TrashSnapshot},
+    watch::{self, TrashWatcher},
+    Error,
+};
+
+/// The store that [`TrashSync`] pushes the changes to the trash to.
+///
+/// Changes are delivered at least once: the cursor is only committed after the changes were
+/// pushed, so changes that were pushed but not committed, because the process exited in between
+/// or [`commit_cursor`](SyncAdapter::commit_cursor) failed, are pushed again by the next sync.
+/// Adapters should therefore treat an added item they already know, or a removed id they don't
+/// know, as a success.
+pub trait SyncAdapter {
+    /// Returns the cursor that was committed last, or `None` if nothing was synced yet, in which
+    /// case every item in the trash is pushed as added.
+    fn load_cursor(&mut self) -> Result<Option<TrashSnapshot>, Error>;
+
+    /// Pushes the changes since the last committed cursor. A failure leaves the cursor where it
+    /// was, so the same changes, along with any newer ones, are pushed by the next sync.
+    fn push(&mut self, diff: &TrashDiff) -> Result<(), Error>;
+
+    /// Stores the cursor after its changes were pushed, for [`load_cursor`](SyncAdapter::load_cursor)
+    /// to return on the next start. A [`TrashSnapshot`] can be stored as its
+    /// [`ids`](TrashSnapshot::ids), or serialized with the `serde` feature.
+    fn commit_cursor(&mut self, cursor: &TrashSnapshot) -> Result<(), Error>;
+}
+
+/// Pushes the changes to the trash to a [`SyncAdapter`], either when asked to with
+/// [`sync_once`](TrashSync::sync_once), like from a timer, or as they happen with
+/// [`watch`](TrashSync::watch).
+///
+/// # Example
+///
+/// ```no_run
+/// use trash::os_limited::{SyncAdapter, TrashDiff, TrashSnapshot, TrashSync};
+///
+/// struct Log(Option<TrashSnapshot>);
+///
+/// impl SyncAdapter for Log {
+///     fn load_cursor(&mut self) -> Result<Option<TrashSnapshot>, trash::Error> {
+///         Ok(self.0.clone())
+///     }
+///     fn push(&mut self, diff: &TrashDiff) -> Result<(), trash::Error> {
+///         println!("{} added, {} removed", diff.added.len(), diff.removed.len());
+///         Ok(())
+///     }
+///     fn commit_cursor(&mut self, cursor: &TrashSnapshot) -> Result<(), trash::Error> {
+///         self.0 = Some(cursor.clone());
+///         Ok(())
+///     }
+/// }
+///
+/// let watcher = TrashSync::new(Log(None)).watch(|e| eprintln!("sync failed: {e}")).unwrap();
+/// ```
+#[derive(Debug)]
+pub struct TrashSync<A> {
+    adapter: A,
+    cursor: Option<TrashSnapshot>,
+}
+
+impl<A: SyncAdapter> TrashSync<A> {
+    pub fn new(adapter: A) -> Self {
+        Self { adapter, cursor: None }
+    }
+
+    pub fn adapter(&self) -> &A {
+        &self.adapter
+    }
+
+    pub fn adapter_mut(&mut self) -> &mut A {
+        &mut self.adapter
+    }
+
+    pub fn into_adapter(self) -> A {
+        self.adapter
+    }
+
+    /// Lists the trash, pushes the changes since the last committed cursor and commits the new
+    /// cursor. Returns the changes that were pushed, nothing is pushed if there are none.
+    ///
+    /// The cursor is loaded from the adapter on the first call and kept in memory after that.
+    pub fn sync_once(&mut self) -> Result<TrashDiff, Error> {
+        let cursor = match &mut self.cursor {
+            Some(cursor) => cursor,
+            cursor @ None => cursor.insert(self.adapter.load_cursor()?.unwrap_or_default()),
+        };
+        let diff = cursor.diff_with(platform::list()?);
+        if diff.is_empty() {
+            return Ok(diff);
+        }
+        self.adapter.push(&diff)?;
+        let mut next = cursor.clone();
+        next.apply(&diff);
+        self.adapter.commit_cursor(&next)?;
+        *cursor = next;
+        Ok(diff)
+    }
+
+    /// Syncs once and then again after every change to the trash, on a background thread that
+    /// runs until the returned watcher is dropped.
+    ///
+    /// Failures are passed to `on_error`. The changes that failed are retried with the next
+    /// change to the trash, call [`sync_once`](TrashSync::sync_once) from a timer instead if they
+    /// have to be retried sooner.
+    pub fn watch<F>(mut self, mut on_error: F) -> Result<TrashWatcher, Error>
+    where
+        A: Send + 'static,
+        F: FnMut(Error) + Send + 'static,
+    {
+        if let Err(e) = self.sync_once() {
+            on_error(e);
+        }
+        watch::watch(move |_| {
+            if let Err(e) = self.sync_once() {
+                on_error(e);
+            }
+        })
+    }
+}
@@ -21,6 +21,171 @@ mod utils {
 
 pub use utils::{get_unique_name, init_logging};
 
+#[test]
+fn trash_item_order_and_identity() {
+    use crate::TrashItem;
+    use std::path::PathBuf;
+
+    let item = |id: &str, name: &str, time_deleted| TrashItem {
+        id: id.into(),
+        name: name.into(),
+        original_parent: Some(PathBuf::from("/")),
+        time_deleted: Some(time_deleted),
+        size: None,
+        kind: None,
+        unknown_reason: None,
+        pinned: false,
+    };
+    let keys = |items: &[TrashItem]| items.iter().map(|i| i.key().to_str().unwrap().to_owned()).collect::<Vec<_>>();
+    let mut items = [item("a", "a", 2), item("c", "b", 1), item("b", "a", 1)];
+    items.sort();
+    assert_eq!(keys(&items), ["a", "b", "c"]);
+    items.sort_by(TrashItem::by_deletion);
+    assert_eq!(keys(&items), ["b", "c", "a"]);
+    assert_eq!(item("x", "one", 1), item("x", "other", 2));
+    assert_eq!(item("x", "one", 1).cmp(&item("x", "other", 2)), std::cmp::Ordering::Equal);
+    assert_eq!(item("x", "x", 60).deletion_time(), Some(std::time::UNIX_EPOCH + std::time::Duration::from_secs(60)));
+    assert_eq!(item("x", "x", -1).deletion_time(), None);
+    assert_eq!(TrashItem { time_deleted: None, ..item("x", "x", 60) }.deletion_time(), None);
+}
+
+#[test]
+fn degraded_items() {
+    use crate::{TrashItem, UnknownReason};
+    use std::path::PathBuf;
+
+    let item = TrashItem {
+        id: "id".into(),
+        name: "name".into(),
+        original_parent: Some(PathBuf::from("/a")),
+        time_deleted: Some(1),
+        size: None,
+        kind: None,
+        unknown_reason: None,
+        pinned: false,
+    };
+    assert!(!item.is_degraded());
+    assert_eq!(item.original_path(), Some(PathBuf::from("/a/name")));
+    let unknown_location =
+        TrashItem { original_parent: None, unknown_reason: Some(UnknownReason::Unreadable), ..item.clone() };
+    assert!(unknown_location.is_degraded());
+    assert_eq!(unknown_location.original_path(), None);
+    assert!(TrashItem { time_deleted: None, unknown_reason: Some(UnknownReason::Unsupported), ..item }.is_degraded());
+}
+
+#[test]
+fn item_age() {
+    use crate::TrashItem;
+    use std::{path::PathBuf, time::Duration};
+
+    let deleted_ago = |seconds: i64| TrashItem {
+        id: "id".into(),
+        name: "name".into(),
+        original_parent: Some(PathBuf::from("/")),
+        time_deleted: Some(chrono::Utc::now().timestamp() - seconds),
+        size: None,
+        kind: None,
+        unknown_reason: None,
+        pinned: false,
+    };
+    let age = deleted_ago(3600).age().unwrap();
+    assert!(age >= Duration::from_secs(3600) && age < Duration::from_secs(3660));
+    assert_eq!(deleted_ago(-3600).age(), Some(Duration::ZERO));
+    assert_eq!(TrashItem { time_deleted: Some(-1), ..deleted_ago(0) }.age(), None);
+    assert_eq!(TrashItem { time_deleted: None, ..deleted_ago(0) }.age(), None);
+    let item = deleted_ago(0);
+    #[cfg(feature = "chrono")]
+    assert_eq!(item.deleted_at_local().map(|time| time.timestamp()), item.time_deleted);
+    assert!(item.age().is_some());
+    #[cfg(feature = "humanize")]
+    {
+        assert_eq!(deleted_ago(3 * 24 * 3600 + 5).humanized_age().as_deref(), Some("3 days ago"));
+        assert_eq!(crate::age::humanize(Duration::from_secs(59)), "just now");
+        assert_eq!(crate::age::humanize(Duration::from_secs(60)), "1 minute ago");
+        assert_eq!(crate::age::humanize(Duration::from_secs(400 * 24 * 3600)), "1 year ago");
+    }
+}
+
+#[test]
+fn platform_support() {
+    use crate::{PlatformSupport, Support, SUPPORT};
+
+    assert_eq!(SUPPORT.delete, Support::Implemented);
+    let os_limited = cfg!(any(
+        target_os = "windows",
+        all(unix, not(target_os = "macos"), not(target_os = "ios"), not(target_os = "android"))
+    ));
+    assert_eq!(SUPPORT.restore.is_implemented(), os_limited);
+    assert_eq!(SUPPORT.shell_ui.is_implemented(), cfg!(target_os = "windows"));
+    let current = PlatformSupport::current();
+    assert!(!current.restore.is_implemented() || SUPPORT.restore.is_implemented());
+}
+
+#[test]
+fn context_builder() {
+    use crate::{CaseSensitivity, Preset, TrashContext};
+    use std::path::Path;
+
+    let ctx = TrashContext::builder()
+        .case_sensitivity(CaseSensitivity::Sensitive)
+        .base_dir("/base")
+        .allow_protected(true)
+        .prescan(false)
+        .show_ui(true)
+        .build();
+    assert_eq!(ctx.case_sensitivity(), CaseSensitivity::Sensitive);
+    assert_eq!(ctx.base_dir(), Some(Path::new("/base")));
+    assert!(ctx.allow_protected() && !ctx.prescan() && !ctx.continue_on_error());
+    #[cfg(target_os = "windows")]
+    assert!(crate::windows::TrashContextExtWindows::show_ui(&ctx));
+
+    #[cfg(target_os = "windows")]
+    assert!(!crate::windows::TrashContextExtWindows::allow_permanent_delete(&ctx));
+    #[cfg(target_os = "windows")]
+    assert!(!crate::windows::TrashContextExtWindows::show_ui(&ctx.with_shell_ui(false)));
+
+    // A preset overrides the settings that were applied before it.
+    let ctx = TrashContext::builder().continue_on_error(true).preset(Preset::Silent).build();
+    assert!(!ctx.continue_on_error());
+}
+
+#[test]
+#[cfg(feature = "serde")]
+fn serde_round_trip() {
+    use crate::{Error, TrashItem};
+    use std::path::PathBuf;
+
+    fn round_trip<T: serde::Serialize + serde::de::DeserializeOwned>(value: &T) -> T {
+        serde_json::from_str(&serde_json::to_string(value).unwrap()).unwrap()
+    }
+
+    let item = TrashItem {
+        id: "id".into(),
+        name: "name".into(),
+        original_parent: Some(PathBuf::from("/a")),
+        time_deleted: Some(7),
+        size: Some(crate::ByteSize::new(3)),
+        kind: Some(crate::TrashItemKind::File),
+        unknown_reason: None,
+        pinned: false,
+    };
+    let copy = round_trip(&item);
+    assert_eq!(
+        (&copy.id, &copy.name, &copy.original_parent, copy.time_deleted, copy.size, copy.kind),
+        (&item.id, &item.name, &item.original_parent, Some(7), item.size, item.kind)
+    );
+
+    let errors = [
+        Error::RestoreCollision { path: PathBuf::from("/a/name"), remaining_items: vec![item] },
+        Error::Protected { path: PathBuf::from("/home"), kind: crate::ProtectedKind::Home },
+        #[cfg(all(unix, not(target_os = "macos"), not(target_os = "ios"), not(target_os = "android")))]
+        Error::FileSystem { path: PathBuf::from("/a"), source: std::io::Error::from_raw_os_error(libc::EACCES) },
+    ];
+    for error in errors {
+        assert_eq!(format!("{:?}", round_trip(&error)), format!("{error:?}"));
+    }
+}
+
 #[cfg(any(
     target_os = "windows",
     all(unix, not(target_os = "macos"), not(target_os = "ios"), not(target_os = "android"))
@@ -30,6 +195,7 @@ mod os_limited {
     use serial_test::serial;
     use std::collections::{hash_map::Entry, HashMap};
     use std::fs::File;
+    use std::path::PathBuf;
 
     use crate as trash;
 
@@ -40,8 +206,8 @@ mod os_limited {
         init_logging();
 
         let deletion_time = chrono::Utc::now();
-        let actual_unix_deletion_time = deletion_time.naive_utc().timestamp();
-        assert_eq!(actual_unix_deletion_time, deletion_time.naive_local().timestamp());
+        let actual_unix_deletion_time = deletion_time.naive_utc().and_utc().timestamp();
+        assert_eq!(actual_unix_deletion_time, deletion_time.naive_local().and_utc().timestamp());
         let file_name_prefix = get_unique_name();
         let batches: usize = 2;
         let files_per_batch: usize = 3;
@@ -71,12 +237,13 @@ mod os_limited {
                     assert_eq!(items.len(), batches);
                     for item in items {
                         if cfg!(feature = "chrono") {
-                            let diff = (item.time_deleted - actual_unix_deletion_time).abs();
+                            let time_deleted = item.time_deleted.unwrap();
+                            let diff = (time_deleted - actual_unix_deletion_time).abs();
                             if diff > MAX_SECONDS_DIFFERENCE {
                                 panic!(
                                     "The deleted item does not have the timestamp that represents its deletion time. Expected: {}. Got: {}",
                                     actual_unix_deletion_time,
-                                    item.time_deleted
+                                    time_deleted
                                 );
                             }
                         }
@@ -88,7 +255,61 @@ mod os_limited {
 
         // Let's try to purge all the items we just created but ignore any errors
         // as this test should succeed as long as `list` works properly.
-        let _ = trash::os_limited::purge_all(items.iter().flat_map(|(_name, item)| item));
+        let _ = trash::os_limited::purge_all(items.values().flatten());
+    }
+
+    #[test]
+    #[serial]
+    fn list_iter_matches_list() {
+        init_logging();
+        let names: Vec<_> = (0..3).map(|i| format!("{}#{}", get_unique_name(), i)).collect();
+        for name in &names {
+            File::create(name).unwrap();
+        }
+        trash::delete_all(&names).unwrap();
+
+        let mut listed: Vec<_> = trash::os_limited::list().unwrap().into_iter().map(|item| item.id).collect();
+        let mut iterated: Vec<_> = trash::os_limited::list_iter().map(|item| item.unwrap().id).collect();
+        listed.sort();
+        iterated.sort();
+        assert_eq!(listed, iterated);
+
+        let items = trash::os_limited::list_iter().filter_map(Result::ok).filter(|item| names.contains(&item.name));
+        trash::os_limited::purge_all(items.collect::<Vec<_>>()).unwrap();
+    }
+
+    #[test]
+    #[serial]
+    fn list_filtered() {
+        init_logging();
+        let prefix = get_unique_name();
+        let names = [format!("{prefix}.log"), format!("{prefix}.txt"), format!("{prefix}-large.log")];
+        for name in &names {
+            File::create(name).unwrap();
+        }
+        std::fs::write(&names[2], [0; 2048]).unwrap();
+        trash::delete_all(&names).unwrap();
+
+        let filter = trash::os_limited::TrashFilter {
+            original_path_prefix: Some(std::env::current_dir().unwrap()),
+            name_glob: Some(format!("{prefix}*.log")),
+            ..Default::default()
+        };
+        let logs = trash::os_limited::list_filtered(&filter).unwrap();
+        let mut found: Vec<_> = logs.iter().map(|item| item.name.as_str()).collect();
+        found.sort();
+        assert_eq!(found, [names[2].as_str(), names[0].as_str()]);
+
+        let large = trash::os_limited::TrashFilter { min_size: Some(trash::ByteSize::kib(1)), ..filter.clone() };
+        let large = trash::os_limited::list_filtered(&large).unwrap();
+        assert_eq!(large.len(), 1);
+        assert_eq!(large[0].name, names[2]);
+
+        let future = trash::os_limited::TrashFilter { deleted_after: Some(i64::MAX), ..filter };
+        assert!(trash::os_limited::list_filtered(&future).unwrap().is_empty());
+
+        let all = trash::os_limited::list().unwrap().into_iter().filter(|item| names.contains(&item.name));
+        trash::os_limited::purge_all(all.collect::<Vec<_>>()).unwrap();
     }
 
     #[test]
@@ -128,6 +349,851 @@ mod os_limited {
         assert_eq!(remaining, 0);
     }
 
+    #[test]
+    #[serial]
+    fn purge_path() {
+        init_logging();
+        let name = get_unique_name();
+        let other = format!("{name}-other");
+        for _ in 0..2 {
+            File::create(&name).unwrap();
+            File::create(&other).unwrap();
+            trash::delete_all([&name, &other]).unwrap();
+        }
+
+        assert_eq!(trash::os_limited::purge_path(&name).unwrap(), 2);
+        assert_eq!(trash::os_limited::purge_path(&name).unwrap(), 0);
+        assert!(trash::os_limited::find(&name).unwrap().is_empty());
+        assert_eq!(trash::os_limited::purge_path(&other).unwrap(), 2);
+    }
+
+    #[test]
+    #[serial]
+    fn relative_to_base_dir() {
+        init_logging();
+        let dir = std::env::current_dir().unwrap().join(get_unique_name());
+        std::fs::create_dir(&dir).unwrap();
+        let name = get_unique_name();
+        File::create(dir.join(&name)).unwrap();
+        let mut ctx = trash::TrashContext::default();
+        ctx.set_base_dir(Some(dir.clone()));
+
+        let resolved = ctx.resolve_path(&name).unwrap();
+        assert_eq!(resolved, dir.canonicalize().unwrap().join(&name));
+        ctx.delete(&name).unwrap();
+        assert!(!resolved.exists());
+        let items = ctx.find(&name).unwrap();
+        assert_eq!(items.len(), 1);
+        ctx.purge_all(items).unwrap();
+        std::fs::remove_dir(&dir).unwrap();
+    }
+
+    #[test]
+    fn protected_paths() {
+        init_logging();
+        let cwd = std::env::current_dir().unwrap();
+        let root = cwd.ancestors().last().unwrap();
+        assert!(matches!(trash::delete(root), Err(trash::Error::TargetedRoot)));
+        assert!(matches!(trash::delete(root.join("..")), Err(trash::Error::TargetedRoot)));
+
+        let home =
+            std::path::PathBuf::from(std::env::var_os(if cfg!(windows) { "USERPROFILE" } else { "HOME" }).unwrap());
+        // The parent of the home folder contains it, unless it's the root.
+        let parent = Some(home.join("..")).filter(|_| home.parent().and_then(|p| p.parent()).is_some());
+        for path in std::iter::once(home.clone()).chain(parent) {
+            match trash::delete(path) {
+                Err(trash::Error::Protected { kind: trash::ProtectedKind::Home, .. }) => {}
+                other => panic!("the home folder wasn't protected: {other:?}"),
+            }
+        }
+    }
+
+    #[test]
+    #[serial]
+    fn delete_permanently() {
+        init_logging();
+        let name = get_unique_name();
+        let dir = format!("{name}-dir");
+        File::create(&name).unwrap();
+        std::fs::create_dir(&dir).unwrap();
+        File::create(std::path::Path::new(&dir).join("inner")).unwrap();
+
+        struct KeepDirs;
+        impl trash::TrashHooks for KeepDirs {
+            fn pre_delete(&self, path: &std::path::Path) -> trash::HookDecision {
+                if path.is_dir() {
+                    trash::HookDecision::Veto
+                } else {
+                    trash::HookDecision::Proceed
+                }
+            }
+        }
+        let ctx = trash::TrashContext::new().with_hooks(KeepDirs);
+        assert!(matches!(ctx.delete_all_permanently([&name, &dir]), Err(trash::Error::Vetoed { .. })));
+        assert!(std::path::Path::new(&name).exists());
+
+        trash::delete_all_permanently([&name, &dir]).unwrap();
+        assert!(!std::path::Path::new(&name).exists());
+        assert!(!std::path::Path::new(&dir).exists());
+        assert!(trash::os_limited::find(&name).unwrap().is_empty());
+        assert!(trash::os_limited::find(&dir).unwrap().is_empty());
+    }
+
+    #[test]
+    #[serial]
+    fn bypass_trash_hook() {
+        init_logging();
+        struct RefuseBypass;
+        impl trash::TrashHooks for RefuseBypass {
+            fn pre_bypass_trash(&self, _path: &std::path::Path) -> trash::HookDecision {
+                trash::HookDecision::Veto
+            }
+        }
+        let name = get_unique_name();
+        File::create(&name).unwrap();
+        let bypasses = trash::destination_for(&name).unwrap().transfer == trash::TrashTransfer::PermanentDelete;
+
+        // The hook is only asked about items that wouldn't end up in the trash.
+        let result = trash::TrashContext::new().with_hooks(RefuseBypass).delete(&name);
+        assert_eq!(matches!(result, Err(trash::Error::Vetoed { .. })), bypasses);
+        if bypasses {
+            std::fs::remove_file(&name).unwrap();
+        } else {
+            trash::os_limited::purge_all(trash::os_limited::find(&name).unwrap()).unwrap();
+        }
+
+        // Through the defaults, `pre_delete` is asked once either way, and the item is reported to
+        // the post hook of what happened to it.
+        struct Record(std::sync::Arc<std::sync::Mutex<Vec<&'static str>>>);
+        impl trash::TrashHooks for Record {
+            fn pre_delete(&self, _path: &std::path::Path) -> trash::HookDecision {
+                self.0.lock().unwrap().push("pre_delete");
+                trash::HookDecision::Proceed
+            }
+            fn post_delete(&self, _path: &std::path::Path) {
+                self.0.lock().unwrap().push("post_delete");
+            }
+            fn post_delete_permanently(&self, _path: &std::path::Path) {
+                self.0.lock().unwrap().push("post_delete_permanently");
+            }
+        }
+        let calls = std::sync::Arc::default();
+        File::create(&name).unwrap();
+        trash::TrashContext::new().with_hooks(Record(std::sync::Arc::clone(&calls))).delete(&name).unwrap();
+        let post = if bypasses { "post_delete_permanently" } else { "post_delete" };
+        assert_eq!(*calls.lock().unwrap(), ["pre_delete", post]);
+        if !bypasses {
+            trash::os_limited::purge_all(trash::os_limited::find(&name).unwrap()).unwrap();
+        }
+    }
+
+    #[test]
+    #[serial]
+    fn delete_with_progress() {
+        use std::sync::{Arc, Mutex};
+
+        struct Record(Arc<Mutex<Vec<trash::DeleteProgress>>>, trash::ScanDecision);
+        impl trash::TrashProgress for Record {
+            fn scanning(&self, _counted: &trash::ProgressCount) -> trash::ScanDecision {
+                self.1
+            }
+            fn progress(&self, progress: &trash::DeleteProgress) {
+                self.0.lock().unwrap().push(*progress);
+            }
+        }
+
+        init_logging();
+        let name = get_unique_name();
+        let dir = PathBuf::from(format!("{name}-dir"));
+        File::create(&name).unwrap();
+        std::fs::create_dir(&dir).unwrap();
+        for i in 0..300 {
+            std::fs::write(dir.join(i.to_string()), "abc").unwrap();
+        }
+
+        // Skipping the scan halfway reports the progress of the paths only.
+        let reports = Arc::new(Mutex::new(Vec::new()));
+        let ctx = trash::TrashContext::new().with_progress(Record(reports.clone(), trash::ScanDecision::Skip));
+        ctx.delete_all_permanently([&dir, &PathBuf::from(&name)]).unwrap();
+        let done: Vec<_> = reports.lock().unwrap().iter().map(|r| (r.paths_done, r.paths_total, r.total)).collect();
+        assert_eq!(done, [(0, 2, None), (1, 2, None), (2, 2, None)]);
+        assert!(!dir.exists());
+
+        std::fs::create_dir(&dir).unwrap();
+        for i in 0..3 {
+            std::fs::write(dir.join(i.to_string()), "abc").unwrap();
+        }
+        let reports = Arc::new(Mutex::new(Vec::new()));
+        let ctx = trash::TrashContext::new().with_progress(Record(reports.clone(), trash::ScanDecision::Continue));
+        ctx.delete_all_permanently([&dir]).unwrap();
+        // Every entry is reported while the folder is deleted, before the folder itself is done.
+        let count = |entries, bytes| trash::ProgressCount { entries, bytes: trash::ByteSize::new(bytes) };
+        let total = Some(count(4, 9));
+        let done: Vec<_> = reports.lock().unwrap().iter().map(|r| (r.paths_done, r.done, r.total)).collect();
+        let expected = [(0, count(0, 0)), (0, count(1, 3)), (0, count(2, 6)), (0, count(3, 9)), (0, count(4, 9))];
+        let mut expected: Vec<_> = expected.into_iter().map(|(paths, done)| (paths, Some(done), total)).collect();
+        expected.push((1, total, total));
+        assert_eq!(done, expected);
+    }
+
+    #[test]
+    #[serial]
+    fn purge_concurrently() {
+        init_logging();
+        let file_name_prefix = get_unique_name();
+        let names: Vec<_> = (0..20).map(|i| format!("{}#{}", file_name_prefix, i)).collect();
+        for path in names.iter() {
+            File::create(path).unwrap();
+        }
+        trash::delete_all(&names).unwrap();
+
+        let mut ctx = trash::TrashContext::new();
+        ctx.set_purge_concurrency(8);
+        let targets: Vec<_> =
+            trash::os_limited::list().unwrap().into_iter().filter(|x| x.name.starts_with(&file_name_prefix)).collect();
+        assert_eq!(targets.len(), names.len());
+        ctx.purge_all(&targets).unwrap();
+        let remaining =
+            trash::os_limited::list().unwrap().into_iter().filter(|x| x.name.starts_with(&file_name_prefix)).count();
+        assert_eq!(remaining, 0);
+        // Purging the same items again fails for each of them.
+        assert!(ctx.purge_all(&targets).is_err());
+    }
+
+    #[test]
+    #[serial]
+    fn delete_concurrently() {
+        init_logging();
+        // The temporary folder is usually on a volume of its own, like a tmpfs.
+        let paths = [PathBuf::from(get_unique_name()), std::env::temp_dir().join(get_unique_name())];
+        for path in &paths {
+            File::create(path).unwrap();
+        }
+        let missing = PathBuf::from(get_unique_name());
+
+        let mut ctx = trash::TrashContext::new();
+        ctx.set_delete_concurrency(2);
+        ctx.set_continue_on_error(true);
+        assert!(ctx.delete_all([&paths[0], &missing, &paths[1]]).is_err());
+        for path in &paths {
+            assert!(!path.exists());
+            trash::os_limited::purge_all(trash::os_limited::find(path).unwrap()).unwrap();
+        }
+    }
+
+    #[test]
+    #[serial]
+    fn cancelled_operations() {
+        init_logging();
+        let path = PathBuf::from(get_unique_name());
+        File::create(&path).unwrap();
+        let token = trash::CancellationToken::new();
+        let ctx = trash::TrashContext::builder().cancellation(token.clone()).build();
+        ctx.delete(&path).unwrap();
+        let items = trash::os_limited::find(&path).unwrap();
+        assert_eq!(items.len(), 1);
+
+        token.cancel();
+        assert!(matches!(ctx.restore_all(items.clone()), Err(trash::Error::Cancelled)));
+        assert!(matches!(ctx.purge_all(&items), Err(trash::Error::Cancelled)));
+        File::create(&path).unwrap();
+        assert!(matches!(ctx.delete(&path), Err(trash::Error::Cancelled)));
+        assert!(path.exists());
+        std::fs::remove_file(&path).unwrap();
+        trash::os_limited::purge_all(items).unwrap();
+    }
+
+    /// Cancels large batches at given points and checks that every item ends up either where it
+    /// was or where it was moved to, and that a cancelled batch left at least one item behind.
+    ///
+    /// Deletes are cancelled by the progress callback once a given number of paths is done, which
+    /// makes them stop at exactly that point. Restores are cancelled from another thread after a
+    /// delay from a seeded generator, so that a failure can be reproduced as far as the timing
+    /// allows.
+    #[test]
+    #[serial]
+    fn cancel_midway() {
+        use rand::{Rng, SeedableRng};
+
+        struct CancelAfter(trash::CancellationToken, usize);
+        impl trash::TrashProgress for CancelAfter {
+            fn progress(&self, progress: &trash::DeleteProgress) {
+                if progress.paths_done == self.1 {
+                    self.0.cancel();
+                }
+            }
+        }
+
+        init_logging();
+        let mut rng = rand::rngs::StdRng::seed_from_u64(0x7261_7368);
+        for cancel_at in [0, 1, 120, 199, 200] {
+            let prefix = get_unique_name();
+            let names: Vec<_> = (0..200).map(|i| PathBuf::from(format!("{prefix}#{i}"))).collect();
+            for name in &names {
+                File::create(name).unwrap();
+            }
+            let in_trash = || -> Vec<trash::TrashItem> {
+                let items = trash::os_limited::list().unwrap();
+                items.into_iter().filter(|item| item.name.starts_with(&prefix)).collect()
+            };
+
+            let token = trash::CancellationToken::new();
+            let ctx = trash::TrashContext::builder().cancellation(token.clone()).build();
+            let ctx = ctx.with_progress(CancelAfter(token, cancel_at));
+            let result = ctx.delete_all(&names);
+            let trashed = in_trash();
+            let remaining = names.iter().filter(|name| name.exists()).count();
+            assert_eq!((trashed.len(), remaining), (cancel_at, names.len() - cancel_at));
+            match result {
+                Ok(()) => assert_eq!(remaining, 0),
+                Err(trash::Error::Cancelled) => assert!(remaining > 0),
+                Err(e) => panic!("Deleting failed with {e:?}"),
+            }
+
+            let token = trash::CancellationToken::new();
+            let ctx = trash::TrashContext::builder().cancellation(token.clone()).build();
+            let delay = std::time::Duration::from_micros(rng.gen_range(0..20_000));
+            let canceller = std::thread::spawn(move || {
+                std::thread::sleep(delay);
+                token.cancel();
+            });
+            let result = ctx.restore_all(trashed.clone());
+            canceller.join().unwrap();
+            let still_trashed = in_trash();
+            let restored = names.iter().filter(|name| name.exists()).count();
+            assert_eq!(still_trashed.len() + restored, names.len());
+            match result {
+                Ok(()) => assert!(still_trashed.is_empty()),
+                Err(trash::Error::Cancelled) => assert!(!still_trashed.is_empty()),
+                Err(e) => panic!("Restoring failed with {e:?}, {delay:?} after the start"),
+            }
+
+            trash::os_limited::purge_all(&still_trashed).unwrap();
+            for name in &names {
+                let _ = std::fs::remove_file(name);
+            }
+        }
+    }
+
+    #[test]
+    #[serial]
+    fn delete_missing_path() {
+        init_logging();
+        let path = std::env::current_dir().unwrap().join(get_unique_name());
+        match trash::delete(&path) {
+            Err(trash::Error::TargetedItemNotFound { path: missing }) => assert_eq!(missing, path),
+            result => panic!("Deleting a missing path resulted in {result:?}"),
+        }
+    }
+
+    #[test]
+    #[serial]
+    fn collect_results_per_item() {
+        init_logging();
+        let names: Vec<_> = (0..3).map(|_| PathBuf::from(get_unique_name())).collect();
+        File::create(&names[0]).unwrap();
+        File::create(&names[2]).unwrap();
+        let results = trash::delete_all_collect(&names);
+        assert!(results[0].is_ok());
+        assert!(results[1].is_err());
+        assert!(results[2].is_ok());
+
+        let mut items: Vec<_> = names.iter().flat_map(|name| trash::os_limited::find(name).unwrap()).collect();
+        items.sort_by(|a, b| a.name.cmp(&b.name));
+        assert_eq!(items.len(), 2);
+        File::create(&names[0]).unwrap();
+        let results = trash::os_limited::restore_all_collect(items.clone());
+        assert!(matches!(results[0], Err(trash::Error::RestoreCollision { .. })));
+        assert!(results[1].is_ok());
+        assert!(names[2].exists());
+
+        let results = trash::os_limited::purge_all_collect(&items);
+        assert!(results[0].is_ok());
+        assert!(results[1].is_err());
+        std::fs::remove_file(&names[0]).unwrap();
+        std::fs::remove_file(&names[2]).unwrap();
+    }
+
+    #[test]
+    #[serial]
+    fn sync_to_adapter() {
+        use trash::os_limited::{SyncAdapter, TrashDiff, TrashSnapshot, TrashSync};
+
+        #[derive(Default)]
+        struct Store {
+            cursor: Option<TrashSnapshot>,
+            pushed: Vec<TrashDiff>,
+            fail: bool,
+        }
+        impl SyncAdapter for Store {
+            fn load_cursor(&mut self) -> Result<Option<TrashSnapshot>, trash::Error> {
+                Ok(self.cursor.clone())
+            }
+            fn push(&mut self, diff: &TrashDiff) -> Result<(), trash::Error> {
+                if self.fail {
+                    return Err(trash::Error::Unknown { description: "store unavailable".into() });
+                }
+                self.pushed.push(diff.clone());
+                Ok(())
+            }
+            fn commit_cursor(&mut self, cursor: &TrashSnapshot) -> Result<(), trash::Error> {
+                self.cursor = Some(cursor.clone());
+                Ok(())
+            }
+        }
+
+        init_logging();
+        let mut sync = TrashSync::new(Store::default());
+        sync.sync_once().unwrap();
+        let path = PathBuf::from(get_unique_name());
+        File::create(&path).unwrap();
+        trash::delete(&path).unwrap();
+        let item = trash::os_limited::find(&path).unwrap().remove(0);
+
+        sync.adapter_mut().fail = true;
+        assert!(sync.sync_once().is_err());
+        sync.adapter_mut().fail = false;
+        let diff = sync.sync_once().unwrap();
+        assert!(diff.added.iter().any(|added| added.id == item.id));
+        assert!(sync.sync_once().unwrap().is_empty());
+
+        // A new driver resumes from the committed cursor.
+        let mut sync = TrashSync::new(sync.into_adapter());
+        trash::os_limited::purge_all([&item]).unwrap();
+        assert!(sync.sync_once().unwrap().removed.contains(&item.id));
+    }
+
+    #[test]
+    #[serial]
+    #[cfg(feature = "archive")]
+    fn read_and_archive_items() {
+        use std::io::Read;
+
+        init_logging();
+        let dir = PathBuf::from(get_unique_name());
+        let long_name = "n".repeat(120);
+        std::fs::create_dir(&dir).unwrap();
+        std::fs::write(dir.join("a.txt"), "alpha").unwrap();
+        std::fs::write(dir.join(&long_name), vec![7; 600]).unwrap();
+        #[cfg(unix)]
+        {
+            use std::os::unix::ffi::OsStrExt;
+
+            std::fs::write(dir.join(std::ffi::OsStr::from_bytes(b"b\xFF")), "beta").unwrap();
+            std::os::unix::fs::symlink(format!("/{long_name}"), dir.join("link")).unwrap();
+        }
+        trash::delete(&dir).unwrap();
+        let item = trash::os_limited::find(&dir).unwrap().remove(0);
+        assert!(item.read_into(&mut Vec::new()).is_err());
+
+        let archive = item.write_archive(Vec::new()).unwrap();
+        assert_eq!(archive.len() % 512, 0);
+        let mut entries = Vec::new();
+        for entry in tar::Archive::new(archive.as_slice()).entries().unwrap() {
+            let mut entry = entry.unwrap();
+            let path = entry.path_bytes().into_owned();
+            let link = entry.link_name_bytes().map(|link| link.into_owned());
+            let mut contents = Vec::new();
+            entry.read_to_end(&mut contents).unwrap();
+            entries.push((path, entry.header().entry_type(), link, contents));
+        }
+        let name = dir.to_str().unwrap();
+        let entry = |suffix: &[u8], kind, link: Option<String>, contents: &[u8]| {
+            ([name.as_bytes(), suffix].concat(), kind, link.map(String::into_bytes), contents.to_vec())
+        };
+        let mut expected = vec![
+            entry(b"/", tar::EntryType::Directory, None, b""),
+            entry(b"/a.txt", tar::EntryType::Regular, None, b"alpha"),
+        ];
+        #[cfg(unix)]
+        {
+            expected.push(entry(b"/b\xFF", tar::EntryType::Regular, None, b"beta"));
+            expected.push(entry(b"/link", tar::EntryType::Symlink, Some(format!("/{long_name}")), b""));
+        }
+        expected.push(entry(format!("/{long_name}").as_bytes(), tar::EntryType::Regular, None, &[7; 600]));
+        assert_eq!(entries, expected);
+
+        trash::os_limited::purge_all([item]).unwrap();
+    }
+
+    #[test]
+    #[serial]
+    fn physical_path() {
+        init_logging();
+        let name = get_unique_name();
+        std::fs::write(&name, "contents").unwrap();
+        trash::delete(&name).unwrap();
+        let item = trash::os_limited::find(&name).unwrap().remove(0);
+        let physical_path = item.physical_path().unwrap();
+        assert!(physical_path.starts_with(item.trash_root().unwrap()));
+        assert_eq!(std::fs::read(physical_path).unwrap(), b"contents");
+        trash::os_limited::purge_all([item]).unwrap();
+    }
+
+    #[test]
+    #[serial]
+    fn find_case_sensitivity() {
+        init_logging();
+        let name = format!("{}-Find", get_unique_name());
+        File::create(&name).unwrap();
+        trash::delete(&name).unwrap();
+
+        let found = trash::os_limited::find(&name).unwrap();
+        assert_eq!(found.len(), 1);
+        let mut ctx = trash::TrashContext::default();
+        ctx.set_case_sensitivity(trash::CaseSensitivity::Sensitive);
+        assert!(ctx.find(name.to_uppercase()).unwrap().is_empty());
+        ctx.set_case_sensitivity(trash::CaseSensitivity::Insensitive);
+        assert_eq!(ctx.find(name.to_uppercase()).unwrap(), found);
+        trash::os_limited::purge_all(found).unwrap();
+    }
+
+    #[test]
+    #[serial]
+    fn contains() {
+        init_logging();
+        let name = format!("{}-Contains", get_unique_name());
+        assert!(!trash::os_limited::contains(&name));
+        for _ in 0..2 {
+            File::create(&name).unwrap();
+            trash::delete(&name).unwrap();
+        }
+        assert!(trash::os_limited::contains(&name));
+        assert!(!trash::os_limited::contains(format!("{name}-other")));
+
+        let mut ctx = trash::TrashContext::default();
+        ctx.set_case_sensitivity(trash::CaseSensitivity::Sensitive);
+        assert!(!ctx.contains(name.to_uppercase()));
+        ctx.set_case_sensitivity(trash::CaseSensitivity::Insensitive);
+        assert!(ctx.contains(name.to_uppercase()));
+
+        // The item that was deleted last is found once the first one is gone.
+        let mut found = trash::os_limited::find(&name).unwrap();
+        trash::os_limited::purge_all([found.remove(0)]).unwrap();
+        assert!(trash::os_limited::contains(&name));
+        trash::os_limited::purge_all(found).unwrap();
+        assert!(!trash::os_limited::contains(&name));
+    }
+
+    #[test]
+    #[serial]
+    fn self_test_in_current_dir() {
+        init_logging();
+        trash::os_limited::self_test_in(std::env::current_dir().unwrap()).unwrap();
+        let missing = format!("{}-missing", get_unique_name());
+        assert!(matches!(trash::os_limited::self_test_in(missing), Err(trash::Error::CouldNotAccess { .. })));
+    }
+
+    #[test]
+    #[serial]
+    fn list_in_display_locale() {
+        init_logging();
+        let name = get_unique_name();
+        File::create(&name).unwrap();
+        trash::delete(&name).unwrap();
+
+        let mut ctx = trash::TrashContext::default();
+        for locale in [trash::DisplayLocale::Raw, trash::DisplayLocale::Language("en-US".into())] {
+            ctx.set_display_locale(locale);
+            let items: Vec<_> = ctx.list().unwrap().into_iter().filter(|x| x.name == name).collect();
+            assert_eq!(items.len(), 1);
+        }
+        assert_eq!(ctx.display_locale(), &trash::DisplayLocale::Language("en-US".into()));
+        trash::os_limited::purge_all(ctx.find(&name).unwrap()).unwrap();
+    }
+
+    #[test]
+    #[serial]
+    fn list_limit() {
+        init_logging();
+        let names: Vec<_> = (0..2).map(|_| get_unique_name()).collect();
+        for name in &names {
+            File::create(name).unwrap();
+        }
+        trash::delete_all(&names).unwrap();
+
+        let mut ctx = trash::TrashContext::default();
+        let count = ctx.list().unwrap().len();
+        ctx.set_list_limit(Some(count));
+        assert_eq!(ctx.list().unwrap().len(), count);
+        ctx.set_list_limit(Some(count - 1));
+        assert!(matches!(ctx.list(), Err(trash::Error::TooManyItems { limit }) if limit == count - 1));
+
+        ctx.set_list_limit(None);
+        let items: Vec<_> = ctx.list().unwrap().into_iter().filter(|x| names.contains(&x.name)).collect();
+        trash::os_limited::purge_all(items).unwrap();
+    }
+
+    #[test]
+    #[serial]
+    fn delete_dir_filtered() {
+        init_logging();
+        let dir = PathBuf::from(get_unique_name());
+        for folder in [".git", "build", "src"] {
+            std::fs::create_dir_all(dir.join(folder)).unwrap();
+        }
+        for file in [".git/HEAD", "build/out.o", "src/main.rs", "src/notes.keep"] {
+            File::create(dir.join(file)).unwrap();
+        }
+        let mut trashed = trash::os_limited::delete_dir_filtered(&dir, [".git", "*.keep"]).unwrap();
+        trashed.sort();
+        let dir = dir.canonicalize().unwrap();
+        assert_eq!(trashed, [dir.join("build"), dir.join("src").join("main.rs")]);
+        assert!(dir.join(".git/HEAD").exists());
+        assert!(dir.join("src/notes.keep").exists());
+        assert!(!dir.join("build").exists());
+
+        let items: Vec<_> =
+            trashed.iter().flat_map(|path| trash::TrashContext::default().find(path).unwrap()).collect();
+        assert_eq!(items.len(), 2);
+        trash::os_limited::purge_all(items).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    #[serial]
+    fn list_volumes() {
+        init_logging();
+        let name = get_unique_name();
+        File::create(&name).unwrap();
+        let destination = trash::destination_for(&name).unwrap();
+        std::fs::remove_file(&name).unwrap();
+
+        let volumes = trash::os_limited::list_volumes().unwrap();
+        let volume = volumes.iter().find(|v| v.root == destination.volume).unwrap();
+        assert_eq!(volume.trash_root, destination.trash_root);
+        assert!(volume.available);
+        assert!(volume.free_bytes.is_some());
+    }
+
+    #[test]
+    #[serial]
+    fn list_on_volume() {
+        init_logging();
+        let name = get_unique_name();
+        File::create(&name).unwrap();
+        let destination = trash::destination_for(&name).unwrap();
+        trash::delete(&name).unwrap();
+
+        for volume in trash::os_limited::list_volumes().unwrap() {
+            let items = trash::os_limited::list_on_volume(&volume).unwrap();
+            assert!(items.iter().all(|item| item.trash_root().as_ref() == Some(&volume.trash_root)));
+            let found = items.iter().filter(|item| item.name == name).count();
+            assert_eq!(found, usize::from(volume.trash_root == destination.trash_root));
+        }
+        trash::os_limited::purge_all(trash::os_limited::find(&name).unwrap()).unwrap();
+    }
+
+    #[test]
+    #[serial]
+    fn admin_restore_own_items() {
+        init_logging();
+        #[cfg(unix)]
+        let owner = trash::admin::TrashOwner::from_uid(unsafe { libc::getuid() }).unwrap();
+        #[cfg(windows)]
+        let owner = trash::admin::TrashOwner::from_name(&std::env::var("USERNAME").unwrap()).unwrap();
+
+        let name = get_unique_name();
+        File::create(&name).unwrap();
+        trash::delete(&name).unwrap();
+        let items: Vec<_> = trash::admin::list(&owner).unwrap().into_iter().filter(|x| x.name == name).collect();
+        assert_eq!(items.len(), 1);
+        trash::admin::restore_all(&owner, items).unwrap();
+        assert!(File::open(&name).is_ok());
+        std::fs::remove_file(&name).unwrap();
+    }
+
+    #[test]
+    #[serial]
+    fn restore_and_purge_by_id() {
+        init_logging();
+        let name = get_unique_name();
+        File::create(&name).unwrap();
+        trash::delete(&name).unwrap();
+        let id = trash::os_limited::find(&name).unwrap().remove(0).id;
+        trash::os_limited::restore_by_id(&id).unwrap();
+        assert!(File::open(&name).is_ok());
+        assert!(matches!(trash::os_limited::purge_by_id(&id), Err(trash::Error::TargetedItemNotFound { .. })));
+
+        trash::delete(&name).unwrap();
+        let id = trash::os_limited::find(&name).unwrap().remove(0).id;
+        trash::os_limited::purge_by_id(&id).unwrap();
+        assert!(trash::os_limited::find(&name).unwrap().is_empty());
+        assert!(trash::os_limited::purge_by_id(&name).is_err());
+
+        File::create(&name).unwrap();
+        trash::delete(&name).unwrap();
+        let item = trash::os_limited::find(&name).unwrap().remove(0);
+        let missing = std::path::Path::new(&item.id).with_extension("missing.trashinfo");
+        let results = trash::os_limited::resolve_ids([item.id.as_os_str(), missing.as_os_str()]);
+        assert_eq!(results[0].as_ref().unwrap(), &item);
+        assert!(matches!(results[1], Err(trash::Error::TargetedItemNotFound { .. })));
+        trash::os_limited::purge_all([item]).unwrap();
+    }
+
+    #[cfg(all(unix, not(target_os = "macos"), not(target_os = "ios"), not(target_os = "android")))]
+    #[test]
+    #[serial]
+    fn uri_list() {
+        init_logging();
+        let name = format!("{} with space", get_unique_name());
+        File::create(&name).unwrap();
+        trash::delete(&name).unwrap();
+        let items = trash::os_limited::find(&name).unwrap();
+        let uri_list = trash::os_limited::uri_list(&items);
+        assert!(uri_list.starts_with("file:///"));
+        assert!(uri_list.ends_with("%20space\r\n"));
+        trash::os_limited::purge_all(items).unwrap();
+    }
+
+    #[test]
+    #[serial]
+    fn snapshot_diff() {
+        init_logging();
+        let names: Vec<_> = (0..2).map(|i| format!("{}#{}", get_unique_name(), i)).collect();
+        for path in names.iter() {
+            File::create(path).unwrap();
+        }
+        trash::delete(&names[0]).unwrap();
+        let before = trash::os_limited::snapshot().unwrap();
+        let first = trash::os_limited::list().unwrap().into_iter().find(|x| x.name == names[0]).unwrap();
+        assert!(before.contains(&first.id));
+
+        trash::delete(&names[1]).unwrap();
+        trash::os_limited::purge_all([&first]).unwrap();
+        let changes = trash::os_limited::diff(&before).unwrap();
+        let added: Vec<_> = changes.added.iter().filter(|x| names.contains(&x.name)).collect();
+        assert_eq!(added.len(), 1);
+        assert_eq!(added[0].name, names[1]);
+        assert!(changes.removed.contains(&first.id));
+
+        let restored = trash::os_limited::TrashSnapshot::from_ids(before.ids().map(ToOwned::to_owned));
+        assert_eq!(restored, before);
+        trash::os_limited::purge_all(added).unwrap();
+    }
+
+    #[test]
+    #[serial]
+    fn list_order() {
+        init_logging();
+        let names: Vec<_> = (0..3).map(|i| format!("{}#{}", get_unique_name(), i)).collect();
+        for path in names.iter() {
+            File::create(path).unwrap();
+        }
+        trash::delete_all(&names).unwrap();
+        let items = trash::os_limited::list().unwrap();
+        assert!(items.windows(2).all(|pair| (pair[1].time_deleted, &pair[0].id) < (pair[0].time_deleted, &pair[1].id)));
+        trash::os_limited::purge_all(items.iter().filter(|x| names.contains(&x.name))).unwrap();
+    }
+
+    #[test]
+    #[serial]
+    fn plan_restore_reasons() {
+        use trash::os_limited::UnrestorableReason;
+
+        init_logging();
+        let prefix = get_unique_name();
+        let (taken, twin, folder) = (format!("{prefix}-taken"), format!("{prefix}-twin"), format!("{prefix}-folder"));
+        let in_folder = format!("{folder}/file");
+        File::create(&taken).unwrap();
+        trash::delete(&taken).unwrap();
+        File::create(&taken).unwrap();
+        for _ in 0..2 {
+            File::create(&twin).unwrap();
+            trash::delete(&twin).unwrap();
+        }
+        std::fs::create_dir(&folder).unwrap();
+        File::create(&in_folder).unwrap();
+        trash::delete(&in_folder).unwrap();
+        std::fs::remove_dir(&folder).unwrap();
+        File::create(&folder).unwrap();
+
+        let items: Vec<_> = trash::os_limited::list()
+            .unwrap()
+            .into_iter()
+            .filter(|x| x.original_path().unwrap().to_string_lossy().contains(&prefix))
+            .collect();
+        let plan = trash::os_limited::plan_restore(items.clone());
+        let reason_of = |name: &str| -> Vec<_> {
+            plan.items
+                .iter()
+                .filter(|planned| planned.item.original_path().unwrap().ends_with(name))
+                .map(|planned| planned.blocked_by)
+                .collect()
+        };
+        assert_eq!(reason_of(&taken), [Some(UnrestorableReason::TargetExists)]);
+        // Only the first of the twins is planned to be restored.
+        assert_eq!(reason_of(&twin), [None, Some(UnrestorableReason::DuplicateOriginalPath)]);
+        assert_eq!(reason_of(&in_folder), [Some(UnrestorableReason::ParentIsFile)]);
+        assert_eq!(plan.restorable().count(), 1);
+        assert!(!plan.is_restorable());
+
+        std::fs::remove_file(&taken).unwrap();
+        std::fs::remove_file(&folder).unwrap();
+        trash::os_limited::purge_all(items).unwrap();
+    }
+
+    #[test]
+    #[serial]
+    fn plan_restore_ignoring_case() {
+        use trash::os_limited::UnrestorableReason;
+
+        init_logging();
+        let prefix = get_unique_name();
+        let (taken, twin) = (format!("{prefix}-taken"), format!("{prefix}-twin"));
+        File::create(&taken).unwrap();
+        trash::delete(&taken).unwrap();
+        File::create(taken.to_uppercase()).unwrap();
+        File::create(&twin).unwrap();
+        trash::delete(&twin).unwrap();
+        File::create(twin.to_uppercase()).unwrap();
+        trash::delete(twin.to_uppercase()).unwrap();
+
+        let items: Vec<_> = trash::os_limited::list()
+            .unwrap()
+            .into_iter()
+            .filter(|x| x.name.to_lowercase().starts_with(&prefix))
+            .collect();
+        let mut ctx = trash::TrashContext::default();
+        ctx.set_case_sensitivity(trash::CaseSensitivity::Insensitive);
+        let plan = ctx.plan_restore(items.clone());
+        let mut reasons: Vec<_> = plan.blocked().map(|(item, reason)| (item.name.to_lowercase(), reason)).collect();
+        reasons.sort_by(|a, b| a.0.cmp(&b.0));
+        assert_eq!(
+            reasons,
+            [(taken.clone(), UnrestorableReason::TargetExists), (twin, UnrestorableReason::DuplicateOriginalPath)]
+        );
+
+        std::fs::remove_file(taken.to_uppercase()).unwrap();
+        trash::os_limited::purge_all(items).unwrap();
+    }
+
+    #[test]
+    #[serial]
+    fn cache_follows_changes() {
+        init_logging();
+        let name = get_unique_name();
+        let cache = trash::os_limited::TrashCache::new().unwrap();
+        let items_before = cache.stats().items;
+        File::create(&name).unwrap();
+        trash::delete(&name).unwrap();
+
+        let wait_for = |found: usize| {
+            let deadline = std::time::Instant::now() + std::time::Duration::from_secs(10);
+            while cache.find(&name).unwrap().len() != found && std::time::Instant::now() < deadline {
+                std::thread::sleep(std::time::Duration::from_millis(50));
+            }
+            cache.find(&name).unwrap()
+        };
+        let found = wait_for(1);
+        assert_eq!(found.len(), 1);
+        assert_eq!(cache.get(&found[0].id), Some(found[0].clone()));
+        assert!(cache.stats().items > items_before);
+
+        trash::os_limited::purge_all(&found).unwrap();
+        assert!(wait_for(0).is_empty());
+    }
+
     #[test]
     #[serial]
     fn restore() {
@@ -163,6 +1229,253 @@ mod os_limited {
         assert_eq!(missing, Vec::<&String>::new());
     }
 
+    #[test]
+    #[serial]
+    fn restore_concurrently() {
+        init_logging();
+        let file_name_prefix = get_unique_name();
+        let names: Vec<_> = (0..6).map(|i| format!("{}#{}", file_name_prefix, i)).collect();
+        for path in names.iter() {
+            File::create(path).unwrap();
+        }
+        trash::delete_all(&names).unwrap();
+        // Another item takes the place of one of them, so that restoring it collides.
+        File::create(&names[4]).unwrap();
+
+        let mut ctx = trash::TrashContext::new();
+        ctx.set_restore_concurrency(trash::RestoreConcurrency { volumes: 2, per_volume: 3 });
+        #[cfg(target_os = "windows")]
+        trash::windows::TrashContextExtWindows::set_single_restore_operation(&mut ctx, false);
+        let targets: Vec<_> =
+            trash::os_limited::list().unwrap().into_iter().filter(|x| x.name.starts_with(&file_name_prefix)).collect();
+        let remaining = match ctx.restore_all(targets) {
+            Err(trash::Error::RestoreCollision { remaining_items, .. }) => remaining_items,
+            other => panic!("expected a collision, got {other:?}"),
+        };
+        // The share of the colliding item stops, the others carry on.
+        assert!(remaining.iter().any(|item| item.name == names[4]));
+        assert!(names[..4].iter().filter(|name| std::path::Path::new(name).is_file()).count() >= 2);
+
+        trash::os_limited::purge_all(&remaining).unwrap();
+        for path in names.iter() {
+            std::fs::remove_file(path).ok();
+        }
+    }
+
+    #[test]
+    #[serial]
+    fn restore_by_original_path() {
+        init_logging();
+        let name = get_unique_name();
+        for contents in ["first", "second"] {
+            std::fs::write(&name, contents).unwrap();
+            trash::delete(&name).unwrap();
+        }
+
+        trash::os_limited::restore_by_original_path(&name).unwrap();
+        assert!(PathBuf::from(&name).exists());
+        let remaining = trash::os_limited::find(&name).unwrap();
+        assert_eq!(remaining.len(), 1);
+        std::fs::remove_file(&name).unwrap();
+        trash::os_limited::purge_all(remaining).unwrap();
+
+        match trash::os_limited::restore_by_original_path(&name) {
+            Err(trash::Error::TargetedItemNotFound { path }) => assert!(path.ends_with(&name)),
+            other => panic!("restore_by_original_path was expected to fail with TargetedItemNotFound: {other:?}"),
+        }
+    }
+
+    #[test]
+    #[serial]
+    fn pin_items() {
+        init_logging();
+        let name = get_unique_name();
+        File::create_new(&name).unwrap();
+        trash::delete(&name).unwrap();
+        let items = trash::os_limited::find(&name).unwrap();
+        assert!(!items[0].pinned);
+
+        // The trash info file keeps its modification time.
+        #[cfg(unix)]
+        let modified = || std::fs::metadata(&items[0].id).unwrap().modified().unwrap();
+        #[cfg(unix)]
+        let before = modified();
+        trash::os_limited::pin(&items).unwrap();
+        let pinned = trash::os_limited::find(&name).unwrap();
+        assert!(pinned[0].pinned);
+        #[cfg(unix)]
+        assert_eq!(modified(), before);
+        assert_eq!(pinned[0].original_path(), items[0].original_path());
+        trash::os_limited::unpin(&pinned).unwrap();
+        assert!(!trash::os_limited::find(&name).unwrap()[0].pinned);
+
+        // Pinned items can still be purged explicitly.
+        trash::os_limited::pin(&items).unwrap();
+        trash::os_limited::purge_all(&items).unwrap();
+        assert!(trash::os_limited::find(&name).unwrap().is_empty());
+    }
+
+    #[test]
+    #[serial]
+    fn restore_merging_folders() {
+        use trash::os_limited::{MergeAction, MergeEntry};
+        use trash::MergePolicy;
+
+        init_logging();
+        let dir = PathBuf::from(get_unique_name());
+        std::fs::create_dir_all(dir.join("sub")).unwrap();
+        for (name, contents) in [("a.txt", "trashed a"), ("c.txt", "trashed c"), ("sub/b.txt", "trashed b")] {
+            std::fs::write(dir.join(name), contents).unwrap();
+        }
+        trash::delete(&dir).unwrap();
+        std::fs::create_dir_all(dir.join("sub")).unwrap();
+        std::fs::write(dir.join("a.txt"), "new a").unwrap();
+        let item = trash::os_limited::find(&dir).unwrap().remove(0);
+
+        let plan = trash::os_limited::plan_merge(&item, MergePolicy::SkipExisting).unwrap();
+        let entry = |path: &str, action| MergeEntry { path: path.into(), action };
+        assert_eq!(
+            plan.entries,
+            [
+                entry("a.txt", MergeAction::Keep),
+                entry("c.txt", MergeAction::Move),
+                entry(&format!("sub{}b.txt", std::path::MAIN_SEPARATOR), MergeAction::Move)
+            ]
+        );
+        assert!(!plan.is_complete());
+
+        // Skipped files stay in the trash along with the folder.
+        let ctx = trash::TrashContext::builder().directory_merge(MergePolicy::SkipExisting).build();
+        ctx.restore_all([item]).unwrap();
+        assert_eq!(std::fs::read_to_string(dir.join("a.txt")).unwrap(), "new a");
+        assert_eq!(std::fs::read_to_string(dir.join("c.txt")).unwrap(), "trashed c");
+        assert_eq!(std::fs::read_to_string(dir.join("sub").join("b.txt")).unwrap(), "trashed b");
+        let item = trash::os_limited::find(&dir).unwrap().remove(0);
+
+        let ctx = trash::TrashContext::builder().directory_merge(MergePolicy::Overwrite).build();
+        assert!(ctx.plan_merge(&item, MergePolicy::Overwrite).unwrap().is_complete());
+        ctx.restore_all([item]).unwrap();
+        assert_eq!(std::fs::read_to_string(dir.join("a.txt")).unwrap(), "trashed a");
+        assert!(trash::os_limited::find(&dir).unwrap().is_empty());
+        // The replaced file is kept in the trash.
+        let replaced = trash::os_limited::find(dir.join("a.txt")).unwrap();
+        assert_eq!(replaced.len(), 1);
+        trash::os_limited::purge_all(replaced).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    #[serial]
+    fn restore_to_other_folder() {
+        init_logging();
+        let source = PathBuf::from(get_unique_name());
+        let target = PathBuf::from(get_unique_name());
+        std::fs::create_dir(&source).unwrap();
+        std::fs::write(source.join("a.txt"), "alpha").unwrap();
+        trash::delete(source.join("a.txt")).unwrap();
+        std::fs::remove_dir(&source).unwrap();
+
+        let items = trash::os_limited::find(source.join("a.txt")).unwrap();
+        assert_eq!(items.len(), 1);
+        trash::os_limited::restore_all_to(items, &target).unwrap();
+        assert_eq!(std::fs::read_to_string(target.join("a.txt")).unwrap(), "alpha");
+        assert!(!source.exists());
+        assert!(trash::os_limited::find(source.join("a.txt")).unwrap().is_empty());
+        std::fs::remove_dir_all(&target).unwrap();
+    }
+
+    #[test]
+    #[serial]
+    fn restore_with_transform() {
+        init_logging();
+        let source = std::env::current_dir().unwrap().join(get_unique_name());
+        let target = std::env::current_dir().unwrap().join(get_unique_name());
+        std::fs::create_dir(&source).unwrap();
+        std::fs::create_dir(&target).unwrap();
+        std::fs::write(source.join("a.txt"), "alpha").unwrap();
+        std::fs::write(source.join("b.txt"), "beta").unwrap();
+        trash::delete_all([source.join("a.txt"), source.join("b.txt")]).unwrap();
+        std::fs::remove_dir(&source).unwrap();
+        std::fs::write(target.join("b.txt"), "taken").unwrap();
+
+        let (from, to) = (source.canonicalize().unwrap_or(source.clone()), target.canonicalize().unwrap());
+        let ctx = trash::TrashContext::new()
+            .with_restore_transform(move |path| to.join(path.strip_prefix(&from).unwrap_or(path)));
+        let mut items = trash::os_limited::find(source.join("a.txt")).unwrap();
+        items.extend(trash::os_limited::find(source.join("b.txt")).unwrap());
+        let plan = ctx.plan_restore(items);
+        let blocked: Vec<_> = plan.blocked().map(|(item, reason)| (item.name.as_str(), reason)).collect();
+        assert_eq!(blocked, [("b.txt", trash::os_limited::UnrestorableReason::TargetExists)]);
+
+        let b = plan.blocked().map(|(item, _)| item.clone()).collect::<Vec<_>>();
+        ctx.restore_all(plan.into_restorable()).unwrap();
+        assert_eq!(std::fs::read_to_string(target.join("a.txt")).unwrap(), "alpha");
+        assert!(!source.exists());
+
+        let rootless = trash::TrashContext::new().with_restore_transform(|_: &std::path::Path| PathBuf::from("/"));
+        let plan = rootless.plan_restore(b.clone());
+        let blocked: Vec<_> = plan.blocked().map(|(_, reason)| reason).collect();
+        assert_eq!(blocked, [trash::os_limited::UnrestorableReason::InvalidTarget]);
+        assert!(rootless.restore_all(b.clone()).is_err());
+        trash::os_limited::purge_all(b).unwrap();
+        std::fs::remove_dir_all(&target).unwrap();
+    }
+
+    #[test]
+    #[serial]
+    fn metrics() {
+        init_logging();
+        let name = get_unique_name();
+        std::fs::write(&name, "12345").unwrap();
+        let before = trash::os_limited::metrics().unwrap();
+        trash::delete(&name).unwrap();
+        let after = trash::os_limited::metrics().unwrap();
+        assert_eq!(after.item_count, before.item_count + 1);
+        assert_eq!(after.total_bytes, before.total_bytes + trash::ByteSize::new(5));
+
+        trash::os_limited::purge_all(trash::os_limited::find(&name).unwrap()).unwrap();
+        assert_eq!(trash::os_limited::metrics().unwrap(), before);
+    }
+
+    #[test]
+    #[serial]
+    fn restore_unknown_location() {
+        init_logging();
+        let unknown = get_unique_name();
+        let known = get_unique_name();
+        let target = PathBuf::from(get_unique_name());
+        File::create(&unknown).unwrap();
+        File::create(&known).unwrap();
+        trash::delete_all([&unknown, &known]).unwrap();
+
+        let mut items: Vec<_> = trash::os_limited::list()
+            .unwrap()
+            .into_iter()
+            .filter(|item| item.name == unknown || item.name == known)
+            .collect();
+        assert_eq!(items.len(), 2);
+        for item in items.iter_mut().filter(|item| item.name == unknown) {
+            item.original_parent = None;
+            item.unknown_reason = Some(trash::UnknownReason::Unreadable);
+        }
+        let (unknown_items, remaining_items) = match trash::os_limited::restore_all(items) {
+            Err(trash::Error::OriginalLocationUnknown { unknown_items, remaining_items }) => {
+                (unknown_items, remaining_items)
+            }
+            _ => panic!("restore_all was expected to return `trash::Error::OriginalLocationUnknown` but did not."),
+        };
+        assert!(unknown_items.iter().all(|item| item.name == unknown && item.is_degraded()));
+        assert!(remaining_items.iter().all(|item| item.name == known));
+        assert!(!PathBuf::from(&known).exists());
+
+        trash::os_limited::restore_all(remaining_items).unwrap();
+        trash::os_limited::restore_all_to(unknown_items, &target).unwrap();
+        assert!(PathBuf::from(&known).exists());
+        assert!(target.join(&unknown).exists());
+        std::fs::remove_file(&known).unwrap();
+        std::fs::remove_dir_all(&target).unwrap();
+    }
+
     #[test]
     #[serial]
     fn restore_collision() {
@@ -218,6 +1531,39 @@ mod os_limited {
         }
     }
 
+    #[test]
+    #[serial]
+    fn restore_collision_policies() {
+        use trash::RestoreCollision;
+
+        init_logging();
+        let dir = PathBuf::from(get_unique_name());
+        std::fs::create_dir(&dir).unwrap();
+        for (policy, name) in [
+            (RestoreCollision::Skip, "skip.txt"),
+            (RestoreCollision::Overwrite, "overwrite.txt"),
+            (RestoreCollision::RenameWithSuffix, "rename.txt"),
+        ] {
+            let path = dir.join(name);
+            std::fs::write(&path, "trashed").unwrap();
+            trash::delete(&path).unwrap();
+            std::fs::write(&path, "existing").unwrap();
+            let ctx = trash::TrashContext::builder().restore_collision(policy).build();
+            ctx.restore_all(trash::os_limited::find(&path).unwrap()).unwrap();
+            let expected = if policy == RestoreCollision::Overwrite { "trashed" } else { "existing" };
+            assert_eq!(std::fs::read_to_string(&path).unwrap(), expected);
+        }
+        assert_eq!(std::fs::read_to_string(dir.join("rename (2).txt")).unwrap(), "trashed");
+        let skipped = trash::os_limited::find(dir.join("skip.txt")).unwrap();
+        assert_eq!(skipped.len(), 1);
+        trash::os_limited::purge_all(skipped).unwrap();
+        // The overwritten file is kept in the trash.
+        let overwritten = trash::os_limited::find(dir.join("overwrite.txt")).unwrap();
+        assert_eq!(overwritten.len(), 1);
+        trash::os_limited::purge_all(overwritten).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
     #[test]
     #[serial]
     fn restore_twins() {
@@ -0,0 +1,44 @@
+//! Deleting items while finding the ids they get in the trash, for the operations that need to
+//! restore exactly the items they moved.
+
+use std::{
+    collections::{HashMap, HashSet},
+    ffi::OsString,
+    path::{Path, PathBuf},
+};
+
+use crate::{matching, Error, TrashContext, TrashItem};
+
+/// What [`delete_all`] moved to the trash.
+pub(crate) struct Trashed {
+    /// The paths that were moved, in the order they were given.
+    pub(crate) paths: Vec<PathBuf>,
+    /// The ids the moved paths got in the trash.
+    pub(crate) ids: Vec<OsString>,
+    /// The result of the delete.
+    pub(crate) result: Result<(), Error>,
+}
+
+/// Deletes the items at the absolute `paths` and finds the ids they got in the trash.
+///
+/// The trash is listed once before and once after the delete, and an item is taken to be a path's
+/// if it was deleted from that path and only appears in the second listing. Items that were in
+/// the trash already, and paths that weren't moved, are thereby left out.
+pub(crate) fn delete_all(ctx: &TrashContext, paths: &[PathBuf]) -> Result<Trashed, Error> {
+    let mut ctx = ctx.clone();
+    ctx.set_list_limit(None);
+    let key = |path: &Path| matching::comparison_key(path, ctx.case_sensitivity().is_sensitive_for(path));
+    let wanted: HashMap<PathBuf, usize> = paths.iter().enumerate().map(|(index, path)| (key(path), index)).collect();
+    let items_of_paths = |items: Vec<TrashItem>| {
+        items.into_iter().filter_map(|item| Some((*wanted.get(&key(&item.original_path()?))?, item.id)))
+    };
+    let before: HashSet<OsString> = items_of_paths(ctx.list()?).map(|(_, id)| id).collect();
+    let result = ctx.delete_all(paths);
+    let mut found: Vec<Option<OsString>> = vec![None; paths.len()];
+    // The listing is ordered from the newest item, which is kept if a path was deleted twice.
+    for (index, id) in items_of_paths(ctx.list()?).filter(|(_, id)| !before.contains(id)) {
+        found[index].get_or_insert(id);
+    }
+    let (paths, ids) = paths.iter().zip(found).filter_map(|(path, id)| Some((path.clone(), id?))).unzip();
+    Ok(Trashed { paths, ids, result })
+}
@@ -0,0 +1,72 @@
+//! Writing the info files of freedesktop trash folders. Besides the freedesktop platform, Windows
+//! writes them for the items it moves to the trash of a WSL distribution.
+
+use std::{
+    ffi::{OsStr, OsString},
+    fs::{self, OpenOptions},
+    io::{self, Write},
+    path::Path,
+};
+
+/// The bytes that are written as they are in the `Path` of a trash info file, the same ones as
+/// GLib leaves unescaped in file paths. All other bytes are percent-encoded.
+pub(crate) fn is_unescaped_path_byte(byte: u8) -> bool {
+    byte.is_ascii_alphanumeric() || b"!$&'()*+,-./:=@_~".contains(&byte)
+}
+
+/// Percent-encodes the bytes of a path for the `Path` of a trash info file.
+pub(crate) fn encode_path(bytes: &[u8]) -> String {
+    let mut encoded = String::with_capacity(bytes.len());
+    for &byte in bytes {
+        if is_unescaped_path_byte(byte) {
+            encoded.push(char::from(byte));
+        } else {
+            encoded.push_str(&format!("%{byte:02X}"));
+        }
+    }
+    encoded
+}
+
+/// The name of an item within the `files` folder of a trash and the name of its info file, for
+/// the `attempt`th try to find a free name, starting at 1.
+pub(crate) fn names(file_name: &OsStr, attempt: usize) -> (OsString, OsString) {
+    let mut in_trash_name = file_name.to_owned();
+    if attempt > 1 {
+        in_trash_name.push(format!(".{attempt}"));
+    }
+    let mut info_name = in_trash_name.clone();
+    info_name.push(".trashinfo");
+    (in_trash_name, info_name)
+}
+
+/// Creates the info file at `info_file_path`, which reserves the name of the item in the `files`
+/// folder. Fails with [`io::ErrorKind::AlreadyExists`] if the name is taken. `deletion_date` is in
+/// the `YYYY-MM-DDThh:mm:ss` format, in local time.
+pub(crate) fn create(info_file_path: &Path, encoded_path: &str, deletion_date: Option<&str>) -> io::Result<()> {
+    let mut file = OpenOptions::new().create_new(true).write(true).open(info_file_path)?;
+    let mut contents = format!("[Trash Info]\nPath={encoded_path}\n");
+    if let Some(deletion_date) = deletion_date {
+        contents.push_str(&format!("DeletionDate={deletion_date}\n"));
+    }
+    file.write_all(contents.as_bytes()).inspect_err(|_| {
+        // A partial info file would list an item that isn't in the trash.
+        let _ = fs::remove_file(info_file_path);
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encoded_paths() {
+        assert_eq!(encode_path(b"/a b/100%/\xC3\xA9"), "/a%20b/100%25/%C3%A9");
+        assert_eq!(encode_path(b"/home/me/a_b-c.txt"), "/home/me/a_b-c.txt");
+    }
+
+    #[test]
+    fn numbered_names() {
+        assert_eq!(names(OsStr::new("a.txt"), 1), ("a.txt".into(), "a.txt.trashinfo".into()));
+        assert_eq!(names(OsStr::new("a.txt"), 3), ("a.txt.3".into(), "a.txt.3.trashinfo".into()));
+    }
+}
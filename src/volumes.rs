@@ -0,0 +1,108 @@
+//! Describes the trash of every mounted volume, for a per-disk view of the trash.
+
+use std::path::{Path, PathBuf};
+
+use log::debug;
+
+#[cfg(windows)]
+use crate::platform::free_bytes;
+use crate::{platform, ByteSize, Error, TrashContext, TrashItem, TrashTransfer};
+
+/// A mounted volume and its trash, as returned by [`list_volumes`](crate::os_limited::list_volumes).
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TrashVolume {
+    /// The root of the volume (mount point or drive).
+    pub root: PathBuf,
+    /// The trash folder that items on the volume are moved into. It doesn't necessarily exist yet,
+    /// in which case it's created by the first delete operation.
+    pub trash_root: PathBuf,
+    /// Whether items on the volume can be moved to the trash by the current user. This is `false`
+    /// if the trash folder can't be created or written to, and for volumes without a trash.
+    pub available: bool,
+    /// The free space on the volume that's available to the current user, if it can be
+    /// determined.
+    pub free_bytes: Option<ByteSize>,
+    /// Whether items at the root of the volume would be renamed or copied into the trash.
+    pub transfer: TrashTransfer,
+}
+
+impl TrashContext {
+    /// Returns the mounted volumes along with their trash, ordered by their root.
+    ///
+    /// Volumes whose trash can't be determined, like drives without a medium, are left out.
+    ///
+    /// See: [`os_limited::list_volumes`](crate::os_limited::list_volumes)
+    pub fn list_volumes(&self) -> Result<Vec<TrashVolume>, Error> {
+        let mut volumes = Vec::new();
+        for root in platform::volumes()? {
+            let (trash_root, _, transfer) = match self.destination_canonicalized(&root) {
+                Ok(destination) => destination,
+                Err(e) => {
+                    debug!("Leaving {:?} out as its trash can't be determined: {:?}", root, e);
+                    continue;
+                }
+            };
+            let available = transfer != TrashTransfer::PermanentDelete && can_write_to(&trash_root);
+            let free_bytes = free_bytes(&root);
+            volumes.push(TrashVolume { root, trash_root, available, free_bytes, transfer });
+        }
+        volumes.sort_by(|a, b| a.root.cmp(&b.root));
+        Ok(volumes)
+    }
+
+    /// Lists the items in the trash of `volume`, which are those whose
+    /// [`trash_root`](TrashItem::trash_root) is the `trash_root` of the volume. Together with
+    /// [`purge_all`](TrashContext::purge_all), this empties the trash of a single volume.
+    ///
+    /// On Windows this enumerates the `$Recycle.Bin` folder of the drive, rather than the Recycle
+    /// Bin that the shell merges from all drives. On freedesktop systems only the info files of
+    /// the trash folder of the volume are read. The items are listed in no particular order, and
+    /// like [`list`](crate::os_limited::list) this fails once there are more items than the
+    /// [`list_limit`](TrashContext::list_limit).
+    ///
+    /// See: [`os_limited::list_on_volume`](crate::os_limited::list_on_volume)
+    pub fn list_on_volume(&self, volume: &TrashVolume) -> Result<Vec<TrashItem>, Error> {
+        if volume.trash_root.symlink_metadata().is_err() {
+            // Nothing was deleted on the volume yet.
+            return Ok(Vec::new());
+        }
+        self.list_items_in(&volume.trash_root)
+    }
+}
+
+/// Whether the current user may write to `folder`, or to the closest existing ancestor that it
+/// would be created in.
+#[cfg(unix)]
+fn can_write_to(folder: &Path) -> bool {
+    use std::os::unix::ffi::OsStrExt;
+
+    let Some(existing) = folder.ancestors().find(|p| p.symlink_metadata().is_ok()) else {
+        return false;
+    };
+    let Ok(existing) = std::ffi::CString::new(existing.as_os_str().as_bytes()) else {
+        return false;
+    };
+    unsafe { libc::access(existing.as_ptr(), libc::W_OK | libc::X_OK) == 0 }
+}
+
+/// The Recycle Bin is maintained by the shell, which creates it on every drive that supports it.
+#[cfg(windows)]
+fn can_write_to(folder: &Path) -> bool {
+    folder.ancestors().any(Path::exists)
+}
+
+/// The free space that's available to unprivileged users, excluding the blocks reserved for root.
+#[cfg(unix)]
+fn free_bytes(root: &Path) -> Option<ByteSize> {
+    use std::os::unix::ffi::OsStrExt;
+
+    let root = std::ffi::CString::new(root.as_os_str().as_bytes()).ok()?;
+    let mut stats = std::mem::MaybeUninit::<libc::statvfs>::uninit();
+    if unsafe { libc::statvfs(root.as_ptr(), stats.as_mut_ptr()) } != 0 {
+        return None;
+    }
+    let stats = unsafe { stats.assume_init() };
+    #[allow(clippy::useless_conversion)] // The field types differ between platforms.
+    Some(ByteSize::new(u64::from(stats.f_bavail).saturating_mul(u64::from(stats.f_frsize))))
+}
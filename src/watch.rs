@@ -0,0 +1,196 @@
+//! Following the changes to the trash as they happen.
+
+use std::{
+    ffi::OsString,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    thread::JoinHandle,
+    time::{Duration, Instant},
+};
+
+use log::warn;
+
+use crate::{
+    platform,
+    snapshot::{TrashDiff, TrashSnapshot},
+    Error, TrashItem,
+};
+
+/// How often the watcher thread checks whether it should stop.
+const STOP_CHECK_INTERVAL: Duration = Duration::from_millis(200);
+/// How often the trash is listed even if no change was noticed, to pick up trash folders that
+/// were created in places that aren't watched.
+const RESCAN_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Keeps a background thread running that reports the changes to the trash.
+///
+/// Dropping the watcher stops the thread.
+///
+/// Obtained from [`watch`](crate::os_limited::watch).
+#[derive(Debug)]
+pub struct TrashWatcher {
+    stop: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl TrashWatcher {
+    /// Stops watching the trash and waits for the background thread to finish.
+    pub fn stop(self) {}
+}
+
+impl Drop for TrashWatcher {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(thread) = self.thread.take() {
+            // The watcher may be dropped by the callback, which runs on the thread itself.
+            if thread.thread().id() != std::thread::current().id() {
+                let _ = thread.join();
+            }
+        }
+    }
+}
+
+/// How the changes are grouped into the events of
+/// [`watch_with`](crate::os_limited::watch_with).
+///
+/// # Example
+///
+/// ```
+/// use std::time::Duration;
+/// use trash::os_limited::WatchOptions;
+///
+/// // Report the changes once the trash was quiet for a quarter of a second, and large removals,
+/// // like emptying the trash, as a single event.
+/// let options = WatchOptions { debounce: Duration::from_millis(250), max_batch: Some(100) };
+/// ```
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct WatchOptions {
+    /// How long the trash has to stay unchanged before its changes are reported, so that an
+    /// operation on many items is reported once it's done rather than in parts. The changes are
+    /// reported after at most half a minute while the trash keeps changing. Defaults to zero,
+    /// which reports changes as soon as they're noticed.
+    pub debounce: Duration,
+    /// The number of removed items above which the removal is reported as a single
+    /// [`WatchEvent::Emptied`] or [`WatchEvent::BulkRemoved`] rather than as part of a
+    /// [`WatchEvent::Changed`]. Defaults to `None`, which reports every change as
+    /// [`WatchEvent::Changed`].
+    pub max_batch: Option<usize>,
+}
+
+impl WatchOptions {
+    /// Turns a change to the trash into the events to report, given whether the trash is empty
+    /// after it.
+    fn events(&self, diff: TrashDiff, now_empty: bool) -> Vec<WatchEvent> {
+        let bulk = self.max_batch.is_some_and(|max_batch| diff.removed.len() > max_batch);
+        if !bulk {
+            return vec![WatchEvent::Changed(diff)];
+        }
+        if now_empty {
+            return vec![WatchEvent::Emptied];
+        }
+        let mut events = Vec::with_capacity(2);
+        if !diff.added.is_empty() {
+            events.push(WatchEvent::Changed(TrashDiff { added: diff.added, removed: Vec::new() }));
+        }
+        events.push(WatchEvent::BulkRemoved(diff.removed));
+        events
+    }
+}
+
+/// A change to the trash, as reported by [`watch_with`](crate::os_limited::watch_with).
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum WatchEvent {
+    /// Items were added to or removed from the trash.
+    Changed(TrashDiff),
+    /// More than [`max_batch`](WatchOptions::max_batch) items were removed at once, and these are
+    /// their ids. The items that were added at the same time are reported by a
+    /// [`Changed`](WatchEvent::Changed) event right before this one.
+    BulkRemoved(Vec<OsString>),
+    /// More than [`max_batch`](WatchOptions::max_batch) items were removed at once, and the trash
+    /// is empty now, which is what emptying the trash looks like.
+    Emptied,
+}
+
+pub(crate) fn watch<F>(mut callback: F) -> Result<TrashWatcher, Error>
+where
+    F: FnMut(TrashDiff) + Send + 'static,
+{
+    start(Duration::ZERO, move |diff, _| callback(diff)).map(|(watcher, _)| watcher)
+}
+
+pub(crate) fn watch_with<F>(options: WatchOptions, mut callback: F) -> Result<TrashWatcher, Error>
+where
+    F: FnMut(WatchEvent) + Send + 'static,
+{
+    let watching = start(options.debounce, move |diff, snapshot| {
+        options.events(diff, snapshot.is_empty()).into_iter().for_each(&mut callback)
+    });
+    watching.map(|(watcher, _)| watcher)
+}
+
+/// Starts watching the trash and returns the items that were in it when the watcher started.
+/// Every change after that is passed to `callback`, along with the snapshot of the trash after
+/// it. A change is reported once no further change was noticed for `debounce`.
+pub(crate) fn start<F>(debounce: Duration, mut callback: F) -> Result<(TrashWatcher, Vec<TrashItem>), Error>
+where
+    F: FnMut(TrashDiff, &TrashSnapshot) + Send + 'static,
+{
+    // The monitor is set up before listing the trash, so that no change slips through in between.
+    let mut monitor = platform::ChangeMonitor::new();
+    let items = platform::list()?;
+    let mut snapshot = TrashSnapshot::from_items(&items);
+    let stop = Arc::new(AtomicBool::new(false));
+    let thread_stop = stop.clone();
+    let thread = std::thread::Builder::new()
+        .name("trash-watch".into())
+        .spawn(move || {
+            let mut last_scan = Instant::now();
+            while !thread_stop.load(Ordering::Relaxed) {
+                if !monitor.wait(STOP_CHECK_INTERVAL) && last_scan.elapsed() < RESCAN_INTERVAL {
+                    continue;
+                }
+                // Waits for the trash to settle, for no longer than until the next rescan.
+                let first_change = Instant::now();
+                while !debounce.is_zero()
+                    && first_change.elapsed() < RESCAN_INTERVAL
+                    && !thread_stop.load(Ordering::Relaxed)
+                    && monitor.wait(debounce)
+                {}
+                last_scan = Instant::now();
+                monitor.refresh();
+                let diff = match platform::list() {
+                    Ok(items) => snapshot.diff_with(items),
+                    Err(e) => {
+                        warn!("Failed to list the trash while watching it: {:?}", e);
+                        continue;
+                    }
+                };
+                if !diff.is_empty() && !thread_stop.load(Ordering::Relaxed) {
+                    snapshot.apply(&diff);
+                    callback(diff, &snapshot);
+                }
+            }
+        })
+        .map_err(|e| Error::Unknown { description: format!("Failed to start watching the trash: {e}") })?;
+    Ok((TrashWatcher { stop, thread: Some(thread) }, items))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn coalesce_large_removals() {
+        let ids = |count: usize| (0..count).map(|i| OsString::from(i.to_string())).collect::<Vec<_>>();
+        let diff = |removed| TrashDiff { added: Vec::new(), removed };
+        let options = WatchOptions { max_batch: Some(2), ..WatchOptions::default() };
+
+        assert_eq!(options.events(diff(ids(2)), false), [WatchEvent::Changed(diff(ids(2)))]);
+        assert_eq!(options.events(diff(ids(3)), false), [WatchEvent::BulkRemoved(ids(3))]);
+        assert_eq!(options.events(diff(ids(3)), true), [WatchEvent::Emptied]);
+        assert_eq!(WatchOptions::default().events(diff(ids(3)), true), [WatchEvent::Changed(diff(ids(3)))]);
+    }
+}
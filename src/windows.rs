@@ -1,14 +1,109 @@
-use crate::{Error, TrashContext, TrashItem, TrashItemMetadata, TrashItemSize};
+//! Windows specific functionality.
+//!
+//! ### Required privileges
+//!
+//! Operations are performed through the shell (`IFileOperation` and the Recycle Bin shell folder)
+//! with the privileges of the calling process, except that
+//!
+//! - with [`RecycleBinAccess::Direct`], the `$I` and `$R` files within `$Recycle.Bin\<SID>` are
+//!   read and removed directly,
+//! - items whose original path is longer than `MAX_PATH` are restored by renaming their `$R` files,
+//!   as the shell can't handle such paths,
+//! - items within WSL distributions are moved to the trash of the distribution.
+//!
+//! | Operation                         | Privileges needed                                          |
+//! |-----------------------------------|------------------------------------------------------------|
+//! | `delete`, `delete_all`            | Delete access to the items and their parent folder         |
+//! | `os_limited::list`, `metadata`    | Read access to the current user's Recycle Bin              |
+//! | `shell_properties`                | Read access to the current user's Recycle Bin              |
+//! | `os_limited::purge_all`           | Delete access to the current user's Recycle Bin            |
+//! | `os_limited::restore_all`         | Write access to the original parent folders of the items   |
+//! | `os_limited::watch`, `TrashCache` | Read access to the current user's Recycle Bin folders      |
+//!
+//! When one of these is not met the operation fails with [`Error::NeedsElevation`]. By enabling the
+//! minimal permissions mode through [`TrashContextExtWindows::set_minimal_permissions`], the shell is
+//! only ever instantiated in-process, so that no out-of-process (potentially elevating) COM server
+//! is started on behalf of the caller. This is what processes running under restrictive
+//! AppContainer or low integrity level tokens need.
+//!
+//! No operation writes the registry. The maximum size of the Recycle Bin on a volume is read from
+//! the current user's part of the registry to find the items that the shell would delete
+//! permanently as they don't fit. This happens for the [`QuotaPolicy`], for `destination_for` and
+//! `estimate`, and for `delete_all` unless permanent deletes are
+//! [allowed](TrashContextExtWindows::set_allow_permanent_delete) and no
+//! [`TrashHooks`](crate::TrashHooks) are set.
+//!
+//! ### Packaged applications
+//!
+//! Packaged (MSIX) applications and processes running inside an AppContainer are detected
+//! automatically, in which case the shell is always instantiated in-process as well, letting the
+//! file system broker carry out the file operations on behalf of the app. Use
+//! [`sandbox_capabilities`] to find out which features are available in the current sandbox.
+//!
+//! ### COM
+//!
+//! COM is initialized on every thread that calls into this crate, with the threading model that's
+//! chosen by the `coinit_*` features. Threads that were initialized with the other model keep
+//! using it. Applications that initialize COM themselves can turn this off with
+//! [`assume_com_initialized`].
+//!
+//! ### Systems without `IFileOperation`
+//!
+//! Some environments don't provide `IFileOperation`, like Wine, Nano Server and some Server Core
+//! installations. When it can't be instantiated, items are deleted through the legacy
+//! `SHFileOperationW` instead, which still moves them to the Recycle Bin. It doesn't support paths
+//! longer than `MAX_PATH`, the [`FileOperationObserver`] and stopping an operation that's already
+//! running through a [`CancellationToken`]. With
+//! [`continue_on_error`](TrashContext::continue_on_error) the items are deleted one at a time, as
+//! it stops at the first item that fails. The other operations, like restoring items, need
+//! `IFileOperation` and fail without it.
+
+use crate::{
+    collision, destination, io_error, limit, matching, metrics::TrashMetrics, parallel, progress::PathProgress, wsl,
+    ByteSize, CancellationToken, DisplayLocale, Error, Preset, RestoreCollision, TrashContext, TrashItem,
+    TrashItemKind, TrashItemMetadata, TrashItemSize, TrashTransfer, UnknownReason,
+};
+use log::{debug, warn};
+use once_cell::sync::Lazy;
 use std::{
     borrow::Borrow,
+    cell::Cell,
+    collections::HashMap,
     ffi::{c_void, OsStr, OsString},
     os::windows::{ffi::OsStrExt, prelude::*},
-    path::PathBuf,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
 };
-use windows::core::{Interface, GUID, PCWSTR, PWSTR};
+use windows::core::{implement, AsImpl, IUnknown, Interface, GUID, HRESULT, PCWSTR, PWSTR};
 use windows::Win32::{
-    Foundation::*, Storage::EnhancedStorage::*, System::Com::*, System::SystemServices::*,
-    UI::Shell::PropertiesSystem::*, UI::Shell::*,
+    Foundation::*,
+    Globalization::{
+        GetThreadPreferredUILanguages, SetThreadPreferredUILanguages, MUI_LANGUAGE_NAME, MUI_THREAD_LANGUAGES,
+    },
+    Security::Authorization::{ConvertSidToStringSidW, ConvertStringSidToSidW, SetNamedSecurityInfoW, SE_FILE_OBJECT},
+    Security::*,
+    Storage::EnhancedStorage::*,
+    Storage::FileSystem::{
+        FindChangeNotificationHandle, FindCloseChangeNotification, FindFirstChangeNotificationW,
+        FindNextChangeNotification, GetDiskFreeSpaceExW, GetDriveTypeW, GetLogicalDriveStringsW,
+        GetVolumeNameForVolumeMountPointW, GetVolumePathNameW, FILE_ADD_FILE, FILE_ADD_SUBDIRECTORY, FILE_ALL_ACCESS,
+        FILE_GENERIC_EXECUTE, FILE_GENERIC_READ, FILE_GENERIC_WRITE, FILE_NOTIFY_CHANGE_DIR_NAME,
+        FILE_NOTIFY_CHANGE_FILE_NAME,
+    },
+    Storage::Packaging::Appx::GetCurrentPackageFullName,
+    System::Com::StructuredStorage::{PropVariantClear, PROPVARIANT},
+    System::Com::*,
+    System::Memory::LocalFree,
+    System::Registry::{RegGetValueW, HKEY_CURRENT_USER, RRF_RT_REG_DWORD},
+    System::SystemServices::*,
+    System::Threading::*,
+    System::WindowsProgramming::DRIVE_REMOTE,
+    UI::Shell::PropertiesSystem::*,
+    UI::Shell::*,
 };
 
 ///////////////////////////////////////////////////////////////////////////
@@ -22,9 +117,11 @@ const SCID_ORIGINAL_LOCATION: PROPERTYKEY = PROPERTYKEY { fmtid: PSGUID_DISPLACE
 const SCID_DATE_DELETED: PROPERTYKEY = PROPERTYKEY { fmtid: PSGUID_DISPLACED, pid: PID_DISPLACED_DATE };
 
 const FOF_SILENT: u32 = 0x0004;
+const FOF_RENAMEONCOLLISION: u32 = 0x0008;
 const FOF_NOCONFIRMATION: u32 = 0x0010;
 const FOF_ALLOWUNDO: u32 = 0x0040;
 const FOF_NOCONFIRMMKDIR: u32 = 0x0200;
+const FOF_SIMPLEPROGRESS: u32 = 0x0100;
 const FOF_NOERRORUI: u32 = 0x0400;
 const FOF_WANTNUKEWARNING: u32 = 0x4000;
 const FOF_NO_UI: u32 = FOF_SILENT | FOF_NOCONFIRMATION | FOF_NOERRORUI | FOF_NOCONFIRMMKDIR;
@@ -33,200 +130,2618 @@ const FOFX_EARLYFAILURE: u32 = 0x00100000;
 
 impl From<windows::core::Error> for Error {
     fn from(err: windows::core::Error) -> Error {
-        Error::Os { code: err.code().0, description: format!("windows error: {err}") }
+        let code = err.code();
+        if code == E_ACCESSDENIED
+            || code == ERROR_ELEVATION_REQUIRED.to_hresult()
+            || code == COPYENGINE_E_REQUIRES_ELEVATION
+            || code == COPYENGINE_E_ACCESS_DENIED_SRC
+            || code == COPYENGINE_E_ACCESS_DENIED_DEST
+        {
+            return Error::NeedsElevation { code: code.0, description: format!("windows error: {err}") };
+        }
+        Error::Os { code: code.0, function: None, description: format!("windows error: {err}") }
+    }
+}
+
+/// Names the function that failed in the errors of Windows calls.
+trait OsFunction<T> {
+    fn in_function(self, function: &str) -> Result<T, Error>;
+}
+
+impl<T> OsFunction<T> for windows::core::Result<T> {
+    fn in_function(self, function: &str) -> Result<T, Error> {
+        self.map_err(|err| os_error(err, function))
+    }
+}
+
+/// Converts the error like `From` does, along with the name of the function that failed.
+fn os_error(err: windows::core::Error, function: &str) -> Error {
+    match Error::from(err) {
+        Error::Os { code, description, .. } => Error::Os { code, function: Some(function.into()), description },
+        error => error,
+    }
+}
+
+/// Whether `CoCreateInstance` failed because `IFileOperation` isn't provided by the system, rather
+/// than for a reason that `SHFileOperationW` would run into as well.
+fn is_file_operation_unavailable(code: HRESULT) -> bool {
+    code == REGDB_E_CLASSNOTREG || code == E_NOINTERFACE
+}
+
+/// Converts the result of `SHFileOperationW`. It returns one of its own `DE_*` codes for some
+/// failures, which predate Win32 error codes and overlap with them, and a Win32 error code otherwise.
+fn legacy_operation_error(code: i32) -> Error {
+    let description = match code {
+        0x71 => "the source and destination are the same file",
+        0x74 => "the source is a root folder",
+        0x75 => return Error::Cancelled,
+        0x78 => {
+            return Error::NeedsElevation {
+                code,
+                description: "SHFileOperationW error 0x78: access to the source was denied".into(),
+            }
+        }
+        0x79 | 0x81 | 0xB7 => "the path is too long",
+        0x7C => "the path of an item is invalid",
+        0x85 => "the file is too large",
+        0x86..=0x88 => "the source is on read-only optical media",
+        0x72 | 0x73 | 0x76 | 0x7A | 0x7D | 0x7E | 0x80 | 0x82..=0x84 => "the operation isn't valid for the items",
+        0x402 => "an unknown error occurred, typically because of an invalid path",
+        0x10000 => "an unknown error occurred at the destination",
+        code => return os_error(WIN32_ERROR(code as u32).to_hresult().into(), "SHFileOperationW"),
+    };
+    Error::Os {
+        code,
+        function: Some("SHFileOperationW".into()),
+        description: format!("SHFileOperationW error {code:#x}: {description}"),
+    }
+}
+
+/// Creating a shell item for `path` failed, reports a missing file as
+/// [`Error::TargetedItemNotFound`].
+fn shell_item_error(path: &Path, function: &str, err: windows::core::Error) -> Error {
+    let code = err.code();
+    if code == ERROR_FILE_NOT_FOUND.to_hresult() || code == ERROR_PATH_NOT_FOUND.to_hresult() {
+        return Error::TargetedItemNotFound { path: path.to_owned() };
+    }
+    os_error(err, function)
+}
+
+fn to_wide_path(path: impl AsRef<OsStr>) -> Vec<u16> {
+    path.as_ref().encode_wide().chain(std::iter::once(0)).collect()
+}
+
+/// What to do when moving items to the Recycle Bin would make it grow past its maximum size.
+///
+/// The maximum size is configured per volume in the properties of the Recycle Bin, or by the
+/// "Maximum allowed Recycle Bin size" group policy, which takes precedence. When neither is
+/// configured, the maximum size is unknown and no action is taken. See [`recycle_bin_usage`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum QuotaPolicy {
+    /// Don't check the size of the Recycle Bin, and leave it to the shell which silently removes
+    /// the oldest items once the maximum size is exceeded.
+    ///
+    /// This is the default.
+    Ignore,
+    /// Log a warning before moving the items to the Recycle Bin.
+    Warn,
+    /// Permanently delete the oldest items of the Recycle Bin on the same volume before moving
+    /// the items there, so that they fit. The purged items are passed to the purge hooks of the
+    /// context. Items pinned with [`TrashContext::pin`] are never purged.
+    ///
+    /// When the items are larger than the Recycle Bin can ever be, nothing is purged and a warning
+    /// is logged instead, as the shell deletes them permanently anyway.
+    PurgeOldest,
+}
+impl QuotaPolicy {
+    /// Returns `QuotaPolicy::Ignore`
+    pub const fn new() -> Self {
+        QuotaPolicy::Ignore
+    }
+}
+impl Default for QuotaPolicy {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// How [`TrashContext::list`] and [`TrashContext::purge_all`] access the Recycle Bin of the current
+/// user.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub enum RecycleBinAccess {
+    /// Go through the shell, like Explorer does. This is the default.
+    #[default]
+    Shell,
+    /// Read and remove the `$I` and `$R` files within `$Recycle.Bin\<SID>` on every drive
+    /// directly, without COM or Explorer. This works in services and in Windows PE, where the shell
+    /// may be unavailable.
+    ///
+    /// This is less faithful than the shell: names are the original file names rather than the
+    /// localized display names, Recycle Bins that only the shell knows about, like those of
+    /// redirected folders, are left out, and the UI settings of the context have no effect. Open
+    /// Explorer windows don't notice purged items until they are refreshed. The ids of the listed
+    /// items are the paths of their `$R` files, just like with the shell, so items can be passed
+    /// between both modes.
+    Direct,
+}
+
+/// Extended `FOFX_*` flags of `IFileOperation` that are passed on to every delete, restore and
+/// purge operation.
+///
+/// Flags can be combined with `|`. See the documentation of [`IFileOperation::SetOperationFlags`]
+/// for the exact meaning of each flag.
+///
+/// [`IFileOperation::SetOperationFlags`]: https://learn.microsoft.com/en-us/windows/win32/api/shobjidl_core/nf-shobjidl_core-ifileoperation-setoperationflags
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct ExtendedFlags(u32);
+impl ExtendedFlags {
+    /// No extended flags. This is the default.
+    pub const NONE: Self = Self(0);
+    /// Stop at the first failure instead of continuing with the remaining items.
+    ///
+    /// Early failure is always enabled unless
+    /// [`continue_on_error`](crate::TrashContext::set_continue_on_error) is set, and this flag
+    /// turns it on even then. Items after the failing one are left untouched in that case.
+    pub const EARLY_FAILURE: Self = Self(FOFX_EARLYFAILURE);
+    /// Process items that are junctions instead of skipping them.
+    pub const NO_SKIP_JUNCTIONS: Self = Self(FOFX_NOSKIPJUNCTIONS);
+    /// Create hard links rather than copies where possible.
+    pub const PREFER_HARD_LINK: Self = Self(FOFX_PREFERHARDLINK);
+    /// Show an elevation prompt when the operation needs elevation, even if the UI is disabled.
+    pub const SHOW_ELEVATION_PROMPT: Self = Self(FOFX_SHOWELEVATIONPROMPT);
+    /// Require elevation, and fail with [`Error::NeedsElevation`] if the process isn't elevated.
+    pub const REQUIRE_ELEVATION: Self = Self(FOFX_REQUIREELEVATION);
+    /// Keep the extension of an item when it's renamed because of a collision.
+    pub const PRESERVE_FILE_EXTENSIONS: Self = Self(FOFX_PRESERVEFILEEXTENSIONS);
+    /// When a collision occurs, keep the newer of the two files.
+    pub const KEEP_NEWER_FILE: Self = Self(FOFX_KEEPNEWERFILE);
+    /// Don't invoke the copy hooks registered by shell extensions.
+    pub const NO_COPY_HOOKS: Self = Self(FOFX_NOCOPYHOOKS);
+    /// Don't show a minimize box in the progress dialog.
+    pub const NO_MINIMIZE_BOX: Self = Self(FOFX_NOMINIMIZEBOX);
+    /// Keep the ACLs of items that are moved across volumes.
+    pub const MOVE_ACLS_ACROSS_VOLUMES: Self = Self(FOFX_MOVEACLSACROSSVOLUMES);
+    /// Don't show the source path in the progress dialog.
+    pub const DONT_DISPLAY_SOURCE_PATH: Self = Self(FOFX_DONTDISPLAYSOURCEPATH);
+    /// Don't show the destination path in the progress dialog.
+    pub const DONT_DISPLAY_DEST_PATH: Self = Self(FOFX_DONTDISPLAYDESTPATH);
+    /// Don't show any paths in the progress dialog.
+    pub const DONT_DISPLAY_LOCATIONS: Self = Self(FOFX_DONTDISPLAYLOCATIONS);
+    /// Record the operation so that it can be undone from Explorer.
+    pub const ADD_UNDO_RECORD: Self = Self(FOFX_ADDUNDORECORD);
+
+    /// All bits that are reserved for `FOFX_*` flags.
+    const MASK: u32 = 0xFFFF_0000;
+
+    /// Creates the flags from the raw `FOFX_*` bits. Bits outside of the range of extended flags
+    /// are dropped, the basic `FOF_*` flags are managed by the context.
+    pub const fn from_bits_truncate(bits: u32) -> Self {
+        Self(bits & Self::MASK)
+    }
+    pub const fn bits(self) -> u32 {
+        self.0
+    }
+    pub const fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+}
+impl std::ops::BitOr for ExtendedFlags {
+    type Output = Self;
+    fn bitor(self, rhs: Self) -> Self {
+        Self(self.0 | rhs.0)
+    }
+}
+impl std::ops::BitOrAssign for ExtendedFlags {
+    fn bitor_assign(&mut self, rhs: Self) {
+        self.0 |= rhs.0;
+    }
+}
+
+#[derive(Clone, Default, Debug)]
+pub struct PlatformTrashContext {
+    minimal_permissions: bool,
+    show_ui: bool,
+    operation_title: Option<String>,
+    quota_policy: QuotaPolicy,
+    extended_flags: ExtendedFlags,
+    recycle_bin_access: RecycleBinAccess,
+    lenient_listing: bool,
+    single_restore_operation: bool,
+    allow_permanent_delete: bool,
+    file_operation_observer: Option<Observer>,
+}
+impl PlatformTrashContext {
+    pub const fn new() -> Self {
+        Self {
+            minimal_permissions: false,
+            show_ui: false,
+            operation_title: None,
+            quota_policy: QuotaPolicy::new(),
+            extended_flags: ExtendedFlags::NONE,
+            recycle_bin_access: RecycleBinAccess::Shell,
+            lenient_listing: false,
+            single_restore_operation: true,
+            allow_permanent_delete: false,
+            file_operation_observer: None,
+        }
+    }
+
+    pub(crate) fn apply_preset(&mut self, preset: Preset) {
+        self.show_ui = preset == Preset::Interactive;
+        self.lenient_listing = preset == Preset::BestEffort;
+    }
+
+    /// Creates a file operation with the UI and failure related flags derived from the context in
+    /// addition to `flags`.
+    fn create_file_operation(&self, continue_on_error: bool, flags: u32) -> Result<IFileOperation, Error> {
+        let early_failure = if continue_on_error { 0 } else { FOFX_EARLYFAILURE };
+        let flags = flags | self.extended_flags.bits();
+        let ui_flags = match (self.show_ui, &self.operation_title) {
+            (false, _) => FOF_NO_UI,
+            (true, Some(_)) => FOF_SIMPLEPROGRESS,
+            (true, None) => 0,
+        };
+        #[cfg(test)]
+        if FILE_OPERATION_UNAVAILABLE.load(std::sync::atomic::Ordering::Relaxed) {
+            return Err(os_error(REGDB_E_CLASSNOTREG.into(), "CoCreateInstance"));
+        }
+        unsafe {
+            let pfo: IFileOperation = CoCreateInstance(&FileOperation as *const _, None, self.class_context())
+                .in_function("CoCreateInstance")?;
+            pfo.SetOperationFlags(ui_flags | early_failure | flags)?;
+            if let (true, Some(title)) = (self.show_ui, &self.operation_title) {
+                let title = to_wide_path(title);
+                pfo.SetProgressMessage(PCWSTR(title.as_ptr()))?;
+            }
+            Ok(pfo)
+        }
+    }
+
+    fn class_context(&self) -> CLSCTX {
+        if self.minimal_permissions || SANDBOX.is_sandboxed() {
+            CLSCTX_INPROC_SERVER
+        } else {
+            CLSCTX_ALL
+        }
+    }
+}
+pub trait TrashContextExtWindows {
+    /// Only use APIs that work with the privileges of a restricted token.
+    ///
+    /// See the [module level documentation](crate::windows) for the privileges each operation needs.
+    fn set_minimal_permissions(&mut self, enabled: bool);
+    fn minimal_permissions(&self) -> bool;
+
+    /// Show the Explorer progress dialog, confirmation prompts and error messages while deleting,
+    /// restoring and purging items. This is off by default.
+    fn set_show_ui(&mut self, show_ui: bool);
+    fn show_ui(&self) -> bool;
+
+    /// The text displayed in the progress dialog when [`show_ui`](Self::show_ui) is enabled.
+    ///
+    /// When set, the simplified progress dialog is shown that doesn't display individual file names.
+    fn set_operation_title(&mut self, title: Option<String>);
+    fn operation_title(&self) -> Option<&str>;
+
+    /// What to do when deleting items would make the Recycle Bin exceed its maximum size.
+    fn set_quota_policy(&mut self, policy: QuotaPolicy);
+    fn quota_policy(&self) -> QuotaPolicy;
+
+    /// Extended flags that are passed on to every shell operation. See [`ExtendedFlags`].
+    fn set_extended_flags(&mut self, flags: ExtendedFlags);
+    fn extended_flags(&self) -> ExtendedFlags;
+
+    /// Whether the Recycle Bin is listed and purged through the shell or by reading its files
+    /// directly. See [`RecycleBinAccess`].
+    fn set_recycle_bin_access(&mut self, access: RecycleBinAccess);
+    fn recycle_bin_access(&self) -> RecycleBinAccess;
+
+    /// List items whose original location or deletion date the shell fails to provide, instead of
+    /// failing the whole listing. Such items have `None` as their
+    /// [`original_parent`](TrashItem::original_parent) or
+    /// [`time_deleted`](TrashItem::time_deleted), see [`TrashItem::is_degraded`]. This is off by
+    /// default, and on with [`Preset::BestEffort`].
+    ///
+    /// Some namespace extensions of other vendors and corrupted Recycle Bins fail to provide these
+    /// columns. The deletion date is read from the `$I` file of the item first, if it has one.
+    fn set_lenient_listing(&mut self, lenient: bool);
+    fn lenient_listing(&self) -> bool;
+
+    /// Restore all the items passed to [`restore_all`](TrashContext::restore_all) with a single
+    /// shell operation, which is faster than restoring them one volume at a time and which Explorer
+    /// lists as a single operation that can be undone. This is on by default, and takes precedence
+    /// over [`restore_concurrency`](TrashContext::restore_concurrency).
+    ///
+    /// Items whose original path is longer than `MAX_PATH` are restored without the shell either
+    /// way, see [`Error::PathTooLong`].
+    fn set_single_restore_operation(&mut self, enabled: bool);
+    fn single_restore_operation(&self) -> bool;
+
+    /// Let the shell delete items permanently that it can't move to the Recycle Bin, which is what
+    /// it does with items on network drives and items that are larger than the maximum size of
+    /// the Recycle Bin. This is off by default, such items are refused with
+    /// [`Error::CouldNotTrashWouldDelete`] and are left where they are.
+    ///
+    /// Items are only known to be too large if a maximum size is configured for the Recycle Bin,
+    /// see [`recycle_bin_usage`].
+    fn set_allow_permanent_delete(&mut self, allow: bool);
+    fn allow_permanent_delete(&self) -> bool;
+
+    /// Report every item that the shell deletes, moves to the Recycle Bin or purges to
+    /// `observer`, as it goes through the items of an operation. See [`FileOperationObserver`].
+    fn set_file_operation_observer(&mut self, observer: Option<Arc<dyn FileOperationObserver>>);
+    fn file_operation_observer(&self) -> Option<&Arc<dyn FileOperationObserver>>;
+}
+impl TrashContextExtWindows for TrashContext {
+    fn set_minimal_permissions(&mut self, enabled: bool) {
+        self.platform_specific.minimal_permissions = enabled;
+    }
+    fn minimal_permissions(&self) -> bool {
+        self.platform_specific.minimal_permissions
+    }
+    fn set_show_ui(&mut self, show_ui: bool) {
+        self.platform_specific.show_ui = show_ui;
+    }
+    fn show_ui(&self) -> bool {
+        self.platform_specific.show_ui
+    }
+    fn set_operation_title(&mut self, title: Option<String>) {
+        self.platform_specific.operation_title = title;
+    }
+    fn operation_title(&self) -> Option<&str> {
+        self.platform_specific.operation_title.as_deref()
+    }
+    fn set_quota_policy(&mut self, policy: QuotaPolicy) {
+        self.platform_specific.quota_policy = policy;
+    }
+    fn quota_policy(&self) -> QuotaPolicy {
+        self.platform_specific.quota_policy
+    }
+    fn set_extended_flags(&mut self, flags: ExtendedFlags) {
+        self.platform_specific.extended_flags = flags;
+    }
+    fn extended_flags(&self) -> ExtendedFlags {
+        self.platform_specific.extended_flags
+    }
+    fn set_recycle_bin_access(&mut self, access: RecycleBinAccess) {
+        self.platform_specific.recycle_bin_access = access;
+    }
+    fn set_lenient_listing(&mut self, lenient: bool) {
+        self.platform_specific.lenient_listing = lenient;
+    }
+    fn lenient_listing(&self) -> bool {
+        self.platform_specific.lenient_listing
+    }
+    fn recycle_bin_access(&self) -> RecycleBinAccess {
+        self.platform_specific.recycle_bin_access
+    }
+    fn set_single_restore_operation(&mut self, enabled: bool) {
+        self.platform_specific.single_restore_operation = enabled;
+    }
+    fn single_restore_operation(&self) -> bool {
+        self.platform_specific.single_restore_operation
+    }
+    fn set_allow_permanent_delete(&mut self, allow: bool) {
+        self.platform_specific.allow_permanent_delete = allow;
+    }
+    fn allow_permanent_delete(&self) -> bool {
+        self.platform_specific.allow_permanent_delete
+    }
+    fn set_file_operation_observer(&mut self, observer: Option<Arc<dyn FileOperationObserver>>) {
+        self.platform_specific.file_operation_observer = observer.map(Observer);
+    }
+    fn file_operation_observer(&self) -> Option<&Arc<dyn FileOperationObserver>> {
+        self.platform_specific.file_operation_observer.as_ref().map(|observer| &observer.0)
+    }
+}
+
+/// Callbacks that the shell invokes for each item it deletes within an operation, as set with
+/// [`TrashContextExtWindows::set_file_operation_observer`].
+///
+/// The shell moves a whole batch to the Recycle Bin in a single operation. When
+/// [`continue_on_error`](TrashContext::continue_on_error) is enabled, it skips the items that fail
+/// and carries on, and the operation only reports that some item failed. These callbacks tell
+/// which ones did, as well as where the other items ended up in the Recycle Bin.
+///
+/// The callbacks are invoked on the thread that performs the operation, while the shell waits for
+/// them. A panic in a callback is caught and fails the callback with `E_FAIL`, as it must not
+/// unwind into the shell. Every method has a default implementation that does nothing.
+///
+/// # Example
+///
+/// ```
+/// use std::{path::Path, sync::Arc};
+/// use trash::{windows::{FileOperationObserver, TrashContextExtWindows}, Error, TrashContext};
+///
+/// struct LogFailures;
+/// impl FileOperationObserver for LogFailures {
+///     fn post_delete_item(&self, path: &Path, result: Result<(), Error>, _recycled: Option<&Path>) {
+///         if let Err(e) = result {
+///             eprintln!("{path:?} was left in place: {e}");
+///         }
+///     }
+/// }
+///
+/// let mut ctx = TrashContext::default();
+/// ctx.set_continue_on_error(true);
+/// ctx.set_file_operation_observer(Some(Arc::new(LogFailures)));
+/// ```
+pub trait FileOperationObserver: Send + Sync {
+    /// Invoked with the path of an item that the shell is about to delete.
+    fn pre_delete_item(&self, _path: &Path) {}
+
+    /// Invoked after the shell tried to delete the item at `path`, with the error if it failed.
+    /// `recycled` is the path of the item in the Recycle Bin, which is its
+    /// [`id`](TrashItem::id), if the item was moved there.
+    fn post_delete_item(&self, _path: &Path, _result: Result<(), Error>, _recycled: Option<&Path>) {}
+}
+
+/// The observer stored in a `PlatformTrashContext`.
+///
+/// This only exists so that `PlatformTrashContext` can keep deriving `Debug`.
+#[derive(Clone)]
+struct Observer(Arc<dyn FileOperationObserver>);
+impl std::fmt::Debug for Observer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("Observer")
+    }
+}
+
+/// How much of the Recycle Bin on a volume is used.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct RecycleBinUsage {
+    /// The total size of the items in the Recycle Bin of the volume.
+    pub bytes: ByteSize,
+    /// The number of items in the Recycle Bin of the volume.
+    pub items: u64,
+    /// The maximum size of the Recycle Bin of the volume, if it's configured.
+    pub capacity: Option<ByteSize>,
+}
+
+/// Returns the usage and the maximum size of the Recycle Bin on the volume with the given root,
+/// such as `C:\`.
+pub fn recycle_bin_usage(volume: &Path) -> Result<RecycleBinUsage, Error> {
+    let wide_volume = to_wide_path(volume);
+    let mut info = SHQUERYRBINFO { cbSize: std::mem::size_of::<SHQUERYRBINFO>() as u32, ..Default::default() };
+    unsafe { SHQueryRecycleBinW(PCWSTR(wide_volume.as_ptr()), &mut info)? };
+    Ok(RecycleBinUsage {
+        // The counts are signed, but never negative unless the shell misbehaves.
+        bytes: ByteSize::new(u64::try_from(info.i64Size).unwrap_or(0)),
+        items: u64::try_from(info.i64NumItems).unwrap_or(0),
+        capacity: recycle_bin_capacity(&wide_volume),
+    })
+}
+
+impl TrashContext {
+    /// Queries the totals of the Recycle Bins of all drives at once.
+    pub(crate) fn trash_metrics(&self) -> Result<TrashMetrics, Error> {
+        let mut info = SHQUERYRBINFO { cbSize: std::mem::size_of::<SHQUERYRBINFO>() as u32, ..Default::default() };
+        unsafe { SHQueryRecycleBinW(PCWSTR::null(), &mut info).in_function("SHQueryRecycleBinW")? };
+        Ok(TrashMetrics {
+            item_count: u64::try_from(info.i64NumItems).unwrap_or(0),
+            total_bytes: ByteSize::new(u64::try_from(info.i64Size).unwrap_or(0)),
+            complete: true,
+        })
+    }
+}
+
+/// The free space on the volume with the given root that's available to the current user, which
+/// takes disk quotas into account.
+pub(crate) fn free_bytes(volume: &Path) -> Option<ByteSize> {
+    let wide_volume = to_wide_path(volume);
+    let mut free_bytes: u64 = 0;
+    unsafe { GetDiskFreeSpaceExW(PCWSTR(wide_volume.as_ptr()), Some(&mut free_bytes), None, None) }
+        .as_bool()
+        .then_some(ByteSize::new(free_bytes))
+}
+
+/// Whether the item at `full_path` is larger than the maximum size of the Recycle Bin on `volume`,
+/// in which case the shell deletes it permanently instead of moving it there. The size of the item
+/// is only computed if a maximum size is configured, and only until it exceeds that size.
+pub(crate) fn exceeds_recycle_bin(volume: &Path, full_path: &Path) -> bool {
+    let Some(capacity) = recycle_bin_capacity(&to_wide_path(volume)) else {
+        return false;
+    };
+    destination::exceeds_size(full_path, capacity)
+}
+
+/// The root of the volume that `full_path` is on.
+fn volume_path_of(full_path: &Path) -> Result<PathBuf, Error> {
+    let wide_path = to_wide_path(full_path);
+    // The volume path can't be longer than the path itself.
+    let mut volume_buffer = vec![0u16; wide_path.len().max(MAX_PATH as usize + 1)];
+    unsafe {
+        if !GetVolumePathNameW(PCWSTR(wide_path.as_ptr()), &mut volume_buffer).as_bool() {
+            return Err(windows::core::Error::from_win32().into());
+        }
+        Ok(wstr_to_os_string(PWSTR(volume_buffer.as_mut_ptr())).into())
+    }
+}
+
+/// How the shell deletes items on the volume, with `wide_volume` as the NUL terminated root of the
+/// volume. Network drives don't have a Recycle Bin, the shell deletes items on them permanently.
+fn volume_transfer(wide_volume: &[u16]) -> TrashTransfer {
+    match unsafe { GetDriveTypeW(PCWSTR(wide_volume.as_ptr())) } {
+        DRIVE_REMOTE => TrashTransfer::PermanentDelete,
+        _ => TrashTransfer::Rename,
+    }
+}
+
+/// Reads the maximum size of the Recycle Bin on a volume from the registry. `wide_volume` is the
+/// NUL terminated root of the volume.
+fn recycle_bin_capacity(wide_volume: &[u16]) -> Option<ByteSize> {
+    const POLICIES: &str = r"Software\Microsoft\Windows\CurrentVersion\Policies\Explorer";
+    const BIT_BUCKET: &str = r"Software\Microsoft\Windows\CurrentVersion\Explorer\BitBucket\Volume";
+
+    // The group policy is a percentage of the size of the volume.
+    if let Some(percent) = read_user_dword(POLICIES, "RecycleBinSize") {
+        let mut total_bytes: u64 = 0;
+        if unsafe { GetDiskFreeSpaceExW(PCWSTR(wide_volume.as_ptr()), None, Some(&mut total_bytes), None) }.as_bool() {
+            return Some(ByteSize::new(total_bytes / 100 * u64::from(percent.min(100))));
+        }
+    }
+    // The setting from the properties of the Recycle Bin is in megabytes, stored under the GUID of
+    // the volume which is the last component of `\\?\Volume{GUID}\`.
+    let mut volume_name = [0u16; 50];
+    if !unsafe { GetVolumeNameForVolumeMountPointW(PCWSTR(wide_volume.as_ptr()), &mut volume_name) }.as_bool() {
+        return None;
+    }
+    let volume_name = String::from_utf16_lossy(&volume_name);
+    let guid =
+        volume_name.trim_end_matches('\0').trim_end_matches('\\').rsplit('\\').next()?.trim_start_matches("Volume");
+    let megabytes = read_user_dword(&format!(r"{BIT_BUCKET}\{guid}"), "MaxCapacity")?;
+    Some(ByteSize::mib(u64::from(megabytes)))
+}
+
+/// The kind of content of an item, as the shell perceives it from its type.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum PerceivedType {
+    /// The type isn't associated with any kind of content.
+    #[default]
+    Unknown,
+    Folder,
+    Text,
+    Image,
+    Audio,
+    Video,
+    Compressed,
+    Document,
+    System,
+    Application,
+    GameMedia,
+    Contacts,
+}
+
+impl PerceivedType {
+    fn from_raw(raw: i32) -> Self {
+        match raw {
+            -1 => PerceivedType::Folder,
+            1 => PerceivedType::Text,
+            2 => PerceivedType::Image,
+            3 => PerceivedType::Audio,
+            4 => PerceivedType::Video,
+            5 => PerceivedType::Compressed,
+            6 => PerceivedType::Document,
+            7 => PerceivedType::System,
+            8 => PerceivedType::Application,
+            9 => PerceivedType::GameMedia,
+            10 => PerceivedType::Contacts,
+            _ => PerceivedType::Unknown,
+        }
+    }
+}
+
+/// The properties of an item in the Recycle Bin that Explorer shows in its columns, beyond the
+/// ones in [`TrashItem`] and [`TrashItemMetadata`].
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct ShellProperties {
+    /// The localized description of the type of the item, like "Text Document".
+    pub type_description: Option<String>,
+    /// The kind of content of the item.
+    pub perceived_type: PerceivedType,
+    /// The `FILE_ATTRIBUTE_*` flags the item had before it was deleted.
+    pub attributes: Option<u32>,
+}
+
+/// Reads the [`ShellProperties`] of an item through its property store.
+///
+/// Like [`os_limited::metadata`](crate::os_limited::metadata), this is meant to be called lazily
+/// for the items that are shown, as it's considerably slower than listing the Recycle Bin.
+/// Properties the shell doesn't provide for the item are left empty.
+pub fn shell_properties(item: &TrashItem) -> Result<ShellProperties, Error> {
+    with_com(|| {
+        let id_as_wide = to_wide_path(&item.id);
+        unsafe {
+            let item: IShellItem2 = SHCreateItemFromParsingName(PCWSTR(id_as_wide.as_ptr()), None)?;
+            let store: IPropertyStore = item.GetPropertyStore(GPS_DEFAULT)?;
+            let type_description =
+                read_property(&store, &PKEY_ItemTypeText, string_from_variant).filter(|text| !text.is_empty());
+            let perceived_type = read_property(&store, &PKEY_PerceivedType, |value| PropVariantToInt32(value))
+                .map_or(PerceivedType::Unknown, PerceivedType::from_raw);
+            // Every item has at least one attribute set, so zero means the property is missing.
+            let attributes =
+                read_property(&store, &PKEY_FileAttributes, |value| PropVariantToUInt32(value)).filter(|&a| a != 0);
+            Ok(ShellProperties { type_description, perceived_type, attributes })
+        }
+    })
+}
+
+fn string_from_variant(value: &PROPVARIANT) -> windows::core::Result<String> {
+    unsafe {
+        let text = PropVariantToStringAlloc(value)?;
+        let result = wstr_to_os_string(text);
+        CoTaskMemFree(Some(text.0 as *const c_void));
+        Ok(result.to_string_lossy().into_owned())
+    }
+}
+
+/// Reads a property from `store` and converts it with `convert`, or returns `None` if either fails.
+unsafe fn read_property<T>(
+    store: &IPropertyStore,
+    key: &PROPERTYKEY,
+    convert: impl FnOnce(&PROPVARIANT) -> windows::core::Result<T>,
+) -> Option<T> {
+    let mut value = store.GetValue(key).ok()?;
+    let result = convert(&value).ok();
+    let _ = PropVariantClear(&mut value);
+    result
+}
+
+fn read_user_dword(key: &str, value: &str) -> Option<u32> {
+    let wide_key = to_wide_path(key);
+    let wide_value = to_wide_path(value);
+    let mut data: u32 = 0;
+    let mut len = std::mem::size_of::<u32>() as u32;
+    let result = unsafe {
+        RegGetValueW(
+            HKEY_CURRENT_USER,
+            PCWSTR(wide_key.as_ptr()),
+            PCWSTR(wide_value.as_ptr()),
+            RRF_RT_REG_DWORD,
+            None,
+            Some(&mut data as *mut u32 as *mut c_void),
+            Some(&mut len),
+        )
+    };
+    (result == ERROR_SUCCESS).then_some(data)
+}
+/// Describes the sandbox the current process runs in, and which features of this crate are
+/// available inside of it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SandboxCapabilities {
+    /// The process has package identity, for example because it was installed from an MSIX package.
+    pub packaged: bool,
+    /// The process runs inside an AppContainer.
+    pub app_container: bool,
+    /// Items can be moved to the Recycle Bin. Inside an AppContainer this requires the
+    /// `broadFileSystemAccess` capability or access to the items granted through a file picker.
+    pub delete: bool,
+    /// The Recycle Bin can be enumerated with `os_limited::list` and `os_limited::metadata`.
+    pub list: bool,
+    /// Items can be restored from the Recycle Bin.
+    pub restore: bool,
+    /// Items can be removed permanently from the Recycle Bin.
+    pub purge: bool,
+}
+impl SandboxCapabilities {
+    fn is_sandboxed(&self) -> bool {
+        self.packaged || self.app_container
+    }
+}
+
+static SANDBOX: Lazy<SandboxCapabilities> = Lazy::new(|| {
+    let packaged = is_packaged();
+    let app_container = is_app_container();
+    // The Recycle Bin folders are outside of the locations an AppContainer is granted access to,
+    // even with `broadFileSystemAccess`, so only deletion is brokered for those processes.
+    SandboxCapabilities {
+        packaged,
+        app_container,
+        delete: true,
+        list: !app_container,
+        restore: !app_container,
+        purge: !app_container,
+    }
+});
+
+/// Returns the capabilities of the sandbox the current process runs in.
+///
+/// The result is determined once per process.
+pub fn sandbox_capabilities() -> SandboxCapabilities {
+    *SANDBOX
+}
+
+fn is_packaged() -> bool {
+    let mut len: u32 = 0;
+    let result = unsafe { GetCurrentPackageFullName(&mut len, PWSTR::null()) };
+    result.0 as i32 != APPMODEL_ERROR_NO_PACKAGE
+}
+
+fn is_app_container() -> bool {
+    unsafe {
+        let mut token = HANDLE::default();
+        if !OpenProcessToken(GetCurrentProcess(), TOKEN_QUERY, &mut token).as_bool() {
+            return false;
+        }
+        let mut is_app_container: u32 = 0;
+        let mut return_length: u32 = 0;
+        let success = GetTokenInformation(
+            token,
+            TokenIsAppContainer,
+            Some(&mut is_app_container as *mut u32 as *mut c_void),
+            std::mem::size_of::<u32>() as u32,
+            &mut return_length,
+        );
+        CloseHandle(token);
+        success.as_bool() && is_app_container != 0
+    }
+}
+
+/// Whether the current user may create items in `folder`, as decided by the access control list
+/// of the folder against the token of the process. Nothing is created to find out.
+pub(crate) fn is_writable(folder: &Path) -> bool {
+    let folder = to_wide_path(folder);
+    let information = (OWNER_SECURITY_INFORMATION | GROUP_SECURITY_INFORMATION | DACL_SECURITY_INFORMATION).0;
+    unsafe {
+        let mut len: u32 = 0;
+        GetFileSecurityW(PCWSTR(folder.as_ptr()), information, PSECURITY_DESCRIPTOR::default(), 0, &mut len);
+        // Use `u64`s so that the buffer is aligned for the pointers within the descriptor.
+        let mut buffer = vec![0u64; (len as usize).div_ceil(8)];
+        let descriptor = PSECURITY_DESCRIPTOR(buffer.as_mut_ptr() as *mut c_void);
+        if len == 0 || !GetFileSecurityW(PCWSTR(folder.as_ptr()), information, descriptor, len, &mut len).as_bool() {
+            return false;
+        }
+        // `AccessCheck` needs an impersonation token, which the token of the process isn't.
+        let mut process_token = HANDLE::default();
+        if !OpenProcessToken(GetCurrentProcess(), TOKEN_DUPLICATE, &mut process_token).as_bool() {
+            return false;
+        }
+        scopeguard::defer! { CloseHandle(process_token); }
+        let mut token = HANDLE::default();
+        if !DuplicateToken(process_token, SecurityImpersonation, &mut token).as_bool() {
+            return false;
+        }
+        scopeguard::defer! { CloseHandle(token); }
+        let mapping = GENERIC_MAPPING {
+            GenericRead: FILE_GENERIC_READ.0,
+            GenericWrite: FILE_GENERIC_WRITE.0,
+            GenericExecute: FILE_GENERIC_EXECUTE.0,
+            GenericAll: FILE_ALL_ACCESS.0,
+        };
+        let mut privileges = PRIVILEGE_SET::default();
+        let mut privileges_len = std::mem::size_of::<PRIVILEGE_SET>() as u32;
+        let (mut granted, mut allowed) = (0, 0);
+        AccessCheck(
+            descriptor,
+            token,
+            (FILE_ADD_FILE | FILE_ADD_SUBDIRECTORY).0,
+            &mapping,
+            Some(&mut privileges),
+            &mut privileges_len,
+            &mut granted,
+            &mut allowed,
+        )
+        .as_bool()
+            && allowed != 0
+    }
+}
+
+/// Returns the string form of the SID of the user the current process runs as, which is the name of
+/// the user's folder within `$Recycle.Bin`.
+fn current_user_sid() -> Result<String, Error> {
+    unsafe {
+        let mut token = HANDLE::default();
+        if !OpenProcessToken(GetCurrentProcess(), TOKEN_QUERY, &mut token).as_bool() {
+            return Err(windows::core::Error::from_win32().into());
+        }
+        scopeguard::defer! { CloseHandle(token); }
+        let mut len: u32 = 0;
+        GetTokenInformation(token, TokenUser, None, 0, &mut len);
+        // Use `u64`s so that the buffer is aligned for the pointers within `TOKEN_USER`.
+        let mut buffer = vec![0u64; (len as usize).div_ceil(8)];
+        if !GetTokenInformation(token, TokenUser, Some(buffer.as_mut_ptr() as *mut c_void), len, &mut len).as_bool() {
+            return Err(windows::core::Error::from_win32().into());
+        }
+        let user = &*(buffer.as_ptr() as *const TOKEN_USER);
+        sid_to_string(user.User.Sid)
+    }
+}
+
+/// Safety: `sid` must point to a valid SID.
+unsafe fn sid_to_string(sid: PSID) -> Result<String, Error> {
+    let mut string = PWSTR::null();
+    if !ConvertSidToStringSidW(sid, &mut string).as_bool() {
+        return Err(windows::core::Error::from_win32().into());
+    }
+    let result = wstr_to_os_string(string);
+    LocalFree(string.0 as isize);
+    result.into_string().map_err(|original| Error::ConvertOsString { original })
+}
+
+/// A SID allocated by `ConvertStringSidToSidW`, which is freed on drop.
+struct LocalSid(PSID);
+impl LocalSid {
+    fn from_string(sid: &str) -> Result<Self, Error> {
+        let wide_sid = to_wide_path(sid);
+        let mut psid = PSID::default();
+        if !unsafe { ConvertStringSidToSidW(PCWSTR(wide_sid.as_ptr()), &mut psid) }.as_bool() {
+            return Err(windows::core::Error::from_win32().into());
+        }
+        Ok(LocalSid(psid))
+    }
+}
+impl Drop for LocalSid {
+    fn drop(&mut self) {
+        unsafe { LocalFree(self.0 .0 as isize) };
+    }
+}
+
+/// Enables a privilege that the current process holds but that's disabled by default. Returns
+/// `false` if the process doesn't hold the privilege.
+fn enable_privilege(name: PCWSTR) -> bool {
+    unsafe {
+        let mut token = HANDLE::default();
+        if !OpenProcessToken(GetCurrentProcess(), TOKEN_ADJUST_PRIVILEGES | TOKEN_QUERY, &mut token).as_bool() {
+            return false;
+        }
+        scopeguard::defer! { CloseHandle(token); }
+        let mut luid = LUID::default();
+        if !LookupPrivilegeValueW(PCWSTR::null(), name, &mut luid).as_bool() {
+            return false;
+        }
+        let privileges = TOKEN_PRIVILEGES {
+            PrivilegeCount: 1,
+            Privileges: [LUID_AND_ATTRIBUTES { Luid: luid, Attributes: SE_PRIVILEGE_ENABLED }],
+        };
+        // This succeeds even when the privilege isn't held, which is only reported through the last error.
+        AdjustTokenPrivileges(token, false, Some(&privileges), 0, None, None).as_bool()
+            && GetLastError() != ERROR_NOT_ALL_ASSIGNED
+    }
+}
+
+/// A user account, identified by its SID.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub(crate) struct UserAccount {
+    pub(crate) sid: String,
+}
+
+impl UserAccount {
+    pub(crate) fn from_name(name: &str) -> Result<Self, Error> {
+        let wide_name = to_wide_path(name);
+        unsafe {
+            let mut sid_len: u32 = 0;
+            let mut domain_len: u32 = 0;
+            let mut sid_use = SID_NAME_USE::default();
+            LookupAccountNameW(
+                PCWSTR::null(),
+                PCWSTR(wide_name.as_ptr()),
+                PSID::default(),
+                &mut sid_len,
+                PWSTR::null(),
+                &mut domain_len,
+                &mut sid_use,
+            );
+            if sid_len == 0 {
+                return Err(windows::core::Error::from_win32().into());
+            }
+            // Use `u64`s so that the buffer is aligned for the SID.
+            let mut sid = vec![0u64; (sid_len as usize).div_ceil(8)];
+            let mut domain = vec![0u16; domain_len as usize];
+            let psid = PSID(sid.as_mut_ptr() as *mut c_void);
+            if !LookupAccountNameW(
+                PCWSTR::null(),
+                PCWSTR(wide_name.as_ptr()),
+                psid,
+                &mut sid_len,
+                PWSTR(domain.as_mut_ptr()),
+                &mut domain_len,
+                &mut sid_use,
+            )
+            .as_bool()
+            {
+                return Err(windows::core::Error::from_win32().into());
+            }
+            Ok(UserAccount { sid: sid_to_string(psid)? })
+        }
+    }
+
+    pub(crate) fn from_sid(sid: &str) -> Result<Self, Error> {
+        // Round trip through the binary form to validate and normalize the SID.
+        let psid = LocalSid::from_string(sid)?;
+        Ok(UserAccount { sid: unsafe { sid_to_string(psid.0)? } })
     }
 }
 
-fn to_wide_path(path: impl AsRef<OsStr>) -> Vec<u16> {
-    path.as_ref().encode_wide().chain(std::iter::once(0)).collect()
+/// Lists the Recycle Bin of another user by reading the `$I` files within `$Recycle.Bin\<SID>` on
+/// every drive, as the shell only exposes the Recycle Bin of the current user.
+///
+/// The ids of the returned items are the paths of their `$I` files.
+pub(crate) fn list_of_user(user: &UserAccount) -> Result<Vec<TrashItem>, Error> {
+    Ok(read_recycle_bin(volumes()?, user.sid.clone()).collect())
+}
+
+/// Lists the Recycle Bin of the current user on the given drives without the shell, see
+/// [`read_recycle_bin`]. The ids of the returned items are the paths of their `$R` files, like
+/// those listed by the shell.
+fn list_directly(volumes: Vec<PathBuf>) -> Result<impl Iterator<Item = TrashItem>, Error> {
+    Ok(read_recycle_bin(volumes, current_user_sid()?).filter_map(|mut item| match data_file_of(Path::new(&item.id)) {
+        // The shell hides index files whose data file is gone, and so does this.
+        Some(data_file) if data_file.symlink_metadata().is_ok() => {
+            item.id = data_file.into_os_string();
+            Some(item)
+        }
+        _ => None,
+    }))
+}
+
+/// Reads the `$I` files within `$Recycle.Bin\<sid>` on the given drives one by one, as the items
+/// are asked for, so that only the folder that's being read is kept open. The ids of the items are
+/// the paths of their `$I` files.
+fn read_recycle_bin(volumes: Vec<PathBuf>, sid: String) -> impl Iterator<Item = TrashItem> {
+    volumes
+        .into_iter()
+        .flat_map(move |volume| {
+            let folder = volume.join("$Recycle.Bin").join(&sid);
+            match std::fs::read_dir(&folder) {
+                Ok(entries) => Some(entries),
+                Err(e) => {
+                    debug!("Skipping {:?}: {}", folder, e);
+                    None
+                }
+            }
+        })
+        .flatten()
+        .flatten()
+        .filter(|entry| entry.file_name().to_string_lossy().starts_with("$I"))
+        .filter_map(|entry| item_of_index_file(entry.path()))
+}
+
+/// Creates the item from its `$I` file, with the path of that file as its id.
+fn item_of_index_file(index_file: PathBuf) -> Option<TrashItem> {
+    let Some(IndexFile { original_path, time_deleted, size }) = parse_index_file(&index_file) else {
+        warn!("Failed to parse the Recycle Bin index file {:?}", index_file);
+        return None;
+    };
+    let (Some(name), Some(original_parent)) = (original_path.file_name(), original_path.parent()) else {
+        warn!("The Recycle Bin index file {:?} holds an invalid path: {:?}", index_file, original_path);
+        return None;
+    };
+    Some(TrashItem {
+        name: name.to_string_lossy().into_owned(),
+        original_parent: Some(original_parent.to_owned()),
+        time_deleted: Some(time_deleted),
+        size: Some(ByteSize::new(size)),
+        kind: data_file_of(&index_file)
+            .and_then(|data_file| data_file.symlink_metadata().ok())
+            .map(|metadata| TrashItemKind::of(metadata.file_type())),
+        unknown_reason: None,
+        pinned: pin_stream_of(&index_file).exists(),
+        id: index_file.into_os_string(),
+    })
+}
+
+/// The `$I` and `$R` files of the item with the given id, which may be the path of either file.
+fn recycled_files_of(id: &Path) -> Option<(PathBuf, PathBuf)> {
+    match id.file_name().map(|name| name.to_string_lossy().starts_with("$I")) {
+        Some(true) => data_file_of(id).map(|data_file| (id.to_owned(), data_file)),
+        _ => index_file_of(id).map(|index_file| (index_file, id.to_owned())),
+    }
+}
+
+/// The `$R` file or folder of the item.
+pub(crate) fn file_in_trash(item: &TrashItem) -> Result<PathBuf, Error> {
+    match recycled_files_of(Path::new(&item.id)) {
+        Some((_, data_file)) => Ok(data_file),
+        None => Err(Error::InvalidItemId { id: item.id.clone() }),
+    }
+}
+
+/// The alternate data stream of the `$I` file that marks an item as pinned. It's removed along with
+/// the `$I` file when the item is restored or purged.
+const PINNED_STREAM: &str = "trash.pinned";
+
+/// The path of the [`PINNED_STREAM`] of the `$I` file at `index_file`.
+fn pin_stream_of(index_file: &Path) -> PathBuf {
+    let mut path = index_file.as_os_str().to_owned();
+    path.push(":");
+    path.push(PINNED_STREAM);
+    path.into()
+}
+
+/// Whether the item with the given id has a [`PINNED_STREAM`].
+fn is_pinned(id: &Path) -> bool {
+    recycled_files_of(id).is_some_and(|(index_file, _)| pin_stream_of(&index_file).exists())
+}
+
+/// Creates or removes the [`PINNED_STREAM`] of the item. Fails on file systems without alternate
+/// data streams, like FAT.
+pub(crate) fn set_pinned(item: &TrashItem, pinned: bool) -> Result<(), Error> {
+    let Some((index_file, _)) = recycled_files_of(Path::new(&item.id)) else {
+        return Err(Error::InvalidItemId { id: item.id.clone() });
+    };
+    let stream = pin_stream_of(&index_file);
+    let result = match pinned {
+        true => std::fs::write(&stream, []),
+        false => match std::fs::remove_file(&stream) {
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+            result => result,
+        },
+    };
+    result.map_err(|e| io_error(&stream, e))
+}
+
+/// Whether the shell can't handle `path` as it's longer than `MAX_PATH`, which includes the
+/// terminating NUL.
+fn exceeds_max_path(path: &Path) -> bool {
+    to_wide_path(path).len() > MAX_PATH as usize
+}
+
+/// Restores an item to `target` without the shell by renaming its `$R` file and removing its `$I`
+/// file. This works for paths that are longer than `MAX_PATH`, as the standard library passes such
+/// paths to Windows with the `\\?\` prefix, regardless of whether long paths are enabled.
+fn restore_directly(item: &TrashItem, target: &Path) -> Result<(), Error> {
+    let Some((index_file, data_file)) = recycled_files_of(Path::new(&item.id)) else {
+        return Err(Error::PathTooLong { path: target.to_owned(), limit: MAX_PATH as usize - 1 });
+    };
+    debug!("Restoring {:?} to {:?} without the shell, as the path is too long for it", data_file, target);
+    if let Some(parent) = target.parent() {
+        std::fs::create_dir_all(parent).map_err(|e| io_error(parent, e))?;
+    }
+    // Unlike the shell, renaming replaces an existing file, so a file that appeared since the
+    // collisions were checked is kept here.
+    if target.symlink_metadata().is_ok() {
+        return Err(Error::RestoreTargetExists { path: target.to_owned() });
+    }
+    std::fs::rename(&data_file, target).map_err(|e| io_error(target, e))?;
+    std::fs::remove_file(&index_file).map_err(|e| io_error(&index_file, e))
+}
+
+/// Removes the `$I` and `$R` files of the items without the shell. The ids of the items may be the
+/// paths of either file.
+fn purge_directly(
+    items: &[&TrashItem],
+    continue_on_error: bool,
+    cancellation: Option<&CancellationToken>,
+) -> Result<(), Error> {
+    let mut first_error = None;
+    for item in items {
+        if cancellation.is_some_and(CancellationToken::is_cancelled) {
+            return Err(Error::Cancelled);
+        }
+        let id = Path::new(&item.id);
+        let result = match recycled_files_of(id) {
+            Some((index_file, data_file)) => remove_recycled(&data_file)
+                .and_then(|()| std::fs::remove_file(&index_file).map_err(|e| io_error(&index_file, e))),
+            None => Err(Error::InvalidItemId { id: item.id.clone() }),
+        };
+        if let Err(e) = result {
+            if !continue_on_error {
+                return Err(e);
+            }
+            warn!("Continuing with the remaining items after an error: {:?}", e);
+            first_error.get_or_insert(e);
+        }
+    }
+    first_error.map_or(Ok(()), Err)
+}
+
+/// Removes a `$R` file, or a `$R` folder with everything in it.
+fn remove_recycled(data_file: &Path) -> Result<(), Error> {
+    let result = match data_file.symlink_metadata() {
+        Ok(metadata) if metadata.is_dir() => std::fs::remove_dir_all(data_file),
+        Ok(_) => std::fs::remove_file(data_file),
+        Err(e) => Err(e),
+    };
+    result.map_err(|e| io_error(data_file, e))
+}
+
+/// The contents of a `$I` file of the Recycle Bin, which describes the `$R` file with the same
+/// suffix.
+#[derive(Debug, PartialEq, Eq)]
+struct IndexFile {
+    original_path: PathBuf,
+    time_deleted: i64,
+    /// The size of the item in bytes, including the contents of folders.
+    size: u64,
+}
+
+/// Parses a `$I` file of the Recycle Bin.
+fn parse_index_file(path: &Path) -> Option<IndexFile> {
+    let data = std::fs::read(path).ok()?;
+    let u64_at = |offset: usize| Some(u64::from_le_bytes(data.get(offset..offset + 8)?.try_into().ok()?));
+    let version = u64_at(0)?;
+    let size = u64_at(8)?;
+    let time_deleted = filetime_to_unix(u64_at(16)?);
+    let path_bytes = match version {
+        // Vista up to Windows 8.1: a fixed size buffer of `MAX_PATH` characters.
+        1 => data.get(24..24 + 2 * MAX_PATH as usize)?,
+        // Windows 10 and later: the length in characters, followed by the path.
+        2 => {
+            // The length comes from the file, so it must not overflow on 32-bit targets.
+            let len = usize::try_from(u32::from_le_bytes(data.get(24..28)?.try_into().ok()?)).ok()?;
+            data.get(28..len.checked_mul(2)?.checked_add(28)?)?
+        }
+        _ => return None,
+    };
+    let wide_path: Vec<u16> =
+        path_bytes.chunks_exact(2).map(|c| u16::from_le_bytes([c[0], c[1]])).take_while(|&c| c != 0).collect();
+    Some(IndexFile { original_path: PathBuf::from(OsString::from_wide(&wide_path)), time_deleted, size })
+}
+
+/// Restores items listed by [`list_of_user`] by moving their `$R` files back to their original
+/// location, and makes `user` the owner of the restored files.
+pub(crate) fn restore_all_of_user(user: &UserAccount, items: Vec<TrashItem>) -> Result<(), Error> {
+    let owner = LocalSid::from_string(&user.sid)?;
+    // Assigning another user as the owner requires this privilege, which administrators hold but
+    // don't have enabled by default.
+    if !enable_privilege(SE_RESTORE_NAME) {
+        warn!("Failed to enable the restore privilege, so changing the owner of restored items may fail");
+    }
+    let mut iter = items.into_iter();
+    while let Some(item) = iter.next() {
+        let index_file = PathBuf::from(&item.id);
+        let Some(data_file) = data_file_of(&index_file) else {
+            return Err(Error::InvalidItemId { id: item.id.clone() });
+        };
+        let original_path = item.known_original_path()?;
+        if original_path.symlink_metadata().is_ok() {
+            let remaining: Vec<_> = std::iter::once(item).chain(iter).collect();
+            return Err(Error::RestoreCollision { path: original_path, remaining_items: remaining });
+        }
+        if let Some(original_parent) = original_path.parent() {
+            std::fs::create_dir_all(original_parent).map_err(|e| io_error(original_parent, e))?;
+        }
+        std::fs::rename(&data_file, &original_path).map_err(|e| io_error(&data_file, e))?;
+        std::fs::remove_file(&index_file).map_err(|e| io_error(&index_file, e))?;
+        set_owner_recursive(&original_path, &owner)?;
+    }
+    Ok(())
+}
+
+/// Makes `owner` the owner of `path` and everything within it, without following links.
+fn set_owner_recursive(path: &Path, owner: &LocalSid) -> Result<(), Error> {
+    let mut pending = vec![path.to_owned()];
+    while let Some(path) = pending.pop() {
+        let wide_path = to_wide_path(&path);
+        let result = unsafe {
+            SetNamedSecurityInfoW(
+                PCWSTR(wide_path.as_ptr()),
+                SE_FILE_OBJECT,
+                OWNER_SECURITY_INFORMATION,
+                owner.0,
+                PSID::default(),
+                None,
+                None,
+            )
+        };
+        if result != ERROR_SUCCESS {
+            return Err(windows::core::Error::from(result.to_hresult()).into());
+        }
+        let metadata = path.symlink_metadata().map_err(crate::into_unknown)?;
+        if metadata.is_dir() && !metadata.is_symlink() {
+            for entry in std::fs::read_dir(&path).map_err(crate::into_unknown)? {
+                pending.push(entry.map_err(crate::into_unknown)?.path());
+            }
+        }
+    }
+    Ok(())
+}
+
+impl TrashContext {
+    /// See https://docs.microsoft.com/en-us/windows/win32/api/shellapi/ns-shellapi-_shfileopstructa
+    pub(crate) fn delete_specified_canonicalized(&self, full_paths: Vec<PathBuf>) -> Result<(), Error> {
+        self.delete_with_flags(&full_paths, FOF_ALLOWUNDO | FOF_WANTNUKEWARNING)
+    }
+
+    pub(crate) fn delete_permanently_canonicalized(&self, full_paths: &[PathBuf]) -> Result<(), Error> {
+        self.delete_with_flags(full_paths, 0)
+    }
+
+    /// Deletes the items in a single shell file operation, which moves them to the Recycle Bin if
+    /// `flags` contain `FOF_ALLOWUNDO`.
+    fn delete_with_flags(&self, full_paths: &[PathBuf], flags: u32) -> Result<(), Error> {
+        with_com(|| {
+            unsafe {
+                let pfo = match self.file_operation(self.continue_on_error, flags) {
+                    Ok(pfo) => pfo,
+                    Err(Error::Os { code, function: Some(function), description })
+                        if function == "CoCreateInstance" && is_file_operation_unavailable(HRESULT(code)) =>
+                    {
+                        debug!("IFileOperation is unavailable, falling back to SHFileOperationW: {}", description);
+                        return self.delete_with_legacy_operation(full_paths, flags);
+                    }
+                    Err(e) => return Err(e),
+                };
+
+                // When every item is in the same folder, which is typical for mass deletes, the folder is
+                // only parsed once and the items are created relative to it. This is considerably faster
+                // than parsing the absolute path of each item.
+                let common_parent = match full_paths.first().and_then(|p| p.parent()) {
+                    Some(parent) if full_paths.len() > 1 && full_paths.iter().all(|p| p.parent() == Some(parent)) => {
+                        Some(shell_item_from_path(parent)?)
+                    }
+                    _ => None,
+                };
+                let mut shell_items = Vec::with_capacity(full_paths.len());
+                for full_path in full_paths.iter() {
+                    let shi: IShellItem = match (&common_parent, full_path.file_name()) {
+                        (Some(parent), Some(name)) => {
+                            let name = to_wide_path(name);
+                            SHCreateItemFromRelativeName(parent, PCWSTR(name.as_ptr()), None)
+                                .map_err(|e| shell_item_error(full_path, "SHCreateItemFromRelativeName", e))?
+                        }
+                        _ => shell_item_from_path(full_path)?,
+                    };
+                    shell_items.push(shi);
+                }
+                // The shell keeps better track of large batches that are queued as a single array than
+                // of the same items queued one by one.
+                match shell_items.as_slice() {
+                    [] => return Ok(()),
+                    [shi] => pfo.DeleteItem(shi, None).in_function("IFileOperation::DeleteItem")?,
+                    _ => {
+                        pfo.DeleteItems(&shell_item_array(&shell_items)?).in_function("IFileOperation::DeleteItems")?
+                    }
+                }
+                pfo.perform()
+            }
+        })
+    }
+
+    /// Deletes the items with `SHFileOperationW`, for the systems that don't provide
+    /// `IFileOperation`. Only the flags that fit into its 16 bits are passed on, the extended
+    /// `FOFX_*` flags are dropped.
+    fn delete_with_legacy_operation(&self, full_paths: &[PathBuf], flags: u32) -> Result<(), Error> {
+        if !self.continue_on_error {
+            return self.legacy_operation(full_paths, flags);
+        }
+        // `SHFileOperationW` stops at the first item that fails, so the items are deleted one at a
+        // time to carry on with the others.
+        let mut first_error = None;
+        for full_path in full_paths {
+            match self.legacy_operation(std::slice::from_ref(full_path), flags) {
+                Ok(()) => {}
+                Err(Error::Cancelled) => return Err(Error::Cancelled),
+                Err(e) => {
+                    warn!("Continuing with the remaining items after an error: {:?}", e);
+                    first_error.get_or_insert(e);
+                }
+            }
+        }
+        first_error.map_or(Ok(()), Err)
+    }
+
+    /// Deletes the items in a single `SHFileOperationW` call.
+    fn legacy_operation(&self, full_paths: &[PathBuf], flags: u32) -> Result<(), Error> {
+        self.check_cancelled()?;
+        // The paths are separated by null characters, and the list ends with an empty path.
+        let mut from = Vec::new();
+        for full_path in full_paths {
+            let full_path = full_path.as_os_str().encode_wide().collect::<Vec<_>>();
+            let prefix = [b'\\' as u16, b'\\' as u16, b'?' as u16, b'\\' as u16];
+            from.extend_from_slice(full_path.strip_prefix(&prefix[..]).unwrap_or(&full_path));
+            from.push(0);
+        }
+        from.push(0);
+        let settings = &self.platform_specific;
+        let title = settings.operation_title.as_ref().map(to_wide_path);
+        let ui_flags = match (settings.show_ui, &title) {
+            (false, _) => FOF_NO_UI,
+            (true, Some(_)) => FOF_SIMPLEPROGRESS,
+            (true, None) => 0,
+        };
+        let mut operation = SHFILEOPSTRUCTW {
+            wFunc: FO_DELETE,
+            pFrom: PCWSTR(from.as_ptr()),
+            fFlags: ((ui_flags | flags) & 0xFFFF) as u16,
+            lpszProgressTitle: title.as_ref().map_or(PCWSTR::null(), |title| PCWSTR(title.as_ptr())),
+            ..Default::default()
+        };
+        let result = unsafe { SHFileOperationW(&mut operation) };
+        if operation.fAnyOperationsAborted.as_bool() {
+            return Err(Error::Cancelled);
+        }
+        match result {
+            0 => Ok(()),
+            code => Err(legacy_operation_error(code)),
+        }
+    }
+
+    pub(crate) fn destination_canonicalized(
+        &self,
+        full_path: &Path,
+    ) -> Result<(PathBuf, PathBuf, TrashTransfer), Error> {
+        let volume = volume_path_of(full_path)?;
+        if let Some(trash_root) = wsl::distro_path(full_path).and_then(|path| path.trash_root()) {
+            return Ok((trash_root, volume, TrashTransfer::Rename));
+        }
+        let transfer = volume_transfer(&to_wide_path(&volume));
+        let trash_root = volume.join("$Recycle.Bin").join(current_user_sid()?);
+        Ok((trash_root, volume, transfer))
+    }
+
+    /// Whether the shell would delete each of the items permanently, because its volume has no
+    /// Recycle Bin or it doesn't fit into the Recycle Bin. The drive type and the capacity of the
+    /// Recycle Bin are looked up once per volume, so that checking a large batch stays cheap.
+    pub(crate) fn bypasses_recycle_bin(&self, full_paths: &[PathBuf]) -> Vec<bool> {
+        let mut volumes: HashMap<PathBuf, (TrashTransfer, Option<ByteSize>)> = HashMap::new();
+        full_paths
+            .iter()
+            .map(|full_path| {
+                if wsl::distro_path(full_path).and_then(|path| path.trash_root()).is_some() {
+                    return false;
+                }
+                let Ok(volume) = volume_path_of(full_path) else {
+                    return false;
+                };
+                let (transfer, capacity) = *volumes.entry(volume).or_insert_with_key(|volume| {
+                    let wide_volume = to_wide_path(volume);
+                    match volume_transfer(&wide_volume) {
+                        TrashTransfer::Rename => (TrashTransfer::Rename, recycle_bin_capacity(&wide_volume)),
+                        transfer => (transfer, None),
+                    }
+                });
+                transfer == TrashTransfer::PermanentDelete
+                    || capacity.is_some_and(|capacity| destination::exceeds_size(full_path, capacity))
+            })
+            .collect()
+    }
+
+    /// Removes all files and folder paths recursively.
+    pub(crate) fn delete_all_canonicalized(&self, full_paths: Vec<PathBuf>) -> Result<(), Error> {
+        let mut first_error = None;
+        let full_paths = self.move_to_distro_trash(full_paths, &mut first_error)?;
+        if full_paths.is_empty() {
+            return first_error.map_or(Ok(()), Err);
+        }
+        let full_paths = match self.platform_specific.allow_permanent_delete {
+            true => full_paths,
+            false => self.refuse_permanent_deletes(full_paths, &mut first_error)?,
+        };
+        if full_paths.is_empty() {
+            return first_error.map_or(Ok(()), Err);
+        }
+        if self.platform_specific.quota_policy != QuotaPolicy::Ignore {
+            self.apply_quota_policy(&full_paths)?;
+        }
+        self.delete_specified_canonicalized(full_paths)?;
+        first_error.map_or(Ok(()), Err)
+    }
+
+    /// Moves the items within WSL distributions to the home trash of the distribution, since the
+    /// shell would delete them permanently, and returns the other paths.
+    fn move_to_distro_trash(
+        &self,
+        full_paths: Vec<PathBuf>,
+        first_error: &mut Option<Error>,
+    ) -> Result<Vec<PathBuf>, Error> {
+        let mut remaining = Vec::with_capacity(full_paths.len());
+        for full_path in full_paths {
+            self.check_cancelled()?;
+            let distro_path = wsl::distro_path(&full_path);
+            let Some((distro_path, trash_root)) =
+                distro_path.and_then(|path| path.trash_root().map(|root| (path, root)))
+            else {
+                remaining.push(full_path);
+                continue;
+            };
+            debug!("Moving {:?} to the trash of its WSL distribution at {:?}", full_path, trash_root);
+            if let Err(e) = wsl::move_to_distro_trash(&full_path, &distro_path, &trash_root) {
+                let e = io_error(&full_path, e);
+                if !self.continue_on_error {
+                    return Err(e);
+                }
+                warn!("Continuing with the remaining items after an error: {:?}", e);
+                first_error.get_or_insert(e);
+            }
+        }
+        Ok(remaining)
+    }
+
+    /// Fails with [`Error::CouldNotTrashWouldDelete`] for the items that the shell would delete
+    /// permanently, and returns the other paths.
+    fn refuse_permanent_deletes(
+        &self,
+        full_paths: Vec<PathBuf>,
+        first_error: &mut Option<Error>,
+    ) -> Result<Vec<PathBuf>, Error> {
+        let mut remaining = Vec::with_capacity(full_paths.len());
+        let bypasses = self.bypasses_recycle_bin(&full_paths);
+        for (full_path, bypasses) in full_paths.into_iter().zip(bypasses) {
+            self.check_cancelled()?;
+            if !bypasses {
+                remaining.push(full_path);
+                continue;
+            }
+            let e = Error::CouldNotTrashWouldDelete { path: full_path };
+            if !self.continue_on_error {
+                return Err(e);
+            }
+            warn!("Continuing with the remaining items after an error: {:?}", e);
+            first_error.get_or_insert(e);
+        }
+        Ok(remaining)
+    }
+
+    /// Checks for each volume whether the items fit into its Recycle Bin, and acts according to
+    /// the quota policy if they don't.
+    fn apply_quota_policy(&self, full_paths: &[PathBuf]) -> Result<(), Error> {
+        let mut bytes_per_volume: HashMap<PathBuf, ByteSize> = HashMap::new();
+        for full_path in full_paths {
+            let (_, volume, transfer) = self.destination_canonicalized(full_path)?;
+            if transfer != TrashTransfer::PermanentDelete {
+                let bytes = destination::total_size(full_path)?;
+                let total = bytes_per_volume.entry(volume).or_default();
+                *total = *total + bytes;
+            }
+        }
+        for (volume, bytes) in bytes_per_volume {
+            let usage = recycle_bin_usage(&volume)?;
+            let Some(capacity) = usage.capacity else {
+                continue;
+            };
+            let projected = usage.bytes + bytes;
+            if projected <= capacity {
+                continue;
+            }
+            if self.platform_specific.quota_policy == QuotaPolicy::Warn || bytes > capacity {
+                warn!(
+                    "Deleting {} makes the Recycle Bin on {:?} exceed its maximum size of {}",
+                    bytes, volume, capacity
+                );
+                continue;
+            }
+            self.purge_oldest(&volume, projected.saturating_sub(capacity))?;
+        }
+        Ok(())
+    }
+
+    /// Purges the oldest items in the Recycle Bin of `volume` until at least `bytes` are freed.
+    fn purge_oldest(&self, volume: &Path, bytes: ByteSize) -> Result<(), Error> {
+        // The ids are the paths of the items within `$Recycle.Bin` on their volume.
+        let mut items: Vec<_> =
+            list()?.into_iter().filter(|item| !item.pinned && Path::new(&item.id).starts_with(volume)).collect();
+        items.sort_by(TrashItem::by_deletion);
+        let mut freed = ByteSize::ZERO;
+        let oldest: Vec<_> = items
+            .into_iter()
+            .take_while(|item| {
+                let done = freed >= bytes;
+                freed = freed + destination::total_size(Path::new(&item.id)).unwrap_or_default();
+                !done
+            })
+            .collect();
+        debug!("Purging {} items from the Recycle Bin on {:?} to free {}", oldest.len(), volume, bytes);
+        self.purge_all(oldest)
+    }
+}
+
+pub(crate) fn list() -> Result<Vec<TrashItem>, Error> {
+    list_limited(None, None, false)
+}
+
+/// Lists the items through the shell, failing once there are more than `limit` items. With a
+/// `trash_root`, only the Recycle Bin of that drive is listed.
+fn list_limited(trash_root: Option<&Path>, limit: Option<usize>, lenient: bool) -> Result<Vec<TrashItem>, Error> {
+    let mut item_vec = Vec::new();
+    for item in ListIter::new(trash_root, lenient) {
+        limit::push(&mut item_vec, item?, limit)?;
+    }
+    Ok(item_vec)
+}
+
+pub(crate) fn list_iter() -> ListIter {
+    ListIter::new(None, false)
+}
+
+/// Enumerates the Recycle Bin that the shell merges from all drives, or only the one at
+/// `trash_root`.
+fn enum_recycle_bin(trash_root: Option<&Path>) -> Result<IEnumShellItems, Error> {
+    with_com(|| {
+        unsafe {
+            let recycle_bin: IShellItem = match trash_root {
+                // The `$Recycle.Bin\<SID>` folder of a drive is parsed into a Recycle Bin of its own,
+                // which only holds the items of that drive.
+                Some(trash_root) => shell_item_from_path(trash_root)?,
+                None => SHGetKnownFolderItem(&FOLDERID_RecycleBinFolder, KF_FLAG_DEFAULT, HANDLE::default())?,
+            };
+            Ok(recycle_bin.BindToHandler(None, &BHID_EnumItems)?)
+        }
+    })
+}
+
+/// Lists the items through the shell one by one, fetching the next item from the enumerator of
+/// the Recycle Bin as it's asked for. A failure to start the enumeration is the only item, and the
+/// listing ends after the enumerator failed.
+pub(crate) struct ListIter {
+    error: Option<Error>,
+    items: Option<IEnumShellItems>,
+    lenient: bool,
+    /// Keeps the languages of the display locale while the names are read.
+    _language: Option<ThreadLanguage>,
+}
+
+impl ListIter {
+    /// With `lenient`, items whose original location or deletion date can't be read are listed
+    /// without them, see [`TrashContextExtWindows::set_lenient_listing`].
+    fn new(trash_root: Option<&Path>, lenient: bool) -> Self {
+        match enum_recycle_bin(trash_root) {
+            Ok(items) => ListIter { error: None, items: Some(items), lenient, _language: None },
+            Err(e) => ListIter { error: Some(e), items: None, lenient, _language: None },
+        }
+    }
+}
+
+impl Iterator for ListIter {
+    type Item = Result<TrashItem, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(e) = self.error.take() {
+            return Some(Err(e));
+        }
+        let items = self.items.as_ref()?;
+        let mut fetched_count: u32 = 0;
+        let mut arr = [None];
+        let fetched = unsafe { items.Next(&mut arr, Some(&mut fetched_count as *mut u32)) };
+        match (fetched, &arr[0]) {
+            (Err(e), _) => {
+                self.items = None;
+                let e = e.into();
+                note_broken_com(&e);
+                Some(Err(e))
+            }
+            (Ok(()), Some(item)) if fetched_count > 0 => {
+                Some(unsafe { trash_item_from_shell_item(item, self.lenient) })
+            }
+            _ => {
+                self.items = None;
+                None
+            }
+        }
+    }
+}
+
+impl TrashContext {
+    /// Lists the items according to the settings of the context.
+    pub(crate) fn list_items(&self) -> Result<Vec<TrashItem>, Error> {
+        match self.platform_specific.recycle_bin_access {
+            // The original file names are all that's available without the shell.
+            RecycleBinAccess::Direct => limit::collect(list_directly(volumes()?)?, self.list_limit),
+            RecycleBinAccess::Shell => self.list_through_shell(None),
+        }
+    }
+
+    /// Lists the items one by one according to the settings of the context, see [`ListIter`] and
+    /// [`read_recycle_bin`].
+    pub(crate) fn list_iter(&self) -> Box<dyn Iterator<Item = Result<TrashItem, Error>>> {
+        let lenient = self.platform_specific.lenient_listing;
+        if self.platform_specific.recycle_bin_access == RecycleBinAccess::Direct {
+            return match volumes().and_then(list_directly) {
+                Ok(items) => Box::new(items.map(Ok)),
+                Err(e) => Box::new(std::iter::once(Err(e))),
+            };
+        }
+        match &self.display_locale {
+            DisplayLocale::System => Box::new(ListIter::new(None, lenient)),
+            DisplayLocale::Language(language) => match ThreadLanguage::set(language) {
+                // As with `list_with_locale`, the language is set before the enumeration starts.
+                Ok(language) => Box::new(ListIter { _language: Some(language), ..ListIter::new(None, lenient) }),
+                Err(e) => Box::new(std::iter::once(Err(e))),
+            },
+            DisplayLocale::Raw => Box::new(ListIter::new(None, lenient).map(|item| {
+                let mut item = item?;
+                match raw_name(Path::new(&item.id)) {
+                    Some(name) => item.name = name,
+                    None => debug!("Keeping the display name of {:?} as its index file can't be read", item.id),
+                }
+                Ok(item)
+            })),
+        }
+    }
+
+    /// Lists the items in the `$Recycle.Bin\<SID>` folder at `trash_root` according to the
+    /// settings of the context.
+    pub(crate) fn list_items_in(&self, trash_root: &Path) -> Result<Vec<TrashItem>, Error> {
+        match self.platform_specific.recycle_bin_access {
+            RecycleBinAccess::Direct => {
+                let drive = trash_root.ancestors().nth(2).map(Path::to_owned);
+                limit::collect(list_directly(drive.into_iter().collect())?, self.list_limit)
+            }
+            RecycleBinAccess::Shell => self.list_through_shell(Some(trash_root)),
+        }
+    }
+
+    fn list_through_shell(&self, trash_root: Option<&Path>) -> Result<Vec<TrashItem>, Error> {
+        let lenient = self.platform_specific.lenient_listing;
+        list_with_locale(&self.display_locale, trash_root, self.list_limit, lenient)
+    }
+
+    /// The items in the Recycle Bin on the drive of `original_path` that were located there. Only
+    /// the `$I` files on that drive are read, which is cheaper than having the shell enumerate the
+    /// whole Recycle Bin. The shell is only asked for the items that match.
+    pub(crate) fn find_original(&self, original_path: &Path, case_sensitive: bool) -> Result<Vec<TrashItem>, Error> {
+        let Some(drive) = original_path.ancestors().last() else {
+            return Ok(Vec::new());
+        };
+        let matches = read_recycle_bin(vec![drive.to_owned()], current_user_sid()?).filter(|item| {
+            item.original_path().is_some_and(|path| matching::paths_match(&path, original_path, case_sensitive))
+        });
+        // Like the shell, index files whose data file is gone are ignored. The items are created
+        // from their data files, so that their ids are the same as those of listed items.
+        let data_files: Vec<_> = matches
+            .filter_map(|item| data_file_of(Path::new(&item.id)))
+            .filter(|data_file| data_file.symlink_metadata().is_ok())
+            .map(PathBuf::into_os_string)
+            .collect();
+        let ids: Vec<_> = data_files.iter().map(OsString::as_os_str).collect();
+        items_by_ids(&ids).into_iter().collect()
+    }
+}
+
+fn list_with_locale(
+    locale: &DisplayLocale,
+    trash_root: Option<&Path>,
+    limit: Option<usize>,
+    lenient: bool,
+) -> Result<Vec<TrashItem>, Error> {
+    match locale {
+        DisplayLocale::System => list_limited(trash_root, limit, lenient),
+        DisplayLocale::Language(language) => {
+            // The shell loads the localized names on the calling thread, and COM calls into an
+            // apartment-threaded Recycle Bin are made on it as well.
+            let _language = ThreadLanguage::set(language)?;
+            list_limited(trash_root, limit, lenient)
+        }
+        DisplayLocale::Raw => {
+            let mut items = list_limited(trash_root, limit, lenient)?;
+            for item in &mut items {
+                match raw_name(Path::new(&item.id)) {
+                    Some(name) => item.name = name,
+                    None => debug!("Keeping the display name of {:?} as its index file can't be read", item.id),
+                }
+            }
+            Ok(items)
+        }
+    }
+}
+
+/// The file name the item had before it was deleted, read from the `$I` file that belongs to the
+/// `$R` file at `data_file`.
+fn raw_name(data_file: &Path) -> Option<String> {
+    let original_path = parse_index_file(&index_file_of(data_file)?)?.original_path;
+    original_path.file_name()?.to_str().map(str::to_owned)
+}
+
+/// The `$I` file that holds the original path and the deletion time of the `$R` file at
+/// `data_file`, like `C:\$Recycle.Bin\<SID>\$IABC123.txt` for `$RABC123.txt`.
+fn index_file_of(data_file: &Path) -> Option<PathBuf> {
+    let name = data_file.file_name()?.to_str()?;
+    let suffix = name.strip_prefix("$R")?;
+    Some(data_file.with_file_name(format!("$I{suffix}")))
+}
+
+/// The ids of the items are the paths of their `$R` or `$I` files within `$Recycle.Bin\<SID>`.
+pub(crate) fn trash_root_of(id: &Path) -> Option<PathBuf> {
+    let user_folder = id.parent()?;
+    let recycle_bin = user_folder.parent()?.file_name()?;
+    recycle_bin.eq_ignore_ascii_case("$Recycle.Bin").then(|| user_folder.to_owned())
+}
+
+/// The `$R` file or folder whose original path and deletion time are held by the `$I` file at
+/// `index_file`.
+fn data_file_of(index_file: &Path) -> Option<PathBuf> {
+    let name = index_file.file_name()?.to_str()?;
+    let suffix = name.strip_prefix("$I")?;
+    Some(index_file.with_file_name(format!("$R{suffix}")))
+}
+
+/// Overrides the preferred user interface languages of the current thread, and puts the previous
+/// ones back when it's dropped.
+struct ThreadLanguage {
+    /// The NUL separated list of the previous languages, which is empty if the thread had none.
+    previous: Vec<u16>,
+}
+
+impl ThreadLanguage {
+    fn set(language: &str) -> Result<Self, Error> {
+        let flags = MUI_LANGUAGE_NAME | MUI_THREAD_LANGUAGES;
+        let (mut count, mut len) = (0u32, 0u32);
+        let mut previous = Vec::new();
+        unsafe {
+            if GetThreadPreferredUILanguages(flags, &mut count, PWSTR::null(), &mut len).as_bool() && len > 0 {
+                previous = vec![0u16; len as usize];
+                if !GetThreadPreferredUILanguages(flags, &mut count, PWSTR(previous.as_mut_ptr()), &mut len).as_bool() {
+                    return Err(windows::core::Error::from_win32().into());
+                }
+            }
+        }
+        // The list is terminated by two NULs.
+        let languages: Vec<u16> = language.encode_utf16().chain([0, 0]).collect();
+        if !unsafe { SetThreadPreferredUILanguages(MUI_LANGUAGE_NAME, PCWSTR(languages.as_ptr()), None) }.as_bool() {
+            return Err(windows::core::Error::from_win32().into());
+        }
+        // A thread without languages of its own is left with just the terminating NULs.
+        if previous.iter().all(|&c| c == 0) {
+            previous.clear();
+        }
+        Ok(ThreadLanguage { previous })
+    }
+}
+
+impl Drop for ThreadLanguage {
+    fn drop(&mut self) {
+        let restored = unsafe {
+            if self.previous.is_empty() {
+                // Clears the languages of the thread, so that it uses those of the process again.
+                SetThreadPreferredUILanguages(0, PCWSTR::null(), None)
+            } else {
+                SetThreadPreferredUILanguages(MUI_LANGUAGE_NAME, PCWSTR(self.previous.as_ptr()), None)
+            }
+        };
+        if !restored.as_bool() {
+            warn!("Failed to restore the user interface languages of the thread");
+        }
+    }
+}
+
+/// With `lenient`, a missing original location or deletion date is logged and left out instead of
+/// failing.
+///
+/// Safety: `item` must be an item of the Recycle Bin folder.
+unsafe fn trash_item_from_shell_item(item: &IShellItem, lenient: bool) -> Result<TrashItem, Error> {
+    let id = get_display_name(item, SIGDN_DESKTOPABSOLUTEPARSING)?;
+    let item2: IShellItem2 = item.cast()?;
+    // The `$I` file holds the exact path the item was deleted from. The name that the shell
+    // displays lacks the extension of known file types if Explorer hides those, and is localized
+    // for some folders. The shell also doesn't provide the deletion date for some items, like those
+    // in the Recycle Bin of drives it has trouble reading.
+    let index_file = index_file_of(Path::new(&id)).and_then(|index_file| parse_index_file(&index_file));
+    let original_path = index_file.as_ref().map(|index_file| index_file.original_path.as_path());
+    let (name, original_parent) = match original_path.and_then(|path| Some((path.file_name()?, path.parent()?))) {
+        Some((name, original_parent)) => (name.to_owned(), Some(original_parent.to_owned())),
+        None => {
+            let original_location = item2
+                .GetProperty(&SCID_ORIGINAL_LOCATION)
+                .and_then(|variant| PropVariantToBSTR(&variant))
+                .map(|bstr| PathBuf::from(OsString::from_wide(bstr.as_wide())));
+            let original_parent = match original_location {
+                Ok(original_location) => Some(original_location),
+                Err(e) if lenient => {
+                    warn!("Listing {:?} without its original location, which can't be read: {:?}", id, e);
+                    None
+                }
+                Err(e) => return Err(e.into()),
+            };
+            (get_display_name(item, SIGDN_PARENTRELATIVE)?, original_parent)
+        }
+    };
+    let date_deleted = match get_date_deleted_unix(&item2) {
+        Ok(date_deleted) => Some(date_deleted),
+        Err(e) => match &index_file {
+            Some(IndexFile { time_deleted, .. }) => {
+                debug!("Read the deletion date of {:?} from its index file after {:?}", id, e);
+                Some(*time_deleted)
+            }
+            None if lenient => {
+                warn!("Listing {:?} without its deletion date, which can't be read: {:?}", id, e);
+                None
+            }
+            None => return Err(e),
+        },
+    };
+    // This is the size column of the Recycle Bin, which includes the contents of folders.
+    let size = item2.GetUInt64(&PKEY_Size).ok().or_else(|| index_file.as_ref().map(|index_file| index_file.size));
+    let size = size.map(ByteSize::new);
+    let kind = Path::new(&id).symlink_metadata().ok().map(|metadata| TrashItemKind::of(metadata.file_type()));
+    let pinned = is_pinned(Path::new(&id));
+
+    Ok(TrashItem {
+        id,
+        name: name.into_string().map_err(|original| Error::ConvertOsString { original })?,
+        unknown_reason: (original_parent.is_none() || date_deleted.is_none()).then_some(UnknownReason::Unreadable),
+        original_parent,
+        time_deleted: date_deleted,
+        size,
+        kind,
+        pinned,
+    })
+}
+
+/// Returns `true` if the canonicalized `path` is the `$Recycle.Bin` folder of a drive, or is inside
+/// of it.
+pub(crate) fn is_trash_folder(path: &Path) -> bool {
+    path.ancestors().any(|ancestor| {
+        let is_recycle_bin = ancestor.file_name().is_some_and(|name| name.eq_ignore_ascii_case("$Recycle.Bin"));
+        is_recycle_bin && ancestor.parent().is_some_and(|drive| drive.parent().is_none())
+    })
+}
+
+/// Creates the item with the given id without enumerating the Recycle Bin. The id is the parsing
+/// name of the item, which is its path within `$Recycle.Bin`.
+pub(crate) fn item_by_id(id: &OsStr) -> Result<TrashItem, Error> {
+    items_by_ids(&[id]).remove(0)
 }
 
-#[derive(Clone, Default, Debug)]
-pub struct PlatformTrashContext;
-impl PlatformTrashContext {
-    pub const fn new() -> Self {
-        PlatformTrashContext
-    }
+/// Resolves many ids at once. The Recycle Bin folder of each volume is only bound once and the
+/// items within it are created relative to it.
+pub(crate) fn items_by_ids(ids: &[&OsStr]) -> Vec<Result<TrashItem, Error>> {
+    let mut folders: HashMap<&Path, Option<IShellItem>> = HashMap::new();
+    ids.iter()
+        .map(|&id| {
+            with_com(|| {
+                let path = Path::new(id);
+                let in_recycle_bin =
+                    path.components().any(|c| c.as_os_str().to_string_lossy().eq_ignore_ascii_case("$Recycle.Bin"));
+                let (true, Some(parent), Some(name)) = (in_recycle_bin, path.parent(), path.file_name()) else {
+                    return Err(Error::InvalidItemId { id: id.to_owned() });
+                };
+                let folder = folders.entry(parent).or_insert_with(|| unsafe { shell_item_from_path(parent).ok() });
+                let not_found = |_| Error::TargetedItemNotFound { path: id.into() };
+                unsafe {
+                    let item: IShellItem = match folder {
+                        Some(folder) => {
+                            let name = to_wide_path(name);
+                            SHCreateItemFromRelativeName(&*folder, PCWSTR(name.as_ptr()), None).map_err(not_found)?
+                        }
+                        None => {
+                            let id_as_wide = to_wide_path(id);
+                            SHCreateItemFromParsingName(PCWSTR(id_as_wide.as_ptr()), None).map_err(not_found)?
+                        }
+                    };
+                    trash_item_from_shell_item(&item, false)
+                }
+            })
+        })
+        .collect()
 }
-impl TrashContext {
-    /// See https://docs.microsoft.com/en-us/windows/win32/api/shellapi/ns-shellapi-_shfileopstructa
-    pub(crate) fn delete_specified_canonicalized(&self, full_paths: Vec<PathBuf>) -> Result<(), Error> {
-        ensure_com_initialized();
-        unsafe {
-            let pfo: IFileOperation = CoCreateInstance(&FileOperation as *const _, None, CLSCTX_ALL).unwrap();
 
-            pfo.SetOperationFlags(FOF_NO_UI | FOF_ALLOWUNDO | FOF_WANTNUKEWARNING)?;
+pub(crate) fn metadata(item: &TrashItem) -> Result<TrashItemMetadata, Error> {
+    with_com(|| {
+        let id_as_wide = to_wide_path(&item.id);
+        let parsing_name = PCWSTR(id_as_wide.as_ptr());
+        let item: IShellItem = unsafe { SHCreateItemFromParsingName(parsing_name, None)? };
+        let is_dir = unsafe { item.GetAttributes(SFGAO_FOLDER)? } == SFGAO_FOLDER;
+        let size = if is_dir {
+            let pesi: IEnumShellItems = unsafe { item.BindToHandler(None, &BHID_EnumItems)? };
+            let mut size: usize = 0;
+            loop {
+                let mut fetched_count: u32 = 0;
+                let mut arr = [None];
+                unsafe { pesi.Next(&mut arr, Some(&mut fetched_count as *mut u32))? };
 
-            for full_path in full_paths.iter() {
-                let path_prefix = ['\\' as u16, '\\' as u16, '?' as u16, '\\' as u16];
-                let wide_path_container = to_wide_path(full_path);
-                let wide_path_slice = if wide_path_container.starts_with(&path_prefix) {
-                    &wide_path_container[path_prefix.len()..]
-                } else {
-                    &wide_path_container[0..]
-                };
+                if fetched_count == 0 {
+                    break;
+                }
 
-                let shi: IShellItem = SHCreateItemFromParsingName(PCWSTR(wide_path_slice.as_ptr()), None)?;
+                match &arr[0] {
+                    Some(_item) => {
+                        size = size.saturating_add(1);
+                    }
+                    None => {
+                        break;
+                    }
+                }
+            }
+            TrashItemSize::Entries(size)
+        } else {
+            let item2: IShellItem2 = item.cast()?;
+            TrashItemSize::Bytes(unsafe { item2.GetUInt64(&PKEY_Size)? })
+        };
+        Ok(TrashItemMetadata { size, foreign_format: None })
+    })
+}
+
+#[cfg(feature = "mime")]
+pub(crate) fn content_type(item: &TrashItem) -> Result<String, Error> {
+    use crate::content_type::{DIRECTORY, UNKNOWN};
 
-                pfo.DeleteItem(&shi, None)?;
+    with_com(|| {
+        let id_as_wide = to_wide_path(&item.id);
+        unsafe {
+            let item: IShellItem2 = SHCreateItemFromParsingName(PCWSTR(id_as_wide.as_ptr()), None)?;
+            if item.GetAttributes(SFGAO_FOLDER)? == SFGAO_FOLDER {
+                return Ok(DIRECTORY.into());
             }
-            pfo.PerformOperations()?;
-            Ok(())
+            let store: IPropertyStore = item.GetPropertyStore(GPS_DEFAULT)?;
+            let content_type = read_property(&store, &PKEY_ContentType, string_from_variant);
+            Ok(content_type.filter(|content_type| !content_type.is_empty()).unwrap_or_else(|| UNKNOWN.into()))
         }
-    }
+    })
+}
 
-    /// Removes all files and folder paths recursively.
-    pub(crate) fn delete_all_canonicalized(&self, full_paths: Vec<PathBuf>) -> Result<(), Error> {
-        self.delete_specified_canonicalized(full_paths)?;
-        Ok(())
-    }
+#[cfg(feature = "thumbnails")]
+pub(crate) fn icon(item: &TrashItem, size: u32) -> Result<crate::Icon, Error> {
+    use windows::Win32::Graphics::Gdi::{
+        DeleteObject, GetDC, GetDIBits, GetObjectW, ReleaseDC, BITMAP, BITMAPINFO, BITMAPINFOHEADER, BI_RGB,
+        DIB_RGB_COLORS,
+    };
+
+    with_com(|| {
+        let id_as_wide = to_wide_path(&item.id);
+        let side = i32::try_from(size).unwrap_or(i32::MAX);
+        unsafe {
+            let factory: IShellItemImageFactory = SHCreateItemFromParsingName(PCWSTR(id_as_wide.as_ptr()), None)?;
+            let bitmap = factory.GetImage(SIZE { cx: side, cy: side }, SIIGBF_RESIZETOFIT)?;
+            scopeguard::defer! { DeleteObject(bitmap); }
+            let mut info = BITMAP::default();
+            if GetObjectW(bitmap, std::mem::size_of::<BITMAP>() as i32, Some(&mut info as *mut BITMAP as *mut c_void))
+                == 0
+            {
+                return Err(windows::core::Error::from_win32().into());
+            }
+            let (width, height) = (info.bmWidth.unsigned_abs(), info.bmHeight.unsigned_abs());
+            let mut header = BITMAPINFO {
+                bmiHeader: BITMAPINFOHEADER {
+                    biSize: std::mem::size_of::<BITMAPINFOHEADER>() as u32,
+                    biWidth: width as i32,
+                    // A negative height requests the rows from the top down.
+                    biHeight: -(height as i32),
+                    biPlanes: 1,
+                    biBitCount: 32,
+                    biCompression: BI_RGB,
+                    ..Default::default()
+                },
+                ..Default::default()
+            };
+            let mut pixels = vec![0u8; width as usize * height as usize * 4];
+            let dc = GetDC(None);
+            let copied =
+                GetDIBits(dc, bitmap, 0, height, Some(pixels.as_mut_ptr() as *mut c_void), &mut header, DIB_RGB_COLORS);
+            ReleaseDC(None, dc);
+            if copied == 0 {
+                return Err(windows::core::Error::from_win32().into());
+            }
+            // The pixels are BGRA with premultiplied alpha, or without alpha at all for some icons.
+            let has_alpha = pixels.chunks_exact(4).any(|pixel| pixel[3] != 0);
+            for pixel in pixels.chunks_exact_mut(4) {
+                pixel.swap(0, 2);
+                if !has_alpha {
+                    pixel[3] = u8::MAX;
+                } else if pixel[3] != 0 && pixel[3] != u8::MAX {
+                    let alpha = u32::from(pixel[3]);
+                    for channel in &mut pixel[..3] {
+                        *channel = (u32::from(*channel) * 255 / alpha).min(255) as u8;
+                    }
+                }
+            }
+            Ok(crate::Icon { width, height, rgba: pixels })
+        }
+    })
 }
 
-pub fn list() -> Result<Vec<TrashItem>, Error> {
-    ensure_com_initialized();
-    unsafe {
-        let mut item_vec = Vec::new();
+impl TrashContext {
+    pub(crate) fn purge_items<I>(&self, items: I) -> Result<(), Error>
+    where
+        I: IntoIterator,
+        <I as IntoIterator>::Item: Borrow<TrashItem>,
+    {
+        /// Batches smaller than this aren't worth a file operation of their own.
+        const MIN_BATCH_LEN: usize = 64;
 
-        let recycle_bin: IShellItem =
-            SHGetKnownFolderItem(&FOLDERID_RecycleBinFolder, KF_FLAG_DEFAULT, HANDLE::default())?;
+        let items: Vec<_> = items.into_iter().collect();
+        let items: Vec<&TrashItem> = items.iter().map(Borrow::borrow).collect();
+        if items.is_empty() {
+            return Ok(());
+        }
+        if self.platform_specific.recycle_bin_access == RecycleBinAccess::Direct {
+            return purge_directly(&items, self.continue_on_error, self.cancellation());
+        }
+        // Several file operations would show several progress dialogs.
+        let threads = if self.platform_specific.show_ui { 1 } else { self.purge_concurrency.max(1) };
+        let batch_len = items.len().div_ceil(threads).max(MIN_BATCH_LEN);
+        let batches: Vec<&[&TrashItem]> = items.chunks(batch_len).collect();
+        let errors = parallel::run(&batches, threads, !self.continue_on_error, |batch| self.purge_batch(batch));
+        errors.into_iter().next().map_or(Ok(()), |(_, e)| Err(e))
+    }
 
-        let pesi: IEnumShellItems = recycle_bin.BindToHandler(None, &BHID_EnumItems)?;
+    /// Empties the Recycle Bins of all volumes at once.
+    pub(crate) fn empty_trash(&self) -> Result<(), Error> {
+        if self.platform_specific.recycle_bin_access == RecycleBinAccess::Direct {
+            let items: Vec<_> = list_directly(volumes()?)?.collect();
+            return purge_directly(&items.iter().collect::<Vec<_>>(), self.continue_on_error, self.cancellation());
+        }
+        self.check_cancelled()?;
+        with_com(|| {
+            let ui_flags = if self.platform_specific.show_ui { 0 } else { SHERB_NOPROGRESSUI };
+            let result = unsafe {
+                SHEmptyRecycleBinW(HWND::default(), PCWSTR::null(), SHERB_NOCONFIRMATION | SHERB_NOSOUND | ui_flags)
+            };
+            match result {
+                // Some versions of Windows fail like this when the Recycle Bin is empty already.
+                Err(e) if e.code() == E_UNEXPECTED => Ok(()),
+                result => result.in_function("SHEmptyRecycleBinW"),
+            }
+        })
+    }
 
-        loop {
-            let mut fetched_count: u32 = 0;
-            let mut arr = [None];
-            pesi.Next(&mut arr, Some(&mut fetched_count as *mut u32))?;
-
-            if fetched_count == 0 {
-                break;
-            }
-
-            match &arr[0] {
-                Some(item) => {
-                    let id = get_display_name(item, SIGDN_DESKTOPABSOLUTEPARSING)?;
-                    let name = get_display_name(item, SIGDN_PARENTRELATIVE)?;
-                    let item2: IShellItem2 = item.cast()?;
-                    let original_location_variant = item2.GetProperty(&SCID_ORIGINAL_LOCATION)?;
-                    let original_location_bstr = PropVariantToBSTR(&original_location_variant)?;
-                    let original_location = OsString::from_wide(original_location_bstr.as_wide());
-                    let date_deleted = get_date_deleted_unix(&item2)?;
-
-                    item_vec.push(TrashItem {
-                        id,
-                        name: name.into_string().map_err(|original| Error::ConvertOsString { original })?,
-                        original_parent: PathBuf::from(original_location),
-                        time_deleted: date_deleted,
-                    });
+    fn purge_batch(&self, items: &[&TrashItem]) -> Result<(), Error> {
+        with_com(|| unsafe {
+            let pfo = self.file_operation(self.continue_on_error, 0)?;
+            for item in items {
+                let id_as_wide = to_wide_path(&item.id);
+                let parsing_name = PCWSTR(id_as_wide.as_ptr());
+                let trash_item: IShellItem = SHCreateItemFromParsingName(parsing_name, None)
+                    .map_err(|e| shell_item_error(Path::new(&item.id), "SHCreateItemFromParsingName", e))?;
+                pfo.DeleteItem(&trash_item, None)?;
+            }
+            pfo.perform()
+        })
+    }
+
+    /// Restores the items and returns the paths they were restored to.
+    pub(crate) fn restore_items(&self, items: Vec<TrashItem>) -> Result<Vec<PathBuf>, Error> {
+        // Do a quick and dirty check if the target items already exist at the location
+        // and if they do, return all of them, if they don't just go ahead with the processing
+        // without giving a damn.
+        // Note that this is not 'thread safe' meaning that if a paralell thread (or process)
+        // does this operation the exact same time or creates files or folders right after this check,
+        // then the files that would collide will not be detected and returned as part of an error.
+        // Instead Windows will display a prompt to the user whether they want to replace or skip.
+        for item in &items {
+            item.known_original_path()?;
+        }
+        let collision = self.restore_collision();
+        let items = match collision {
+            RestoreCollision::Fail => {
+                for item in items.iter() {
+                    let path = item.known_original_path()?;
+                    if path.exists() {
+                        return Err(Error::RestoreCollision { path, remaining_items: items });
+                    }
                 }
-                None => {
-                    break;
+                items
+            }
+            RestoreCollision::Skip => {
+                items.into_iter().filter(|item| !item.original_path().is_some_and(|path| path.exists())).collect()
+            }
+            RestoreCollision::Overwrite => {
+                // What is at the original paths is moved to the trash first, so that the shell
+                // doesn't merge restored folders into existing ones.
+                for item in items.iter() {
+                    let path = item.known_original_path()?;
+                    if !collision::trash_existing(self, &path)? {
+                        return Err(Error::RestoreCollision { path, remaining_items: items });
+                    }
                 }
+                items
+            }
+            RestoreCollision::RenameWithSuffix => items,
+        };
+        // The shell fails with an unspecific error for paths longer than `MAX_PATH`.
+        let (long, items): (Vec<_>, Vec<_>) =
+            items.into_iter().partition(|item| item.original_path().is_some_and(|path| exceeds_max_path(&path)));
+        // The free names are chosen up front rather than by the shell, so that the targets are known.
+        let target_of = |item: &TrashItem| -> Result<PathBuf, Error> {
+            let target = item.known_original_path()?;
+            match collision == RestoreCollision::RenameWithSuffix && target.symlink_metadata().is_ok() {
+                true => Ok(collision::free_path(&target)),
+                false => Ok(target),
             }
+        };
+        let mut targets = Vec::with_capacity(long.len() + items.len());
+        for item in &long {
+            self.check_cancelled()?;
+            let target = target_of(item)?;
+            restore_directly(item, &target)?;
+            targets.push(target);
         }
+        with_com(|| {
+            unsafe {
+                let flags = match collision {
+                    RestoreCollision::RenameWithSuffix => FOF_RENAMEONCOLLISION,
+                    RestoreCollision::Fail | RestoreCollision::Overwrite | RestoreCollision::Skip => 0,
+                };
+                // All the moves are queued before they're performed at once, so that Explorer records
+                // the restore as a single operation that can be undone.
+                let pfo = self.file_operation(false, flags | FOF_ALLOWUNDO)?;
+                for item in items.iter() {
+                    let id_as_wide = to_wide_path(&item.id);
+                    let parsing_name = PCWSTR(id_as_wide.as_ptr());
+                    let trash_item: IShellItem = SHCreateItemFromParsingName(parsing_name, None)
+                        .map_err(|e| shell_item_error(Path::new(&item.id), "SHCreateItemFromParsingName", e))?;
+                    // Items whose original location is unknown were rejected above.
+                    let parent_path_wide = to_wide_path(item.original_parent.as_deref().unwrap_or(Path::new("")));
+                    let orig_folder_shi: IShellItem =
+                        SHCreateItemFromParsingName(PCWSTR(parent_path_wide.as_ptr()), None)
+                            .in_function("SHCreateItemFromParsingName")?;
+                    let target = target_of(item)?;
+                    let name_wstr = to_wide_path(target.file_name().unwrap_or(item.name.as_ref()));
+
+                    pfo.MoveItem(&trash_item, &orig_folder_shi, PCWSTR(name_wstr.as_ptr()), None)
+                        .in_function("IFileOperation::MoveItem")?;
+                    targets.push(target);
+                }
+                if !items.is_empty() {
+                    pfo.perform()?;
+                }
+                Ok(targets)
+            }
+        })
+    }
 
-        Ok(item_vec)
+    /// Creates a file operation with the settings of the context, which the shell aborts once the
+    /// [`cancellation`](TrashContext::cancellation) token is cancelled, which reports its items to
+    /// the [`file_operation_observer`](TrashContextExtWindows::file_operation_observer), and which
+    /// reports how far it got to the [`TrashProgress`](crate::TrashProgress).
+    fn file_operation(&self, continue_on_error: bool, flags: u32) -> Result<FileOperation, Error> {
+        let pfo = self.platform_specific.create_file_operation(continue_on_error, flags)?;
+        let token = self.cancellation().cloned();
+        let observer = self.platform_specific.file_operation_observer.as_ref().map(|observer| observer.0.clone());
+        let progress = self.path_progress().cloned();
+        let sink = match (token, observer, progress) {
+            (None, None, None) => None,
+            (token, observer, progress) => {
+                let sink = ProgressSink::create(token, observer, progress);
+                let cookie = unsafe { pfo.Advise(&sink)? };
+                Some((sink, cookie))
+            }
+        };
+        Ok(FileOperation { pfo, sink })
     }
 }
 
-pub fn metadata(item: &TrashItem) -> Result<TrashItemMetadata, Error> {
-    ensure_com_initialized();
-    let id_as_wide = to_wide_path(&item.id);
-    let parsing_name = PCWSTR(id_as_wide.as_ptr());
-    let item: IShellItem = unsafe { SHCreateItemFromParsingName(parsing_name, None)? };
-    let is_dir = unsafe { item.GetAttributes(SFGAO_FOLDER)? } == SFGAO_FOLDER;
-    let size = if is_dir {
-        let pesi: IEnumShellItems = unsafe { item.BindToHandler(None, &BHID_EnumItems)? };
-        let mut size = 0;
-        loop {
-            let mut fetched_count: u32 = 0;
-            let mut arr = [None];
-            unsafe { pesi.Next(&mut arr, Some(&mut fetched_count as *mut u32))? };
+/// A shell file operation along with the sink that cancels it and reports its items, as created
+/// by [`TrashContext::file_operation`].
+///
+/// Dropping it unadvises the sink and releases the operation, which in turn releases the shell
+/// items that were queued on it, whether or not they were performed.
+struct FileOperation {
+    pfo: IFileOperation,
+    sink: Option<(IFileOperationProgressSink, u32)>,
+}
+
+impl std::ops::Deref for FileOperation {
+    type Target = IFileOperation;
 
-            if fetched_count == 0 {
-                break;
+    fn deref(&self) -> &IFileOperation {
+        &self.pfo
+    }
+}
+
+impl FileOperation {
+    /// Performs the queued operations. Fails with [`Error::Cancelled`] if the cancellation made
+    /// the shell skip or abort any of the items, regardless of how the shell reported them. A
+    /// cancellation that comes after the shell got through all items doesn't change the result.
+    unsafe fn perform(&self) -> Result<(), Error> {
+        let result = perform_operations(&self.pfo);
+        if let Some((sink, _)) = &self.sink {
+            let sink: &ProgressSink = sink.as_impl();
+            let vetoed_items = sink.vetoed_items.load(Ordering::Relaxed);
+            let vetoed_progress = sink.vetoed_progress.load(Ordering::Relaxed);
+            if vetoed_items > 0 || (vetoed_progress > 0 && result.is_err()) {
+                debug!(
+                    "The file operation was cancelled after {} items were completed",
+                    sink.completed_items.load(Ordering::Relaxed)
+                );
+                return Err(Error::Cancelled);
             }
+        }
+        result
+    }
+}
 
-            match &arr[0] {
-                Some(_item) => {
-                    size += 1;
-                }
-                None => {
-                    break;
-                }
+impl Drop for FileOperation {
+    fn drop(&mut self) {
+        if let Some((_, cookie)) = self.sink.take() {
+            if let Err(e) = unsafe { self.pfo.Unadvise(cookie) } {
+                warn!("Failed to detach the progress sink from the file operation: {:?}", e);
             }
         }
-        TrashItemSize::Entries(size)
-    } else {
-        let item2: IShellItem2 = item.cast()?;
-        TrashItemSize::Bytes(unsafe { item2.GetUInt64(&PKEY_Size)? })
-    };
-    Ok(TrashItemMetadata { size })
+    }
 }
 
-pub fn purge_all<I>(items: I) -> Result<(), Error>
-where
-    I: IntoIterator,
-    <I as IntoIterator>::Item: Borrow<TrashItem>,
-{
-    ensure_com_initialized();
-    unsafe {
-        let pfo: IFileOperation = CoCreateInstance(&FileOperation as *const _, None, CLSCTX_ALL)?;
-        pfo.SetOperationFlags(FOF_NO_UI)?;
-        let mut at_least_one = false;
-        for item in items {
-            at_least_one = true;
-            let id_as_wide = to_wide_path(&item.borrow().id);
-            let parsing_name = PCWSTR(id_as_wide.as_ptr());
-            let trash_item: IShellItem = SHCreateItemFromParsingName(parsing_name, None)?;
-            pfo.DeleteItem(&trash_item, None)?;
+/// A progress sink that makes the shell skip the remaining items of a file operation once its
+/// token is cancelled, that counts the items it completed and skipped, that passes the deleted
+/// items on to the observer, and that reports the progress of the shell.
+///
+/// The shell calls the sink on the thread that performs the operation, between the items and
+/// while it copies the data of an item. The token is checked at each of these points, so the
+/// operation returns promptly even when a single large item is being moved across volumes.
+#[implement(IFileOperationProgressSink)]
+struct ProgressSink {
+    token: Option<CancellationToken>,
+    observer: Option<Arc<dyn FileOperationObserver>>,
+    progress: Option<PathProgress>,
+    /// How many items the shell was told to skip before starting them.
+    vetoed_items: AtomicUsize,
+    /// How many times the shell was told to abort the item it was in the middle of.
+    vetoed_progress: AtomicUsize,
+    /// How many items the shell reported as done.
+    completed_items: AtomicUsize,
+}
+
+impl ProgressSink {
+    fn create(
+        token: Option<CancellationToken>,
+        observer: Option<Arc<dyn FileOperationObserver>>,
+        progress: Option<PathProgress>,
+    ) -> IFileOperationProgressSink {
+        ProgressSink {
+            token,
+            observer,
+            progress,
+            vetoed_items: AtomicUsize::new(0),
+            vetoed_progress: AtomicUsize::new(0),
+            completed_items: AtomicUsize::new(0),
+        }
+        .into()
+    }
+
+    /// Carries on, or fails with `E_ABORT` once the token is cancelled, counting the abort in
+    /// `vetoes`.
+    fn proceed(&self, vetoes: &AtomicUsize) -> windows::core::Result<()> {
+        if !self.token.as_ref().is_some_and(CancellationToken::is_cancelled) {
+            return Ok(());
         }
-        if at_least_one {
-            pfo.PerformOperations()?;
+        vetoes.fetch_add(1, Ordering::Relaxed);
+        Err(E_ABORT.into())
+    }
+
+    /// Skips the item that is about to be started once the token is cancelled.
+    fn proceed_with_item(&self) -> windows::core::Result<()> {
+        self.proceed(&self.vetoed_items)
+    }
+
+    /// Counts the item as completed if the shell reports it as such.
+    fn complete_item(&self, result: HRESULT) -> windows::core::Result<()> {
+        if result.is_ok() {
+            self.completed_items.fetch_add(1, Ordering::Relaxed);
         }
         Ok(())
     }
+
+    /// Calls the observer, if there's one. A panic of the observer must not unwind into the shell,
+    /// so it fails the call with `E_FAIL` instead.
+    fn observe(&self, call: impl FnOnce(&dyn FileOperationObserver)) -> windows::core::Result<()> {
+        let Some(observer) = &self.observer else {
+            return Ok(());
+        };
+        std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| call(observer.as_ref()))).map_err(|_| {
+            warn!("The file operation observer panicked");
+            E_FAIL.into()
+        })
+    }
 }
 
-pub fn restore_all<I>(items: I) -> Result<(), Error>
-where
-    I: IntoIterator<Item = TrashItem>,
-{
-    let items: Vec<_> = items.into_iter().collect();
+#[allow(non_snake_case)]
+impl IFileOperationProgressSink_Impl for ProgressSink {
+    fn StartOperations(&self) -> windows::core::Result<()> {
+        self.proceed_with_item()
+    }
+
+    fn FinishOperations(&self, _: HRESULT) -> windows::core::Result<()> {
+        Ok(())
+    }
+
+    fn PreRenameItem(&self, _: u32, _: &Option<IShellItem>, _: &PCWSTR) -> windows::core::Result<()> {
+        self.proceed_with_item()
+    }
+
+    fn PostRenameItem(
+        &self,
+        _: u32,
+        _: &Option<IShellItem>,
+        _: &PCWSTR,
+        result: HRESULT,
+        _: &Option<IShellItem>,
+    ) -> windows::core::Result<()> {
+        self.complete_item(result)
+    }
+
+    fn PreMoveItem(
+        &self,
+        _: u32,
+        _: &Option<IShellItem>,
+        _: &Option<IShellItem>,
+        _: &PCWSTR,
+    ) -> windows::core::Result<()> {
+        self.proceed_with_item()
+    }
+
+    fn PostMoveItem(
+        &self,
+        _: u32,
+        _: &Option<IShellItem>,
+        _: &Option<IShellItem>,
+        _: &PCWSTR,
+        result: HRESULT,
+        _: &Option<IShellItem>,
+    ) -> windows::core::Result<()> {
+        self.complete_item(result)
+    }
+
+    fn PreCopyItem(
+        &self,
+        _: u32,
+        _: &Option<IShellItem>,
+        _: &Option<IShellItem>,
+        _: &PCWSTR,
+    ) -> windows::core::Result<()> {
+        self.proceed_with_item()
+    }
+
+    fn PostCopyItem(
+        &self,
+        _: u32,
+        _: &Option<IShellItem>,
+        _: &Option<IShellItem>,
+        _: &PCWSTR,
+        result: HRESULT,
+        _: &Option<IShellItem>,
+    ) -> windows::core::Result<()> {
+        self.complete_item(result)
+    }
 
-    // Do a quick and dirty check if the target items already exist at the location
-    // and if they do, return all of them, if they don't just go ahead with the processing
-    // without giving a damn.
-    // Note that this is not 'thread safe' meaning that if a paralell thread (or process)
-    // does this operation the exact same time or creates files or folders right after this check,
-    // then the files that would collide will not be detected and returned as part of an error.
-    // Instead Windows will display a prompt to the user whether they want to replace or skip.
-    for item in items.iter() {
-        let path = item.original_path();
-        if path.exists() {
-            return Err(Error::RestoreCollision { path, remaining_items: items });
+    fn PreDeleteItem(&self, _: u32, item: &Option<IShellItem>) -> windows::core::Result<()> {
+        if let Some(path) = path_of_shell_item(item) {
+            self.observe(|observer| observer.pre_delete_item(&path))?;
         }
+        self.proceed_with_item()
     }
-    ensure_com_initialized();
-    unsafe {
-        let pfo: IFileOperation = CoCreateInstance(&FileOperation as *const _, None, CLSCTX_ALL)?;
-        pfo.SetOperationFlags(FOF_NO_UI | FOFX_EARLYFAILURE)?;
-        for item in items.iter() {
-            let id_as_wide = to_wide_path(&item.id);
-            let parsing_name = PCWSTR(id_as_wide.as_ptr());
-            let trash_item: IShellItem = SHCreateItemFromParsingName(parsing_name, None)?;
-            let parent_path_wide = to_wide_path(&item.original_parent);
-            let orig_folder_shi: IShellItem = SHCreateItemFromParsingName(PCWSTR(parent_path_wide.as_ptr()), None)?;
-            let name_wstr = to_wide_path(&item.name);
 
-            pfo.MoveItem(&trash_item, &orig_folder_shi, PCWSTR(name_wstr.as_ptr()), None)?;
+    fn PostDeleteItem(
+        &self,
+        _: u32,
+        item: &Option<IShellItem>,
+        result: HRESULT,
+        recycled: &Option<IShellItem>,
+    ) -> windows::core::Result<()> {
+        if let Some(path) = path_of_shell_item(item) {
+            let recycled = path_of_shell_item(recycled);
+            let item_result = result.ok().map_err(|e| os_error(e, "IFileOperation::DeleteItem"));
+            self.observe(|observer| observer.post_delete_item(&path, item_result, recycled.as_deref()))?;
         }
-        if !items.is_empty() {
-            pfo.PerformOperations()?;
+        self.complete_item(result)
+    }
+
+    fn PreNewItem(&self, _: u32, _: &Option<IShellItem>, _: &PCWSTR) -> windows::core::Result<()> {
+        self.proceed_with_item()
+    }
+
+    fn PostNewItem(
+        &self,
+        _: u32,
+        _: &Option<IShellItem>,
+        _: &PCWSTR,
+        _: &PCWSTR,
+        _: u32,
+        result: HRESULT,
+        _: &Option<IShellItem>,
+    ) -> windows::core::Result<()> {
+        self.complete_item(result)
+    }
+
+    fn UpdateProgress(&self, total: u32, done: u32) -> windows::core::Result<()> {
+        if let Some(progress) = &self.progress {
+            // Like a panicking observer, a panicking progress callback must not unwind into the shell.
+            let report = || progress.fraction_done(done.into(), total.into());
+            std::panic::catch_unwind(std::panic::AssertUnwindSafe(report)).map_err(|_| {
+                warn!("The progress callback panicked");
+                windows::core::Error::from(E_FAIL)
+            })?;
         }
+        self.proceed(&self.vetoed_progress)
+    }
+
+    fn ResetTimer(&self) -> windows::core::Result<()> {
+        Ok(())
+    }
+
+    fn PauseTimer(&self) -> windows::core::Result<()> {
+        Ok(())
+    }
+
+    fn ResumeTimer(&self) -> windows::core::Result<()> {
         Ok(())
     }
 }
 
+/// The file system path of the shell item that the shell passed to a progress sink, if there's
+/// one.
+fn path_of_shell_item(item: &Option<IShellItem>) -> Option<PathBuf> {
+    unsafe { get_display_name(item.as_ref()?, SIGDN_FILESYSPATH).ok().map(PathBuf::from) }
+}
+
+/// Performs the queued operations and fails if any of them didn't complete.
+///
+/// The shell reports failures of individual items through its progress sink rather than through
+/// the result of `PerformOperations`. Without early failure it skips the failing items and carries
+/// on, so whether that happened is only known from `GetAnyOperationsAborted`.
+unsafe fn perform_operations(pfo: &IFileOperation) -> Result<(), Error> {
+    pfo.PerformOperations().in_function("IFileOperation::PerformOperations")?;
+    if pfo.GetAnyOperationsAborted()?.as_bool() {
+        return Err(Error::Os {
+            code: E_ABORT.0,
+            function: Some("IFileOperation::PerformOperations".into()),
+            description: "The shell aborted the operation for at least one of the items".into(),
+        });
+    }
+    Ok(())
+}
+
+/// Creates a shell data object holding the given items of the Recycle Bin, to put them on the
+/// clipboard with `OleSetClipboard` or to let users drag them out of an application with
+/// `DoDragDrop`.
+///
+/// The data object is created by the Recycle Bin itself, so it offers the same formats as when the
+/// items are dragged out of Explorer, including `CF_HDROP` and the shell ID list format. Dropping
+/// the items onto a folder moves them out of the Recycle Bin.
+pub fn data_object(items: &[TrashItem]) -> Result<IDataObject, Error> {
+    if items.is_empty() {
+        return Err(Error::Os {
+            code: E_INVALIDARG.0,
+            function: Some("SHCreateShellItemArrayFromIDLists".into()),
+            description: "A data object can't be created without any items".into(),
+        });
+    }
+    with_com(|| unsafe {
+        let mut shell_items = Vec::with_capacity(items.len());
+        for item in items {
+            let id_as_wide = to_wide_path(&item.id);
+            let shell_item: IShellItem = SHCreateItemFromParsingName(PCWSTR(id_as_wide.as_ptr()), None)
+                .map_err(|_| Error::TargetedItemNotFound { path: item.id.clone().into() })?;
+            shell_items.push(shell_item);
+        }
+        Ok(shell_item_array(&shell_items)?.BindToHandler(None, &BHID_DataObject)?)
+    })
+}
+
+/// Collects the shell items into an array, which lets the shell handle them in a single call.
+unsafe fn shell_item_array(shell_items: &[IShellItem]) -> Result<IShellItemArray, Error> {
+    /// Frees the ID lists on drop.
+    struct IdLists(Vec<*mut Common::ITEMIDLIST>);
+    impl Drop for IdLists {
+        fn drop(&mut self) {
+            for &id_list in self.0.iter() {
+                unsafe { ILFree(Some(id_list)) };
+            }
+        }
+    }
+
+    let mut id_lists = IdLists(Vec::with_capacity(shell_items.len()));
+    for shell_item in shell_items {
+        id_lists.0.push(SHGetIDListFromObject(shell_item).in_function("SHGetIDListFromObject")?);
+    }
+    let id_list_ptrs: Vec<*const Common::ITEMIDLIST> = id_lists.0.iter().map(|&p| p as *const _).collect();
+    SHCreateShellItemArrayFromIDLists(&id_list_ptrs).in_function("SHCreateShellItemArrayFromIDLists")
+}
+
+/// Returns the root directories of all drives, such as `C:\`.
+pub(crate) fn volumes() -> Result<Vec<PathBuf>, Error> {
+    let len = unsafe { GetLogicalDriveStringsW(None) };
+    let mut buffer = vec![0u16; len as usize];
+    let written = unsafe { GetLogicalDriveStringsW(Some(&mut buffer)) };
+    if written == 0 {
+        return Err(windows::core::Error::from_win32().into());
+    }
+    // The buffer holds NUL terminated strings, followed by an additional NUL.
+    Ok(buffer[..written as usize]
+        .split(|&c| c == 0)
+        .filter(|drive| !drive.is_empty())
+        .map(|drive| PathBuf::from(OsString::from_wide(drive)))
+        .collect())
+}
+
+/// Notices changes to the Recycle Bin folders of the current user on all drives.
+///
+/// When no folder can be watched, a change is reported every few seconds, so that the Recycle Bin
+/// is polled instead.
+pub(crate) struct ChangeMonitor {
+    handles: Vec<FindChangeNotificationHandle>,
+    last_poll: Instant,
+}
+
+impl ChangeMonitor {
+    const POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+    pub(crate) fn new() -> Self {
+        let mut monitor = ChangeMonitor { handles: Vec::new(), last_poll: Instant::now() };
+        monitor.refresh();
+        monitor
+    }
+
+    /// Starts watching the Recycle Bin folders that were created since the last call.
+    pub(crate) fn refresh(&mut self) {
+        self.close();
+        let (Ok(sid), Ok(volumes)) = (current_user_sid(), volumes()) else {
+            warn!("Failed to find the Recycle Bin folders, polling the Recycle Bin instead");
+            return;
+        };
+        for volume in volumes {
+            let folder = volume.join("$Recycle.Bin").join(&sid);
+            let wide_folder = to_wide_path(&folder);
+            let filter = FILE_NOTIFY_CHANGE_FILE_NAME | FILE_NOTIFY_CHANGE_DIR_NAME;
+            match unsafe { FindFirstChangeNotificationW(PCWSTR(wide_folder.as_ptr()), false, filter) } {
+                Ok(handle) => self.handles.push(handle),
+                Err(e) => debug!("Failed to watch {:?}: {}", folder, e),
+            }
+        }
+    }
+
+    /// Waits at most `timeout` for a change. Returns `true` if the Recycle Bin may have changed.
+    pub(crate) fn wait(&mut self, timeout: Duration) -> bool {
+        if self.handles.is_empty() {
+            std::thread::sleep(timeout);
+            if self.last_poll.elapsed() < Self::POLL_INTERVAL {
+                return false;
+            }
+            self.last_poll = Instant::now();
+            return true;
+        }
+        let handles: Vec<_> = self.handles.iter().map(|handle| HANDLE(handle.0)).collect();
+        let mut timeout = timeout.as_millis().min(u32::MAX as u128 - 1) as u32;
+        let mut changed = false;
+        // Rearm every signaled handle, so that a single change doesn't get reported repeatedly.
+        loop {
+            let result = unsafe { WaitForMultipleObjects(&handles, false, timeout) };
+            let index = result.0.wrapping_sub(WAIT_OBJECT_0.0) as usize;
+            if index >= handles.len() {
+                return changed;
+            }
+            unsafe { FindNextChangeNotification(self.handles[index]) };
+            changed = true;
+            timeout = 0;
+        }
+    }
+
+    fn close(&mut self) {
+        for handle in self.handles.drain(..) {
+            unsafe { FindCloseChangeNotification(handle) };
+        }
+    }
+}
+
+impl Drop for ChangeMonitor {
+    fn drop(&mut self) {
+        self.close();
+    }
+}
+
+/// Creates a shell item from an absolute file system path, which may be prefixed with `\\?\`.
+///
+/// The shell can't parse paths that are longer than `MAX_PATH` as a whole. For those, the longest
+/// ancestor that it can parse is parsed, and the remaining components are parsed one by one
+/// relative to it.
+unsafe fn shell_item_from_path(path: &Path) -> Result<IShellItem, Error> {
+    let path_prefix = ['\\' as u16, '\\' as u16, '?' as u16, '\\' as u16];
+    let wide_path_container = to_wide_path(path);
+    let wide_path_slice = if wide_path_container.starts_with(&path_prefix) {
+        &wide_path_container[path_prefix.len()..]
+    } else {
+        &wide_path_container[0..]
+    };
+    if wide_path_slice.len() > MAX_PATH as usize {
+        return shell_item_from_long_path(path);
+    }
+    SHCreateItemFromParsingName(PCWSTR(wide_path_slice.as_ptr()), None)
+        .map_err(|e| shell_item_error(path, "SHCreateItemFromParsingName", e))
+}
+
+/// Creates a shell item for a path that's longer than `MAX_PATH`, see [`shell_item_from_path`].
+///
+/// The components are parsed as file system folders, rather than by the namespace extensions that
+/// handle some files, like archives, so that every step stays within the file system.
+unsafe fn shell_item_from_long_path(path: &Path) -> Result<IShellItem, Error> {
+    let too_long = || Error::PathTooLong { path: path.to_owned(), limit: MAX_PATH as usize - 1 };
+    let mut ancestor = path;
+    let mut names = Vec::new();
+    while exceeds_max_path(ancestor) {
+        names.push(ancestor.file_name().ok_or_else(too_long)?);
+        ancestor = ancestor.parent().ok_or_else(too_long)?;
+    }
+    let bind_ctx = CreateBindCtx(0).in_function("CreateBindCtx")?;
+    // Only the presence of the parameter matters, so any object does as its value.
+    let value: IUnknown = CreateBindCtx(0).in_function("CreateBindCtx")?.cast()?;
+    bind_ctx.RegisterObjectParam(STR_PARSE_PREFER_FOLDER_BROWSING, &value)?;
+    let mut item = shell_item_from_path(ancestor)?;
+    for name in names.iter().rev() {
+        let name = to_wide_path(name);
+        item = SHCreateItemFromRelativeName(&item, PCWSTR(name.as_ptr()), &bind_ctx)
+            .map_err(|e| shell_item_error(path, "SHCreateItemFromRelativeName", e))?;
+    }
+    Ok(item)
+}
+
 unsafe fn get_display_name(psi: &IShellItem, sigdnname: SIGDN) -> Result<OsString, Error> {
     let name = psi.GetDisplayName(sigdnname)?;
     let result = wstr_to_os_string(name);
@@ -244,21 +2759,48 @@ unsafe fn wstr_to_os_string(wstr: PWSTR) -> OsString {
 }
 
 unsafe fn get_date_deleted_unix(item: &IShellItem2) -> Result<i64, Error> {
+    let time = item.GetFileTime(&SCID_DATE_DELETED)?;
+    Ok(filetime_to_unix(((time.dwHighDateTime as u64) << 32) | (time.dwLowDateTime as u64)))
+}
+
+/// Converts a Windows file time to seconds since the Unix epoch.
+fn filetime_to_unix(time: u64) -> i64 {
     /// January 1, 1970 as Windows file time
-    const EPOCH_AS_FILETIME: u64 = 116444736000000000;
-    const HUNDREDS_OF_NANOSECONDS: u64 = 10000000;
+    const EPOCH_AS_FILETIME: i64 = 116444736000000000;
+    const HUNDREDS_OF_NANOSECONDS: i64 = 10000000;
 
-    let time = item.GetFileTime(&SCID_DATE_DELETED)?;
-    let time_u64 = ((time.dwHighDateTime as u64) << 32) | (time.dwLowDateTime as u64);
-    let rel_to_linux_epoch = time_u64 - EPOCH_AS_FILETIME;
-    let seconds_since_unix_epoch = rel_to_linux_epoch / HUNDREDS_OF_NANOSECONDS;
+    // Times before the Unix epoch only show up in corrupt `$I` files, so they're clamped to it.
+    // Times beyond the range of `i64` saturate instead of wrapping around to negative values.
+    let time = i64::try_from(time).unwrap_or(i64::MAX);
+    let rel_to_linux_epoch = time.saturating_sub(EPOCH_AS_FILETIME).max(0);
+    rel_to_linux_epoch / HUNDREDS_OF_NANOSECONDS
+}
+
+/// Makes `IFileOperation` look unavailable, to test the fallback to `SHFileOperationW`.
+#[cfg(test)]
+static FILE_OPERATION_UNAVAILABLE: std::sync::atomic::AtomicBool = std::sync::atomic::AtomicBool::new(false);
 
-    Ok(seconds_since_unix_epoch as i64)
+/// Set by [`assume_com_initialized`].
+static COM_ASSUMED_INITIALIZED: std::sync::atomic::AtomicBool = std::sync::atomic::AtomicBool::new(false);
+
+/// Stops this crate from initializing COM, for applications that manage COM on their threads
+/// themselves, like GUI frameworks. From then on, COM has to be initialized on every thread before
+/// it calls into this crate, in either threading model.
+///
+/// Without this, COM is initialized on each thread on first use with the threading model chosen
+/// by the `coinit_*` features. If the thread was already initialized with the other model, its
+/// existing apartment is used as it is.
+pub fn assume_com_initialized() {
+    COM_ASSUMED_INITIALIZED.store(true, std::sync::atomic::Ordering::Relaxed);
 }
 
-struct CoInitializer {}
+/// Initializes COM on the current thread and uninitializes it when dropped, unless COM was
+/// already initialized on the thread with another threading model.
+struct CoInitializer {
+    uninitialize: bool,
+}
 impl CoInitializer {
-    fn new() -> CoInitializer {
+    fn new() -> windows::core::Result<CoInitializer> {
         //let first = INITIALIZER_THREAD_COUNT.fetch_add(1, Ordering::SeqCst) == 0;
         #[cfg(all(not(feature = "coinit_multithreaded"), not(feature = "coinit_apartmentthreaded")))]
         {
@@ -281,25 +2823,389 @@ impl CoInitializer {
         if cfg!(feature = "coinit_speed_over_memory") {
             init_mode |= COINIT_SPEED_OVER_MEMORY;
         }
-        let hr = unsafe { CoInitializeEx(None, init_mode) };
-        if hr.is_err() {
-            panic!("Call to CoInitializeEx failed. HRESULT: {:?}. Consider using `trash` with the feature `coinit_multithreaded`", hr);
+        match unsafe { CoInitializeEx(None, init_mode) } {
+            Ok(()) => Ok(CoInitializer { uninitialize: true }),
+            // The thread belongs to an apartment already, which works just as well. Since this
+            // call failed, it mustn't be balanced by a call to `CoUninitialize`.
+            Err(e) if e.code() == RPC_E_CHANGED_MODE => {
+                debug!("COM is initialized with another threading model on the current thread, using it as it is");
+                Ok(CoInitializer { uninitialize: false })
+            }
+            Err(e) => Err(e),
         }
-        CoInitializer {}
     }
 }
 impl Drop for CoInitializer {
     fn drop(&mut self) {
-        // TODO: This does not get called because it's a global static.
-        // Is there an atexit in Win32?
-        unsafe {
-            CoUninitialize();
+        if self.uninitialize {
+            unsafe {
+                CoUninitialize();
+            }
         }
     }
 }
 thread_local! {
-    static CO_INITIALIZER: CoInitializer = CoInitializer::new();
+    /// COM stays initialized on a thread once an operation succeeded in doing so. A failure isn't
+    /// kept, so that the next operation on the thread tries again.
+    static CO_INITIALIZER: Cell<Option<CoInitializer>> = const { Cell::new(None) };
+    /// Set when a call failed in a way that leaves COM unusable on the thread, see [`is_com_broken`].
+    static COM_BROKEN: Cell<bool> = const { Cell::new(false) };
+}
+fn ensure_com_initialized() -> Result<(), Error> {
+    if COM_ASSUMED_INITIALIZED.load(std::sync::atomic::Ordering::Relaxed) {
+        return Ok(());
+    }
+    let result = CO_INITIALIZER
+        .try_with(|initializer| {
+            let mut current = initializer.take();
+            if COM_BROKEN.with(|broken| broken.replace(false)) {
+                // The operation that broke COM has returned and released its objects by now, so it
+                // can be torn down and set up again.
+                debug!("Reinitializing COM on the current thread");
+                drop(current.take());
+            }
+            if current.is_none() {
+                current = Some(CoInitializer::new()?);
+            }
+            initializer.set(current);
+            Ok(())
+        })
+        .unwrap_or_else(|_| Err(CO_E_NOTINITIALIZED.into()));
+    result.map_err(|e: windows::core::Error| Error::Os {
+        code: e.code().0,
+        function: Some("CoInitializeEx".into()),
+        description: format!(
+            "Call to CoInitializeEx failed: {e}. Consider using `trash` with the feature `coinit_multithreaded`"
+        ),
+    })
 }
-fn ensure_com_initialized() {
-    CO_INITIALIZER.with(|_| {});
+
+/// Runs an operation that uses COM on the current thread, after making sure that COM is
+/// initialized. If the operation fails in a way that leaves COM unusable, see [`is_com_broken`],
+/// only this operation fails, and the next one on the thread starts over with COM.
+fn with_com<T>(operation: impl FnOnce() -> Result<T, Error>) -> Result<T, Error> {
+    ensure_com_initialized()?;
+    let result = operation();
+    if let Err(e) = &result {
+        note_broken_com(e);
+    }
+    result
+}
+
+/// Makes the next operation on the thread set up COM again if `error` left it unusable.
+fn note_broken_com(error: &Error) {
+    if breaks_com(error) {
+        warn!("COM became unusable on the current thread: {error:?}");
+        let _ = COM_BROKEN.try_with(|broken| broken.set(true));
+    }
+}
+
+fn breaks_com(error: &Error) -> bool {
+    match error {
+        Error::Os { code, .. } => is_com_broken(HRESULT(*code)),
+        Error::Batch { errors } => errors.failures().iter().any(|failure| breaks_com(&failure.error)),
+        _ => false,
+    }
+}
+
+/// Returns `true` for errors after which COM can't be used on the thread anymore, because it was
+/// uninitialized behind our back or the server it talks to went away.
+fn is_com_broken(code: windows::core::HRESULT) -> bool {
+    [CO_E_NOTINITIALIZED, RPC_E_DISCONNECTED, RPC_E_SERVER_DIED, RPC_E_SERVER_DIED_DNE].contains(&code)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        ensure_com_initialized, filetime_to_unix, index_file_of, item_by_id, legacy_operation_error, parse_index_file,
+        shell_item_from_path, shell_properties, with_com, FileOperationObserver, IndexFile, PerceivedType,
+        RecycleBinAccess, TrashContextExtWindows, COM_BROKEN, FILE_OPERATION_UNAVAILABLE, FOF_ALLOWUNDO,
+        RPC_E_DISCONNECTED,
+    };
+    use crate::tests::get_unique_name;
+    use crate::Error;
+    use std::{path::Path, sync::Arc};
+    use windows::Win32::System::Com::{CoInitializeEx, CoUninitialize, COINIT_APARTMENTTHREADED, COINIT_MULTITHREADED};
+
+    #[test]
+    fn filetime_saturates() {
+        assert_eq!(filetime_to_unix(116444736000000000), 0);
+        assert_eq!(filetime_to_unix(0), 0);
+        assert_eq!(filetime_to_unix(u64::MAX), (i64::MAX - 116444736000000000) / 10000000);
+    }
+
+    #[test]
+    #[serial_test::serial]
+    fn restore_to_long_path() {
+        let name = get_unique_name();
+        std::fs::write(&name, "").unwrap();
+        crate::delete(&name).unwrap();
+        let mut item = crate::os_limited::find(&name).unwrap().remove(0);
+        let long_parent =
+            std::env::current_dir().unwrap().join(format!("{name}-{}", "x".repeat(100))).join("y".repeat(200));
+        item.original_parent = Some(long_parent.clone());
+        crate::os_limited::restore_all([item]).unwrap();
+        assert!(long_parent.join(&name).exists());
+        std::fs::remove_dir_all(long_parent.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    #[serial_test::serial]
+    fn delete_in_long_path() {
+        let name = get_unique_name();
+        let root = std::env::current_dir().unwrap().join(format!("{name}-{}", "x".repeat(100)));
+        let long_parent = root.join("y".repeat(200));
+        std::fs::create_dir_all(&long_parent).unwrap();
+        let files: Vec<_> = (0..3).map(|i| long_parent.join(format!("{name}#{i}"))).collect();
+        for file in &files {
+            std::fs::write(file, "").unwrap();
+        }
+        assert!(files[0].as_os_str().len() > 300);
+
+        crate::delete(&files[0]).unwrap();
+        // Items in the same folder are created relative to it.
+        crate::delete_all(&files[1..]).unwrap();
+        assert!(files.iter().all(|file| !file.exists()));
+        let items: Vec<_> = files.iter().flat_map(|file| crate::os_limited::find(file).unwrap()).collect();
+        assert_eq!(items.len(), 3);
+        crate::os_limited::purge_all(items).unwrap();
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    #[serial_test::serial]
+    fn restore_in_single_operation() {
+        let names: Vec<_> = (0..3).map(|i| format!("{}#{i}", get_unique_name())).collect();
+        for name in &names {
+            std::fs::write(name, name).unwrap();
+        }
+        crate::delete_all(&names).unwrap();
+        let items: Vec<_> = names.iter().flat_map(|name| crate::os_limited::find(name).unwrap()).collect();
+        // A collision fails the whole batch before any of it is moved.
+        std::fs::write(&names[2], "").unwrap();
+        assert!(matches!(crate::os_limited::restore_all(items.clone()), Err(crate::Error::RestoreCollision { .. })));
+        assert!(!std::path::Path::new(&names[0]).exists());
+        std::fs::remove_file(&names[2]).unwrap();
+
+        crate::os_limited::restore_all(items).unwrap();
+        for name in &names {
+            assert_eq!(std::fs::read_to_string(name).unwrap(), *name);
+            std::fs::remove_file(name).unwrap();
+        }
+    }
+
+    #[test]
+    #[serial_test::serial]
+    fn sink_vetoes_queued_items() {
+        let name = std::env::current_dir().unwrap().join(get_unique_name());
+        std::fs::write(&name, "").unwrap();
+        let token = crate::CancellationToken::new();
+        let ctx = crate::TrashContext::builder().cancellation(token.clone()).build();
+        ensure_com_initialized().unwrap();
+        unsafe {
+            let pfo = ctx.file_operation(false, FOF_ALLOWUNDO).unwrap();
+            pfo.DeleteItem(&shell_item_from_path(&name).unwrap(), None).unwrap();
+            token.cancel();
+            assert!(matches!(pfo.perform(), Err(crate::Error::Cancelled)));
+        }
+        assert!(name.exists());
+        std::fs::remove_file(&name).unwrap();
+    }
+
+    #[test]
+    #[serial_test::serial]
+    fn observer_sees_deleted_items() {
+        use std::{path::PathBuf, sync::Mutex};
+
+        #[derive(Default)]
+        struct Recorder(Mutex<Vec<(PathBuf, bool, Option<PathBuf>)>>);
+        impl FileOperationObserver for Recorder {
+            fn post_delete_item(&self, path: &Path, result: Result<(), crate::Error>, recycled: Option<&Path>) {
+                self.0.lock().unwrap().push((path.to_owned(), result.is_ok(), recycled.map(Path::to_owned)));
+            }
+        }
+
+        let names: Vec<_> = (0..2).map(|_| std::env::current_dir().unwrap().join(get_unique_name())).collect();
+        for name in &names {
+            std::fs::write(name, "").unwrap();
+        }
+        let recorder = Arc::new(Recorder::default());
+        let mut ctx = crate::TrashContext::default();
+        ctx.set_file_operation_observer(Some(recorder.clone()));
+        ctx.delete_all(&names).unwrap();
+
+        let recorded = recorder.0.lock().unwrap();
+        assert_eq!(recorded.len(), 2);
+        for (path, succeeded, recycled) in recorded.iter() {
+            assert!(names.contains(path) && *succeeded);
+            let item = item_by_id(recycled.as_ref().unwrap().as_os_str()).unwrap();
+            assert_eq!(item.original_path().as_ref(), Some(path));
+        }
+        let items: Vec<_> = names.iter().flat_map(|name| crate::os_limited::find(name).unwrap()).collect();
+        crate::os_limited::purge_all(items).unwrap();
+    }
+
+    #[test]
+    fn index_file_with_huge_length() {
+        let path = std::env::temp_dir().join(get_unique_name());
+        let mut data = Vec::new();
+        data.extend_from_slice(&2u64.to_le_bytes());
+        data.extend_from_slice(&(5u64 << 40).to_le_bytes());
+        data.extend_from_slice(&u64::MAX.to_le_bytes());
+        data.extend_from_slice(&u32::MAX.to_le_bytes());
+        std::fs::write(&path, data).unwrap();
+        assert_eq!(parse_index_file(&path), None);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    #[serial_test::serial]
+    fn deletion_date_from_index_file() {
+        let name = get_unique_name();
+        std::fs::write(&name, "").unwrap();
+        crate::delete(&name).unwrap();
+        let item = crate::os_limited::find(&name).unwrap().remove(0);
+        let index_file = index_file_of(std::path::Path::new(&item.id)).unwrap();
+        let IndexFile { original_path, time_deleted, size } = parse_index_file(&index_file).unwrap();
+        crate::os_limited::purge_all([item.clone()]).unwrap();
+        assert_eq!(Some(original_path), item.original_path());
+        assert_eq!(Some(time_deleted), item.time_deleted);
+        assert_eq!(Some(crate::ByteSize::new(size)), item.size);
+        assert_eq!(index_file_of(std::path::Path::new(r"C:\$Recycle.Bin\S-1\$IABC.txt")), None);
+    }
+
+    #[test]
+    #[serial_test::serial]
+    fn direct_recycle_bin_access() {
+        use crate::TrashContext;
+
+        let name = get_unique_name();
+        std::fs::write(&name, "").unwrap();
+        crate::delete(&name).unwrap();
+        let from_shell = crate::os_limited::find(&name).unwrap().remove(0);
+
+        let mut ctx = TrashContext::default();
+        ctx.set_recycle_bin_access(RecycleBinAccess::Direct);
+        let direct: Vec<_> = ctx.list().unwrap().into_iter().filter(|item| item.name == name).collect();
+        assert_eq!(direct, [from_shell]);
+        ctx.purge_all(&direct).unwrap();
+        assert!(crate::os_limited::find(&name).unwrap().is_empty());
+    }
+
+    #[test]
+    #[serial_test::serial]
+    fn shell_properties_of_text_file() {
+        let name = format!("{}.txt", get_unique_name());
+        std::fs::write(&name, "text").unwrap();
+        crate::delete(&name).unwrap();
+        let item = crate::os_limited::find(&name).unwrap().remove(0);
+        let properties = shell_properties(&item).unwrap();
+        crate::os_limited::purge_all([item]).unwrap();
+        assert_eq!(properties.perceived_type, PerceivedType::Text);
+        assert!(properties.type_description.is_some());
+        assert!(properties.attributes.is_some());
+    }
+
+    #[test]
+    #[serial_test::serial]
+    fn delete_without_file_operation() {
+        use std::os::windows::fs::OpenOptionsExt;
+        use std::sync::atomic::Ordering;
+
+        let names: Vec<_> = (0..3).map(|i| format!("{}#{i}", get_unique_name())).collect();
+        for name in &names {
+            std::fs::write(name, "").unwrap();
+        }
+        // The open file can't be moved to the Recycle Bin while it isn't shared for deletion.
+        let locked = std::fs::OpenOptions::new().read(true).share_mode(0).open(&names[1]).unwrap();
+        let mut ctx = crate::TrashContext::default();
+        ctx.set_continue_on_error(true);
+        FILE_OPERATION_UNAVAILABLE.store(true, Ordering::Relaxed);
+        let result = ctx.delete_all(&names);
+        FILE_OPERATION_UNAVAILABLE.store(false, Ordering::Relaxed);
+        assert!(result.is_err());
+        assert!(!Path::new(&names[0]).exists() && !Path::new(&names[2]).exists());
+        assert!(Path::new(&names[1]).exists());
+
+        let items: Vec<_> =
+            [&names[0], &names[2]].iter().flat_map(|name| crate::os_limited::find(name).unwrap()).collect();
+        assert_eq!(items.len(), 2);
+        crate::os_limited::purge_all(items).unwrap();
+        drop(locked);
+        std::fs::remove_file(&names[1]).unwrap();
+    }
+
+    #[test]
+    fn legacy_operation_errors() {
+        assert!(matches!(legacy_operation_error(0x75), Error::Cancelled));
+        assert!(matches!(legacy_operation_error(0x78), Error::NeedsElevation { code: 0x78, .. }));
+        let Error::Os { code, function, .. } = legacy_operation_error(0x7C) else { panic!() };
+        assert_eq!((code, function.as_deref()), (0x7C, Some("SHFileOperationW")));
+        // Other codes are Win32 error codes.
+        let Error::Os { code, .. } = legacy_operation_error(0x20) else { panic!() };
+        assert_eq!(code, super::ERROR_SHARING_VIOLATION.to_hresult().0);
+    }
+
+    #[test]
+    fn com_recovers_on_the_same_thread() {
+        // Runs on its own thread, so that other tests don't see COM being reinitialized.
+        std::thread::spawn(|| {
+            let error = crate::Error::from(windows::core::Error::from(RPC_E_DISCONNECTED));
+            assert!(matches!(error, crate::Error::Os { .. }));
+            assert!(!COM_BROKEN.with(|broken| broken.get()));
+            assert!(with_com(|| Err::<(), _>(error)).is_err());
+            assert!(COM_BROKEN.with(|broken| broken.get()));
+
+            let name = get_unique_name();
+            std::fs::write(&name, "").unwrap();
+            crate::delete(&name).unwrap();
+            assert!(!COM_BROKEN.with(|broken| broken.get()));
+            let items = crate::os_limited::find(&name).unwrap();
+            crate::os_limited::purge_all(items).unwrap();
+        })
+        .join()
+        .unwrap();
+    }
+
+    #[test]
+    #[serial_test::serial]
+    fn original_path_keeps_extensions() {
+        let dir = std::env::current_dir().unwrap().canonicalize().unwrap();
+        let name = get_unique_name();
+        let paths: Vec<_> =
+            [".txt", ".tar.gz", ".txt.txt"].iter().map(|extension| dir.join(format!("{name}{extension}"))).collect();
+        for path in &paths {
+            std::fs::write(path, "").unwrap();
+        }
+        crate::delete_all(&paths).unwrap();
+        let is_deleted = |item: &crate::TrashItem| paths.iter().any(|path| path.file_name().unwrap() == &*item.name);
+        let items: Vec<_> = crate::os_limited::list().unwrap().into_iter().filter(is_deleted).collect();
+        let mut original_paths: Vec<_> = items.iter().map(|item| item.original_path().unwrap()).collect();
+        original_paths.sort();
+        let mut expected =
+            paths.iter().map(|path| crate::matching::strip_verbatim(path).into_owned()).collect::<Vec<_>>();
+        expected.sort();
+        assert_eq!(original_paths, expected);
+        crate::os_limited::purge_all(items).unwrap();
+    }
+
+    #[test]
+    #[serial_test::serial]
+    fn com_initialized_with_other_model() {
+        std::thread::spawn(|| {
+            let other_model = match cfg!(feature = "coinit_multithreaded") {
+                true => COINIT_APARTMENTTHREADED,
+                false => COINIT_MULTITHREADED,
+            };
+            unsafe { CoInitializeEx(None, other_model) }.unwrap();
+            let name = get_unique_name();
+            std::fs::write(&name, "").unwrap();
+            crate::delete(&name).unwrap();
+            let items = crate::os_limited::find(&name).unwrap();
+            crate::os_limited::purge_all(items).unwrap();
+            unsafe { CoUninitialize() };
+        })
+        .join()
+        .unwrap();
+    }
 }
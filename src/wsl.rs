@@ -0,0 +1,262 @@
+//! Crossing between Windows and the Windows Subsystem for Linux (WSL).
+//!
+//! Within WSL, the Windows drives are mounted at `/mnt/<letter>`. Items on them can be moved to
+//! the Windows Recycle Bin through PowerShell, which WSL runs on the Windows side. From Windows,
+//! the files of a distribution are reached through the `\\wsl$\<distro>` share. The shell deletes
+//! items on network shares permanently, so they're moved to the home trash within the
+//! distribution instead.
+
+use std::path::Path;
+#[cfg(target_os = "windows")]
+use std::{fs, io, path::PathBuf};
+#[cfg(target_os = "linux")]
+use std::{path::PathBuf, process::Command};
+
+#[cfg(target_os = "windows")]
+use crate::trash_info;
+
+#[cfg(target_os = "linux")]
+use crate::Error;
+
+/// PowerShell is started once for this many paths, which keeps its command line well below the
+/// limit of 32767 characters for typical paths.
+#[cfg(target_os = "linux")]
+const RECYCLE_BATCH: usize = 64;
+
+/// Whether the process runs within WSL.
+#[cfg(target_os = "linux")]
+pub(crate) fn is_wsl() -> bool {
+    std::env::var_os("WSL_DISTRO_NAME").is_some() || Path::new("/proc/sys/fs/binfmt_misc/WSLInterop").exists()
+}
+
+/// The folder that holds the mounted Windows drives, the `root` of the `automount` section of
+/// `/etc/wsl.conf`, or `/mnt` if that isn't set.
+#[cfg(target_os = "linux")]
+pub(crate) fn automount_root() -> PathBuf {
+    let config = std::fs::read_to_string("/etc/wsl.conf").unwrap_or_default();
+    automount_root_in(&config).unwrap_or_else(|| PathBuf::from("/mnt"))
+}
+
+/// Reads the `root` of the `automount` section from the contents of a `wsl.conf` file.
+#[cfg(target_os = "linux")]
+fn automount_root_in(config: &str) -> Option<PathBuf> {
+    let mut in_automount = false;
+    for line in config.lines() {
+        let line = line.trim();
+        if let Some(section) = line.strip_prefix('[').and_then(|line| line.strip_suffix(']')) {
+            in_automount = section.trim().eq_ignore_ascii_case("automount");
+            continue;
+        }
+        let Some((key, value)) = line.split_once('=') else {
+            continue;
+        };
+        if in_automount && key.trim().eq_ignore_ascii_case("root") {
+            let value = value.split('#').next().unwrap_or_default().trim().trim_matches('"');
+            return value.starts_with('/').then(|| PathBuf::from(value));
+        }
+    }
+    None
+}
+
+/// The Windows path of a path on a Windows drive mounted within `root`, like `C:\Users` for
+/// `/mnt/c/Users`, or `None` if the path isn't on one.
+#[cfg(target_os = "linux")]
+pub(crate) fn windows_path(root: &Path, path: &Path) -> Option<String> {
+    let rest = path.strip_prefix(root).ok()?.to_str()?;
+    let (drive, rest) = rest.split_once('/').unwrap_or((rest, ""));
+    let mut letters = drive.chars();
+    let letter = letters.next().filter(char::is_ascii_alphabetic)?;
+    if letters.next().is_some() {
+        return None;
+    }
+    Some(format!("{}:\\{}", letter.to_ascii_uppercase(), rest.replace('/', "\\")))
+}
+
+/// Moves the items with the given Windows paths to the Recycle Bin, stopping at the first failure.
+/// The shell is asked not to show any confirmations, progress or error dialogs.
+#[cfg(target_os = "linux")]
+pub(crate) fn recycle(windows_paths: &[String]) -> Result<(), Error> {
+    for batch in windows_paths.chunks(RECYCLE_BATCH) {
+        let paths: Vec<_> = batch.iter().map(|path| format!("'{}'", path.replace('\'', "''"))).collect();
+        let script = format!(
+            "$ErrorActionPreference = 'Stop'; {RECYCLER} foreach ($path in @({})) {{ {RECYCLE_PATH} }}",
+            paths.join(", ")
+        );
+        let output = Command::new("powershell.exe")
+            .args(["-NoProfile", "-NonInteractive", "-Command", &script])
+            .output()
+            .map_err(|e| Error::Os {
+                code: e.raw_os_error().unwrap_or_default(),
+                function: Some("powershell.exe".into()),
+                description: format!("Failed to start powershell.exe: {e}"),
+            })?;
+        if !output.status.success() {
+            return Err(Error::Os {
+                code: output.status.code().unwrap_or(-1),
+                function: Some("powershell.exe".into()),
+                description: format!(
+                    "PowerShell failed to move {:?} to the Recycle Bin: {}",
+                    batch,
+                    String::from_utf8_lossy(&output.stderr).trim()
+                ),
+            });
+        }
+    }
+    Ok(())
+}
+
+/// Declares `SHFileOperationW` to PowerShell. Unlike the `FileSystem` class of Visual Basic, it can
+/// move items to the Recycle Bin without showing any dialogs, including the error dialogs.
+#[cfg(target_os = "linux")]
+const RECYCLER: &str = r#"Add-Type -TypeDefinition '
+using System;
+using System.Runtime.InteropServices;
+public static class TrashRsRecycler {
+    [StructLayout(LayoutKind.Sequential, CharSet = CharSet.Unicode)]
+    struct SHFILEOPSTRUCT {
+        public IntPtr hwnd;
+        public uint wFunc;
+        public string pFrom;
+        public string pTo;
+        public ushort fFlags;
+        public bool fAnyOperationsAborted;
+        public IntPtr hNameMappings;
+        public string lpszProgressTitle;
+    }
+    [DllImport("shell32.dll", CharSet = CharSet.Unicode)]
+    static extern int SHFileOperationW(ref SHFILEOPSTRUCT operation);
+    public static int Recycle(string path) {
+        SHFILEOPSTRUCT operation = new SHFILEOPSTRUCT();
+        operation.wFunc = 3;
+        operation.pFrom = path + "\0";
+        operation.fFlags = 0x0040 | 0x0010 | 0x0004 | 0x0400;
+        int result = SHFileOperationW(ref operation);
+        return result == 0 && operation.fAnyOperationsAborted ? 0x4C7 : result;
+    }
+}';"#;
+
+/// Moves the item at `$path` to the Recycle Bin and throws if that fails.
+#[cfg(target_os = "linux")]
+const RECYCLE_PATH: &str = "if (-not (Test-Path -LiteralPath $path)) { throw \"$path doesn't exist\" }; \
+     $result = [TrashRsRecycler]::Recycle($path); \
+     if ($result -ne 0) { throw \"Failed to move $path to the Recycle Bin, error $result\" }";
+
+/// A path within the files of a WSL distribution, as reached from Windows.
+#[cfg(target_os = "windows")]
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) struct DistroPath {
+    /// The share of the distribution, like `\\wsl$\Ubuntu`.
+    pub(crate) share: PathBuf,
+    /// The path within the distribution, like `/home/user/notes.txt`.
+    pub(crate) linux_path: String,
+}
+
+/// Recognizes paths within `\\wsl$\<distro>` and `\\wsl.localhost\<distro>`, including their
+/// verbatim `\\?\UNC\` forms.
+#[cfg(target_os = "windows")]
+pub(crate) fn distro_path(path: &Path) -> Option<DistroPath> {
+    let path = crate::matching::strip_verbatim(path);
+    let (host, rest) = path.to_str()?.strip_prefix(r"\\")?.split_once('\\')?;
+    if !host.eq_ignore_ascii_case("wsl$") && !host.eq_ignore_ascii_case("wsl.localhost") {
+        return None;
+    }
+    let (distro, rest) = rest.split_once('\\').unwrap_or((rest, ""));
+    if distro.is_empty() {
+        return None;
+    }
+    Some(DistroPath {
+        share: PathBuf::from(format!(r"\\{host}\{distro}")),
+        linux_path: format!("/{}", rest.trim_end_matches('\\').replace('\\', "/")),
+    })
+}
+
+#[cfg(target_os = "windows")]
+impl DistroPath {
+    /// The home trash of the user whose home folder holds the path, or `None` for paths outside
+    /// of `/home/<user>` and `/root`.
+    pub(crate) fn trash_root(&self) -> Option<PathBuf> {
+        let mut components = self.linux_path.split('/').skip(1);
+        let home = match (components.next()?, components.next()) {
+            ("root", Some(_)) => self.share.join("root"),
+            ("home", Some(user)) if !user.is_empty() && components.next().is_some() => {
+                self.share.join("home").join(user)
+            }
+            _ => return None,
+        };
+        Some(home.join(".local").join("share").join("Trash"))
+    }
+}
+
+/// Moves the item at `full_path` to the home trash within its distribution, following the
+/// freedesktop trash specification.
+#[cfg(target_os = "windows")]
+pub(crate) fn move_to_distro_trash(full_path: &Path, distro_path: &DistroPath, trash_root: &Path) -> io::Result<()> {
+    let files = trash_root.join("files");
+    let info = trash_root.join("info");
+    fs::create_dir_all(&files)?;
+    fs::create_dir_all(&info)?;
+    let name = full_path.file_name().ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "no file name"))?;
+    let encoded_path = trash_info::encode_path(distro_path.linux_path.as_bytes());
+    let deletion_date = deletion_date();
+    for attempt in 1.. {
+        let (in_trash_name, info_name) = trash_info::names(name, attempt);
+        let info_path = info.join(info_name);
+        match trash_info::create(&info_path, &encoded_path, Some(&deletion_date)) {
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists => continue,
+            result => result?,
+        }
+        let result = fs::rename(full_path, files.join(in_trash_name));
+        if result.is_err() {
+            let _ = fs::remove_file(&info_path);
+        }
+        return result;
+    }
+    Ok(())
+}
+
+/// The current local time in the `YYYY-MM-DDThh:mm:ss` format of trash info files.
+#[cfg(target_os = "windows")]
+fn deletion_date() -> String {
+    let time = unsafe { windows::Win32::System::SystemInformation::GetLocalTime() };
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}",
+        time.wYear, time.wMonth, time.wDay, time.wHour, time.wMinute, time.wSecond
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn windows_paths() {
+        let root = Path::new("/mnt");
+        assert_eq!(windows_path(root, Path::new("/mnt/c/Users/me/a.txt")).as_deref(), Some(r"C:\Users\me\a.txt"));
+        assert_eq!(windows_path(root, Path::new("/mnt/d")).as_deref(), Some(r"D:\"));
+        assert_eq!(windows_path(root, Path::new("/mnt/wsl/a")), None);
+        assert_eq!(windows_path(root, Path::new("/home/me/a.txt")), None);
+        assert_eq!(windows_path(Path::new("/win/"), Path::new("/win/c/a.txt")).as_deref(), Some(r"C:\a.txt"));
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn automount_roots() {
+        let config = "[boot]\nsystemd=true\n\n[automount]\nenabled = true\nroot = \"/win/\" # drives\n";
+        assert_eq!(automount_root_in(config), Some(PathBuf::from("/win/")));
+        assert_eq!(automount_root_in("[network]\nroot = /win/\n"), None);
+        assert_eq!(automount_root_in(""), None);
+    }
+
+    #[cfg(target_os = "windows")]
+    #[test]
+    fn distro_paths() {
+        let path = distro_path(Path::new(r"\\?\UNC\wsl.localhost\Ubuntu\home\me\a b.txt")).unwrap();
+        assert_eq!(path.share, PathBuf::from(r"\\wsl.localhost\Ubuntu"));
+        assert_eq!(path.linux_path, "/home/me/a b.txt");
+        assert_eq!(path.trash_root(), Some(PathBuf::from(r"\\wsl.localhost\Ubuntu\home\me\.local\share\Trash")));
+        assert_eq!(distro_path(Path::new(r"\\wsl$\Ubuntu\etc\hosts")).unwrap().trash_root(), None);
+        assert_eq!(distro_path(Path::new(r"\\server\share\a.txt")), None);
+        assert_eq!(distro_path(Path::new(r"C:\a.txt")), None);
+    }
+}
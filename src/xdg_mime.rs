@@ -0,0 +1,247 @@
+//! Looking up MIME types and their icons in the shared MIME-info database.
+//!
+//! See: <https://specifications.freedesktop.org/shared-mime-info-spec/latest/>
+
+#[cfg(feature = "thumbnails")]
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use once_cell::sync::Lazy;
+
+use crate::glob;
+
+/// The data folders, ordered by their precedence.
+pub(crate) fn data_dirs() -> Vec<PathBuf> {
+    let data_home = match std::env::var_os("XDG_DATA_HOME") {
+        Some(data_home) if !data_home.is_empty() => Some(PathBuf::from(data_home)),
+        _ => std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".local/share")),
+    };
+    let data_dirs = match std::env::var("XDG_DATA_DIRS") {
+        Ok(dirs) if !dirs.is_empty() => dirs,
+        _ => "/usr/local/share:/usr/share".into(),
+    };
+    data_home.into_iter().chain(data_dirs.split(':').filter(|dir| !dir.is_empty()).map(PathBuf::from)).collect()
+}
+
+struct Glob {
+    weight: u32,
+    mime_type: String,
+    pattern: String,
+    case_sensitive: bool,
+}
+
+static GLOBS: Lazy<Vec<Glob>> = Lazy::new(|| {
+    let mut globs = Vec::new();
+    for dir in data_dirs() {
+        let Ok(contents) = std::fs::read_to_string(dir.join("mime/globs2")) else {
+            continue;
+        };
+        for line in contents.lines().filter(|line| !line.starts_with('#')) {
+            let mut fields = line.split(':');
+            let (Some(weight), Some(mime_type), Some(pattern)) = (fields.next(), fields.next(), fields.next()) else {
+                continue;
+            };
+            let case_sensitive = fields.next().is_some_and(|flags| flags.split(',').any(|flag| flag == "cs"));
+            let pattern = if case_sensitive { pattern.to_owned() } else { pattern.to_lowercase() };
+            let weight = weight.parse().unwrap_or(50);
+            globs.push(Glob { weight, mime_type: mime_type.to_owned(), pattern, case_sensitive });
+        }
+    }
+    globs
+});
+
+/// Maps MIME types to the names of their icons, read from the `icons` or `generic-icons` file.
+#[cfg(feature = "thumbnails")]
+fn load_icons(file_name: &str) -> HashMap<String, String> {
+    let mut icons = HashMap::new();
+    for dir in data_dirs() {
+        let Ok(contents) = std::fs::read_to_string(dir.join("mime").join(file_name)) else {
+            continue;
+        };
+        for (mime_type, icon) in contents.lines().filter_map(|line| line.split_once(':')) {
+            // Folders that come first take precedence.
+            icons.entry(mime_type.to_owned()).or_insert_with(|| icon.to_owned());
+        }
+    }
+    icons
+}
+
+#[cfg(feature = "thumbnails")]
+static ICONS: Lazy<HashMap<String, String>> = Lazy::new(|| load_icons("icons"));
+#[cfg(feature = "thumbnails")]
+static GENERIC_ICONS: Lazy<HashMap<String, String>> = Lazy::new(|| load_icons("generic-icons"));
+
+/// Guesses the MIME type of a file from its name.
+pub(crate) fn from_name(name: &str) -> Option<&'static str> {
+    let lowercase = name.to_lowercase();
+    GLOBS
+        .iter()
+        .filter(|glob| glob::matches(&glob.pattern, if glob.case_sensitive { name } else { &lowercase }))
+        // The longest of the patterns with the highest weight is the most specific one.
+        .max_by_key(|glob| (glob.weight, glob.pattern.len()))
+        .map(|glob| glob.mime_type.as_str())
+}
+
+/// The names of the icons for a MIME type, starting with the most specific one.
+#[cfg(feature = "thumbnails")]
+pub(crate) fn icon_names(mime_type: &str) -> Vec<String> {
+    let media = mime_type.split('/').next().unwrap_or(mime_type);
+    let mut names: Vec<String> = ICONS.get(mime_type).cloned().into_iter().collect();
+    names.push(mime_type.replace('/', "-"));
+    names.extend(GENERIC_ICONS.get(mime_type).cloned());
+    names.push(format!("{media}-x-generic"));
+    names.push("application-x-generic".into());
+    names
+}
+
+/// No more than this many bytes are read from the start of a file to find its type.
+#[cfg(feature = "mime")]
+const MAX_MAGIC_LEN: usize = 64 * 1024;
+
+/// A test whether some bytes of a file have a certain value. The tests below it, with a greater
+/// `indent`, are only tried if it succeeds.
+#[cfg(feature = "mime")]
+struct Matchlet {
+    indent: usize,
+    offset: usize,
+    range: usize,
+    value: Vec<u8>,
+    mask: Option<Vec<u8>>,
+}
+
+#[cfg(feature = "mime")]
+impl Matchlet {
+    fn matches(&self, data: &[u8]) -> bool {
+        (self.offset..self.offset.saturating_add(self.range)).any(|start| {
+            let Some(bytes) = data.get(start..start.saturating_add(self.value.len())) else {
+                return false;
+            };
+            match &self.mask {
+                Some(mask) => bytes.iter().zip(&self.value).zip(mask).all(|((b, v), m)| b & m == v & m),
+                None => bytes == self.value,
+            }
+        })
+    }
+}
+
+/// The MIME type that a file has if one of the matchlets matches.
+#[cfg(feature = "mime")]
+type MagicSection = (String, Vec<Matchlet>);
+
+/// The tests of the `magic` files, ordered by their priority, along with the number of bytes of a
+/// file they need.
+#[cfg(feature = "mime")]
+static MAGIC: Lazy<(Vec<MagicSection>, usize)> = Lazy::new(|| {
+    let mut sections: Vec<(u32, String, Vec<Matchlet>)> = Vec::new();
+    for dir in data_dirs() {
+        let Ok(contents) = std::fs::read(dir.join("mime/magic")) else {
+            continue;
+        };
+        if let Some(mut rest) = contents.strip_prefix(b"MIME-Magic\0\n") {
+            while let Some(parsed) = parse_section(rest) {
+                let (priority, mime_type, matchlets, next) = parsed;
+                sections.push((priority, mime_type, matchlets));
+                rest = next;
+            }
+        }
+    }
+    // The sort is stable, so folders that come first take precedence for the same priority.
+    sections.sort_by_key(|section| std::cmp::Reverse(section.0));
+    let len = sections
+        .iter()
+        .flat_map(|(_, _, matchlets)| matchlets)
+        .map(|m| m.offset.saturating_add(m.range).saturating_add(m.value.len()))
+        .max()
+        .unwrap_or(0)
+        .min(MAX_MAGIC_LEN);
+    (sections.into_iter().map(|(_, mime_type, matchlets)| (mime_type, matchlets)).collect(), len)
+});
+
+/// Parses a `[priority:mime/type]` section and its matchlets, returning what's left after it.
+#[cfg(feature = "mime")]
+fn parse_section(data: &[u8]) -> Option<(u32, String, Vec<Matchlet>, &[u8])> {
+    let data = data.strip_prefix(b"[")?;
+    let end = data.iter().position(|&b| b == b'\n')?;
+    let header = std::str::from_utf8(data[..end].strip_suffix(b"]")?).ok()?;
+    let (priority, mime_type) = header.split_once(':')?;
+    let mut rest = &data[end + 1..];
+    let mut matchlets = Vec::new();
+    while !rest.is_empty() && rest[0] != b'[' {
+        let (matchlet, next) = parse_matchlet(rest)?;
+        matchlets.push(matchlet);
+        rest = next;
+    }
+    Some((priority.parse().ok()?, mime_type.to_owned(), matchlets, rest))
+}
+
+/// Parses a `[indent]>start-offset=value[&mask][~word-size][+range-length]` line.
+#[cfg(feature = "mime")]
+fn parse_matchlet(data: &[u8]) -> Option<(Matchlet, &[u8])> {
+    fn number(data: &[u8]) -> (Option<usize>, &[u8]) {
+        let len = data.iter().take_while(|b| b.is_ascii_digit()).count();
+        let value = std::str::from_utf8(&data[..len]).ok().and_then(|digits| digits.parse().ok());
+        (value, &data[len..])
+    }
+
+    let (indent, data) = number(data);
+    let data = data.strip_prefix(b">")?;
+    let (offset, data) = number(data);
+    let data = data.strip_prefix(b"=")?;
+    let len = usize::from(u16::from_be_bytes([*data.first()?, *data.get(1)?]));
+    let mut value = data.get(2..2 + len)?.to_vec();
+    let mut data = &data[2 + len..];
+    let mut mask = None;
+    if let Some(rest) = data.strip_prefix(b"&") {
+        mask = Some(rest.get(..len)?.to_vec());
+        data = &rest[len..];
+    }
+    let mut word_size = 1;
+    if let Some(rest) = data.strip_prefix(b"~") {
+        let (size, rest) = number(rest);
+        word_size = size.unwrap_or(1);
+        data = rest;
+    }
+    let mut range = 1;
+    if let Some(rest) = data.strip_prefix(b"+") {
+        let (length, rest) = number(rest);
+        range = length.unwrap_or(1);
+        data = rest;
+    }
+    let data = data.strip_prefix(b"\n")?;
+    // Multi-byte words are stored big endian.
+    if word_size > 1 && cfg!(target_endian = "little") {
+        for bytes in std::iter::once(&mut value).chain(mask.as_mut()) {
+            bytes.chunks_exact_mut(word_size).for_each(<[u8]>::reverse);
+        }
+    }
+    Some((Matchlet { indent: indent.unwrap_or(0), offset: offset?, range: range.max(1), value, mask }, data))
+}
+
+/// The number of bytes from the start of a file that [`from_data`] looks at.
+#[cfg(feature = "mime")]
+pub(crate) fn magic_len() -> usize {
+    MAGIC.1
+}
+
+/// Finds the MIME type of a file from its first bytes, see [`magic_len`].
+#[cfg(feature = "mime")]
+pub(crate) fn from_data(data: &[u8]) -> Option<&'static str> {
+    MAGIC.0.iter().find(|(_, matchlets)| matches_any(matchlets, 0, data)).map(|(mime_type, _)| mime_type.as_str())
+}
+
+/// Returns `true` if one of the matchlets at the level of the first one matches, along with one of
+/// the matchlets nested below it, if there are any.
+#[cfg(feature = "mime")]
+fn matches_any(matchlets: &[Matchlet], indent: usize, data: &[u8]) -> bool {
+    let mut rest = matchlets;
+    while let Some((first, others)) = rest.split_first() {
+        let nested_len = others.iter().take_while(|m| m.indent > indent).count();
+        let (nested, next) = others.split_at(nested_len);
+        if first.indent == indent && first.matches(data) && (nested.is_empty() || matches_any(nested, indent + 1, data))
+        {
+            return true;
+        }
+        rest = next;
+    }
+    false
+}
@@ -0,0 +1,40 @@
+#![cfg(all(
+    feature = "tokio",
+    any(
+        target_os = "windows",
+        all(unix, not(target_os = "macos"), not(target_os = "ios"), not(target_os = "android"))
+    )
+))]
+
+use std::fs::File;
+use std::path::Path;
+
+use serial_test::serial;
+
+fn unique_name(suffix: &str) -> String {
+    format!("trash-futures-test-{}-{}", std::process::id(), suffix)
+}
+
+#[tokio::test]
+#[serial]
+async fn delete_list_and_restore() {
+    let name = unique_name("file");
+    File::create(&name).unwrap();
+
+    trash::futures::delete_all([&name]).await.unwrap();
+    assert!(!Path::new(&name).exists());
+
+    let items: Vec<_> = trash::futures::list().await.unwrap().into_iter().filter(|item| item.name == name).collect();
+    assert_eq!(items.len(), 1);
+
+    trash::futures::restore_all(items).await.unwrap();
+    assert!(Path::new(&name).exists());
+    std::fs::remove_file(&name).unwrap();
+}
+
+#[tokio::test]
+#[serial]
+async fn missing_path_fails() {
+    let name = unique_name("missing");
+    assert!(trash::futures::delete_all([&name]).await.is_err());
+}
@@ -0,0 +1,46 @@
+#![cfg(feature = "helper")]
+
+use std::fs::File;
+use std::path::Path;
+use std::process::Command;
+
+use trash::helper::HelperClient;
+
+fn unique_name(suffix: &str) -> String {
+    format!("trash-helper-test-{}-{}", std::process::id(), suffix)
+}
+
+#[test]
+fn delete_through_helper() {
+    let ctx = trash::TrashContext::builder().recursive(false).build();
+    let mut client = HelperClient::spawn(&ctx, Command::new(env!("CARGO_BIN_EXE_trash-helper"))).unwrap();
+
+    let trashed = unique_name("trashed");
+    File::create(&trashed).unwrap();
+    client.delete_all([&trashed]).unwrap();
+    assert!(!Path::new(&trashed).exists());
+
+    // Errors of the helper are sent back as they are.
+    match client.delete_all([&trashed]) {
+        Err(trash::Error::Unknown { description }) => panic!("the helper failed: {description}"),
+        Err(_) => {}
+        Ok(()) => panic!("a missing file was deleted"),
+    }
+
+    // The helper applies the settings of the client's context.
+    let folder = unique_name("folder");
+    std::fs::create_dir(&folder).unwrap();
+    File::create(Path::new(&folder).join("file")).unwrap();
+    assert!(matches!(client.delete_all([&folder]), Err(trash::Error::DirectoryNotEmpty { .. })));
+    std::fs::remove_dir_all(&folder).unwrap();
+    client.shutdown().unwrap();
+}
+
+#[test]
+fn delete_without_elevation_when_allowed() {
+    let name = unique_name("allowed");
+    File::create(&name).unwrap();
+    let ctx = trash::TrashContext::default();
+    trash::helper::delete_all_or_elevate(&ctx, [&name], || panic!("the helper isn't needed")).unwrap();
+    assert!(!Path::new(&name).exists());
+}
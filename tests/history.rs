@@ -0,0 +1,87 @@
+#![cfg(all(
+    feature = "history",
+    any(
+        target_os = "windows",
+        all(unix, not(target_os = "macos"), not(target_os = "ios"), not(target_os = "android"))
+    )
+))]
+
+use std::fs::File;
+use std::path::Path;
+
+use serial_test::serial;
+use trash::history::{HistoryOperation, TrashHistory};
+use trash::TrashContext;
+
+fn unique_name(suffix: &str) -> String {
+    format!("trash-history-test-{}-{}", std::process::id(), suffix)
+}
+
+#[test]
+#[serial]
+fn undo_and_redo_across_reopening() {
+    let ctx = TrashContext::default();
+    let file = std::env::temp_dir().join(unique_name("history.json"));
+    let name = unique_name("file");
+    File::create(&name).unwrap();
+
+    let mut history = TrashHistory::open(&file).unwrap();
+    history.delete_all(&ctx, [&name]).unwrap();
+    assert!(!Path::new(&name).exists());
+    let items = ctx.find(&name).unwrap();
+    history.restore_all(&ctx, items).unwrap();
+    assert!(Path::new(&name).exists());
+    assert_eq!(history.undo_entries().len(), 2);
+
+    // The history survives being reopened.
+    let mut history = TrashHistory::open(&file).unwrap();
+    let undone = history.undo_last(&ctx).unwrap().unwrap();
+    assert_eq!(undone.operation, HistoryOperation::Restore);
+    assert!(!Path::new(&name).exists());
+    history.undo_last(&ctx).unwrap().unwrap();
+    assert!(Path::new(&name).exists());
+    assert!(history.undo_last(&ctx).unwrap().is_none());
+
+    let redone = history.redo(&ctx).unwrap().unwrap();
+    assert_eq!(redone.operation, HistoryOperation::Delete);
+    assert!(!Path::new(&name).exists());
+    assert_eq!(history.redo_entries().len(), 1);
+
+    // Recording an operation forgets what could be redone.
+    let other = unique_name("other");
+    File::create(&other).unwrap();
+    history.set_capacity(1);
+    history.delete_all(&ctx, [&other]).unwrap();
+    assert!(history.redo_entries().is_empty());
+    assert_eq!(history.undo_entries().len(), 1);
+
+    trash::os_limited::purge_all(ctx.find(&name).unwrap().into_iter().chain(ctx.find(&other).unwrap())).unwrap();
+    std::fs::remove_file(&file).unwrap();
+}
+
+#[test]
+#[serial]
+fn undo_restore_to_renamed_path() {
+    let ctx = TrashContext::builder().restore_collision(trash::RestoreCollision::RenameWithSuffix).build();
+    let file = std::env::temp_dir().join(unique_name("renamed-history.json"));
+    let name = unique_name("renamed.txt");
+    let renamed = unique_name("renamed (2).txt");
+    std::fs::write(&name, "trashed").unwrap();
+
+    let mut history = TrashHistory::open(&file).unwrap();
+    // The missing path is left out of the recorded delete.
+    history.delete_all(&ctx, [&name, &unique_name("missing")]).unwrap_err();
+    assert_eq!(history.undo_entries()[0].ids.len(), 1);
+    std::fs::write(&name, "existing").unwrap();
+    history.restore_all(&ctx, ctx.find(&name).unwrap()).unwrap();
+    assert_eq!(std::fs::read_to_string(&renamed).unwrap(), "trashed");
+
+    // Undoing the restore moves the renamed file back, not the one that was in the way.
+    history.undo_last(&ctx).unwrap().unwrap();
+    assert!(!Path::new(&renamed).exists());
+    assert_eq!(std::fs::read_to_string(&name).unwrap(), "existing");
+
+    trash::os_limited::purge_all(ctx.find(&renamed).unwrap()).unwrap();
+    std::fs::remove_file(&name).unwrap();
+    std::fs::remove_file(&file).unwrap();
+}
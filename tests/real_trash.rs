@@ -0,0 +1,179 @@
+//! Round trips through the trash of the current user, end to end through the public API.
+//!
+//! These tests move files into the real trash of the machine, so they only run when the
+//! `TRASH_TEST_REAL_TRASH` environment variable is set to `1`:
+//!
+//! ```sh
+//! TRASH_TEST_REAL_TRASH=1 cargo test --test real_trash
+//! ```
+//!
+//! Every test works in a uniquely named folder in the current directory. The folder, and whatever
+//! the test left in the trash from it, is removed when the test ends, also when it fails.
+#![cfg(any(
+    target_os = "windows",
+    all(unix, not(target_os = "macos"), not(target_os = "ios"), not(target_os = "android"))
+))]
+
+use std::ffi::OsStr;
+use std::fs;
+use std::path::PathBuf;
+
+use serial_test::serial;
+use trash::{os_limited, Error, TrashItem};
+
+const ENABLE_VAR: &str = "TRASH_TEST_REAL_TRASH";
+
+/// A folder of its own for a test, which is removed along with its items in the trash on drop.
+struct Sandbox {
+    dir: PathBuf,
+}
+
+impl Sandbox {
+    /// Returns `None` if the tests against the real trash aren't enabled.
+    fn new(test: &str) -> Option<Self> {
+        if std::env::var_os(ENABLE_VAR).as_deref() != Some(OsStr::new("1")) {
+            eprintln!("Skipping {test}, set {ENABLE_VAR}=1 to run it against the real trash");
+            return None;
+        }
+        let _ = env_logger::builder().is_test(true).try_init();
+        let name = format!("trash-real-{test}-{}-{}", std::process::id(), chrono::Local::now().timestamp_millis());
+        let dir = std::env::current_dir().unwrap().join(name);
+        fs::create_dir(&dir).unwrap();
+        // The trash reports the canonical original location.
+        Some(Sandbox { dir: dir.canonicalize().unwrap() })
+    }
+
+    fn file(&self, name: &str, contents: &str) -> PathBuf {
+        let path = self.dir.join(name);
+        fs::write(&path, contents).unwrap();
+        path
+    }
+
+    fn folder(&self, name: &str) -> PathBuf {
+        let path = self.dir.join(name);
+        fs::create_dir(&path).unwrap();
+        fs::write(path.join("inner.txt"), "inner").unwrap();
+        path
+    }
+
+    /// The items in the trash that were deleted from the sandbox.
+    fn trashed(&self) -> Vec<TrashItem> {
+        os_limited::list()
+            .unwrap()
+            .into_iter()
+            .filter(|item| item.original_parent.as_deref() == Some(&self.dir))
+            .collect()
+    }
+
+    fn trashed_named(&self, name: &str) -> TrashItem {
+        let mut items: Vec<_> = self.trashed().into_iter().filter(|item| item.name == name).collect();
+        assert_eq!(items.len(), 1, "expected a single item named {name:?} in the trash");
+        items.remove(0)
+    }
+}
+
+impl Drop for Sandbox {
+    fn drop(&mut self) {
+        let leftovers = self.trashed();
+        if !leftovers.is_empty() {
+            if let Err(e) = os_limited::purge_all(leftovers) {
+                eprintln!("Failed to purge the items of {:?} from the trash: {e}", self.dir);
+            }
+        }
+        if let Err(e) = fs::remove_dir_all(&self.dir) {
+            eprintln!("Failed to remove {:?}: {e}", self.dir);
+        }
+    }
+}
+
+#[test]
+#[serial]
+fn delete_list_restore_purge() {
+    let Some(sandbox) = Sandbox::new("round-trip") else { return };
+    let file = sandbox.file("file.txt", "contents");
+    let folder = sandbox.folder("folder");
+
+    trash::delete_all([&file, &folder]).unwrap();
+    assert!(!file.exists() && !folder.exists());
+    let file_item = sandbox.trashed_named("file.txt");
+    assert_eq!(file_item.original_path().as_deref(), Some(file.as_path()));
+    assert_eq!(file_item.size, Some(trash::ByteSize::new("contents".len() as u64)));
+    assert!(!file_item.is_degraded());
+    assert_eq!(sandbox.trashed().len(), 2);
+
+    os_limited::restore_all([file_item]).unwrap();
+    assert_eq!(fs::read_to_string(&file).unwrap(), "contents");
+
+    os_limited::purge_all([sandbox.trashed_named("folder")]).unwrap();
+    assert!(sandbox.trashed().is_empty());
+    assert!(!folder.exists());
+}
+
+#[test]
+#[serial]
+fn restore_collision() {
+    let Some(sandbox) = Sandbox::new("collision") else { return };
+    let file = sandbox.file("taken.txt", "trashed");
+    trash::delete(&file).unwrap();
+    sandbox.file("taken.txt", "new");
+
+    match os_limited::restore_all(sandbox.trashed()) {
+        Err(Error::RestoreCollision { path, remaining_items }) => {
+            assert_eq!(path, file);
+            assert_eq!(remaining_items.len(), 1);
+        }
+        other => panic!("expected a restore collision, got {other:?}"),
+    }
+    assert_eq!(fs::read_to_string(&file).unwrap(), "new");
+    assert_eq!(sandbox.trashed().len(), 1);
+}
+
+#[test]
+#[serial]
+fn names_that_need_encoding() {
+    let Some(sandbox) = Sandbox::new("names") else { return };
+    let names = ["with space.txt", "percent%20.txt", "ünïcödé.txt", "hash#.txt"];
+    let files: Vec<_> = names.iter().map(|name| sandbox.file(name, name)).collect();
+
+    trash::delete_all(&files).unwrap();
+    let items: Vec<_> = names.iter().map(|name| sandbox.trashed_named(name)).collect();
+    os_limited::restore_all(items).unwrap();
+    for (name, file) in names.iter().zip(&files) {
+        assert_eq!(fs::read_to_string(file).unwrap(), *name);
+    }
+}
+
+#[test]
+#[serial]
+fn restore_to_other_folder() {
+    let Some(sandbox) = Sandbox::new("restore-to") else { return };
+    let file = sandbox.file("moved.txt", "moved");
+    trash::delete(&file).unwrap();
+
+    let target = sandbox.dir.join("target");
+    os_limited::restore_all_to([sandbox.trashed_named("moved.txt")], &target).unwrap();
+    assert_eq!(fs::read_to_string(target.join("moved.txt")).unwrap(), "moved");
+    assert!(!file.exists());
+}
+
+#[test]
+#[serial]
+fn metrics_follow_the_trash() {
+    let Some(sandbox) = Sandbox::new("metrics") else { return };
+    let file = sandbox.file("counted.txt", "12345");
+    let before = os_limited::metrics().unwrap();
+    trash::delete(&file).unwrap();
+    assert_eq!(os_limited::metrics().unwrap().item_count, before.item_count + 1);
+    os_limited::purge_all(sandbox.trashed()).unwrap();
+    assert_eq!(os_limited::metrics().unwrap().item_count, before.item_count);
+}
+
+#[test]
+#[serial]
+fn missing_paths_leave_the_trash_alone() {
+    let Some(sandbox) = Sandbox::new("missing") else { return };
+    let before = os_limited::metrics().unwrap();
+    assert!(trash::delete(sandbox.dir.join("missing.txt")).is_err());
+    assert_eq!(os_limited::metrics().unwrap(), before);
+    assert!(sandbox.dir.exists());
+}
@@ -165,3 +165,187 @@ fn recursive_file_with_content_deletion() {
     trash::delete(parent_dir).unwrap();
     assert!(!parent_dir.exists());
 }
+
+#[test]
+#[serial]
+fn hooks_skip_and_veto() {
+    use trash::{HookDecision, TrashContext, TrashHooks};
+
+    struct Keep(String);
+    impl TrashHooks for Keep {
+        fn pre_delete(&self, path: &Path) -> HookDecision {
+            if path.ends_with(&self.0) {
+                HookDecision::Skip
+            } else {
+                HookDecision::Proceed
+            }
+        }
+    }
+    struct VetoAll;
+    impl TrashHooks for VetoAll {
+        fn pre_delete(&self, _path: &Path) -> HookDecision {
+            HookDecision::Veto
+        }
+    }
+
+    init_logging();
+    let kept = get_unique_name();
+    let deleted = get_unique_name();
+    File::create(&kept).unwrap();
+    File::create(&deleted).unwrap();
+
+    let vetoing = TrashContext::new().with_hooks(VetoAll);
+    assert!(matches!(vetoing.delete_all([&kept, &deleted]), Err(trash::Error::Vetoed { .. })));
+    assert!(Path::new(&kept).exists() && Path::new(&deleted).exists());
+
+    let skipping = TrashContext::new().with_hooks(Keep(kept.clone()));
+    skipping.delete_all([&kept, &deleted]).unwrap();
+    assert!(Path::new(&kept).exists());
+    assert!(!Path::new(&deleted).exists());
+    std::fs::remove_file(&kept).unwrap();
+}
+
+#[test]
+#[serial]
+fn best_effort_continues_after_error() {
+    use trash::{Preset, TrashContext};
+
+    init_logging();
+    let missing = get_unique_name();
+    let existing = get_unique_name();
+    File::create(&existing).unwrap();
+
+    let ctx = TrashContext::from_preset(Preset::BestEffort);
+    assert!(ctx.delete_all([&missing, &existing]).is_err());
+    assert!(!Path::new(&existing).exists());
+}
+
+#[test]
+fn destination_for_file() {
+    init_logging();
+    let path = get_unique_name();
+    std::fs::write(&path, b"12345").unwrap();
+    let destination = trash::destination_for(&path).unwrap();
+    assert_eq!(destination.bytes, trash::ByteSize::new(5));
+    assert_eq!(destination.transfer, trash::TrashTransfer::Rename);
+    assert!(Path::new(&path).exists(), "computing the destination must not move anything");
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn estimate_batch() {
+    init_logging();
+    let dir = get_unique_name();
+    let file = get_unique_name();
+    std::fs::create_dir_all(Path::new(&dir).join("inner")).unwrap();
+    std::fs::write(Path::new(&dir).join("inner").join("a.txt"), b"123").unwrap();
+    std::fs::write(&file, b"12345").unwrap();
+
+    let estimate = trash::estimate([&dir, &file, &file]).unwrap();
+    assert_eq!(estimate.paths, 2);
+    assert_eq!((estimate.entries, estimate.directories), (4, 2));
+    assert_eq!(estimate.bytes, trash::ByteSize::new(8));
+    assert!(!estimate.deletes_permanently);
+    assert!(Path::new(&file).exists(), "estimating must not move anything");
+
+    // Kept duplicates and the contents of folders that are in the batch too are counted once.
+    let mut ctx = trash::TrashContext::new();
+    ctx.set_duplicate_paths(trash::DuplicatePaths::Keep);
+    let inner = Path::new(&dir).join("inner");
+    assert_eq!(ctx.estimate([Path::new(&dir), &inner, Path::new(&file), Path::new(&file)]).unwrap(), estimate);
+    std::fs::remove_dir_all(&dir).unwrap();
+    std::fs::remove_file(&file).unwrap();
+}
+
+#[test]
+#[serial]
+fn duplicate_paths_in_batch() {
+    use trash::{DuplicateKind, DuplicatePaths, Error, TrashContext};
+
+    init_logging();
+    let path = get_unique_name();
+    File::create(&path).unwrap();
+    let twice = [PathBuf::from(&path), Path::new(".").join(&path)];
+
+    let mut ctx = TrashContext::new();
+    let normalized = ctx.normalize_paths(&twice).unwrap();
+    assert_eq!(normalized.paths.len(), 1);
+    assert_eq!(normalized.duplicates[0].kind, DuplicateKind::Identical);
+    assert_eq!(normalized.duplicates[0].duplicate_of, normalized.paths[0]);
+
+    ctx.set_duplicate_paths(DuplicatePaths::Fail);
+    assert!(matches!(ctx.delete_all(&twice), Err(Error::DuplicatePath { .. })));
+    assert!(Path::new(&path).exists());
+
+    ctx.set_duplicate_paths(DuplicatePaths::Skip);
+    ctx.delete_all(&twice).unwrap();
+    assert!(!Path::new(&path).exists());
+}
+
+#[test]
+#[serial]
+fn path_resolver_maps_paths() {
+    use trash::{Error, PathResolver, PlatformResolver, TrashContext};
+
+    /// Serves the files of the current folder under `/virtual`.
+    struct Virtual;
+    impl PathResolver for Virtual {
+        fn resolve(&self, path: &Path, base_dir: Option<&Path>) -> Result<PathBuf, Error> {
+            match path.strip_prefix("/virtual") {
+                Ok(relative) => PlatformResolver.resolve(relative, base_dir),
+                Err(_) => Err(Error::CanonicalizePath { original: path.to_owned() }),
+            }
+        }
+    }
+    struct Relative;
+    impl PathResolver for Relative {
+        fn resolve(&self, path: &Path, _base_dir: Option<&Path>) -> Result<PathBuf, Error> {
+            Ok(path.to_owned())
+        }
+    }
+
+    init_logging();
+    let name = get_unique_name();
+    File::create(&name).unwrap();
+
+    let ctx = TrashContext::builder().path_resolver(Virtual).build();
+    assert!(matches!(ctx.delete(&name), Err(Error::CanonicalizePath { .. })));
+    assert!(matches!(
+        TrashContext::new().with_path_resolver(Relative).delete(&name),
+        Err(Error::CanonicalizePath { .. })
+    ));
+    assert!(Path::new(&name).exists());
+
+    let virtual_path = Path::new("/virtual").join(&name);
+    assert_eq!(ctx.resolve_path(&virtual_path).unwrap(), trash::TrashContext::new().resolve_path(&name).unwrap());
+    ctx.delete(&virtual_path).unwrap();
+    assert!(!Path::new(&name).exists());
+}
+
+#[test]
+#[serial]
+fn non_recursive_refuses_filled_folders() {
+    use trash::{Error, TrashContext};
+
+    init_logging();
+    let filled = PathBuf::from(get_unique_name());
+    let empty = PathBuf::from(get_unique_name());
+    let file = PathBuf::from(get_unique_name());
+    create_dir(&filled).unwrap();
+    File::create(filled.join("inner")).unwrap();
+    create_dir(&empty).unwrap();
+    File::create(&file).unwrap();
+
+    let ctx = TrashContext::builder().recursive(false).build();
+    match ctx.delete_all([&file, &empty, &filled]) {
+        Err(Error::DirectoryNotEmpty { path }) => assert!(path.ends_with(&filled)),
+        other => panic!("expected DirectoryNotEmpty, got {other:?}"),
+    }
+    assert!(file.exists() && empty.exists() && filled.exists());
+    assert!(matches!(ctx.delete_permanently(&filled), Err(Error::DirectoryNotEmpty { .. })));
+
+    ctx.delete_all([&file, &empty]).unwrap();
+    assert!(!file.exists() && !empty.exists());
+    TrashContext::builder().recursive(true).build().delete(&filled).unwrap();
+    assert!(!filled.exists());
+}